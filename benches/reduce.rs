@@ -0,0 +1,30 @@
+// LogHistogram bucket reduction for increasing bucket counts.
+// Run with: cargo bench --bench reduce
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use float_diff::LogHistogram;
+
+fn bench_reduce(c: &mut Criterion) {
+    let mut group = c.benchmark_group("reduce");
+    for &bucket_count in &[10, 100, 300, 600] {
+        let mut histo = LogHistogram::new(5);
+        for exp in 0..bucket_count {
+            // Vary counts so that reduction order isn't trivial.
+            for _ in 0..(exp * 7 % 13 + 1) {
+                histo.add(10f64.powi(exp - 300));
+            }
+        }
+        group.bench_with_input(BenchmarkId::from_parameter(bucket_count), &histo, |b, histo| {
+            b.iter(|| {
+                let mut fresh = histo.clone();
+                // Adding a value invalidates the cached reduction.
+                fresh.add(1.0);
+                fresh.reduce();
+                fresh
+            })
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_reduce);
+criterion_main!(benches);
@@ -0,0 +1,52 @@
+// DiffSummary ingestion and display.
+// Run with: cargo bench --bench summary
+use criterion::{criterion_group, criterion_main, Criterion, Throughput};
+use float_diff::{diff, DiffSummary64};
+use std::hint::black_box;
+
+const COUNT: usize = 100_000;
+
+// Expected values, and computed values with a mix of exact matches and
+// small relative errors, as is typical of regression runs.
+fn data() -> (Vec<f64>, Vec<f64>) {
+    let ys: Vec<f64> = (0..COUNT).map(|i| (i as f64 * 0.001).sin() * 1e3).collect();
+    let xs = ys
+        .iter()
+        .enumerate()
+        .map(|(i, &y)| if i % 4 == 0 { y * (1.0 + 1e-12 * (i % 7) as f64) } else { y })
+        .collect();
+    (xs, ys)
+}
+
+fn bench_add(c: &mut Criterion) {
+    let (xs, ys) = data();
+    let mut group = c.benchmark_group("summary");
+    group.throughput(Throughput::Elements(COUNT as u64));
+    group.bench_function("add", |b| {
+        b.iter(|| {
+            let mut summary = DiffSummary64::new("bench", 1e-9, false, 10, &diff::diff_rel);
+            for (index, (&x, &y)) in xs.iter().zip(&ys).enumerate() {
+                summary.add(black_box(x), black_box(y), index);
+            }
+            summary
+        })
+    });
+    group.bench_function("add_slices", |b| {
+        b.iter(|| {
+            let mut summary = DiffSummary64::new("bench", 1e-9, false, 10, &diff::diff_rel);
+            summary.add_slices(black_box(&xs), black_box(&ys));
+            summary
+        })
+    });
+    group.finish();
+}
+
+fn bench_display(c: &mut Criterion) {
+    let (xs, ys) = data();
+    let mut summary = DiffSummary64::new("bench", 1e-9, false, 10, &diff::diff_rel);
+    summary.add_slices(&xs, &ys);
+    c.bench_function("summary/display", |b| b.iter(|| format!("{}", black_box(&summary))));
+}
+
+criterion_group!(benches, bench_add, bench_display);
+criterion_main!(benches);
@@ -0,0 +1,83 @@
+// Attribute macros for float-diff, re-exported by float-diff's macros feature.
+use proc_macro::TokenStream;
+use quote::{format_ident, quote};
+use syn::{parse_macro_input, FnArg, ItemFn, LitStr, Pat};
+
+// Turn a function into a test whose arguments are summaries created from a
+// comparison config file, e.g.
+//   #[float_diff::test(channels = "tests/channels.toml")]
+//   fn test_solver(pressure: &mut DiffSummary64, phase: &mut DiffSummary64) { ... }
+// The channels path is relative to the crate's manifest directory, and each
+// argument receives the channel with the same name. Each argument must be
+// named after a distinct channel, but not every channel needs an argument.
+// After the function returns, every channel's summary is registered with
+// float_diff::registry, then checked with DiffSummary::assert_all.
+#[proc_macro_attribute]
+pub fn test(attr: TokenStream, item: TokenStream) -> TokenStream {
+    let mut channels: Option<LitStr> = None;
+    let attr_parser = syn::meta::parser(|meta| {
+        if meta.path.is_ident("channels") {
+            channels = Some(meta.value()?.parse()?);
+            Ok(())
+        } else {
+            Err(meta.error("expected channels = \"path\""))
+        }
+    });
+    parse_macro_input!(attr with attr_parser);
+    let input = parse_macro_input!(item as ItemFn);
+    let channels = match channels {
+        Some(channels) => channels,
+        None => {
+            return syn::Error::new(input.sig.ident.span(), "float_diff::test requires channels = \"path\"")
+                .to_compile_error()
+                .into()
+        }
+    };
+    match expand(&channels, input) {
+        Ok(tokens) => tokens.into(),
+        Err(err) => err.to_compile_error().into(),
+    }
+}
+
+fn expand(channels: &LitStr, input: ItemFn) -> syn::Result<proc_macro2::TokenStream> {
+    let mut names = Vec::new();
+    for arg in &input.sig.inputs {
+        let name = match arg {
+            FnArg::Typed(typed) => match &*typed.pat {
+                Pat::Ident(pat) => pat.ident.to_string(),
+                pat => return Err(syn::Error::new_spanned(pat, "channel arguments must be plain names")),
+            },
+            FnArg::Receiver(receiver) => return Err(syn::Error::new_spanned(receiver, "float_diff::test can't take self")),
+        };
+        names.push(name.trim_start_matches("r#").to_string());
+    }
+    let attrs = &input.attrs;
+    let vis = &input.vis;
+    let ident = &input.sig.ident;
+    let output = &input.sig.output;
+    let inner_ident = format_ident!("__{}_channels", ident);
+    let mut inner = input.clone();
+    inner.sig.ident = inner_ident.clone();
+    inner.attrs.clear();
+    inner.vis = syn::Visibility::Inherited;
+    Ok(quote! {
+        #[test]
+        #(#attrs)*
+        #vis fn #ident() #output {
+            #inner
+            let config = ::float_diff::config::ComparisonConfig::from_path(
+                concat!(env!("CARGO_MANIFEST_DIR"), "/", #channels)
+            ).unwrap_or_else(|err| panic!("{}: {}", #channels, err));
+            let mut summaries = config.summaries();
+            let result = {
+                let mut slots: Vec<_> = summaries.iter_mut().map(Some).collect();
+                #inner_ident(#(::float_diff::config::take_channel(&mut slots, #names)),*)
+            };
+            for summary in &summaries {
+                ::float_diff::registry::register(summary);
+            }
+            ::float_diff::DiffSummary64::assert_all(&summaries);
+            result
+        }
+    })
+}
@@ -0,0 +1,141 @@
+// Python bindings for float-diff summaries, so that notebook exploration
+// can apply the same comparators and tolerances as Rust tests, e.g.
+//   import float_diff, numpy as np
+//   summary = float_diff.DiffSummary("pressure", 1e-9, comparator="rel")
+//   summary.add_numpy(np.array(computed), np.array(expected))
+//   print(summary.report())
+//   summaries = float_diff.from_config("tests/channels.toml")
+// Build the extension module with maturin, which enables the
+// extension-module feature per pyproject.toml.
+use std::mem::ManuallyDrop;
+use float_diff::config::{self, ComparisonConfig};
+use float_diff::DiffSummary64;
+use numpy::PyReadonlyArray1;
+use pyo3::exceptions::{PyIOError, PyValueError};
+use pyo3::prelude::*;
+
+// A summary and the name it owns. Summaries borrow their names, so the
+// name is leaked on creation and reclaimed on drop, after the summary.
+#[pyclass(name = "DiffSummary", unsendable)]
+struct PyDiffSummary {
+    summary: ManuallyDrop<DiffSummary64<'static>>,
+    name: *mut str,
+}
+
+impl PyDiffSummary {
+    fn create(name: &str, allow_diff: f64, comparator: &str, allow_sign: bool, bucket_count: usize) -> PyResult<Self> {
+        let calc_diff = config::comparator(comparator)
+            .ok_or_else(|| PyValueError::new_err(format!("unknown comparator {}", comparator)))?;
+        // Check what DiffSummary64::new asserts, so that Python sees a ValueError rather than a panic.
+        if bucket_count <= 2 {
+            return Err(PyValueError::new_err(format!("summary {} has {} buckets, but needs more than 2", name, bucket_count)));
+        }
+        if allow_diff.is_nan() || allow_diff < 0.0 {
+            return Err(PyValueError::new_err(format!("summary {} tolerance must be non-negative, got {}", name, allow_diff)));
+        }
+        let name: *mut str = Box::leak(name.to_string().into_boxed_str());
+        // Safety: name stays valid until drop, which drops the summary first.
+        let summary = DiffSummary64::new(unsafe { &*name }, allow_diff, allow_sign, bucket_count, calc_diff)
+            .with_direction(config::direction(comparator));
+        Ok(PyDiffSummary { summary: ManuallyDrop::new(summary), name })
+    }
+}
+
+impl Drop for PyDiffSummary {
+    fn drop(&mut self) {
+        // Safety: the summary is dropped only here, and was the only user of name.
+        unsafe {
+            ManuallyDrop::drop(&mut self.summary);
+            drop(Box::from_raw(self.name));
+        }
+    }
+}
+
+#[pymethods]
+impl PyDiffSummary {
+    // comparator is one of the names accepted by float_diff::config::comparator.
+    #[new]
+    #[pyo3(signature = (name, allow_diff, comparator = "abs", allow_sign = false, bucket_count = 5))]
+    fn new(name: &str, allow_diff: f64, comparator: &str, allow_sign: bool, bucket_count: usize) -> PyResult<Self> {
+        PyDiffSummary::create(name, allow_diff, comparator, allow_sign, bucket_count)
+    }
+
+    fn add(&mut self, x: f64, y: f64, index: usize) {
+        self.summary.add(x, y, index);
+    }
+
+    // Add each (x, y) pair from a pair of equal length 1-d arrays, with each
+    // item's position as its index.
+    fn add_numpy(&mut self, xs: PyReadonlyArray1<'_, f64>, ys: PyReadonlyArray1<'_, f64>) -> PyResult<()> {
+        let (x_len, y_len) = (xs.as_array().len(), ys.as_array().len());
+        if x_len != y_len {
+            return Err(PyValueError::new_err(format!("add_numpy lengths differ: {} vs {}", x_len, y_len)));
+        }
+        match (xs.as_slice(), ys.as_slice()) {
+            (Ok(xs), Ok(ys)) => self.summary.add_slices(xs, ys),
+            // Strided arrays, e.g. from slicing, aren't contiguous.
+            _ => self.summary.add_slices(&xs.as_array().to_vec(), &ys.as_array().to_vec()),
+        }
+        Ok(())
+    }
+
+    fn is_ok(&self) -> bool {
+        self.summary.is_ok()
+    }
+
+    #[getter]
+    fn name(&self) -> &str {
+        self.summary.name
+    }
+
+    #[getter]
+    fn count(&self) -> usize {
+        self.summary.count()
+    }
+
+    #[getter]
+    fn fail_count(&self) -> usize {
+        self.summary.fail_count()
+    }
+
+    #[getter]
+    fn worst_diff(&self) -> f64 {
+        self.summary.worst_diff()
+    }
+
+    // The summary's Display output.
+    fn report(&self) -> String {
+        self.summary.to_string()
+    }
+
+    fn to_json(&self) -> String {
+        self.summary.to_json()
+    }
+
+    fn __str__(&self) -> String {
+        self.summary.to_string()
+    }
+}
+
+// Create a summary for each channel of a comparison config file, as
+// ComparisonConfig::summaries does.
+#[pyfunction]
+fn from_config(path: &str) -> PyResult<Vec<PyDiffSummary>> {
+    let config = ComparisonConfig::from_path(path).map_err(|err| match err {
+        config::ConfigError::Io(err) => PyIOError::new_err(err.to_string()),
+        err => PyValueError::new_err(err.to_string()),
+    })?;
+    config
+        .channels
+        .iter()
+        .map(|channel| PyDiffSummary::create(&channel.name, channel.tolerance, &channel.comparator, channel.allow_sign, channel.buckets))
+        .collect()
+}
+
+#[pymodule]
+#[pyo3(name = "float_diff")]
+fn float_diff_python(module: &Bound<'_, PyModule>) -> PyResult<()> {
+    module.add_class::<PyDiffSummary>()?;
+    module.add_function(wrap_pyfunction!(from_config, module)?)?;
+    Ok(())
+}
@@ -0,0 +1,46 @@
+/* C interface to float-diff summaries, built with the ffi feature.
+ * See src/ffi.rs for details. */
+#ifndef FLOAT_DIFF_H
+#define FLOAT_DIFF_H
+
+#include <stdbool.h>
+#include <stddef.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+typedef enum FloatDiffComparator {
+    FLOAT_DIFF_ABS = 0,
+    FLOAT_DIFF_REL = 1,
+    FLOAT_DIFF_ULPS = 2,
+    FLOAT_DIFF_LESSER = 3,
+    FLOAT_DIFF_LOG = 4,
+    FLOAT_DIFF_PERCENT = 5,
+    FLOAT_DIFF_TOTAL_ORDER = 6
+} FloatDiffComparator;
+
+/* Statuses returned by functions that can fail. Panics never unwind into C. */
+#define FLOAT_DIFF_OK 0
+#define FLOAT_DIFF_PANICKED (-1)
+
+typedef struct FloatDiffSummary FloatDiffSummary;
+
+/* Returns NULL for invalid settings, such as a bucket_count of 2 or less. */
+FloatDiffSummary *float_diff_summary_new(const char *name, FloatDiffComparator comparator,
+                                         double allow_diff, bool allow_sign, size_t bucket_count);
+void float_diff_summary_free(FloatDiffSummary *summary);
+/* Return FLOAT_DIFF_OK or FLOAT_DIFF_PANICKED. */
+int float_diff_summary_add(FloatDiffSummary *summary, double x, double y, size_t index);
+int float_diff_summary_add_slices(FloatDiffSummary *summary, const double *xs, const double *ys, size_t len);
+/* Returns 1 if ok, 0 if not, or FLOAT_DIFF_PANICKED. */
+int float_diff_summary_is_ok(const FloatDiffSummary *summary);
+/* Release the result with float_diff_string_free. Returns NULL if formatting panicked. */
+char *float_diff_summary_report(const FloatDiffSummary *summary);
+void float_diff_string_free(char *text);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif
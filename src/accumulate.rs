@@ -0,0 +1,270 @@
+// Accumulation files, for reporting results from tests that run in
+// separate processes (for example under cargo nextest), where the
+// in-process registry only sees its own process's summaries.
+// Each process appends registry snapshots to its own file in a shared
+// directory, and once the suite finishes, report merges the snapshots for
+// each channel name and produces the consolidated table, as does
+//   float-diff report <dir>
+// Each file line holds one snapshot's tab-separated fields:
+//   name, count, fail_count, sign_count, worst_diff, allow_diff, ok, worst_score
+// with tabs, newlines and backslashes in names escaped. Records without a
+// worst_score, from older files, use worst_diff.
+// Alternatively, append_binary writes files with binary::EXTENSION in the
+// binary module's format, a header followed by encoded snapshots, which
+// load reads alongside text files.
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::Path;
+use crate::binary;
+use crate::input_error::DiffInputError;
+use crate::registry::{self, Report, RegistryEntry};
+
+// The environment variable naming the directory that registry::register
+// appends snapshots to.
+pub const ACCUMULATE_DIR_ENV: &str = "FLOAT_DIFF_ACCUMULATE_DIR";
+
+// The extension of accumulation files.
+pub const EXTENSION: &str = "fdacc";
+
+// The names of a record's fields, in file order.
+const COLUMNS: [&str; 8] = ["name", "count", "fail_count", "sign_count", "worst_diff", "allow_diff", "ok", "worst_score"];
+
+// The number of leading columns every record has.
+const REQUIRED_COLUMNS: usize = 7;
+
+// Append a snapshot to this process's accumulation file in dir, creating
+// dir and the file as needed.
+pub fn append<P: AsRef<Path>>(dir: P, entry: &RegistryEntry) -> io::Result<()> {
+    let dir = dir.as_ref();
+    fs::create_dir_all(dir)?;
+    let path = dir.join(format!("{}.{}", std::process::id(), EXTENSION));
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    // Write each record in one call, so that records from concurrent tests don't interleave.
+    file.write_all(format_record(entry).as_bytes())
+}
+
+// As append, in this process's binary accumulation file.
+pub fn append_binary<P: AsRef<Path>>(dir: P, entry: &RegistryEntry) -> io::Result<()> {
+    let dir = dir.as_ref();
+    fs::create_dir_all(dir)?;
+    let path = dir.join(format!("{}.{}", std::process::id(), binary::EXTENSION));
+    binary::append(&path, |writer| writer.entry(entry))
+}
+
+// Read every snapshot from the text and binary accumulation files in dir,
+// in file name order, then record order.
+pub fn load<P: AsRef<Path>>(dir: P) -> Result<Vec<RegistryEntry>, DiffInputError> {
+    let mut paths = Vec::new();
+    for dir_entry in fs::read_dir(dir)? {
+        let path = dir_entry?.path();
+        if path.extension().is_some_and(|extension| extension == EXTENSION) || binary::has_extension(&path) {
+            paths.push(path);
+        }
+    }
+    paths.sort();
+    let mut entries = Vec::new();
+    for path in paths {
+        if binary::has_extension(&path) {
+            entries.extend(binary::decode_entries(&fs::read(&path)?, &path.display().to_string())?);
+            continue;
+        }
+        let text = fs::read_to_string(&path)?;
+        for (line_index, line) in text.lines().enumerate() {
+            if line.is_empty() {
+                continue;
+            }
+            entries.push(parse_record(line, &path, line_index + 1)?);
+        }
+    }
+    Ok(entries)
+}
+
+// Merge snapshots with the same name, via RegistryEntry::merge, in order
+// of each name's first appearance, returning the first error merging them.
+pub fn merge(entries: &[RegistryEntry]) -> Result<Vec<RegistryEntry>, DiffInputError> {
+    let mut merged: Vec<RegistryEntry> = Vec::new();
+    for entry in entries {
+        match merged.iter_mut().find(|existing| existing.name == entry.name) {
+            Some(existing) => existing.merge(entry)?,
+            None => {
+                let mut entry = entry.clone();
+                entry.summary = entry.line();
+                merged.push(entry);
+            }
+        }
+    }
+    Ok(merged)
+}
+
+// The consolidated table of the snapshots in dir's accumulation files,
+// failures first.
+pub fn report<P: AsRef<Path>>(dir: P) -> Result<Report, DiffInputError> {
+    let mut entries = merge(&load(dir)?)?;
+    registry::rank(&mut entries);
+    Ok(Report { entries })
+}
+
+// Format a snapshot as a record line, including its newline.
+pub(crate) fn format_record(entry: &RegistryEntry) -> String {
+    format!(
+        "{}\t{}\t{}\t{}\t{:e}\t{:e}\t{}\t{:e}\n",
+        escape(&entry.name),
+        entry.count,
+        entry.fail_count,
+        entry.sign_count,
+        entry.worst_diff,
+        entry.allow_diff,
+        if entry.is_ok { "ok" } else { "fail" },
+        entry.worst_score
+    )
+}
+
+// Parse a record line, without its newline, from line_number of path.
+pub(crate) fn parse_record(line: &str, path: &Path, line_number: usize) -> Result<RegistryEntry, DiffInputError> {
+    let fields: Vec<&str> = line.split('\t').collect();
+    if fields.len() < REQUIRED_COLUMNS {
+        let column = COLUMNS[fields.len()];
+        return Err(DiffInputError::MissingColumn { source: path.display().to_string(), line: line_number, column });
+    }
+    let invalid = |text: &str| DiffInputError::Parse { source: path.display().to_string(), line: line_number, text: text.to_string() };
+    if fields.len() > COLUMNS.len() {
+        return Err(invalid(line));
+    }
+    let count = |field: &str| field.parse::<usize>().map_err(|_| invalid(field));
+    let diff = |field: &str| field.parse::<f64>().map_err(|_| invalid(field));
+    let mut entry = RegistryEntry {
+        name: unescape(fields[0]).ok_or_else(|| invalid(fields[0]))?,
+        count: count(fields[1])?,
+        fail_count: count(fields[2])?,
+        sign_count: count(fields[3])?,
+        worst_diff: diff(fields[4])?,
+        allow_diff: diff(fields[5])?,
+        worst_score: match fields.get(7) {
+            Some(field) => diff(field)?,
+            None => diff(fields[4])?,
+        },
+        is_ok: match fields[6] {
+            "ok" => true,
+            "fail" => false,
+            field => return Err(invalid(field)),
+        },
+        summary: String::new(),
+    };
+    entry.summary = entry.line();
+    Ok(entry)
+}
+
+pub(crate) fn escape(name: &str) -> String {
+    name.replace('\\', "\\\\").replace('\t', "\\t").replace('\n', "\\n")
+}
+
+pub(crate) fn unescape(field: &str) -> Option<String> {
+    let mut name = String::with_capacity(field.len());
+    let mut chars = field.chars();
+    while let Some(c) = chars.next() {
+        if c == '\\' {
+            name.push(match chars.next()? {
+                '\\' => '\\',
+                't' => '\t',
+                'n' => '\n',
+                _ => return None,
+            });
+        } else {
+            name.push(c);
+        }
+    }
+    Some(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{append, append_binary, load, report};
+    use crate::diff;
+    use crate::diff_summary_f64::DiffSummary;
+    use crate::registry::RegistryEntry;
+
+    #[test]
+    fn test_accumulate() {
+        let dir = std::env::temp_dir().join(format!("float-diff-accumulate-{}", std::process::id()));
+        let mut first = DiffSummary::new("pressure\tinlet", 0.1, false, 4, &diff::diff_abs);
+        first.add(1.0, 1.05, 0);
+        let mut second = DiffSummary::new("pressure\tinlet", 0.1, false, 4, &diff::diff_abs);
+        second.add(1.0, 1.5, 0);
+        second.add(-1.0, 1.0, 1);
+        let mut other = DiffSummary::new("phase", 0.1, true, 4, &diff::diff_abs);
+        other.add(1.0, 1.0, 0);
+        for summary in &[&first, &other, &second] {
+            append(&dir, &RegistryEntry::new(summary)).unwrap();
+        }
+        std::fs::write(dir.join("notes.txt"), "not a record\n").unwrap();
+
+        let entries = load(&dir).unwrap();
+        assert_eq!(entries.len(), 3);
+        assert_eq!(entries[0].name, "pressure\tinlet");
+        assert_eq!(entries[2].worst_diff, 2.0);
+
+        let report = report(&dir).unwrap();
+        assert_eq!(report.entries.len(), 2);
+        let merged = &report.entries[0];
+        assert_eq!((merged.count, merged.fail_count, merged.sign_count), (3, 2, 1));
+        assert!(!merged.is_ok);
+        assert_eq!(
+            report.to_string(),
+            "2 summaries, 1 failed\n  FAILED pressure\tinlet: count 3, fail 2, sign 1, worst diff 2e0, allow 1e-1\n  ok     phase: count 1, fail 0, sign 0, worst diff 0e0, allow 1e-1"
+        );
+
+        append_binary(&dir, &RegistryEntry::new(&other)).unwrap();
+        append_binary(&dir, &RegistryEntry::new(&second)).unwrap();
+        let entries = load(&dir).unwrap();
+        assert_eq!(entries.len(), 5);
+        assert_eq!(entries[4], entries[2]);
+        assert_eq!(super::merge(&entries).unwrap()[0].count, 5);
+        std::fs::remove_file(dir.join(format!("{}.fdb", std::process::id()))).unwrap();
+
+        std::fs::write(dir.join("old.fdacc"), "phase\t2\t0\t0\t5e-2\t1e-1\tok\n").unwrap();
+        let entries = load(&dir).unwrap();
+        assert_eq!(entries[3].worst_score, 5e-2);
+        std::fs::remove_file(dir.join("old.fdacc")).unwrap();
+
+        std::fs::write(dir.join("retuned.fdacc"), "phase\t2\t0\t0\t5e-2\t2e-1\tok\n").unwrap();
+        assert_eq!(super::report(&dir).err().unwrap().to_string(), "phase: tolerance mismatch, 1e-1 vs 2e-1");
+        std::fs::remove_file(dir.join("retuned.fdacc")).unwrap();
+
+        std::fs::write(dir.join("bad.fdacc"), "x\t1\n").unwrap();
+        let err = load(&dir).unwrap_err();
+        assert!(err.to_string().ends_with("bad.fdacc line 1: missing column fail_count"), "{}", err);
+        std::fs::write(dir.join("bad.fdacc"), "x\t1\t0\t0\t1e0\t1e-1\tmaybe\n").unwrap();
+        let err = load(&dir).unwrap_err();
+        assert!(err.to_string().ends_with("bad.fdacc line 1: invalid value \"maybe\""), "{}", err);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_append_binary_concurrent() {
+        let dir = std::env::temp_dir().join(format!("float-diff-accumulate-concurrent-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let mut summary = DiffSummary::new("phase", 0.1, true, 4, &diff::diff_abs);
+        summary.add(1.0, 1.05, 0);
+        let entry = RegistryEntry::new(&summary);
+        // Start every thread at once, so that several find the file empty.
+        let barrier = std::sync::Arc::new(std::sync::Barrier::new(16));
+        let threads: Vec<_> = (0..16)
+            .map(|_| {
+                let (dir, entry, barrier) = (dir.clone(), entry.clone(), barrier.clone());
+                std::thread::spawn(move || {
+                    barrier.wait();
+                    for _ in 0..8 {
+                        append_binary(&dir, &entry).unwrap();
+                    }
+                })
+            })
+            .collect();
+        for thread in threads {
+            thread.join().unwrap();
+        }
+        let entries = load(&dir).unwrap();
+        assert_eq!(entries.len(), 128);
+        assert!(entries.iter().all(|loaded| *loaded == entries[0]));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,327 @@
+// Interop with the approx crate's AbsDiffEq, RelativeEq and UlpsEq traits.
+// The abs_diff_eq, relative_eq and ulps_eq comparators apply the same checks
+// as the approx crate's f64 implementations, so code that already chooses
+// approx epsilons can summarize with them. Their differences are in
+// multiples of the allowed difference, so use an allow_diff of 1.0, e.g.
+//   let compare = approx_compat::relative_eq(f64::EPSILON, 1e-9);
+//   let mut summary = DiffSummary64::new("pressure", 1.0, false, 5, &compare);
+// An item is then within tolerance exactly when the approx check passes.
+// Conversely, ApproxValue lets any comparator serve as approx-style equality:
+//   approx::assert_abs_diff_eq!(ApproxValue::new(x, &diff::diff_ulps), ApproxValue::new(y, &diff::diff_ulps), epsilon = 4.0);
+// For migrating test suites incrementally, the fd_assert_abs_diff_eq!,
+// fd_assert_relative_eq! and fd_assert_ulps_eq! macros take the same
+// keyword arguments as approx's assert macros, in any order, with the same
+// defaults for f32 and f64 operands, but fail with a DiffSummary's message.
+// Given a name argument, they also register the comparison in the global
+// registry under that name, e.g.
+//   fd_assert_relative_eq!(x, y, max_relative = 1e-9, name = "solver/pressure");
+use approx::AbsDiffEq;
+use float_cmp::Ulps;
+use num_traits::ToPrimitive;
+use std::any::TypeId;
+use std::fmt::Debug;
+use crate::diff_summary_f64::DiffSummary;
+use crate::registry;
+
+// Compare as approx's AbsDiffEq does, with differences as multiples of epsilon.
+pub fn abs_diff_eq(epsilon: f64) -> impl Fn(f64, f64) -> (f64, bool) {
+    assert!(epsilon >= 0.0, "abs_diff_eq requires a non-negative epsilon, got {}", epsilon);
+    move |x, y| {
+        // As in approx, infinities of the same sign differ by nan, and so are never equal.
+        let abs_diff = (x - y).abs();
+        (if abs_diff == 0.0 { 0.0 } else { abs_diff / epsilon }, x.is_sign_negative() != y.is_sign_negative())
+    }
+}
+
+// Compare as approx's RelativeEq does: the smaller of the absolute
+// difference as a multiple of epsilon, and the difference relative to the
+// larger magnitude as a multiple of max_relative.
+pub fn relative_eq(epsilon: f64, max_relative: f64) -> impl Fn(f64, f64) -> (f64, bool) {
+    assert!(epsilon >= 0.0 && max_relative >= 0.0, "relative_eq requires non-negative epsilon and max_relative");
+    move |x, y| {
+        let sign_change = x.is_sign_negative() != y.is_sign_negative();
+        if x == y {
+            return (0.0, sign_change);
+        }
+        if x.is_infinite() || y.is_infinite() {
+            return (f64::INFINITY, sign_change);
+        }
+        let abs_diff = (x - y).abs();
+        let largest = x.abs().max(y.abs());
+        ((abs_diff / epsilon).min(abs_diff / (largest * max_relative)), sign_change)
+    }
+}
+
+// Compare as approx's UlpsEq does: the smaller of the absolute difference
+// as a multiple of epsilon, and, for same-sign values, the ULPs difference
+// as a multiple of max_ulps.
+pub fn ulps_eq(epsilon: f64, max_ulps: u32) -> impl Fn(f64, f64) -> (f64, bool) {
+    assert!(epsilon >= 0.0, "ulps_eq requires a non-negative epsilon, got {}", epsilon);
+    move |x, y| {
+        let sign_change = x.is_sign_negative() != y.is_sign_negative();
+        if x == y {
+            return (0.0, sign_change);
+        }
+        let by_abs = (x - y).abs() / epsilon;
+        if sign_change || x.is_nan() || y.is_nan() {
+            return (by_abs, sign_change);
+        }
+        // Cast to f64 before abs to avoid risk of overflow, as in diff_ulps.
+        let ulps = (x.ulps(&y) as f64).abs();
+        (by_abs.min(ulps / max_ulps as f64), sign_change)
+    }
+}
+
+// A value compared through a comparator, for use with approx's
+// assert_abs_diff_eq! and abs_diff_eq!. Values are equal within epsilon
+// when the comparator's difference is at most epsilon, without a sign change.
+#[derive(Clone, Copy)]
+pub struct ApproxValue<'a> {
+    pub value: f64,
+    pub calc_diff: &'a dyn Fn(f64, f64) -> (f64, bool),
+}
+
+impl<'a> ApproxValue<'a> {
+    pub fn new(value: f64, calc_diff: &'a dyn Fn(f64, f64) -> (f64, bool)) -> Self {
+        ApproxValue { value, calc_diff }
+    }
+}
+
+impl Debug for ApproxValue<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:e}", self.value)
+    }
+}
+
+// Exact equality, i.e. a difference of 0 without a sign change.
+impl PartialEq for ApproxValue<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        self.abs_diff_eq(other, 0.0)
+    }
+}
+
+impl AbsDiffEq for ApproxValue<'_> {
+    type Epsilon = f64;
+
+    fn default_epsilon() -> f64 {
+        0.0
+    }
+
+    // Uses self's comparator, with self as x and other as y.
+    fn abs_diff_eq(&self, other: &Self, epsilon: f64) -> bool {
+        let (diff, sign_change) = (self.calc_diff)(self.value, other.value);
+        diff <= epsilon && !sign_change
+    }
+}
+
+// Check one comparison for the fd_assert_* macros, registering it if named,
+// then asserting it with DiffSummary::assert. Sign changes are allowed, as
+// in approx, since the comparators only pass them for tiny differences.
+#[doc(hidden)]
+pub fn assert_compat<X: ToPrimitive, Y: ToPrimitive>(
+    expression: &str,
+    name: Option<&str>,
+    x: X,
+    y: Y,
+    calc_diff: &dyn Fn(f64, f64) -> (f64, bool),
+) {
+    let mut summary = DiffSummary::new(name.unwrap_or(expression), 1.0, true, 5, calc_diff);
+    summary.add(x, y, 0);
+    if name.is_some() {
+        registry::register(&summary);
+    }
+    summary.assert();
+}
+
+// The keyword arguments given to an fd_assert_* macro, in any order.
+#[doc(hidden)]
+#[derive(Default)]
+pub struct CompatArgs<'a> {
+    pub epsilon: Option<f64>,
+    pub max_relative: Option<f64>,
+    pub max_ulps: Option<u32>,
+    pub name: Option<&'a str>,
+}
+
+// The default epsilon and max_relative for comparing x and y, per approx's
+// implementation for their type: f32::EPSILON if either is an f32, since
+// f64::EPSILON would demand more precision than an f32 has, or else f64::EPSILON.
+#[doc(hidden)]
+pub fn default_epsilon<X: 'static, Y: 'static>(_x: &X, _y: &Y) -> f64 {
+    let is_f32 = |id| id == TypeId::of::<f32>();
+    if is_f32(TypeId::of::<X>()) || is_f32(TypeId::of::<Y>()) {
+        f64::from(f32::EPSILON)
+    } else {
+        f64::EPSILON
+    }
+}
+
+// Set the fields of a CompatArgs from keyword arguments, in any order,
+// accepting only those valid for the abs, relative or ulps macro.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __fd_compat_args {
+    ($kind: ident $args: ident $(,)?) => {};
+    ($kind: ident $args: ident, name = $value: expr $(, $($rest: tt)*)?) => {
+        $args.name = Some($value);
+        $crate::__fd_compat_args!($kind $args $(, $($rest)*)?);
+    };
+    ($kind: ident $args: ident, epsilon = $value: expr $(, $($rest: tt)*)?) => {
+        $args.epsilon = Some(::core::convert::Into::<f64>::into($value));
+        $crate::__fd_compat_args!($kind $args $(, $($rest)*)?);
+    };
+    (relative $args: ident, max_relative = $value: expr $(, $($rest: tt)*)?) => {
+        $args.max_relative = Some(::core::convert::Into::<f64>::into($value));
+        $crate::__fd_compat_args!(relative $args $(, $($rest)*)?);
+    };
+    (ulps $args: ident, max_ulps = $value: expr $(, $($rest: tt)*)?) => {
+        $args.max_ulps = Some($value);
+        $crate::__fd_compat_args!(ulps $args $(, $($rest)*)?);
+    };
+}
+
+// As approx::assert_abs_diff_eq!, e.g.
+//   fd_assert_abs_diff_eq!(x, y, epsilon = 1e-9);
+// epsilon defaults to the EPSILON of the operands' type.
+#[macro_export]
+macro_rules! fd_assert_abs_diff_eq {
+    ($x: expr, $y: expr $(, $($args: tt)*)?) => {{
+        let (x, y) = ($x, $y);
+        // Unmodified when no keyword arguments are given.
+        #[allow(unused_mut)]
+        let mut args = $crate::approx_compat::CompatArgs::default();
+        $crate::__fd_compat_args!(abs args $(, $($args)*)?);
+        let epsilon = args.epsilon.unwrap_or_else(|| $crate::approx_compat::default_epsilon(&x, &y));
+        $crate::approx_compat::assert_compat(
+            concat!(stringify!($x), " vs ", stringify!($y)),
+            args.name,
+            x,
+            y,
+            &$crate::approx_compat::abs_diff_eq(epsilon),
+        )
+    }};
+}
+
+// As approx::assert_relative_eq!, e.g.
+//   fd_assert_relative_eq!(x, y, max_relative = 1e-9, epsilon = 1e-12);
+// epsilon and max_relative default to the EPSILON of the operands' type.
+#[macro_export]
+macro_rules! fd_assert_relative_eq {
+    ($x: expr, $y: expr $(, $($args: tt)*)?) => {{
+        let (x, y) = ($x, $y);
+        // Unmodified when no keyword arguments are given.
+        #[allow(unused_mut)]
+        let mut args = $crate::approx_compat::CompatArgs::default();
+        $crate::__fd_compat_args!(relative args $(, $($args)*)?);
+        let default_epsilon = $crate::approx_compat::default_epsilon(&x, &y);
+        $crate::approx_compat::assert_compat(
+            concat!(stringify!($x), " vs ", stringify!($y)),
+            args.name,
+            x,
+            y,
+            &$crate::approx_compat::relative_eq(
+                args.epsilon.unwrap_or(default_epsilon),
+                args.max_relative.unwrap_or(default_epsilon),
+            ),
+        )
+    }};
+}
+
+// As approx::assert_ulps_eq!, e.g.
+//   fd_assert_ulps_eq!(x, y, max_ulps = 4);
+// epsilon defaults to the EPSILON of the operands' type, and max_ulps to 4.
+#[macro_export]
+macro_rules! fd_assert_ulps_eq {
+    ($x: expr, $y: expr $(, $($args: tt)*)?) => {{
+        let (x, y) = ($x, $y);
+        // Unmodified when no keyword arguments are given.
+        #[allow(unused_mut)]
+        let mut args = $crate::approx_compat::CompatArgs::default();
+        $crate::__fd_compat_args!(ulps args $(, $($args)*)?);
+        let epsilon = args.epsilon.unwrap_or_else(|| $crate::approx_compat::default_epsilon(&x, &y));
+        $crate::approx_compat::assert_compat(
+            concat!(stringify!($x), " vs ", stringify!($y)),
+            args.name,
+            x,
+            y,
+            &$crate::approx_compat::ulps_eq(epsilon, args.max_ulps.unwrap_or(4)),
+        )
+    }};
+}
+
+#[cfg(test)]
+mod tests {
+    use std::panic::{catch_unwind, AssertUnwindSafe};
+    use super::{abs_diff_eq, relative_eq, ulps_eq, ApproxValue};
+    use crate::diff;
+    use crate::diff_summary_f64::DiffSummary;
+
+    #[test]
+    fn test_matches_approx() {
+        let values = [
+            0.0, -0.0, 1e-300, 1.0, 1.0 + 1e-15, 1.0 + 1e-9, 1.0 + 1e-6, -1.0, 1e10, 1e10 + 1.0,
+            f64::INFINITY, f64::NEG_INFINITY, f64::NAN, f64::MAX, f64::MIN_POSITIVE,
+        ];
+        let abs = abs_diff_eq(1e-9);
+        let rel = relative_eq(1e-12, 1e-9);
+        let ulps = ulps_eq(1e-12, 4);
+        for &x in &values {
+            for &y in &values {
+                assert_eq!(abs(x, y).0 <= 1.0, approx::abs_diff_eq!(x, y, epsilon = 1e-9), "abs {} {}", x, y);
+                assert_eq!(
+                    rel(x, y).0 <= 1.0,
+                    approx::relative_eq!(x, y, epsilon = 1e-12, max_relative = 1e-9),
+                    "rel {} {}",
+                    x,
+                    y
+                );
+                assert_eq!(ulps(x, y).0 <= 1.0, approx::ulps_eq!(x, y, epsilon = 1e-12, max_ulps = 4), "ulps {} {}", x, y);
+            }
+        }
+
+        let mut summary = DiffSummary::new("approx", 1.0, true, 4, &rel);
+        summary.add(1.0, 1.0 + 1e-10, 0);
+        summary.add(1e10, 1e10 + 1.0, 1);
+        assert!(summary.is_ok());
+        summary.add(1.0, 1.0 + 1e-6, 2);
+        assert_eq!(summary.fail_count(), 1);
+    }
+
+    #[test]
+    fn test_approx_value() {
+        let x = ApproxValue::new(1.0, &diff::diff_ulps);
+        let y = ApproxValue::new(1.0 + 2.0 * f64::EPSILON, &diff::diff_ulps);
+        approx::assert_abs_diff_eq!(x, y, epsilon = 2.0);
+        assert!(approx::abs_diff_ne!(x, y, epsilon = 1.0));
+        assert_ne!(x, y);
+        assert_eq!(x, ApproxValue::new(1.0, &diff::diff_abs));
+        assert!(approx::abs_diff_ne!(ApproxValue::new(-0.0, &diff::diff_abs), ApproxValue::new(0.0, &diff::diff_abs)));
+        assert_eq!(format!("{:?}", y), "1.0000000000000004e0");
+    }
+
+    #[test]
+    fn test_fd_assert_macros() {
+        let (x, y) = (1.0, 1.0 + 1e-12);
+        fd_assert_abs_diff_eq!(x, y, epsilon = 1e-9);
+        fd_assert_abs_diff_eq!(0.1 + 0.2, 0.3);
+        fd_assert_relative_eq!(x, y, max_relative = 1e-9);
+        fd_assert_relative_eq!(x, y, epsilon = 1e-15, max_relative = 1e-9,);
+        fd_assert_ulps_eq!(1.0, 1.0 + 2.0 * f64::EPSILON);
+        fd_assert_ulps_eq!(1.0f32, 1.0f64, epsilon = 0.0, max_ulps = 0);
+        let failed = catch_unwind(AssertUnwindSafe(|| fd_assert_relative_eq!(x, y)));
+        let message = failed.unwrap_err().downcast::<String>().unwrap();
+        assert!(message.starts_with("assert failed item 0, x vs y: 1e0 vs 1.000000000001e0 diff abs "), "{}", message);
+        fd_assert_relative_eq!(x, y, max_relative = 1e-9, epsilon = 1e-15);
+        fd_assert_ulps_eq!(x, y, name = "approx_compat/ulps", max_ulps = 10_000, epsilon = 0.0);
+
+        // f32 operands default to f32::EPSILON, as in approx.
+        let (a, b) = (1.0f32, 1.0f32 + f32::EPSILON);
+        fd_assert_abs_diff_eq!(0.1f32 + 0.2f32, 0.3f32);
+        fd_assert_relative_eq!(a, b, epsilon = 0.0f32);
+        assert!(catch_unwind(|| fd_assert_relative_eq!(a, b + f32::EPSILON, epsilon = 0.0f32)).is_err());
+
+        fd_assert_abs_diff_eq!(x, y, name = "approx_compat/abs", epsilon = 1e-9);
+        let registered = crate::registry::entries().into_iter().filter(|entry| entry.name == "approx_compat/abs").count();
+        assert_eq!(registered, 1);
+    }
+}
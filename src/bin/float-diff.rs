@@ -0,0 +1,93 @@
+// Command line tools for float-diff results.
+//   float-diff report [--format=stable-v1] <dir> [<pattern>]
+// prints the consolidated table of the accumulation files in dir, as
+// written by tests run with FLOAT_DIFF_ACCUMULATE_DIR=<dir>, and exits
+// with status 1 if any channel failed. If given, only channels whose
+// hierarchical names match pattern, e.g. "geodesic/inverse/*", are included,
+// and it exits with status 2 if none match, as for a misspelled pattern.
+// With --format=stable-v1, channels are printed in the frozen machine
+// format documented in the machine module, for parsing by other tools.
+//   float-diff trend [--csv] <file> [<runs>]
+// prints per-channel sparklines, or CSV, of the last runs (default 10) in
+// a trend file, as written by trend::append.
+use float_diff::{accumulate, machine, trend};
+use std::process::exit;
+
+const USAGE: &str = "usage: float-diff report [--format=stable-v1] <dir> [<pattern>]\n       float-diff trend [--csv] <file> [<runs>]";
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    match args.iter().map(String::as_str).collect::<Vec<_>>().as_slice() {
+        ["report", rest @ ..] => {
+            let (machine_format, rest) = match rest {
+                [format, rest @ ..] if format.starts_with("--format=") => {
+                    if format["--format=".len()..] != *machine::STABLE_V1 {
+                        usage();
+                    }
+                    (true, rest)
+                }
+                _ => (false, rest),
+            };
+            let (dir, pattern) = match rest {
+                [dir] => (*dir, None),
+                [dir, pattern] => (*dir, Some(*pattern)),
+                _ => usage(),
+            };
+            match accumulate::report(dir) {
+                Ok(report) => {
+                    let report = match pattern {
+                        Some(pattern) => {
+                            let filtered = report.filter(pattern);
+                            if filtered.entries.is_empty() {
+                                eprintln!("float-diff: {}: no channels match {}", dir, pattern);
+                                exit(2);
+                            }
+                            filtered
+                        }
+                        None => report,
+                    };
+                    if machine_format {
+                        print!("{}", report.fmt_machine());
+                    } else {
+                        println!("{}", report);
+                    }
+                    if !report.is_ok() {
+                        exit(1);
+                    }
+                }
+                Err(err) => {
+                    eprintln!("float-diff: {}: {}", dir, err);
+                    exit(2);
+                }
+            }
+        }
+        ["trend", rest @ ..] => {
+            let (csv, rest) = match rest {
+                ["--csv", rest @ ..] => (true, rest),
+                _ => (false, rest),
+            };
+            let (path, runs) = match rest {
+                [path] => (*path, 10),
+                [path, runs] => match runs.parse() {
+                    Ok(runs) => (*path, runs),
+                    Err(_) => usage(),
+                },
+                _ => usage(),
+            };
+            match trend::trend(path, runs) {
+                Ok(trend) if csv => print!("{}", trend.to_csv()),
+                Ok(trend) => println!("{}", trend),
+                Err(err) => {
+                    eprintln!("float-diff: {}: {}", path, err);
+                    exit(2);
+                }
+            }
+        }
+        _ => usage(),
+    }
+}
+
+fn usage() -> ! {
+    eprintln!("{}", USAGE);
+    exit(2);
+}
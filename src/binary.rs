@@ -0,0 +1,363 @@
+// A compact binary format for persisting results, for nightly archives
+// where text is bulky, such as histograms of millions of ulps differences
+// or large golden files.
+// Every binary file or buffer starts with MAGIC, then FORMAT_VERSION, so
+// that readers can tell binary from text files and refuse newer formats.
+// After the header, counts are LEB128 varints, signed integers are
+// zigzag-encoded varints, f64 values are their little-endian bits, and
+// strings are a varint byte length followed by UTF-8.
+// Accumulation files are written in binary when ACCUMULATE_FORMAT_ENV is
+// "binary", and trend and golden files when their path has EXTENSION.
+// Their loaders accept either format.
+use std::collections::BTreeMap;
+use std::fs::OpenOptions;
+use std::io::{self, Write};
+use std::path::Path;
+use std::sync::Mutex;
+use crate::input_error::DiffInputError;
+use crate::log_histogram::LogHistogram;
+use crate::registry::RegistryEntry;
+
+// The leading bytes of binary data. The first byte can't start UTF-8
+// text followed by "FDB", so no text file is mistaken for binary.
+pub const MAGIC: [u8; 4] = *b"\xF0FDB";
+
+// The version of the format, following MAGIC.
+pub const FORMAT_VERSION: u8 = 1;
+
+// The extension of trend and golden files written in binary.
+pub const EXTENSION: &str = "fdb";
+
+// The environment variable selecting the accumulation file format, "text"
+// (the default) or "binary".
+pub const ACCUMULATE_FORMAT_ENV: &str = "FLOAT_DIFF_ACCUMULATE_FORMAT";
+
+// Serializes appends, so that concurrent threads can't both find a file
+// empty and both write a header.
+static APPEND_LOCK: Mutex<()> = Mutex::new(());
+
+// Indicate whether data starts with a binary header, of any version.
+pub fn is_binary(bytes: &[u8]) -> bool {
+    bytes.starts_with(&MAGIC)
+}
+
+// Indicate whether path has the binary extension.
+pub fn has_extension(path: &Path) -> bool {
+    path.extension().is_some_and(|extension| extension == EXTENSION)
+}
+
+// Encode snapshots, with a header.
+pub fn encode_entries(entries: &[RegistryEntry]) -> Vec<u8> {
+    let mut writer = Writer::with_header();
+    for entry in entries {
+        writer.entry(entry);
+    }
+    writer.bytes
+}
+
+// Decode snapshots encoded by encode_entries. source names the data in errors.
+pub fn decode_entries(bytes: &[u8], source: &str) -> Result<Vec<RegistryEntry>, DiffInputError> {
+    let mut reader = Reader::new(bytes, source)?;
+    let mut entries = Vec::new();
+    while !reader.is_done() {
+        entries.push(reader.entry()?);
+    }
+    Ok(entries)
+}
+
+// Encode a histogram's configuration and counts, with a header.
+// Cached reductions aren't stored, and are recalculated when needed.
+pub fn encode_histogram(histo: &LogHistogram) -> Vec<u8> {
+    let mut writer = Writer::with_header();
+    writer.histogram(histo);
+    writer.bytes
+}
+
+// Decode a histogram encoded by encode_histogram.
+pub fn decode_histogram(bytes: &[u8], source: &str) -> Result<LogHistogram, DiffInputError> {
+    let mut reader = Reader::new(bytes, source)?;
+    let histo = reader.histogram()?;
+    reader.finish()?;
+    Ok(histo)
+}
+
+// Append what write adds to a Writer to the binary file at path, after a
+// header if the file is empty. Each append is a single write.
+pub(crate) fn append(path: &Path, write: impl FnOnce(&mut Writer)) -> io::Result<()> {
+    let _guard = APPEND_LOCK.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    let mut writer = if file.metadata()?.len() == 0 { Writer::with_header() } else { Writer::new() };
+    write(&mut writer);
+    file.write_all(&writer.bytes)
+}
+
+pub(crate) struct Writer {
+    pub bytes: Vec<u8>,
+}
+
+impl Writer {
+    pub fn new() -> Self {
+        Writer { bytes: Vec::new() }
+    }
+
+    pub fn with_header() -> Self {
+        let mut writer = Writer::new();
+        writer.bytes.extend_from_slice(&MAGIC);
+        writer.bytes.push(FORMAT_VERSION);
+        writer
+    }
+
+    pub fn usize(&mut self, mut value: usize) {
+        while value >= 0x80 {
+            self.bytes.push(value as u8 | 0x80);
+            value >>= 7;
+        }
+        self.bytes.push(value as u8);
+    }
+
+    pub fn isize(&mut self, value: isize) {
+        self.usize(((value << 1) ^ (value >> (isize::BITS - 1))) as usize);
+    }
+
+    pub fn f64(&mut self, value: f64) {
+        self.bytes.extend_from_slice(&value.to_bits().to_le_bytes());
+    }
+
+    pub fn bool(&mut self, value: bool) {
+        self.bytes.push(value as u8);
+    }
+
+    pub fn str(&mut self, value: &str) {
+        self.usize(value.len());
+        self.bytes.extend_from_slice(value.as_bytes());
+    }
+
+    pub fn entry(&mut self, entry: &RegistryEntry) {
+        self.str(&entry.name);
+        self.usize(entry.count);
+        self.usize(entry.fail_count);
+        self.usize(entry.sign_count);
+        self.f64(entry.worst_diff);
+        self.f64(entry.allow_diff);
+        self.f64(entry.worst_score);
+        self.bool(entry.is_ok);
+    }
+
+    fn histogram(&mut self, histo: &LogHistogram) {
+        self.usize(histo.max_display_buckets);
+        self.bool(histo.signed);
+        self.f64(histo.negligible);
+        self.bool(histo.boundary.is_some());
+        self.f64(histo.boundary.unwrap_or(0.0));
+        for &count in &[histo.num_nan, histo.num_inf, histo.num_neg_inf, histo.num_zero, histo.num_negligible, histo.num_above_boundary] {
+            self.usize(count);
+        }
+        for buckets in &[&histo.log10_buckets, &histo.log10_buckets_neg] {
+            self.usize(buckets.len());
+            for (&exp, &count) in buckets.iter() {
+                self.isize(exp);
+                self.usize(count);
+            }
+        }
+    }
+}
+
+pub(crate) struct Reader<'b> {
+    bytes: &'b [u8],
+    pos: usize,
+    source: &'b str,
+}
+
+impl<'b> Reader<'b> {
+    // Read data starting with a header, checking its magic and version.
+    pub fn new(bytes: &'b [u8], source: &'b str) -> Result<Self, DiffInputError> {
+        let mut reader = Reader { bytes, pos: 0, source };
+        if !is_binary(bytes) {
+            return Err(reader.error("not float-diff binary data"));
+        }
+        reader.pos = MAGIC.len();
+        if reader.byte()? != FORMAT_VERSION {
+            reader.pos -= 1;
+            return Err(reader.error("unsupported format version"));
+        }
+        Ok(reader)
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.pos == self.bytes.len()
+    }
+
+    pub fn finish(&self) -> Result<(), DiffInputError> {
+        match self.is_done() {
+            true => Ok(()),
+            false => Err(self.error("unexpected trailing data")),
+        }
+    }
+
+    fn error(&self, message: &'static str) -> DiffInputError {
+        DiffInputError::Binary { source: self.source.to_string(), offset: self.pos, message }
+    }
+
+    fn byte(&mut self) -> Result<u8, DiffInputError> {
+        let byte = *self.bytes.get(self.pos).ok_or_else(|| self.error("unexpected end of data"))?;
+        self.pos += 1;
+        Ok(byte)
+    }
+
+    fn take(&mut self, len: usize) -> Result<&'b [u8], DiffInputError> {
+        if self.bytes.len() - self.pos < len {
+            return Err(self.error("unexpected end of data"));
+        }
+        self.pos += len;
+        Ok(&self.bytes[self.pos - len..self.pos])
+    }
+
+    pub fn usize(&mut self) -> Result<usize, DiffInputError> {
+        let mut value = 0usize;
+        let mut shift = 0;
+        loop {
+            let byte = self.byte()?;
+            if shift >= usize::BITS || (shift > 0 && (byte & 0x7f) as usize >> (usize::BITS - shift) != 0) {
+                return Err(self.error("integer overflow"));
+            }
+            value |= ((byte & 0x7f) as usize) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+            shift += 7;
+        }
+    }
+
+    pub fn isize(&mut self) -> Result<isize, DiffInputError> {
+        let value = self.usize()?;
+        Ok((value >> 1) as isize ^ -((value & 1) as isize))
+    }
+
+    pub fn f64(&mut self) -> Result<f64, DiffInputError> {
+        let mut word = [0u8; 8];
+        word.copy_from_slice(self.take(8)?);
+        Ok(f64::from_bits(u64::from_le_bytes(word)))
+    }
+
+    pub fn bool(&mut self) -> Result<bool, DiffInputError> {
+        match self.byte()? {
+            0 => Ok(false),
+            1 => Ok(true),
+            _ => {
+                self.pos -= 1;
+                Err(self.error("invalid bool"))
+            }
+        }
+    }
+
+    pub fn str(&mut self) -> Result<String, DiffInputError> {
+        let len = self.usize()?;
+        let bytes = self.take(len)?;
+        String::from_utf8(bytes.to_vec()).map_err(|_| self.error("invalid UTF-8"))
+    }
+
+    pub fn entry(&mut self) -> Result<RegistryEntry, DiffInputError> {
+        let mut entry = RegistryEntry {
+            name: self.str()?,
+            count: self.usize()?,
+            fail_count: self.usize()?,
+            sign_count: self.usize()?,
+            worst_diff: self.f64()?,
+            allow_diff: self.f64()?,
+            worst_score: self.f64()?,
+            is_ok: self.bool()?,
+            summary: String::new(),
+        };
+        entry.summary = entry.line();
+        Ok(entry)
+    }
+
+    fn histogram(&mut self) -> Result<LogHistogram, DiffInputError> {
+        let max_display_buckets = self.usize()?;
+        if max_display_buckets < 3 {
+            return Err(self.error("invalid bucket count"));
+        }
+        let mut histo = match self.bool()? {
+            true => LogHistogram::new_signed(max_display_buckets),
+            false => LogHistogram::new(max_display_buckets),
+        };
+        histo.negligible = self.f64()?;
+        let has_boundary = self.bool()?;
+        let boundary = self.f64()?;
+        histo.boundary = if has_boundary { Some(boundary) } else { None };
+        histo.num_nan = self.usize()?;
+        histo.num_inf = self.usize()?;
+        histo.num_neg_inf = self.usize()?;
+        histo.num_zero = self.usize()?;
+        histo.num_negligible = self.usize()?;
+        histo.num_above_boundary = self.usize()?;
+        histo.log10_buckets = self.buckets()?;
+        histo.log10_buckets_neg = self.buckets()?;
+        Ok(histo)
+    }
+
+    fn buckets(&mut self) -> Result<BTreeMap<isize, usize>, DiffInputError> {
+        let len = self.usize()?;
+        let mut buckets = BTreeMap::new();
+        for _ in 0..len {
+            let exp = self.isize()?;
+            buckets.insert(exp, self.usize()?);
+        }
+        Ok(buckets)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{decode_entries, decode_histogram, encode_entries, encode_histogram, is_binary, Reader, Writer, FORMAT_VERSION};
+    use crate::diff;
+    use crate::diff_summary_f64::DiffSummary;
+    use crate::log_histogram::LogHistogram;
+    use crate::registry::RegistryEntry;
+
+    #[test]
+    fn test_binary() {
+        let mut writer = Writer::with_header();
+        let ints = [0, 1, 127, 128, 300, usize::MAX];
+        for &value in &ints {
+            writer.usize(value);
+        }
+        for &value in &[0, -1, 1, isize::MIN, isize::MAX] {
+            writer.isize(value);
+        }
+        writer.str("ünïcode\tname");
+        let mut reader = Reader::new(&writer.bytes, "ints").unwrap();
+        assert_eq!(ints.iter().map(|_| reader.usize().unwrap()).collect::<Vec<_>>(), ints);
+        assert_eq!((0..5).map(|_| reader.isize().unwrap()).collect::<Vec<_>>(), vec![0, -1, 1, isize::MIN, isize::MAX]);
+        assert_eq!(reader.str().unwrap(), "ünïcode\tname");
+        assert!(reader.is_done());
+
+        let mut summary = DiffSummary::new("pressure", 0.1, false, 4, &diff::diff_abs);
+        summary.add(1.0, 1.5, 0);
+        summary.add(f64::NAN, 1.0, 1);
+        let entries = vec![RegistryEntry::new(&summary), RegistryEntry::new(&DiffSummary::new("phase", 1e-9, true, 4, &diff::diff_abs))];
+        let bytes = encode_entries(&entries);
+        assert!(is_binary(&bytes) && bytes.len() < 80, "{}", bytes.len());
+        let decoded = decode_entries(&bytes, "entries").unwrap();
+        assert_eq!(decoded[0].worst_diff.to_bits(), entries[0].worst_diff.to_bits());
+        assert_eq!((decoded[1].name.as_str(), decoded[1].allow_diff, decoded[1].is_ok), ("phase", 1e-9, true));
+        assert_eq!(decoded[0].summary, entries[0].line());
+
+        let mut histo = LogHistogram::new_signed(5).with_negligible(1e-300).with_boundary(0.5);
+        for &diff in &[0.0, 1e-301, 0.75, -3.0e-9, 2.0e100, f64::NAN, f64::NEG_INFINITY] {
+            histo.add(diff);
+        }
+        let decoded = decode_histogram(&encode_histogram(&histo), "histo").unwrap();
+        assert_eq!(decoded.to_string(), histo.to_string());
+        assert_eq!(decoded.bucket_counts(), histo.bucket_counts());
+        assert_eq!((decoded.boundary(), decoded.count_above_boundary()), (Some(0.5), 1));
+
+        let err = decode_entries(b"pressure\t1\n", "notes.txt").unwrap_err();
+        assert_eq!(err.to_string(), "notes.txt byte 0: not float-diff binary data");
+        let mut newer = bytes.clone();
+        newer[4] = FORMAT_VERSION + 1;
+        assert_eq!(decode_entries(&newer, "newer").unwrap_err().to_string(), "newer byte 4: unsupported format version");
+        let err = decode_entries(&bytes[..bytes.len() - 3], "cut").unwrap_err();
+        assert!(err.to_string().ends_with("unexpected end of data"), "{}", err);
+    }
+}
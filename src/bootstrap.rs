@@ -0,0 +1,138 @@
+use crate::util::mix64;
+
+// Streaming bootstrap confidence intervals for a summary's mean difference
+// and failure rate, for noisy comparisons such as of Monte Carlo results.
+// Rather than resampling retained data, each item is given a Poisson(1)
+// weight in each replicate, which approximates resampling with replacement
+// while using memory proportional only to the number of replicates.
+// Weights are derived from the seed, the item's index and the replicate,
+// so results are reproducible, and don't depend on how a dataset is split
+// between summaries that are later merged.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Bootstrap {
+    seed: u64,
+    replicates: Vec<Replicate>,
+}
+
+// The weighted statistics of one bootstrap replicate.
+#[derive(Clone, Copy, Debug, PartialEq, Default)]
+struct Replicate {
+    // The total weight of all items, and of items with finite differences.
+    weight: f64,
+    finite_weight: f64,
+    // The weighted sum of finite differences.
+    sum_diff: f64,
+    // The total weight of items outside tolerance.
+    fail_weight: f64,
+}
+
+impl Bootstrap {
+    pub fn new(replicate_count: usize, seed: u64) -> Self {
+        assert!(replicate_count >= 2, "bootstrap requires at least 2 replicates, got {}", replicate_count);
+        Bootstrap { seed, replicates: vec![Replicate::default(); replicate_count] }
+    }
+
+    pub fn add(&mut self, index: usize, diff: f64, failed: bool) {
+        let item_seed = mix64(self.seed ^ index as u64);
+        for (i, replicate) in self.replicates.iter_mut().enumerate() {
+            let weight = poisson_weight(mix64(item_seed ^ i as u64));
+            if weight == 0.0 {
+                continue;
+            }
+            replicate.weight += weight;
+            if diff.is_finite() {
+                replicate.finite_weight += weight;
+                replicate.sum_diff += weight * diff;
+            }
+            if failed {
+                replicate.fail_weight += weight;
+            }
+        }
+    }
+
+    // Combine another bootstrap's replicates into this one.
+    // Both must have the same seed and number of replicates.
+    pub fn merge(&mut self, other: &Bootstrap) {
+        assert!(
+            self.seed == other.seed && self.replicates.len() == other.replicates.len(),
+            "Cannot merge bootstraps with different seeds or replicate counts"
+        );
+        for (replicate, other) in self.replicates.iter_mut().zip(&other.replicates) {
+            replicate.weight += other.weight;
+            replicate.finite_weight += other.finite_weight;
+            replicate.sum_diff += other.sum_diff;
+            replicate.fail_weight += other.fail_weight;
+        }
+    }
+
+    pub fn replicate_count(&self) -> usize {
+        self.replicates.len()
+    }
+
+    // The (low, high) percentile interval of the mean finite difference at
+    // the given confidence, e.g. 0.95, or nans if there were no finite differences.
+    pub fn mean_diff_interval(&self, confidence: f64) -> (f64, f64) {
+        interval(self.replicates.iter().map(|replicate| replicate.sum_diff / replicate.finite_weight), confidence)
+    }
+
+    // The (low, high) percentile interval of the fraction of items outside
+    // tolerance, from 0 to 1, at the given confidence, or nans if there were no items.
+    pub fn fail_rate_interval(&self, confidence: f64) -> (f64, f64) {
+        interval(self.replicates.iter().map(|replicate| replicate.fail_weight / replicate.weight), confidence)
+    }
+}
+
+// Sample a Poisson(1) weight from 64 random bits, by inverting its CDF.
+fn poisson_weight(bits: u64) -> f64 {
+    let u = (bits >> 11) as f64 / (1u64 << 53) as f64;
+    let mut probability = (-1.0f64).exp();
+    let mut cdf = probability;
+    let mut k = 0;
+    // Weights above 20 have negligible probability.
+    while u >= cdf && k < 20 {
+        k += 1;
+        probability /= k as f64;
+        cdf += probability;
+    }
+    k as f64
+}
+
+// The central interval holding the given fraction of the values, ignoring nans.
+fn interval(values: impl Iterator<Item = f64>, confidence: f64) -> (f64, f64) {
+    assert!(confidence > 0.0 && confidence < 1.0, "confidence must be within (0, 1), got {}", confidence);
+    let mut values: Vec<f64> = values.filter(|value| !value.is_nan()).collect();
+    if values.is_empty() {
+        return (f64::NAN, f64::NAN);
+    }
+    values.sort_by(|a, b| a.partial_cmp(b).unwrap());
+    let tail = (1.0 - confidence) / 2.0;
+    let last = (values.len() - 1) as f64;
+    (values[(tail * last).floor() as usize], values[((1.0 - tail) * last).ceil() as usize])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{poisson_weight, Bootstrap};
+    use crate::util::mix64;
+
+    #[test]
+    fn test_bootstrap() {
+        let mean_weight = (0..10000).map(|i| poisson_weight(mix64(i))).sum::<f64>() / 10000.0;
+        assert!((mean_weight - 1.0).abs() < 0.05, "{}", mean_weight);
+
+        let mut bootstrap = Bootstrap::new(200, 7);
+        let (mut first, mut second) = (Bootstrap::new(200, 7), Bootstrap::new(200, 7));
+        for i in 0..1000 {
+            let diff = (i % 10) as f64 * 0.1;
+            bootstrap.add(i, diff, diff > 0.75);
+            if i % 3 == 0 { &mut first } else { &mut second }.add(i, diff, diff > 0.75);
+        }
+        let (lo, hi) = bootstrap.mean_diff_interval(0.95);
+        assert!(lo < 0.45 && 0.45 < hi && hi - lo < 0.1, "{} {}", lo, hi);
+        let (lo, hi) = bootstrap.fail_rate_interval(0.95);
+        assert!(lo < 0.2 && 0.2 < hi && hi - lo < 0.1, "{} {}", lo, hi);
+        first.merge(&second);
+        assert_eq!(first.fail_rate_interval(0.9), bootstrap.fail_rate_interval(0.9));
+        assert!(Bootstrap::new(10, 1).mean_diff_interval(0.95).0.is_nan());
+    }
+}
@@ -0,0 +1,269 @@
+use num_traits::ToPrimitive;
+use std::fmt::Display;
+use std::ops::Deref;
+use std::time::{Duration, Instant};
+use crate::diff::Direction;
+use crate::diff_summary_f64::{DiffSummary, LengthPolicy, PassingExample, Quantization, Sampling, SignLimit, SignedZeroPolicy, SummarySnapshot};
+use crate::input_error::DiffInputError;
+
+// A DiffSummary whose memory use is bounded independent of the number of
+// items added, for streaming effectively unbounded data, such as from a
+// long-running fuzzer.
+// Only options with fixed-size state can be enabled, so the options that
+// retain items (with_failure_capacity, with_top_n, with_reservoir and
+// with_bucket_samples), take unbounded configuration (with_ranges,
+// with_view and with_metadata), or write every item out (with_csv_tee,
+// with_spill and with_trace) aren't available through this type.
+// The histogram holds at most one bucket per f64 decimal exponent.
+// Read access to the underlying summary is through Deref.
+pub struct BoundedDiffSummary<'a> {
+    summary: DiffSummary<'a>,
+}
+
+impl<'a> BoundedDiffSummary<'a> {
+    pub fn new(name: &'a str, allow_diff: f64, allow_sign: bool, bucket_count: usize, calc_diff: &'a dyn Fn(f64, f64) -> (f64, bool)) -> Self {
+        BoundedDiffSummary { summary: DiffSummary::new(name, allow_diff, allow_sign, bucket_count, calc_diff) }
+    }
+
+    // Apply a builder option to the underlying summary.
+    fn with(self, build: impl FnOnce(DiffSummary<'a>) -> DiffSummary<'a>) -> Self {
+        BoundedDiffSummary { summary: build(self.summary) }
+    }
+
+    // As DiffSummary::with_warn_diff.
+    pub fn with_warn_diff(self, warn_diff: f64) -> Self {
+        self.with(|summary| summary.with_warn_diff(warn_diff))
+    }
+
+    // As DiffSummary::with_sign_epsilon.
+    pub fn with_sign_epsilon(self, sign_epsilon: f64) -> Self {
+        self.with(|summary| summary.with_sign_epsilon(sign_epsilon))
+    }
+
+    // As DiffSummary::with_allow_fail_count.
+    pub fn with_allow_fail_count(self, count: usize) -> Self {
+        self.with(|summary| summary.with_allow_fail_count(count))
+    }
+
+    // As DiffSummary::with_allow_fail_fraction.
+    pub fn with_allow_fail_fraction(self, fraction: f64) -> Self {
+        self.with(|summary| summary.with_allow_fail_fraction(fraction))
+    }
+
+    // As DiffSummary::with_sign_limit.
+    pub fn with_sign_limit(self, limit: SignLimit) -> Self {
+        self.with(|summary| summary.with_sign_limit(limit))
+    }
+
+    // As DiffSummary::with_signed_zero_policy.
+    pub fn with_signed_zero_policy(self, policy: SignedZeroPolicy) -> Self {
+        self.with(|summary| summary.with_signed_zero_policy(policy))
+    }
+
+    // As DiffSummary::with_zero_epsilon.
+    pub fn with_zero_epsilon(self, zero_epsilon: f64) -> Self {
+        self.with(|summary| summary.with_zero_epsilon(zero_epsilon))
+    }
+
+    // As DiffSummary::with_strict_mode.
+    pub fn with_strict_mode(self) -> Self {
+        self.with(|summary| summary.with_strict_mode())
+    }
+
+    // As DiffSummary::with_negligible_diff.
+    pub fn with_negligible_diff(self, negligible: f64) -> Self {
+        self.with(|summary| summary.with_negligible_diff(negligible))
+    }
+
+    // As DiffSummary::with_tolerance_boundary.
+    pub fn with_tolerance_boundary(self) -> Self {
+        self.with(|summary| summary.with_tolerance_boundary())
+    }
+
+    // As DiffSummary::with_quantization.
+    pub fn with_quantization(self, quantization: Quantization) -> Self {
+        self.with(|summary| summary.with_quantization(quantization))
+    }
+
+    // As DiffSummary::with_sampling.
+    pub fn with_sampling(self, sampling: Sampling) -> Self {
+        self.with(|summary| summary.with_sampling(sampling))
+    }
+
+    // As DiffSummary::with_deadline.
+    pub fn with_deadline(self, deadline: Instant) -> Self {
+        self.with(|summary| summary.with_deadline(deadline))
+    }
+
+    // As DiffSummary::with_timeout.
+    pub fn with_timeout(self, timeout: Duration) -> Self {
+        self.with(|summary| summary.with_timeout(timeout))
+    }
+
+    // As DiffSummary::with_max_items.
+    pub fn with_max_items(self, max_items: usize) -> Self {
+        self.with(|summary| summary.with_max_items(max_items))
+    }
+
+    // As DiffSummary::with_linear_fit.
+    pub fn with_linear_fit(self, allow_slope_deviation: f64) -> Self {
+        self.with(|summary| summary.with_linear_fit(allow_slope_deviation))
+    }
+
+    // As DiffSummary::with_drift. The segment count is fixed up front.
+    pub fn with_drift(self, segment_count: usize, index_count: usize) -> Self {
+        self.with(|summary| summary.with_drift(segment_count, index_count))
+    }
+
+    // As DiffSummary::with_bootstrap. Memory is fixed by the replicate count.
+    pub fn with_bootstrap(self, replicate_count: usize, seed: u64) -> Self {
+        self.with(|summary| summary.with_bootstrap(replicate_count, seed))
+    }
+
+    // As DiffSummary::with_extremes.
+    pub fn with_extremes(self) -> Self {
+        self.with(|summary| summary.with_extremes())
+    }
+
+    // As DiffSummary::with_class_strata. There are at most 12 classes.
+    pub fn with_class_strata(self) -> Self {
+        self.with(|summary| summary.with_class_strata())
+    }
+
+    // As DiffSummary::with_tolerance_suggestion.
+    pub fn with_tolerance_suggestion(self, margin: f64) -> Self {
+        self.with(|summary| summary.with_tolerance_suggestion(margin))
+    }
+
+    // As DiffSummary::with_error_model.
+    pub fn with_error_model(self) -> Self {
+        self.with(|summary| summary.with_error_model())
+    }
+
+    // As DiffSummary::with_passing_example. At most PASSING_POOL items are sampled.
+    pub fn with_passing_example(self, example: PassingExample) -> Self {
+        self.with(|summary| summary.with_passing_example(example))
+    }
+
+    // As DiffSummary::with_fingerprint.
+    pub fn with_fingerprint(self) -> Self {
+        self.with(|summary| summary.with_fingerprint())
+    }
+
+    // As DiffSummary::with_dataset_id.
+    pub fn with_dataset_id(self, id: &str) -> Self {
+        self.with(|summary| summary.with_dataset_id(id))
+    }
+
+    // As DiffSummary::with_convergence.
+    pub fn with_convergence(self, min_order: f64) -> Self {
+        self.with(|summary| summary.with_convergence(min_order))
+    }
+
+    // As DiffSummary::with_convergence_by.
+    pub fn with_convergence_by(self, min_order: f64, step: &'a dyn Fn(usize) -> f64) -> Self {
+        self.with(|summary| summary.with_convergence_by(min_order, step))
+    }
+
+    // As DiffSummary::with_triage.
+    pub fn with_triage(self, near_zero: f64, large: f64) -> Self {
+        self.with(|summary| summary.with_triage(near_zero, large))
+    }
+
+    // As DiffSummary::with_direction.
+    pub fn with_direction(self, calc_direction: &'a dyn Fn(f64, f64) -> Option<Direction>) -> Self {
+        self.with(|summary| summary.with_direction(calc_direction))
+    }
+
+    // As DiffSummary::with_unit.
+    pub fn with_unit(self, unit: &'a str) -> Self {
+        self.with(|summary| summary.with_unit(unit))
+    }
+
+    // As DiffSummary::with_publisher.
+    pub fn with_publisher(self, publish: &'a dyn Fn(&SummarySnapshot), worst_delta: f64, fail_delta: usize) -> Self {
+        self.with(|summary| summary.with_publisher(publish, worst_delta, fail_delta))
+    }
+
+    // As DiffSummary::add.
+    pub fn add<X: ToPrimitive, Y: ToPrimitive>(&mut self, x: X, y: Y, index: usize) {
+        self.summary.add(x, y, index);
+    }
+
+    // As DiffSummary::add_slices.
+    pub fn add_slices<X: ToPrimitive + Copy, Y: ToPrimitive + Copy>(&mut self, xs: &[X], ys: &[Y]) {
+        self.summary.add_slices(xs, ys);
+    }
+
+    // As DiffSummary::add_iter.
+    pub fn add_iter<X: ToPrimitive, Y: ToPrimitive, I: IntoIterator<Item = (X, Y)>>(&mut self, items: I) {
+        self.summary.add_iter(items);
+    }
+
+    // As DiffSummary::add_streams.
+    pub fn add_streams<X, Y, IX, IY>(&mut self, xs: IX, ys: IY, policy: LengthPolicy) -> Result<(), DiffInputError>
+    where
+        X: ToPrimitive,
+        Y: ToPrimitive,
+        IX: IntoIterator<Item = X>,
+        IY: IntoIterator<Item = Y>,
+    {
+        self.summary.add_streams(xs, ys, policy)
+    }
+
+    // As DiffSummary::set_sample_context.
+    pub fn set_sample_context(&mut self, context: Option<&str>) {
+        self.summary.set_sample_context(context);
+    }
+
+    // As DiffSummary::merge, from another bounded summary.
+    pub fn merge(&mut self, other: &BoundedDiffSummary) {
+        self.summary.merge(&other.summary);
+    }
+
+    // The underlying summary, e.g. to merge into an unbounded one.
+    pub fn into_inner(self) -> DiffSummary<'a> {
+        self.summary
+    }
+}
+
+impl<'a> Deref for BoundedDiffSummary<'a> {
+    type Target = DiffSummary<'a>;
+
+    fn deref(&self) -> &DiffSummary<'a> {
+        &self.summary
+    }
+}
+
+impl Clone for BoundedDiffSummary<'_> {
+    fn clone(&self) -> Self {
+        BoundedDiffSummary { summary: self.summary.clone() }
+    }
+}
+
+impl Display for BoundedDiffSummary<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.summary.fmt(f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::BoundedDiffSummary;
+    use crate::diff;
+    use crate::diff_summary_f64::Sampling;
+
+    #[test]
+    fn test_bounded() {
+        let mut summary = BoundedDiffSummary::new("fuzz", 0.5, false, 4, &diff::diff_abs)
+            .with_warn_diff(0.25)
+            .with_sampling(Sampling::EveryNth(2));
+        summary.add_iter((0..1000).map(|i| (i as f64, i as f64 + (i % 3) as f64 * 0.2)));
+        let mut other = BoundedDiffSummary::new("fuzz", 0.5, false, 4, &diff::diff_abs).with_warn_diff(0.25);
+        other.add(1.0, 2.0, 0);
+        summary.merge(&other);
+        assert_eq!((summary.count(), summary.fail_count(), summary.warn_count()), (501, 1, 167));
+        assert!(!summary.is_ok());
+        assert_eq!(summary.to_string(), summary.clone().into_inner().to_string());
+    }
+}
@@ -0,0 +1,124 @@
+// Adapters for composing comparators, i.e. functions of the form
+// Fn(x: f64, y: f64) -> (diff, sign_change) such as those in the diff module.
+// Each returns a new comparator, which can itself be composed further,
+// and passed to DiffSummary by reference.
+// For example, relative differences of logs, scaled by a column factor:
+// let compare = scale(map(diff::diff_rel, f64::ln), 0.5);
+
+use crate::diff::is_diff_worse;
+
+// Apply f to both x and y before comparing them, e.g. f64::ln to compare logs.
+pub fn map<C, F>(inner: C, f: F) -> impl Fn(f64, f64) -> (f64, bool)
+where
+    C: Fn(f64, f64) -> (f64, bool),
+    F: Fn(f64) -> f64,
+{
+    move |x, y| inner(f(x), f(y))
+}
+
+// Clamp both x and y to [lo, hi] before comparing them.
+// Nan values are left unchanged.
+pub fn clamp<C>(inner: C, lo: f64, hi: f64) -> impl Fn(f64, f64) -> (f64, bool)
+where
+    C: Fn(f64, f64) -> (f64, bool),
+{
+    assert!(lo <= hi, "clamp requires lo <= hi, got [{}, {}]", lo, hi);
+    move |x, y| inner(x.max(lo).min(hi), y.max(lo).min(hi))
+}
+
+// Multiply differences by factor, e.g. to normalize columns with different
+// scales to a common tolerance. The factor must be positive.
+pub fn scale<C>(inner: C, factor: f64) -> impl Fn(f64, f64) -> (f64, bool)
+where
+    C: Fn(f64, f64) -> (f64, bool),
+{
+    assert!(factor > 0.0, "scale requires a positive factor, got {}", factor);
+    move |x, y| {
+        let (diff, sign_change) = inner(x, y);
+        (diff * factor, sign_change)
+    }
+}
+
+// Use fallback rather than primary when both |x| and |y| are below threshold,
+// e.g. falling back from ULPs to absolute differences near zero.
+pub fn fallback<A, B>(primary: A, fallback: B, threshold: f64) -> impl Fn(f64, f64) -> (f64, bool)
+where
+    A: Fn(f64, f64) -> (f64, bool),
+    B: Fn(f64, f64) -> (f64, bool),
+{
+    move |x, y| {
+        if x.abs() < threshold && y.abs() < threshold {
+            fallback(x, y)
+        } else {
+            primary(x, y)
+        }
+    }
+}
+
+// Compare zeros of differing sign, such as 0.0 vs -0.0, as two positive
+// zeros, so that they're neither a sign change nor a difference.
+// As DiffSummary::with_signed_zero_policy, for use outside a summary.
+pub fn ignore_signed_zero<C>(inner: C) -> impl Fn(f64, f64) -> (f64, bool)
+where
+    C: Fn(f64, f64) -> (f64, bool),
+{
+    move |x, y| if x == 0.0 && y == 0.0 { inner(0.0, 0.0) } else { inner(x, y) }
+}
+
+// Take the worse of two comparators' differences, along with its sign change status.
+// Nan is worse than infinity, as elsewhere.
+pub fn max_of<A, B>(a: A, b: B) -> impl Fn(f64, f64) -> (f64, bool)
+where
+    A: Fn(f64, f64) -> (f64, bool),
+    B: Fn(f64, f64) -> (f64, bool),
+{
+    move |x, y| {
+        let (diff_a, diff_b) = (a(x, y), b(x, y));
+        if is_diff_worse(diff_b.0, diff_a.0) { diff_b } else { diff_a }
+    }
+}
+
+// Take the lesser of two comparators' differences, along with its sign change status.
+// Generalizes diff::diff_lesser to arbitrary comparators.
+pub fn min_of<A, B>(a: A, b: B) -> impl Fn(f64, f64) -> (f64, bool)
+where
+    A: Fn(f64, f64) -> (f64, bool),
+    B: Fn(f64, f64) -> (f64, bool),
+{
+    move |x, y| {
+        let (diff_a, diff_b) = (a(x, y), b(x, y));
+        if is_diff_worse(diff_a.0, diff_b.0) { diff_b } else { diff_a }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{clamp, fallback, ignore_signed_zero, map, max_of, min_of, scale};
+    use crate::diff;
+    use crate::diff_summary_f64::DiffSummary;
+
+    #[test]
+    fn test_combinators() {
+        let logs = map(diff::diff_abs, f64::log2);
+        assert_eq!(logs(8.0, 2.0), (2.0, false));
+        let clamped = clamp(diff::diff_abs, 0.0, 1.0);
+        assert_eq!(clamped(5.0, 0.5), (0.5, false));
+        let scaled = scale(diff::diff_abs, 0.5);
+        assert_eq!(scaled(3.0, 2.0), (0.5, false));
+        let near_zero = fallback(diff::diff_ulps, diff::diff_abs, 1e-10);
+        assert_eq!(near_zero(1e-20, 0.0), (1e-20, false));
+        assert_eq!(near_zero(1.0, 1.0), (0.0, false));
+        let worst = max_of(diff::diff_abs, diff::diff_rel);
+        assert_eq!(worst(4.0, 2.0), (2.0, false));
+        assert!(max_of(diff::diff_abs, |_, _| (f64::NAN, false))(1.0, 1.0).0.is_nan());
+        let zeros = ignore_signed_zero(diff::diff_ulps);
+        assert_eq!((zeros(-0.0, 0.0), zeros(-1.0, 1.0).1), ((0.0, false), true));
+        let least = min_of(diff::diff_abs, diff::diff_rel);
+        assert_eq!(least(4.0, 2.0).0, 2.0 / 3.0);
+        // Composed comparators feed a summary like any other.
+        let composed = scale(min_of(diff::diff_abs, map(diff::diff_rel, f64::abs)), 2.0);
+        let mut summary = DiffSummary::new("composed", 1.0, false, 4, &composed);
+        summary.add(-4.0, 2.0, 0);
+        assert_eq!(summary.worst_diff(), 4.0 / 3.0);
+    }
+}
@@ -0,0 +1,291 @@
+// Data-driven comparison settings, so that tolerances can be tuned per
+// channel in a TOML or YAML file rather than in Rust.
+// A TOML file looks like:
+//   [[channels]]
+//   name = "pressure"
+//   comparator = "rel"
+//   tolerance = 1e-9
+//   allow_sign = false
+//   buckets = 5
+//   check_tolerance = true
+// allow_sign defaults to false, buckets to 5, and check_tolerance to true.
+// buckets must be more than 2.
+// Unless check_tolerance is false, loading fails for tolerances whose scale
+// is implausible for the comparator, per ComparatorKind::check.
+use serde::Deserialize;
+use std::fmt::Display;
+use std::path::Path;
+use crate::diff::{self, Direction};
+use crate::diff_summary_f64::DiffSummary;
+use crate::summary_config::DiffSummaryConfig;
+use crate::tolerance_check::{ComparatorKind, ImplausibleTolerance};
+
+// An error loading a comparison config.
+#[derive(Debug)]
+pub enum ConfigError {
+    // The file couldn't be read.
+    Io(std::io::Error),
+    // The text couldn't be parsed, or had the wrong structure.
+    Parse(String),
+    // A channel named a comparator that isn't known.
+    UnknownComparator { channel: String, comparator: String },
+    // A channel's tolerance is implausible for its comparator.
+    ImplausibleTolerance { channel: String, error: ImplausibleTolerance },
+    // A channel has too few histogram buckets, which must be more than 2.
+    InvalidBuckets { channel: String, buckets: usize },
+    // A channel's tolerance is negative or nan, so no item could be within it.
+    InvalidTolerance { channel: String, tolerance: f64 },
+    // The file extension isn't one that an enabled feature can load.
+    UnsupportedFormat(String),
+}
+
+impl Display for ConfigError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ConfigError::Io(err) => write!(f, "config read failed: {}", err),
+            ConfigError::Parse(message) => write!(f, "config parse failed: {}", message),
+            ConfigError::UnknownComparator { channel, comparator } => {
+                write!(f, "channel {} has unknown comparator {}", channel, comparator)
+            }
+            ConfigError::ImplausibleTolerance { channel, error } => write!(f, "channel {}: {}", channel, error),
+            ConfigError::InvalidBuckets { channel, buckets } => {
+                write!(f, "channel {} has {} buckets, but needs more than 2", channel, buckets)
+            }
+            ConfigError::InvalidTolerance { channel, tolerance } => {
+                write!(f, "channel {} has tolerance {:e}, but needs a non-negative one", channel, tolerance)
+            }
+            ConfigError::UnsupportedFormat(extension) => write!(f, "unsupported config format {:?}", extension),
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+impl From<std::io::Error> for ConfigError {
+    fn from(err: std::io::Error) -> Self {
+        ConfigError::Io(err)
+    }
+}
+
+// The settings for one channel's summary.
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ChannelConfig {
+    pub name: String,
+    // One of the names accepted by comparator.
+    pub comparator: String,
+    pub tolerance: f64,
+    #[serde(default)]
+    pub allow_sign: bool,
+    #[serde(default = "default_buckets")]
+    pub buckets: usize,
+    // Whether to reject tolerances that are implausible for the comparator.
+    #[serde(default = "default_check_tolerance")]
+    pub check_tolerance: bool,
+}
+
+fn default_buckets() -> usize {
+    5
+}
+
+fn default_check_tolerance() -> bool {
+    true
+}
+
+// Settings for a set of channels, as loaded from a file.
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ComparisonConfig {
+    pub channels: Vec<ChannelConfig>,
+}
+
+// The comparator with the given name: "abs", "rel", "ulps", "lesser",
+// "log", "percent", "ratio", "degrees", "periodic_degrees", "periodic_radians",
+// or "total_order", per the diff module's diff_* functions.
+pub fn comparator(name: &str) -> Option<&'static dyn Fn(f64, f64) -> (f64, bool)> {
+    let calc_diff: &'static dyn Fn(f64, f64) -> (f64, bool) = match name {
+        "abs" => &diff::diff_abs,
+        "rel" => &diff::diff_rel,
+        "ulps" => &diff::diff_ulps,
+        "lesser" => &diff::diff_lesser,
+        "log" => &diff::diff_log,
+        "percent" => &diff::diff_percent,
+        "ratio" => &diff::diff_ratio,
+        "degrees" => &diff::diff_degrees,
+        "periodic_degrees" => &diff::diff_periodic_degrees,
+        "periodic_radians" => &diff::diff_periodic_radians,
+        "total_order" => &diff::diff_total_order,
+        _ => return None,
+    };
+    Some(calc_diff)
+}
+
+// The direction function to pair with the named comparator: the short way
+// around the circle for the angle comparators, or diff::direction otherwise.
+pub fn direction(name: &str) -> &'static dyn Fn(f64, f64) -> Option<Direction> {
+    match name {
+        "degrees" | "periodic_degrees" => &diff::direction_periodic_degrees,
+        "periodic_radians" => &diff::direction_periodic_radians,
+        _ => &diff::direction,
+    }
+}
+
+impl ComparisonConfig {
+    #[cfg(feature = "toml")]
+    pub fn from_toml_str(text: &str) -> Result<Self, ConfigError> {
+        let config: ComparisonConfig = toml::from_str(text).map_err(|err| ConfigError::Parse(err.to_string()))?;
+        config.validated()
+    }
+
+    #[cfg(feature = "yaml")]
+    pub fn from_yaml_str(text: &str) -> Result<Self, ConfigError> {
+        let config: ComparisonConfig = serde_yaml::from_str(text).map_err(|err| ConfigError::Parse(err.to_string()))?;
+        config.validated()
+    }
+
+    // Load a config file, choosing the format by extension:
+    // .toml with the toml feature, or .yaml/.yml with the yaml feature.
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Result<Self, ConfigError> {
+        let path = path.as_ref();
+        let extension = path.extension().and_then(|extension| extension.to_str()).unwrap_or("");
+        match extension {
+            #[cfg(feature = "toml")]
+            "toml" => ComparisonConfig::from_toml_str(&std::fs::read_to_string(path)?),
+            #[cfg(feature = "yaml")]
+            "yaml" | "yml" => ComparisonConfig::from_yaml_str(&std::fs::read_to_string(path)?),
+            _ => Err(ConfigError::UnsupportedFormat(extension.to_string())),
+        }
+    }
+
+    // Check that every channel names a known comparator, with a plausible
+    // tolerance unless check_tolerance is false.
+    #[cfg(any(feature = "toml", feature = "yaml"))]
+    fn validated(self) -> Result<Self, ConfigError> {
+        for channel in &self.channels {
+            let kind = match ComparatorKind::from_name(&channel.comparator) {
+                Some(kind) => kind,
+                None => {
+                    return Err(ConfigError::UnknownComparator {
+                        channel: channel.name.clone(),
+                        comparator: channel.comparator.clone(),
+                    })
+                }
+            };
+            if channel.buckets <= 2 {
+                return Err(ConfigError::InvalidBuckets { channel: channel.name.clone(), buckets: channel.buckets });
+            }
+            if channel.tolerance.is_nan() || channel.tolerance < 0.0 {
+                return Err(ConfigError::InvalidTolerance { channel: channel.name.clone(), tolerance: channel.tolerance });
+            }
+            if channel.check_tolerance {
+                kind.check(channel.tolerance)
+                    .map_err(|error| ConfigError::ImplausibleTolerance { channel: channel.name.clone(), error })?;
+            }
+        }
+        Ok(self)
+    }
+
+    // Create a DiffSummaryConfig for each channel, in file order, for
+    // callers that want to adjust summaries before creating them.
+    pub fn configs(&self) -> Vec<DiffSummaryConfig<'_>> {
+        self.channels
+            .iter()
+            .map(|channel| {
+                let calc_diff = comparator(&channel.comparator)
+                    .unwrap_or_else(|| panic!("channel {} has unknown comparator {}", channel.name, channel.comparator));
+                DiffSummaryConfig::new(&channel.name, channel.tolerance, channel.allow_sign, channel.buckets, calc_diff)
+                    .with_direction(direction(&channel.comparator))
+            })
+            .collect()
+    }
+
+    // Create a summary for each channel, in file order, as DiffSummary::new_vec does.
+    pub fn summaries(&self) -> Vec<DiffSummary<'_>> {
+        self.configs().iter().map(DiffSummaryConfig::build).collect()
+    }
+}
+
+// Take the summary for the named channel from slots, which holds one
+// entry per channel. Used by the float_diff::test attribute.
+#[doc(hidden)]
+pub fn take_channel<'s, 'a>(slots: &mut [Option<&'s mut DiffSummary<'a>>], name: &str) -> &'s mut DiffSummary<'a> {
+    let position = slots
+        .iter()
+        .position(|slot| slot.as_ref().is_some_and(|summary| summary.name == name))
+        .unwrap_or_else(|| panic!("no unused channel named {} in config", name));
+    slots[position].take().unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    #[allow(unused_imports)]
+    use super::{ComparisonConfig, ConfigError};
+
+    #[cfg(feature = "toml")]
+    #[test]
+    fn test_toml() {
+        let config = ComparisonConfig::from_toml_str(
+            r#"
+            [[channels]]
+            name = "pressure"
+            comparator = "rel"
+            tolerance = 1e-9
+
+            [[channels]]
+            name = "phase"
+            comparator = "abs"
+            tolerance = 0.5
+            allow_sign = true
+            buckets = 3
+            "#,
+        )
+        .unwrap();
+        let mut summaries = config.summaries();
+        assert_eq!(summaries.len(), 2);
+        assert_eq!(summaries[0].name, "pressure");
+        assert_eq!(summaries[1].allow_diff(), 0.5);
+        assert!(summaries[1].allow_sign());
+        summaries[0].add(1.0 + 1e-6, 1.0, 0);
+        assert!(!summaries[0].is_ok());
+
+        let unknown = ComparisonConfig::from_toml_str("[[channels]]\nname = \"x\"\ncomparator = \"bogus\"\ntolerance = 1.0\n");
+        assert!(matches!(unknown, Err(ConfigError::UnknownComparator { .. })));
+        assert!(matches!(ComparisonConfig::from_toml_str("channels = 3"), Err(ConfigError::Parse(_))));
+
+        let implausible = "[[channels]]\nname = \"x\"\ncomparator = \"ulps\"\ntolerance = 1e-9\n";
+        assert_eq!(
+            ComparisonConfig::from_toml_str(implausible).unwrap_err().to_string(),
+            "channel x: tolerance 1e-9 is implausible for the ulps comparator, which expects 0 or 1e0 to inf"
+        );
+        assert!(ComparisonConfig::from_toml_str(&format!("{}check_tolerance = false\n", implausible)).is_ok());
+
+        let few_buckets = "[[channels]]\nname = \"x\"\ncomparator = \"abs\"\ntolerance = 1.0\nbuckets = 2\n";
+        assert_eq!(
+            ComparisonConfig::from_toml_str(few_buckets).unwrap_err().to_string(),
+            "channel x has 2 buckets, but needs more than 2"
+        );
+        let negative = "[[channels]]\nname = \"x\"\ncomparator = \"abs\"\ntolerance = -1.0\ncheck_tolerance = false\n";
+        assert_eq!(
+            ComparisonConfig::from_toml_str(negative).unwrap_err().to_string(),
+            "channel x has tolerance -1e0, but needs a non-negative one"
+        );
+
+        let heading = ComparisonConfig::from_toml_str("[[channels]]\nname = \"x\"\ncomparator = \"degrees\"\ntolerance = 5.0\n").unwrap();
+        let mut summary = heading.summaries().remove(0);
+        summary.add(359.0, 1.0, 0);
+        assert_eq!((summary.over_count(), summary.under_count()), (0, 1));
+    }
+
+    #[cfg(feature = "yaml")]
+    #[test]
+    fn test_yaml() {
+        let config = ComparisonConfig::from_yaml_str(
+            "channels:\n  - name: count\n    comparator: ulps\n    tolerance: 4\n",
+        )
+        .unwrap();
+        let summaries = config.summaries();
+        assert_eq!(summaries[0].name, "count");
+        assert_eq!(summaries[0].allow_diff(), 4.0);
+        assert!(matches!(ComparisonConfig::from_path("channels.ini"), Err(ConfigError::UnsupportedFormat(_))));
+    }
+}
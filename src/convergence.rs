@@ -0,0 +1,85 @@
+use crate::linear_fit::LinearFit;
+
+// Estimate of the order of accuracy of a series of test cases ordered by a
+// refinement parameter, for checking that differences shrink as step^order
+// as the step size shrinks, such as with grid spacing.
+// Uses a least-squares fit of ln(diff) against ln(step), whose slope is
+// the order. Items with zero or non-finite differences, or non-positive
+// or non-finite steps, carry no information about the rate and are ignored.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Convergence {
+    fit: LinearFit,
+    min_order: f64,
+}
+
+impl Convergence {
+    pub fn new(min_order: f64) -> Self {
+        assert!(min_order.is_finite(), "min_order must be finite, got {}", min_order);
+        Convergence { fit: LinearFit::new(), min_order }
+    }
+
+    pub fn add(&mut self, step: f64, diff: f64) {
+        if step > 0.0 && diff > 0.0 {
+            // LinearFit ignores the infinite logs of infinite steps or diffs.
+            self.fit.add(diff.ln(), step.ln());
+        }
+    }
+
+    // Combine another estimate's data into this one.
+    pub fn merge(&mut self, other: &Convergence) {
+        self.fit.merge(&other.fit);
+    }
+
+    // The number of items included in the estimate.
+    pub fn count(&self) -> usize {
+        self.fit.count()
+    }
+
+    // The estimated order of accuracy, or nan if the steps don't vary.
+    pub fn order(&self) -> f64 {
+        self.fit.slope()
+    }
+
+    // The minimum acceptable order.
+    pub fn min_order(&self) -> f64 {
+        self.min_order
+    }
+
+    // The fraction of the variation in ln(diff) explained by the fit,
+    // as a measure of how closely differences follow a single rate.
+    pub fn r_squared(&self) -> f64 {
+        self.fit.r_squared()
+    }
+
+    // Indicate whether the estimated order is at least min_order.
+    // True with fewer than two items, or steps that don't vary, since the
+    // rate can't be estimated.
+    pub fn is_ok(&self) -> bool {
+        self.count() < 2 || self.order().is_nan() || self.order() >= self.min_order
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Convergence;
+
+    #[test]
+    fn test_convergence() {
+        let mut convergence = Convergence::new(1.5);
+        assert!(convergence.is_ok());
+        for &step in &[0.1, 0.05, 0.025, 0.0125] {
+            convergence.add(step, 3.0 * step * step);
+        }
+        convergence.add(0.0, 1.0);
+        convergence.add(0.01, 0.0);
+        assert_eq!(convergence.count(), 4);
+        assert!((convergence.order() - 2.0).abs() < 1e-12);
+        assert!(convergence.is_ok());
+        let mut first_order = Convergence::new(1.5);
+        first_order.add(0.1, 0.1);
+        first_order.add(0.01, 0.01);
+        assert!(!first_order.is_ok());
+        first_order.merge(&convergence);
+        assert_eq!(first_order.count(), 6);
+    }
+}
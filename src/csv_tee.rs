@@ -0,0 +1,53 @@
+use std::cell::RefCell;
+use std::io::{self, BufWriter, Write};
+use std::rc::Rc;
+
+// A buffered CSV sink for every item a summary records, with rows of
+//   index,x,y,diff,sign_change
+// Items are recorded without a way to report errors, so the first write
+// error is kept, and later rows are dropped, until flush reports it.
+// Clones of a summary share the sink, so their rows go to the same stream.
+// The writer is owned, and so 'static, to keep summaries covariant in
+// their lifetime.
+#[derive(Clone)]
+pub(crate) struct CsvTee {
+    inner: Rc<RefCell<TeeState>>,
+}
+
+struct TeeState {
+    writer: BufWriter<Box<dyn Write>>,
+    error: Option<io::Error>,
+}
+
+impl CsvTee {
+    // Create a sink that writes to writer, starting with a header row.
+    pub fn new<W: Write + 'static>(writer: W) -> Self {
+        let mut state = TeeState { writer: BufWriter::new(Box::new(writer)), error: None };
+        state.write_row(format_args!("index,x,y,diff,sign_change"));
+        CsvTee { inner: Rc::new(RefCell::new(state)) }
+    }
+
+    // Write a row, with x and y formatted by the caller.
+    pub fn add(&self, index: usize, x: impl std::fmt::Display, y: impl std::fmt::Display, diff: f64, sign_change: bool) {
+        self.inner.borrow_mut().write_row(format_args!("{},{},{},{:e},{}", index, x, y, diff, sign_change));
+    }
+
+    // Flush buffered rows, returning the first error since the last flush, if any.
+    pub fn flush(&self) -> io::Result<()> {
+        let mut state = self.inner.borrow_mut();
+        if let Some(err) = state.error.take() {
+            return Err(err);
+        }
+        state.writer.flush()
+    }
+}
+
+impl TeeState {
+    fn write_row(&mut self, row: std::fmt::Arguments<'_>) {
+        if self.error.is_none() {
+            if let Err(err) = writeln!(self.writer, "{}", row) {
+                self.error = Some(err);
+            }
+        }
+    }
+}
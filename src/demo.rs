@@ -0,0 +1,129 @@
+// Synthetic comparison data with controllable characteristics, for trying
+// display modes and reports without real data, and for golden tests of the
+// crate's own output formats, e.g.
+//   let data = DemoData::new(1000, 7).with_bias(1e-9).with_nan_rate(0.01);
+//   let mut summary = DiffSummary64::new("demo", 1e-8, false, 5, &diff::diff_rel);
+//   data.feed(&mut summary);
+//   println!("{}", summary);
+// Data depends only on the settings and seed, and avoids library math
+// functions, so output is reproducible across platforms.
+use crate::diff_summary_f64::DiffSummary;
+use crate::util::mix64;
+
+// The magnitudes of expected values, before scaling by a factor in [0.5, 1.5).
+const MAGNITUDES: [f64; 7] = [1e-3, 1e-2, 1e-1, 1e0, 1e1, 1e2, 1e3];
+
+// Settings for generated (x, y) items. Each expected value (y) has a
+// magnitude from 5e-4 to 1.5e3, and each computed value (x) is
+// y * (1 + error), where error is bias plus uniform noise in [-noise, noise].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct DemoData {
+    pub count: usize,
+    pub seed: u64,
+    pub noise: f64,
+    pub bias: f64,
+    // The fraction of items whose noise is scaled by 1 / u, for uniform u
+    // in (0, 1], giving a heavy tail of large errors.
+    pub tail_rate: f64,
+    // The fraction of items whose x is nan.
+    pub nan_rate: f64,
+    // The fraction of items whose x has its sign flipped.
+    pub sign_flip_rate: f64,
+}
+
+impl DemoData {
+    // count items with relative noise of 1e-12, and no bias, tail, nans or sign flips.
+    pub fn new(count: usize, seed: u64) -> Self {
+        DemoData { count, seed, noise: 1e-12, bias: 0.0, tail_rate: 0.0, nan_rate: 0.0, sign_flip_rate: 0.0 }
+    }
+
+    pub fn with_noise(mut self, noise: f64) -> Self {
+        self.noise = noise;
+        self
+    }
+
+    pub fn with_bias(mut self, bias: f64) -> Self {
+        self.bias = bias;
+        self
+    }
+
+    pub fn with_heavy_tail(mut self, tail_rate: f64) -> Self {
+        self.tail_rate = tail_rate;
+        self
+    }
+
+    pub fn with_nan_rate(mut self, nan_rate: f64) -> Self {
+        self.nan_rate = nan_rate;
+        self
+    }
+
+    pub fn with_sign_flip_rate(mut self, sign_flip_rate: f64) -> Self {
+        self.sign_flip_rate = sign_flip_rate;
+        self
+    }
+
+    // The (x, y) item with the given index.
+    pub fn item(&self, index: usize) -> (f64, f64) {
+        let item_seed = mix64(self.seed ^ mix64(index as u64));
+        // An independent uniform value in [0, 1) for each use.
+        let uniform = |stream: u64| (mix64(item_seed ^ stream) >> 11) as f64 / (1u64 << 53) as f64;
+        let magnitude = MAGNITUDES[(uniform(0) * MAGNITUDES.len() as f64) as usize];
+        let y = magnitude * (0.5 + uniform(1));
+        let mut noise = self.noise * (2.0 * uniform(2) - 1.0);
+        if uniform(3) < self.tail_rate {
+            noise /= 1.0 - uniform(4);
+        }
+        let x = y * (1.0 + self.bias + noise);
+        if uniform(5) < self.nan_rate {
+            (f64::NAN, y)
+        } else if uniform(6) < self.sign_flip_rate {
+            (-x, y)
+        } else {
+            (x, y)
+        }
+    }
+
+    // Each (x, y) item, in index order.
+    pub fn iter(&self) -> impl Iterator<Item = (f64, f64)> + '_ {
+        (0..self.count).map(move |index| self.item(index))
+    }
+
+    // Add every item to summary, with its position as its index.
+    pub fn feed(&self, summary: &mut DiffSummary) {
+        summary.add_iter(self.iter());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::DemoData;
+    use crate::diff;
+    use crate::diff_summary_f64::DiffSummary;
+    use crate::display_options::{DisplayOptions, Layout};
+
+    #[test]
+    fn test_demo() {
+        let data = DemoData::new(1000, 7).with_bias(1e-9).with_noise(1e-10).with_heavy_tail(0.05).with_nan_rate(0.01).with_sign_flip_rate(0.02);
+        assert_eq!(data.iter().collect::<Vec<_>>()[..3], [data.item(0), data.item(1), data.item(2)]);
+        let nan_count = data.iter().filter(|item| item.0.is_nan()).count();
+        assert!(5 < nan_count && nan_count < 20, "{}", nan_count);
+
+        let mut summary = DiffSummary::new("demo", 1e-8, false, 5, &diff::diff_rel);
+        data.feed(&mut summary);
+        assert_eq!(summary.count(), 1000);
+        assert_eq!(
+            summary.to_string(),
+            "demo: count 1000, worst index 176 NaN vs 9.13821366418518e-2 diff NaN, 4% failed tolerance 1e-8 \
+                first index 37 -6.670837922190269e-2 vs 6.670837915260432e-2 diff 2e0, e-9 48%, e-8 48%, e-7 1%, e0 3%, nan 1%, \
+                p50 1.1e-9 p95 9.5e-9 p99 9.3e0, sign diffs 3% first index 37 -6.670837922190269e-2 vs 6.670837915260432e-2"
+        );
+        let verbose = summary.display_with(&DisplayOptions::new().with_layout(Layout::Verbose)).to_string();
+        assert_eq!(
+            verbose,
+            "demo: count 1000, status failed, nonfinite lenient, worst index 176 NaN vs 9.13821366418518e-2 diff NaN, 4% failed tolerance 1e-8 \
+                first index 37 -6.670837922190269e-2 vs 6.670837915260432e-2 diff 2e0, mean 5.85858596035305e-2 rms 3.423035453683137e-1, \
+                over 96% under 3%, e-9 48%, e-8 48%, e-7 1%, e0 3%, nan 1%, p50 1.1e-9 p95 9.5e-9 p99 9.3e0, \
+                sign diffs 3% disallowed first index 37 -6.670837922190269e-2 vs 6.670837915260432e-2"
+        );
+    }
+}
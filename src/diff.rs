@@ -1,6 +1,17 @@
 extern crate float_cmp;
 
-use float_cmp::Ulps;
+use float_cmp::{F64Margin, Ulps};
+use num_traits::ToPrimitive;
+
+// Apply a comparator such as diff_abs to any primitive numeric x and y,
+// such as f32 or i64 values, which are converted to f64 first.
+pub fn diff_num<X: ToPrimitive, Y: ToPrimitive>(
+    calc_diff: &dyn Fn(f64, f64) -> (f64, bool),
+    x: X,
+    y: Y,
+) -> (f64, bool) {
+    calc_diff(crate::util::to_f64(x), crate::util::to_f64(y))
+}
 
 // Return true if diff a is "worse" than diff b.
 // NAN is worse than INFINITY is worse than anything finite.
@@ -11,6 +22,25 @@ pub fn is_diff_worse(a: f64, b: f64) -> bool {
     (a.is_nan() && !b.is_nan()) || a > b
 }
 
+// Whether a computed value is above or below the expected one.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Direction {
+    Over,
+    Under,
+}
+
+// Return the direction of x's error relative to y, which the (f64, bool)
+// comparator result doesn't convey, or None if they're equal or either is nan.
+pub fn direction(x: f64, y: f64) -> Option<Direction> {
+    if x > y {
+        Some(Direction::Over)
+    } else if x < y {
+        Some(Direction::Under)
+    } else {
+        None
+    }
+}
+
 // Return the absolute difference between two values.
 // If both values are nan or same-sign infinite, consider the difference to be 0.
 pub fn diff_abs(x: f64, y: f64) -> (f64, bool) {
@@ -37,6 +67,100 @@ pub fn diff_rel(x: f64, y: f64) -> (f64, bool) {
     (diff, sign_change)
 }
 
+// How diff_log_with handles values that have no real logarithm.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum LogPolicy {
+    // Negative values give a nan difference. Zero is treated as having a log
+    // of -infinity, so zero vs zero has difference 0, and zero vs non-zero infinity.
+    Strict,
+    // Compare the logs of magnitudes, reporting sign differences as sign changes.
+    Magnitude,
+    // Raise values below the floor, which must be positive, to the floor before taking logs.
+    Floor(f64),
+}
+
+// Return |ln(x) - ln(y)|, the difference of natural logs, for quantities
+// spanning many orders of magnitude, such as probabilities.
+// Uses LogPolicy::Strict for values without a real logarithm.
+pub fn diff_log(x: f64, y: f64) -> (f64, bool) {
+    diff_log_with(x, y, LogPolicy::Strict)
+}
+
+// Return |ln(x) - ln(y)|, handling values without a real logarithm per policy.
+// If both values are nan, or have the same infinite log, consider the difference to be 0.
+pub fn diff_log_with(x: f64, y: f64, policy: LogPolicy) -> (f64, bool) {
+    let sign_change = x.is_sign_negative() != y.is_sign_negative();
+    let (log_x, log_y) = match policy {
+        LogPolicy::Strict => {
+            if x < 0.0 || y < 0.0 {
+                return (f64::NAN, sign_change);
+            }
+            (x.ln(), y.ln())
+        }
+        LogPolicy::Magnitude => (x.abs().ln(), y.abs().ln()),
+        LogPolicy::Floor(floor) => {
+            assert!(floor > 0.0, "LogPolicy::Floor requires a positive floor, got {:e}", floor);
+            (x.max(floor).ln(), y.max(floor).ln())
+        }
+    };
+    (diff_abs(log_x, log_y).0, sign_change)
+}
+
+// How diff_percent_with handles an expected value of zero.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PercentZero {
+    // A non-zero x gives an infinite difference, and zero x gives 0.
+    Infinite,
+    // Use 100 * |x|, as if the expected value were 1.
+    Absolute,
+    // Any x gives a nan difference.
+    Nan,
+}
+
+// Return the percent difference 100 * |x - expected| / |expected|,
+// using PercentZero::Infinite when expected is zero.
+pub fn diff_percent(x: f64, expected: f64) -> (f64, bool) {
+    diff_percent_with(x, expected, PercentZero::Infinite)
+}
+
+// Return the percent difference 100 * |x - expected| / |expected|,
+// handling an expected value of zero per zero.
+// If both values are nan or same-sign infinite, consider the difference to be 0.
+pub fn diff_percent_with(x: f64, expected: f64, zero: PercentZero) -> (f64, bool) {
+    let (diff, sign_change) = diff_abs(x, expected);
+    let percent = if diff == 0.0 || diff.is_nan() {
+        diff
+    } else if expected == 0.0 {
+        match zero {
+            PercentZero::Infinite => f64::INFINITY,
+            PercentZero::Absolute => 100.0 * diff,
+            PercentZero::Nan => f64::NAN,
+        }
+    } else {
+        100.0 * diff / expected.abs()
+    };
+    (percent, sign_change)
+}
+
+// Return |x / y - 1|, the difference of the ratio of x to the expected
+// value y from 1, for gain or scale quantities expected to match as ratios.
+// If both values are nan, zero, or same-sign infinite, consider the difference to be 0.
+// A zero y with non-zero x gives an infinite difference, and nan vs non-nan gives nan.
+pub fn diff_ratio(x: f64, y: f64) -> (f64, bool) {
+    let sign_change = x.is_sign_negative() != y.is_sign_negative();
+    let diff = if x.is_nan() || y.is_nan() {
+        if x.is_nan() && y.is_nan() { 0.0 } else { f64::NAN }
+    } else if y == 0.0 {
+        if x == 0.0 { 0.0 } else { f64::INFINITY }
+    } else if x.is_infinite() && y.is_infinite() {
+        // The ratio of infinities is taken as +1 or -1 by sign, rather than nan.
+        if sign_change { 2.0 } else { 0.0 }
+    } else {
+        (x / y - 1.0).abs()
+    };
+    (diff, sign_change)
+}
+
 // Return the lesser of the absolute and relative difference between two values.
 // If both values are nan or same-sign infinite, consider the difference to be 0.
 // Can be helpful in cases where there is a wide range of expected values,
@@ -75,6 +199,57 @@ pub fn diff_ulps(x: f64, y: f64) -> (f64, bool) {
     (ulps, x.is_sign_negative() != y.is_sign_negative())
 }
 
+// Calculate difference in f32 ULPs, counting representable f32 values
+// between x and y after rounding both to f32, for comparing the results of
+// single-precision functions in their native representation.
+// Special handling of nan and infinity follows diff_ulps,
+// but 0.0 and -0.0 are considered 0 ULPs apart.
+pub fn diff_ulps_f32(x: f64, y: f64) -> (f64, bool) {
+    let (x, y) = (x as f32, y as f32);
+    let ulps = if x.is_nan() != y.is_nan() {
+        f64::NAN
+    } else if x.is_nan() {
+        0.0
+    } else if x.is_finite() != y.is_finite() {
+        f64::INFINITY
+    } else {
+        (f32_position(x) - f32_position(y)).abs() as f64
+    };
+    (ulps, x.is_sign_negative() != y.is_sign_negative())
+}
+
+// Map a non-nan f32 to an integer with the same ordering, where adjacent
+// representable values differ by 1, and both zeros map to 0.
+fn f32_position(x: f32) -> i64 {
+    let magnitude = i64::from(x.to_bits() & 0x7fff_ffff);
+    if x.is_sign_negative() { -magnitude } else { magnitude }
+}
+
+// Build a comparator that applies float-cmp's approx_eq check with margin,
+// for code standardized on F64Margin. Differences are in multiples of the
+// margin: the smaller of the absolute difference over margin.epsilon and
+// the ULPs difference over margin.ulps. Use an allow_diff of 1.0, so that
+// an item is within tolerance exactly when approx_eq passes, e.g.
+//   let compare = diff::diff_margin(F64Margin { epsilon: 1e-12, ulps: 4 });
+//   let mut summary = DiffSummary64::new("pressure", 1.0, false, 5, &compare);
+// The margin is float-cmp 0.8's F64Margin, as used by this crate.
+pub fn diff_margin(margin: F64Margin) -> impl Fn(f64, f64) -> (f64, bool) {
+    assert!(margin.epsilon >= 0.0 && margin.ulps >= 0, "diff_margin requires a non-negative margin");
+    move |x, y| {
+        let sign_change = x.is_sign_negative() != y.is_sign_negative();
+        if x == y {
+            return (0.0, sign_change);
+        }
+        let by_abs = (x - y).abs() / margin.epsilon;
+        // Saturate, as float-cmp does, since i64::MIN has no absolute value.
+        let ulps = x.ulps(&y).checked_abs().unwrap_or(i64::MAX);
+        let by_ulps = ulps as f64 / margin.ulps as f64;
+        // Either check passing suffices. min ignores a nan by_abs, as
+        // approx_eq falls through a failed epsilon check to the ULPs check.
+        (by_abs.min(by_ulps), sign_change)
+    }
+}
+
 // Return the absolute difference between two values using a cyclic range,
 // for example angles using a preferred range of [0, 360].
 // Any range enforcement adjustments are reported as a sign change.
@@ -105,6 +280,154 @@ pub fn diff_cyclic(x: f64, y: f64, range_min: f64, range_max: f64) -> (f64, bool
     }
 }
 
+// Return the absolute difference between two angles in degrees, the short
+// way around the circle, per diff_cyclic with the range [-180, 180].
+// Crossing the wrap point, e.g. (179, -179), is reported as a sign change.
+pub fn diff_degrees(x: f64, y: f64) -> (f64, bool) {
+    diff_cyclic(x, y, -180.0, 180.0)
+}
+
+// The period of angles in radians, for use with diff_periodic.
+pub const PERIOD_RADIANS: f64 = std::f64::consts::TAU;
+
+// The period of angles in degrees, for use with diff_periodic.
+pub const PERIOD_DEGREES: f64 = 360.0;
+
+// Return the distance between two values the short way around a circle of
+// the given period, e.g. 2 for (359, 1) with period 360, at most period / 2.
+// Unlike diff_cyclic, no range is chosen, and values on either side of
+// zero or beyond one period are just positions on the circle, so no sign
+// change is ever reported. Both nan counts as a difference of 0, and any
+// other nan or infinite value as a nan difference.
+pub fn diff_periodic(x: f64, y: f64, period: f64) -> (f64, bool) {
+    assert!(period > 0.0 && period.is_finite(), "period must be positive and finite");
+    if x.is_nan() && y.is_nan() {
+        return (0.0, false);
+    }
+    // Reducing each value first is exact, so large values lose no precision
+    // to the subtraction.
+    let diff = ((x % period) - (y % period)).abs() % period;
+    (diff.min(period - diff), false)
+}
+
+// Return the difference between two angles in radians, per diff_periodic
+// with PERIOD_RADIANS.
+pub fn diff_periodic_radians(x: f64, y: f64) -> (f64, bool) {
+    diff_periodic(x, y, PERIOD_RADIANS)
+}
+
+// Return the difference between two angles in degrees, per diff_periodic
+// with PERIOD_DEGREES.
+pub fn diff_periodic_degrees(x: f64, y: f64) -> (f64, bool) {
+    diff_periodic(x, y, PERIOD_DEGREES)
+}
+
+// Return the direction of x relative to y the short way around a circle of
+// the given period, e.g. Under for (359, 1) with period 360, matching
+// diff_periodic. None if they're the same position, exactly opposite, or
+// either is nan or infinite.
+pub fn direction_periodic(x: f64, y: f64, period: f64) -> Option<Direction> {
+    assert!(period > 0.0 && period.is_finite(), "period must be positive and finite");
+    let offset = ((x % period) - (y % period)).rem_euclid(period);
+    if offset.is_nan() || offset == 0.0 || offset == period / 2.0 {
+        None
+    } else if offset < period / 2.0 {
+        Some(Direction::Over)
+    } else {
+        Some(Direction::Under)
+    }
+}
+
+// Return the direction of one angle in radians relative to another, per
+// direction_periodic with PERIOD_RADIANS.
+pub fn direction_periodic_radians(x: f64, y: f64) -> Option<Direction> {
+    direction_periodic(x, y, PERIOD_RADIANS)
+}
+
+// Return the direction of one angle in degrees relative to another, per
+// direction_periodic with PERIOD_DEGREES. Also suits diff_degrees.
+pub fn direction_periodic_degrees(x: f64, y: f64) -> Option<Direction> {
+    direction_periodic(x, y, PERIOD_DEGREES)
+}
+
+// Return the distance between two values in IEEE 754 totalOrder, which ranks
+// -nan < -inf < ... < -0.0 < 0.0 < ... < inf < nan, distinguishing nan payloads.
+// The distance is the number of steps between the values in that order,
+// so it is 0 only for bit-identical values, and 1 for 0.0 vs -0.0.
+pub fn diff_total_order(x: f64, y: f64) -> (f64, bool) {
+    let distance = (i128::from(total_order_key(x)) - i128::from(total_order_key(y))).abs();
+    (distance as f64, x.is_sign_negative() != y.is_sign_negative())
+}
+
+// Map a value's bits to an integer whose ordering matches IEEE 754 totalOrder.
+fn total_order_key(x: f64) -> i64 {
+    let bits = x.to_bits() as i64;
+    // For negative values, flip all but the sign bit, reversing their order.
+    bits ^ ((((bits >> 63) as u64) >> 1) as i64)
+}
+
+// The unit in which angular differences are reported.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AngleUnit {
+    Radians,
+    Degrees,
+}
+
+impl AngleUnit {
+    // The period of angles in this unit, for use with diff_periodic.
+    pub fn period(self) -> f64 {
+        match self {
+            AngleUnit::Radians => PERIOD_RADIANS,
+            AngleUnit::Degrees => PERIOD_DEGREES,
+        }
+    }
+
+    // Convert an angle in radians to this unit.
+    pub fn from_radians(self, radians: f64) -> f64 {
+        match self {
+            AngleUnit::Radians => radians,
+            AngleUnit::Degrees => radians.to_degrees(),
+        }
+    }
+}
+
+// Return the angle in radians between two 3-vectors, which needn't be unit length.
+// Uses atan2 of the cross and dot products, which stays accurate for
+// nearly parallel vectors, unlike acos of the dot product alone.
+pub fn diff_angle_vec3(x: &[f64; 3], y: &[f64; 3]) -> f64 {
+    let cross = [
+        x[1] * y[2] - x[2] * y[1],
+        x[2] * y[0] - x[0] * y[2],
+        x[0] * y[1] - x[1] * y[0],
+    ];
+    let dot = x[0] * y[0] + x[1] * y[1] + x[2] * y[2];
+    norm(&cross).atan2(dot)
+}
+
+// Return the geodesic angle in radians between the rotations represented by
+// two quaternions, which needn't be unit length.
+// Since q and -q represent the same rotation, the result is at most pi.
+pub fn diff_angle_quat(x: &[f64; 4], y: &[f64; 4]) -> f64 {
+    let (x_norm, y_norm) = (norm(x), norm(y));
+    let mut sum = [0.0; 4];
+    let mut difference = [0.0; 4];
+    let dot: f64 = x.iter().zip(y).map(|(a, b)| a * b).sum();
+    let sign = if dot < 0.0 { -1.0 } else { 1.0 };
+    for i in 0..4 {
+        let (a, b) = (x[i] / x_norm, sign * y[i] / y_norm);
+        sum[i] = a + b;
+        difference[i] = a - b;
+    }
+    // atan2 gives half the angle between the quaternions on the unit sphere,
+    // which is itself half the rotation angle.
+    4.0 * norm(&difference).atan2(norm(&sum))
+}
+
+// The Euclidean norm of a vector.
+fn norm(v: &[f64]) -> f64 {
+    v.iter().map(|a| a * a).sum::<f64>().sqrt()
+}
+
 // Adjust a value to fall within a specified cyclic range.
 fn cyclic_range(x: f64, range_min: f64, range_max: f64) -> f64 {
     let span = range_max - range_min;
@@ -120,7 +443,90 @@ fn cyclic_range(x: f64, range_min: f64, range_max: f64) -> f64 {
 
 #[cfg(test)]
 mod tests {
-    use super::{diff_abs, diff_cyclic, diff_lesser, diff_rel, diff_ulps};
+    use super::{diff_abs, diff_angle_quat, diff_angle_vec3, diff_cyclic, diff_degrees, diff_lesser, diff_log, diff_log_with, diff_margin, diff_num, diff_percent, diff_percent_with, diff_periodic, diff_periodic_degrees, diff_periodic_radians, diff_ratio, diff_rel, diff_total_order, diff_ulps, diff_ulps_f32, AngleUnit, PERIOD_RADIANS, PERIOD_DEGREES};
+    use super::{direction, direction_periodic, Direction, LogPolicy, PercentZero};
+    use float_cmp::{ApproxEq, F64Margin};
+    use std::f64::consts::{E, FRAC_PI_2, PI};
+
+    #[test]
+    fn test_percent() {
+        assert_eq!(diff_percent(11.0, 10.0), (10.0, false));
+        assert_eq!(diff_percent(-5.0, -4.0), (25.0, false));
+        assert_eq!(diff_percent(0.0, 0.0), (0.0, false));
+        assert_eq!(diff_percent(0.5, 0.0), (f64::INFINITY, false));
+        assert_eq!(diff_percent_with(0.5, 0.0, PercentZero::Absolute), (50.0, false));
+        assert!(diff_percent_with(0.5, 0.0, PercentZero::Nan).0.is_nan());
+        assert!(diff_percent(f64::NAN, 1.0).0.is_nan());
+    }
+
+    #[test]
+    fn test_ratio() {
+        assert_eq!(diff_ratio(2.5, 2.0), (0.25, false));
+        assert_eq!(diff_ratio(-1.5, -2.0), (0.25, false));
+        assert_eq!(diff_ratio(1.0, -1.0), (2.0, true));
+        assert_eq!(diff_ratio(0.0, 0.0), (0.0, false));
+        assert_eq!(diff_ratio(-0.0, 0.0), (0.0, true));
+        assert_eq!(diff_ratio(1e-300, 0.0), (f64::INFINITY, false));
+        assert_eq!(diff_ratio(0.0, 1e-300), (1.0, false));
+        assert_eq!(diff_ratio(f64::INFINITY, f64::INFINITY), (0.0, false));
+        assert_eq!(diff_ratio(f64::INFINITY, f64::NEG_INFINITY), (2.0, true));
+        assert_eq!(diff_ratio(f64::INFINITY, 1.0), (f64::INFINITY, false));
+        assert_eq!(diff_ratio(1.0, f64::INFINITY), (1.0, false));
+        assert_eq!(diff_ratio(f64::NAN, f64::NAN), (0.0, false));
+        assert!(diff_ratio(f64::NAN, 1.0).0.is_nan());
+        assert!(diff_ratio(1.0, f64::NAN).0.is_nan());
+    }
+
+    #[test]
+    fn test_log() {
+        assert_eq!(diff_log(E, 1.0), (1.0, false));
+        assert_eq!(diff_log(1e-300, 1e-300), (0.0, false));
+        assert_eq!(diff_log(0.0, 0.0), (0.0, false));
+        assert_eq!(diff_log(0.0, 1e-300), (f64::INFINITY, false));
+        assert!(diff_log(-1.0, 1.0).0.is_nan());
+        assert!(diff_log(-1.0, -1.0).0.is_nan());
+        assert_eq!(diff_log(f64::NAN, f64::NAN), (0.0, false));
+        assert_eq!(diff_log_with(-E, 1.0, LogPolicy::Magnitude), (1.0, true));
+        assert_eq!(diff_log_with(0.0, -1e-20, LogPolicy::Floor(1e-10)), (0.0, true));
+        assert_eq!(diff_log_with(0.0, E * 1e-10, LogPolicy::Floor(1e-10)).0, 1.0);
+    }
+
+    #[test]
+    fn test_angle() {
+        assert_eq!(diff_angle_vec3(&[1.0, 0.0, 0.0], &[0.0, 2.0, 0.0]), FRAC_PI_2);
+        assert_eq!(diff_angle_vec3(&[0.0, 0.0, 1.0], &[0.0, 0.0, 1.0]), 0.0);
+        assert_eq!(diff_angle_vec3(&[1.0, 0.0, 0.0], &[-1.0, 0.0, 0.0]), PI);
+        // Accurate for tiny angles, where acos(dot) would return 0.
+        let tiny = diff_angle_vec3(&[1.0, 0.0, 0.0], &[1.0, 1e-9, 0.0]);
+        assert!((tiny - 1e-9).abs() < 1e-20);
+        let identity = [1.0, 0.0, 0.0, 0.0];
+        assert_eq!(diff_angle_quat(&identity, &[-1.0, 0.0, 0.0, 0.0]), 0.0);
+        // A rotation of pi/2 about z.
+        let half = FRAC_PI_2 / 2.0;
+        let quarter_turn = [half.cos(), 0.0, 0.0, half.sin()];
+        assert!((diff_angle_quat(&identity, &quarter_turn) - FRAC_PI_2).abs() < 1e-15);
+        assert!(diff_angle_vec3(&[f64::NAN, 0.0, 0.0], &[1.0, 0.0, 0.0]).is_nan());
+    }
+
+    #[test]
+    fn test_total_order() {
+        assert_eq!(diff_total_order(1.5, 1.5), (0.0, false));
+        assert_eq!(diff_total_order(-0.0, 0.0), (1.0, true));
+        assert_eq!(diff_total_order(1.0, f64::from_bits(1.0f64.to_bits() + 1)), (1.0, false));
+        let nan = f64::NAN;
+        assert_eq!(diff_total_order(nan, nan), (0.0, false));
+        assert_eq!(diff_total_order(f64::from_bits(nan.to_bits() + 1), nan), (1.0, false));
+        assert_eq!(diff_total_order(f64::INFINITY, nan), (diff_total_order(0.0, nan).0 - diff_total_order(0.0, f64::INFINITY).0, false));
+        let (diff, sign_change) = diff_total_order(-nan, nan);
+        assert!(sign_change && diff > diff_total_order(f64::NEG_INFINITY, f64::INFINITY).0);
+    }
+
+    #[test]
+    fn test_num() {
+        assert_eq!(diff_num(&diff_abs, 3i64, 2.5f32), (0.5, false));
+        assert_eq!(diff_num(&diff_rel, 1u8, -1i32), (2.0, true));
+        assert_eq!(diff_num(&diff_abs, 0.5, 0.25), (0.25, false));
+    }
 
     #[test]
     fn test_abs() {
@@ -138,6 +544,13 @@ mod tests {
         assert_eq!(diff_abs(f64::INFINITY, f64::NEG_INFINITY), (f64::INFINITY, true));
     }
 
+    #[test]
+    fn test_degrees() {
+        assert_eq!(diff_degrees(10.0, 10.5), (0.5, false));
+        assert_eq!(diff_degrees(179.0, -179.0), (2.0, true));
+        assert_eq!(diff_degrees(0.0, 360.0), (0.0, true));
+    }
+
     #[test]
     fn test_cyclic() {
         // Values chosen to be cleanly representable as exact f64
@@ -161,6 +574,25 @@ mod tests {
         assert_eq!(diff_cyclic(0.0, 721.0, -180.0, 180.0), (1.0, true));
     }
 
+    #[test]
+    fn test_periodic() {
+        // Values chosen to be cleanly representable as exact f64
+        assert_eq!(diff_periodic(0.0, 0.5, 360.0), (0.5, false));
+        assert_eq!(diff_periodic(359.0, 1.0, 360.0), (2.0, false));
+        assert_eq!(diff_periodic(-1.0, 1.0, 360.0), (2.0, false));
+        assert_eq!(diff_periodic(0.0, 721.0, 360.0), (1.0, false));
+        assert_eq!(diff_periodic(-180.0, 180.0, 360.0), (0.0, false));
+        assert_eq!(diff_periodic(90.0, 270.0, 360.0), (180.0, false));
+        assert_eq!(diff_periodic(1e20, 1e20 + 16384.0, 360.0), (176.0, false));
+        assert_eq!(diff_periodic(f64::NAN, -f64::NAN, 1.0), (0.0, false));
+        assert!(diff_periodic(f64::NAN, 0.0, 1.0).0.is_nan());
+        assert!(diff_periodic(f64::INFINITY, f64::INFINITY, 1.0).0.is_nan());
+        assert_eq!(diff_periodic_degrees(-179.0, 179.0), (2.0, false));
+        let (diff, sign_change) = diff_periodic_radians(3.0, -3.0);
+        assert!((diff - (PERIOD_RADIANS - 6.0)).abs() < 1e-15 && !sign_change);
+        assert_eq!(AngleUnit::Degrees.period(), PERIOD_DEGREES);
+    }
+
     #[test]
     fn test_lesser() {
         // Values chosen to be cleanly representable as exact f64
@@ -194,6 +626,24 @@ mod tests {
         assert!(diff.0.is_nan() && diff.1);
     }
 
+    #[test]
+    fn test_margin() {
+        let margin = F64Margin { epsilon: 1e-12, ulps: 4 };
+        let compare = diff_margin(margin);
+        let values = [
+            0.0, -0.0, 1e-300, 1.0, 1.0 + f64::EPSILON, 1.0 + 1e-11, -1.0, 1e10, 1e10 + 1.0,
+            f64::INFINITY, f64::NEG_INFINITY, f64::NAN, f64::MAX, f64::MIN_POSITIVE,
+        ];
+        for &x in &values {
+            for &y in &values {
+                assert_eq!(compare(x, y).0 <= 1.0, x.approx_eq(y, margin), "{} {}", x, y);
+            }
+        }
+        assert_eq!(compare(1.0, 1.0 + 2.0 * f64::EPSILON).0, 2.0 * f64::EPSILON / 1e-12);
+        assert_eq!(compare(1e10, f64::from_bits(1e10f64.to_bits() + 2)), (0.5, false));
+        assert_eq!(diff_margin(F64Margin { epsilon: 0.0, ulps: 0 })(1.0, 1.5).0, f64::INFINITY);
+    }
+
     #[test]
     fn test_ulps() {
         assert_eq!(diff_ulps(0.0, 0.0), (0.0, false));
@@ -202,4 +652,39 @@ mod tests {
         assert!(f64::is_infinite(diff_ulps(f64::MAX, f64::INFINITY).0));
     }
 
+    #[test]
+    fn test_direction() {
+        assert_eq!(direction(1.5, 1.0), Some(Direction::Over));
+        assert_eq!(direction(-2.0, 1.0), Some(Direction::Under));
+        assert_eq!(direction(f64::NEG_INFINITY, f64::MIN), Some(Direction::Under));
+        assert_eq!(direction(0.0, -0.0), None);
+        assert_eq!(direction(f64::NAN, 1.0), None);
+    }
+
+    #[test]
+    fn test_direction_periodic() {
+        assert_eq!(direction_periodic(359.0, 1.0, 360.0), Some(Direction::Under));
+        assert_eq!(direction_periodic(1.0, 359.0, 360.0), Some(Direction::Over));
+        assert_eq!(direction_periodic(-1.0, 721.0, 360.0), Some(Direction::Under));
+        assert_eq!(direction_periodic(10.0, 5.0, 360.0), Some(Direction::Over));
+        assert_eq!(direction_periodic(0.0, 360.0, 360.0), None);
+        assert_eq!(direction_periodic(180.0, 0.0, 360.0), None);
+        assert_eq!(direction_periodic(f64::INFINITY, 0.0, 360.0), None);
+        assert_eq!(direction_periodic(f64::NAN, 0.0, 360.0), None);
+    }
+
+    #[test]
+    fn test_ulps_f32() {
+        let next = f32::from_bits(1.0f32.to_bits() + 2);
+        assert_eq!(diff_ulps_f32(1.0, f64::from(next)), (2.0, false));
+        // Values that differ only beyond f32 precision are equal.
+        assert_eq!(diff_ulps_f32(1.0, 1.0 + 1e-12), (0.0, false));
+        assert_eq!(diff_ulps_f32(0.0, -0.0), (0.0, true));
+        let tiny = f64::from(f32::from_bits(1));
+        assert_eq!(diff_ulps_f32(tiny, -tiny), (2.0, true));
+        assert_eq!(diff_ulps_f32(f64::from(f32::MAX), f64::from(-f32::MAX)).0, 2.0 * f64::from(0x7f7f_ffffu32));
+        assert_eq!(diff_ulps_f32(1e300, 1.0).0, f64::INFINITY);
+        assert!(diff_ulps_f32(f64::NAN, 1.0).0.is_nan());
+    }
+
 }
\ No newline at end of file
@@ -0,0 +1,185 @@
+// Comparators for auditing decimal-vs-float migrations, available with the
+// "rust_decimal" feature.
+// Differences are calculated exactly in Decimal, then reported as f64.
+
+use num_traits::ToPrimitive;
+use rust_decimal::Decimal;
+use std::fmt::Display;
+use crate::diff;
+use crate::diff_summary_f64::DiffSummary;
+
+// A value that can be compared as a Decimal: either a Decimal or a float.
+pub trait DecimalInput: Copy {
+    // The exact Decimal equivalent of this value, if any.
+    // Floats keep their binary approximation error, to Decimal's 28 digit
+    // precision, rather than being rounded to the shortest decimal.
+    fn as_decimal(self) -> Option<Decimal>;
+
+    // The nearest f64 to this value, used for sample reporting.
+    fn as_f64(self) -> f64;
+}
+
+impl DecimalInput for Decimal {
+    fn as_decimal(self) -> Option<Decimal> {
+        Some(self)
+    }
+
+    fn as_f64(self) -> f64 {
+        self.to_f64().unwrap_or(f64::NAN)
+    }
+}
+
+impl DecimalInput for f64 {
+    fn as_decimal(self) -> Option<Decimal> {
+        Decimal::from_f64_retain(self)
+    }
+
+    fn as_f64(self) -> f64 {
+        self
+    }
+}
+
+impl DecimalInput for f32 {
+    fn as_decimal(self) -> Option<Decimal> {
+        Decimal::from_f64_retain(f64::from(self))
+    }
+
+    fn as_f64(self) -> f64 {
+        f64::from(self)
+    }
+}
+
+// Return the exact absolute difference between two Decimal values, as f64.
+// Differences too large for Decimal are reported as infinite.
+pub fn diff_abs_decimal(x: Decimal, y: Decimal) -> (f64, bool) {
+    let diff = match x.checked_sub(y) {
+        Some(diff) => diff.abs().to_f64().unwrap_or(f64::INFINITY),
+        None => f64::INFINITY,
+    };
+    (diff, x.is_sign_negative() != y.is_sign_negative())
+}
+
+// Return the relative difference between two Decimal values, as f64.
+// The difference and scale are calculated exactly, with only the final
+// division subject to Decimal rounding.
+pub fn diff_rel_decimal(x: Decimal, y: Decimal) -> (f64, bool) {
+    let sign_change = x.is_sign_negative() != y.is_sign_negative();
+    let diff = match x.checked_sub(y) {
+        Some(diff) if diff.is_zero() => 0.0,
+        Some(diff) => {
+            let rel = x.abs().checked_add(y.abs())
+                .and_then(|sum| (diff.abs() * Decimal::TWO).checked_div(sum));
+            match rel {
+                Some(rel) => rel.to_f64().unwrap_or(f64::INFINITY),
+                // Fall back to f64 when Decimal's range is exceeded.
+                None => diff::diff_rel(x.as_f64(), y.as_f64()).0,
+            }
+        }
+        None => diff::diff_rel(x.as_f64(), y.as_f64()).0,
+    };
+    (diff, sign_change)
+}
+
+// Apply a Decimal comparator such as diff_abs_decimal to a Decimal or float
+// on either side.
+// Floats without a Decimal equivalent (nan and infinities) are compared
+// as f64 using diff::diff_abs.
+pub fn diff_decimal<X: DecimalInput, Y: DecimalInput>(
+    calc_diff: &dyn Fn(Decimal, Decimal) -> (f64, bool),
+    x: X,
+    y: Y,
+) -> (f64, bool) {
+    match (x.as_decimal(), y.as_decimal()) {
+        (Some(x), Some(y)) => calc_diff(x, y),
+        _ => diff::diff_abs(x.as_f64(), y.as_f64()),
+    }
+}
+
+// A DiffSummary whose items are compared exactly in Decimal, for example
+// a float reimplementation (x) against decimal reference results (y).
+// Samples are reported using the nearest f64 values.
+pub struct DecimalDiffSummary<'a> {
+    summary: DiffSummary<'a>,
+    decimal_diff: &'a dyn Fn(Decimal, Decimal) -> (f64, bool),
+}
+
+impl<'a> DecimalDiffSummary<'a> {
+    // Create a summary using decimal_diff, e.g. &diff_abs_decimal, for all comparisons.
+    pub fn new(
+        name: &'a str,
+        allow_diff: f64,
+        allow_sign: bool,
+        bucket_count: usize,
+        decimal_diff: &'a dyn Fn(Decimal, Decimal) -> (f64, bool),
+    ) -> Self {
+        DecimalDiffSummary {
+            // The f64 comparator is unused, since differences are supplied directly.
+            summary: DiffSummary::new(name, allow_diff, allow_sign, bucket_count, &diff::diff_abs),
+            decimal_diff,
+        }
+    }
+
+    // Add an item, with a Decimal or float on either side.
+    pub fn add<X: DecimalInput, Y: DecimalInput>(&mut self, x: X, y: Y, index: usize) {
+        let (diff, sign_change) = diff_decimal(self.decimal_diff, x, y);
+        self.summary.add_diff(x.as_f64(), y.as_f64(), diff, sign_change, index);
+    }
+
+    // The underlying summary, for access to its statistics.
+    pub fn summary(&self) -> &DiffSummary<'a> {
+        &self.summary
+    }
+
+    // Indicate whether data currently satisfies allowed tolerance and sign change acceptance.
+    pub fn is_ok(&self) -> bool {
+        self.summary.is_ok()
+    }
+
+    // Assert that worst diff is within tolerance, and sign changes are allowed.
+    pub fn assert(&self) {
+        self.summary.assert();
+    }
+}
+
+impl Display for DecimalDiffSummary<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.summary.fmt(f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rust_decimal::Decimal;
+    use std::str::FromStr;
+    use super::{diff_abs_decimal, diff_decimal, diff_rel_decimal, DecimalDiffSummary};
+
+    fn dec(s: &str) -> Decimal {
+        Decimal::from_str(s).unwrap()
+    }
+
+    #[test]
+    fn test_decimal_diff() {
+        // 0.1 + 0.2 vs 0.3 is inexact in f64, but exact here.
+        assert_eq!(diff_abs_decimal(dec("0.3"), dec("0.1") + dec("0.2")), (0.0, false));
+        assert_eq!(diff_abs_decimal(dec("-1.25"), dec("1.25")), (2.5, true));
+        assert_eq!(diff_rel_decimal(dec("3"), dec("1")), (1.0, false));
+        assert_eq!(diff_rel_decimal(dec("0"), dec("0")), (0.0, false));
+        // The f64 nearest 0.1 differs from decimal 0.1 by about 5.55e-18.
+        let (diff, _) = diff_decimal(&diff_abs_decimal, 0.1f64, dec("0.1"));
+        assert!((diff / 5.551115123e-18 - 1.0).abs() < 1e-9);
+        assert!(diff_decimal(&diff_abs_decimal, f64::NAN, dec("1")).0.is_nan());
+    }
+
+    #[test]
+    fn test_decimal_summary() {
+        let mut summary = DecimalDiffSummary::new("ledger", 1e-9, false, 4, &diff_abs_decimal);
+        summary.add(0.1 + 0.2, dec("0.3"), 0);
+        summary.add(dec("10.5"), dec("10.5"), 1);
+        summary.add(2.5f32, 2.5f64, 2);
+        assert!(summary.is_ok());
+        summary.add(1.0, dec("1.001"), 3);
+        assert!(!summary.is_ok());
+        assert_eq!(summary.summary().count(), 4);
+        assert_eq!(summary.summary().worst_sample().unwrap().index, 3);
+    }
+}
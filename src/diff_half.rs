@@ -0,0 +1,125 @@
+// Comparators and summary constructors for half-precision (f16 and bf16)
+// results, available with the "half" feature.
+// Values are accepted as f64, to fit DiffSummary's calc_diff signature,
+// and rounded to the 16-bit type before comparison. Since f16 and bf16
+// values convert to f64 exactly, results from f16 or bf16 computations
+// are compared in their native representation.
+
+use half::{bf16, f16};
+use crate::diff;
+use crate::diff_summary_f64::DiffSummary;
+
+// Calculate difference in f16 ULPs, counting representable f16 values
+// between x and y, rather than f64 values.
+// Special handling of nan and infinity follows diff::diff_ulps,
+// but 0.0 and -0.0 are considered 0 ULPs apart.
+pub fn diff_ulps_f16(x: f64, y: f64) -> (f64, bool) {
+    let (x, y) = (f16::from_f64(x), f16::from_f64(y));
+    lattice_ulps(x.to_f64(), y.to_f64(), x.to_bits(), y.to_bits())
+}
+
+// Calculate difference in bf16 ULPs. See diff_ulps_f16.
+pub fn diff_ulps_bf16(x: f64, y: f64) -> (f64, bool) {
+    let (x, y) = (bf16::from_f64(x), bf16::from_f64(y));
+    lattice_ulps(x.to_f64(), y.to_f64(), x.to_bits(), y.to_bits())
+}
+
+// Return the relative difference between two values after rounding to f16.
+// See diff::diff_rel.
+pub fn diff_rel_f16(x: f64, y: f64) -> (f64, bool) {
+    diff::diff_rel(f16::from_f64(x).to_f64(), f16::from_f64(y).to_f64())
+}
+
+// Return the relative difference between two values after rounding to bf16.
+// See diff::diff_rel.
+pub fn diff_rel_bf16(x: f64, y: f64) -> (f64, bool) {
+    diff::diff_rel(bf16::from_f64(x).to_f64(), bf16::from_f64(y).to_f64())
+}
+
+// Calculate the ULPs difference of two 16-bit floats given as both f64 values and bits.
+fn lattice_ulps(x: f64, y: f64, x_bits: u16, y_bits: u16) -> (f64, bool) {
+    let ulps = if x.is_nan() != y.is_nan() {
+        f64::NAN
+    } else if x.is_nan() {
+        0.0
+    } else if x.is_finite() != y.is_finite() {
+        f64::INFINITY
+    } else {
+        (lattice_position(x_bits) - lattice_position(y_bits)).abs() as f64
+    };
+    (ulps, x.is_sign_negative() != y.is_sign_negative())
+}
+
+// Map the bits of a non-nan 16-bit float to an integer with the same ordering,
+// where adjacent representable values differ by 1, and both zeros map to 0.
+fn lattice_position(bits: u16) -> i32 {
+    let magnitude = i32::from(bits & 0x7fff);
+    if bits & 0x8000 != 0 { -magnitude } else { magnitude }
+}
+
+impl<'a> DiffSummary<'a> {
+    // Create a summary comparing values in f16 ULPs, with tolerance allow_ulps.
+    pub fn new_f16_ulps(name: &'a str, allow_ulps: f64, allow_sign: bool, bucket_count: usize) -> Self {
+        DiffSummary::new(name, allow_ulps, allow_sign, bucket_count, &diff_ulps_f16)
+    }
+
+    // Create a summary comparing values in bf16 ULPs, with tolerance allow_ulps.
+    pub fn new_bf16_ulps(name: &'a str, allow_ulps: f64, allow_sign: bool, bucket_count: usize) -> Self {
+        DiffSummary::new(name, allow_ulps, allow_sign, bucket_count, &diff_ulps_bf16)
+    }
+
+    // Create a summary comparing relative differences of values rounded to f16,
+    // with a tolerance of allow_epsilons times f16's machine epsilon.
+    pub fn new_f16_rel(name: &'a str, allow_epsilons: f64, allow_sign: bool, bucket_count: usize) -> Self {
+        let allow_diff = allow_epsilons * f16::EPSILON.to_f64();
+        DiffSummary::new(name, allow_diff, allow_sign, bucket_count, &diff_rel_f16)
+    }
+
+    // Create a summary comparing relative differences of values rounded to bf16,
+    // with a tolerance of allow_epsilons times bf16's machine epsilon.
+    pub fn new_bf16_rel(name: &'a str, allow_epsilons: f64, allow_sign: bool, bucket_count: usize) -> Self {
+        let allow_diff = allow_epsilons * bf16::EPSILON.to_f64();
+        DiffSummary::new(name, allow_diff, allow_sign, bucket_count, &diff_rel_bf16)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use half::{bf16, f16};
+    use super::{diff_rel_bf16, diff_ulps_bf16, diff_ulps_f16};
+    use crate::diff_summary_f64::DiffSummary;
+
+    #[test]
+    fn test_ulps_f16() {
+        let one = f16::ONE.to_f64();
+        let next = f16::from_bits(f16::ONE.to_bits() + 3).to_f64();
+        assert_eq!(diff_ulps_f16(one, next), (3.0, false));
+        // Values that differ only beyond f16 precision are equal.
+        assert_eq!(diff_ulps_f16(1.0, 1.0 + 1e-6), (0.0, false));
+        assert_eq!(diff_ulps_f16(0.0, -0.0), (0.0, true));
+        let tiny = f16::from_bits(1).to_f64();
+        assert_eq!(diff_ulps_f16(tiny, -tiny), (2.0, true));
+        assert_eq!(diff_ulps_f16(f64::MAX, 1.0).0, f64::INFINITY);
+        assert!(diff_ulps_f16(f64::NAN, 1.0).0.is_nan());
+    }
+
+    #[test]
+    fn test_bf16() {
+        let one = bf16::ONE.to_f64();
+        let next = bf16::from_bits(bf16::ONE.to_bits() + 1).to_f64();
+        assert_eq!(diff_ulps_bf16(one, next), (1.0, false));
+        assert_eq!(diff_rel_bf16(2.0, 2.0 + 1e-4), (0.0, false));
+    }
+
+    #[test]
+    fn test_summary_f16() {
+        let mut summary = DiffSummary::new_f16_ulps("f16", 1.0, false, 4);
+        summary.add(f16::from_f32(1.5), f16::from_f32(1.5), 0);
+        summary.add(f16::from_bits(0x3c01), f16::ONE, 1);
+        assert!(summary.is_ok());
+        summary.add(f16::from_bits(0x3c02), f16::ONE, 2);
+        assert!(!summary.is_ok());
+        let summary = DiffSummary::new_f16_rel("f16 rel", 2.0, false, 4);
+        assert_eq!(summary.allow_diff(), 2.0 * f16::EPSILON.to_f64());
+    }
+}
@@ -1,45 +1,44 @@
+use crate::sample_record::SampleRecord;
+
 // Summary of count of times a condition occurs for DiffSummary,
-// and information about a sample occurrence (first for sign
-// difference, worst for numeric difference).
+// and a sample occurrence (first for sign difference, worst for
+// numeric difference).
+#[derive(Clone, Debug)]
 pub struct DiffPartSummary {
-    pub sample_x: f64,
-    pub sample_y: f64,
-    pub sample_index: usize,
+    pub sample: SampleRecord,
     pub count: usize,
 }
 
-impl Copy for DiffPartSummary {
-}
-
-impl Clone for DiffPartSummary {
-    fn clone(&self) -> Self {
-        DiffPartSummary {
-            sample_x: self.sample_x,
-            sample_y: self.sample_y,
-            sample_index: self.sample_index,
-            count: self.count,
-        }
-    }
-}
-
 impl DiffPartSummary {
     pub fn new() -> Self {
         DiffPartSummary {
-            sample_x: f64::NAN,
-            sample_y: f64::NAN,
-            sample_index: 0,
+            sample: SampleRecord::empty(),
             count: 0,
         }
     }
 
     // Update the summary based on an iteration.
-    // If "worst" is true, update sample_* values even if this isn't the first item added.
-    pub fn add(&mut self, x: f64, y: f64, index: usize, worst: bool) {
+    // If "worst" is true, replace the sample even if this isn't the first item added.
+    // The sample is only created when it's kept.
+    pub fn add(&mut self, worst: bool, sample: impl FnOnce() -> SampleRecord) {
         if worst || self.count == 0 {
-            self.sample_x = x;
-            self.sample_y = y;
-            self.sample_index = index;
+            self.sample = sample();
         }
         self.count += 1;
     }
+
+    // Combine another summary's counts into this one.
+    // If "worst" is true, take the other's sample even if this summary
+    // already has a sample of its own.
+    pub fn merge(&mut self, other: &DiffPartSummary, worst: bool) {
+        if other.count > 0 && (worst || self.count == 0) {
+            self.sample = other.sample.clone();
+        }
+        self.count += other.count;
+    }
+
+    // The sample, if any item has been added.
+    pub fn sample(&self) -> Option<&SampleRecord> {
+        if self.count > 0 { Some(&self.sample) } else { None }
+    }
 }
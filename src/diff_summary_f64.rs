@@ -1,7 +1,366 @@
-use std::fmt::Display;
+use num_traits::ToPrimitive;
+use std::collections::{BTreeMap, HashSet};
+use std::fmt::{Display, Formatter};
+use std::io;
+use std::ops::Range;
+use std::path::Path;
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+use crate::csv_tee::CsvTee;
+use crate::diff::{self, AngleUnit, Direction};
 use crate::diff_part_summary::DiffPartSummary;
-use crate::log_histogram::LogHistogram;
-use crate::util;
+use crate::display_options::DisplayOptions;
+use crate::input_error::DiffInputError;
+use crate::bootstrap::Bootstrap;
+use crate::convergence::Convergence;
+use crate::linear_fit::LinearFit;
+use crate::drift::DriftSegments;
+use crate::extremes::Extremes;
+use crate::fingerprint::Fingerprint;
+use crate::log_histogram::{BucketKey, LogHistogram};
+use crate::outliers::OutlierReport;
+use crate::run_comparison::RunComparison;
+use crate::sample_record::SampleRecord;
+use crate::spill::{Spill, SpillReader};
+use crate::stats::ErrorModel;
+use crate::status::{StatusReason, SummaryStatus};
+use crate::timing::Timing;
+use crate::tolerance_check::{ComparatorKind, ToleranceCheck};
+use crate::reservoir::Reservoir;
+use crate::top_n::TopN;
+use crate::tolerance_suggestion::ToleranceSuggestion;
+use crate::triage::Triage;
+use crate::value_class::ValueClass;
+use crate::util::{self, FloatFormat, FmtF64, Highlight, Paint, Percent, Precise, WithUnit};
+
+// A passing summary whose worst diff exceeds this fraction of its tolerance
+// is highlighted as marginal in colored output.
+const MARGINAL_FRACTION: f64 = 0.5;
+
+// The number of differences to collect before inserting them into the histogram,
+// when adding items in bulk.
+const HISTO_BATCH: usize = 64;
+
+// The number of histogram buckets shown by the preset constructors, such
+// as relative, matching the default for channels in comparison configs.
+const PRESET_BUCKETS: usize = 5;
+
+// What makes an item or summary the "worst", for worst_sample and
+// worst_score, and so for ranking summaries in multi-summary reports.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WorstMetric {
+    // The difference per the summary's calc_diff.
+    Diff,
+    // The difference as a multiple of the tolerance, diff / allow_diff, so
+    // that summaries with different tolerances rank fairly.
+    ToleranceRatio,
+    // The distance between x and y in ULPs, per diff::diff_ulps, whatever
+    // the summary's calc_diff.
+    Ulps,
+}
+
+// How to choose the "worst" sign change sample, when that is tracked.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SignWorstMetric {
+    // The item with the largest |x| + |y|.
+    Magnitude,
+    // The item with the largest difference, per the summary's calc_diff.
+    Diff,
+}
+
+// A limit on sign changes for a summary that doesn't allow them outright,
+// since near-zero values may legitimately flip sign occasionally.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SignLimit {
+    // Allow up to this many sign changes.
+    Count(usize),
+    // Allow sign changes in up to this fraction of items, from 0 to 1.
+    Fraction(f64),
+}
+
+impl Display for SignLimit {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SignLimit::Count(count) => write!(f, "limit {}", count),
+            SignLimit::Fraction(fraction) => write!(f, "limit {}%", 100.0 * fraction),
+        }
+    }
+}
+
+// A number of failures to accept, for reference data with a few known-bad
+// items, so that a summary passes despite them.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum FailBudget {
+    // Allow up to this many failures.
+    Count(usize),
+    // Allow failures in up to this fraction of items, from 0 to 1.
+    Fraction(f64),
+}
+
+impl FailBudget {
+    // The number of failures allowed among count items.
+    pub fn allowed(&self, count: usize) -> usize {
+        match *self {
+            FailBudget::Count(allowed) => allowed,
+            FailBudget::Fraction(fraction) => (fraction * count as f64).floor() as usize,
+        }
+    }
+}
+
+// How to handle subnormal values, when they are tracked.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SubnormalPolicy {
+    // Count subnormal values for reporting only.
+    Count,
+    // Also treat subnormal computed (x) values as failures in is_ok and assert.
+    Fail,
+}
+
+// How to treat pairs of zeros with differing signs, such as 0.0 vs -0.0.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SignedZeroPolicy {
+    // Compare them as given, so that they're a sign change, and for some
+    // comparators such as diff_ulps, a large difference.
+    Strict,
+    // Compare them as two positive zeros, for references that are careless
+    // about the sign of zero. Such pairs are counted for reporting only.
+    Ignore,
+}
+
+// What to do with items whose (x, y) pair, compared by bit pattern,
+// has already been added, per DiffSummary::with_duplicate_detection.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DuplicatePolicy {
+    // Count duplicates for reporting, and add them as usual.
+    Count,
+    // Count duplicates, but exclude them from all other statistics.
+    Skip,
+}
+
+// How add_results handles items where exactly one of x and y is an error.
+// Items where both are errors agree, and are only counted.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ErrorPolicy {
+    // Record the item as a failure, with a nan difference, and nan in place of the error.
+    Fail,
+    // Count the item for reporting only.
+    Skip,
+}
+
+// How add_streams handles items left over when one stream is longer.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LengthPolicy {
+    // Return DiffInputError::UnpairedStream, without adding leftover items.
+    Error,
+    // Ignore leftover items, counting only the first as unpaired, since
+    // reading on would never end for an unbounded stream.
+    Truncate,
+    // Add leftover items with nan differences, so that each fails, even
+    // against a nan, and count them as unpaired.
+    PadNan,
+}
+
+// How to round x and y before calculating their difference, so that
+// differences reflect real disagreement rather than representation loss,
+// for example in reference data printed with limited digits.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Quantization {
+    // Round to the given number of significant decimal digits.
+    SignificantDigits(usize),
+    // Round to the nearest multiple of the given number of ULPs.
+    Ulps(u64),
+}
+
+impl Quantization {
+    // Round a value per this quantization. Non-finite values are unchanged.
+    pub fn apply(self, x: f64) -> f64 {
+        if !x.is_finite() {
+            return x;
+        }
+        match self {
+            Quantization::SignificantDigits(digits) => {
+                assert!(digits > 0, "SignificantDigits requires at least 1 digit");
+                // Round-trip through decimal text, matching how printed values were rounded.
+                format!("{:.*e}", digits - 1, x).parse().unwrap()
+            }
+            Quantization::Ulps(granularity) => {
+                assert!(granularity > 0, "Ulps granularity must be positive");
+                let sign = x.to_bits() & (1 << 63);
+                let magnitude = x.to_bits() & !(1 << 63);
+                let rounded = magnitude.saturating_add(granularity / 2) / granularity * granularity;
+                // Rounding up from the largest finite values gives infinity.
+                f64::from_bits(sign | rounded.min(f64::INFINITY.to_bits()))
+            }
+        }
+    }
+}
+
+// Which items to evaluate, for producing a cheap smoke-level summary of a
+// huge dataset from the same call sites as a full comparison.
+// Selection depends only on each item's index, so it is reproducible, and
+// summaries of split datasets still merge consistently.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Sampling {
+    // Evaluate items whose index is a multiple of n.
+    EveryNth(usize),
+    // Evaluate each item with the given probability, chosen by hashing the
+    // index with the seed.
+    Random { probability: f64, seed: u64 },
+}
+
+impl Sampling {
+    // Indicate whether the item with the given index is evaluated.
+    // usize::is_multiple_of is newer than the toolchains this crate supports.
+    #[allow(clippy::manual_is_multiple_of)]
+    pub fn includes(self, index: usize) -> bool {
+        match self {
+            Sampling::EveryNth(n) => {
+                assert!(n > 0, "EveryNth requires n > 0");
+                index % n == 0
+            }
+            Sampling::Random { probability, seed } => {
+                let hash = util::mix64(seed ^ util::mix64(index as u64));
+                // The top 53 bits, as a uniform value in [0, 1).
+                ((hash >> 11) as f64 / (1u64 << 53) as f64) < probability
+            }
+        }
+    }
+}
+
+// The default selection of items that spot_check prints.
+pub const DEFAULT_SPOT_CHECK: Sampling = Sampling::Random { probability: 0.01, seed: 0 };
+
+// Which passing item with_passing_example retains as representative.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PassingExample {
+    // The item with the median difference, among a uniform random sample
+    // of PASSING_POOL passing items.
+    Median,
+    // A uniform random passing item, chosen reproducibly from the seed.
+    Random { seed: u64 },
+}
+
+// Shows "median" or "random".
+impl Display for PassingExample {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PassingExample::Median => write!(f, "median"),
+            PassingExample::Random { .. } => write!(f, "random"),
+        }
+    }
+}
+
+// The number of passing items sampled to choose a median passing example.
+pub const PASSING_POOL: usize = 101;
+
+// Which watchdog limit stopped a summary from ingesting further items.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Truncation {
+    // The deadline, set by with_deadline or with_timeout, passed.
+    Deadline,
+    // The item limit, set by with_max_items, was reached.
+    MaxItems,
+}
+
+// Shows "deadline" or "max items".
+impl Display for Truncation {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Truncation::Deadline => write!(f, "deadline"),
+            Truncation::MaxItems => write!(f, "max items"),
+        }
+    }
+}
+
+// How a pair of values would score against a summary, as returned by evaluate.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ItemOutcome {
+    // The difference, per the summary's calc_diff.
+    pub diff: f64,
+    // Whether x and y differ in sign, per the summary's calc_diff.
+    pub sign_change: bool,
+    // Whether x is over or under y, or None if equal or either is nan.
+    pub direction: Option<Direction>,
+    // Whether the difference is within the summary's tolerance.
+    pub within_tolerance: bool,
+    // Whether the sign change status is acceptable to the summary.
+    pub sign_ok: bool,
+    // Whether the difference would become the summary's new worst.
+    pub is_worst: bool,
+    // The histogram bucket the difference would be counted in.
+    pub bucket: BucketKey,
+}
+
+impl ItemOutcome {
+    // Indicate whether the pair passes both tolerance and sign change checks.
+    pub fn is_ok(&self) -> bool {
+        self.within_tolerance && self.sign_ok
+    }
+}
+
+// How a recorded item scored, as passed to an observer in an ItemRecord.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ItemStatus {
+    // The difference was zero, with no disallowed sign change.
+    Exact,
+    // The difference was within tolerance, and within warn_diff if set.
+    Ok,
+    // The difference was within tolerance, but beyond warn_diff.
+    Warned,
+    // The difference was outside tolerance, or the sign changed where
+    // sign changes aren't allowed.
+    Failed,
+}
+
+// A structured description of one recorded item, as passed to the observer
+// set by with_observer, for feeding comparisons into a database, message
+// queue or other downstream pipeline.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ItemRecord {
+    pub index: usize,
+    pub x: f64,
+    pub y: f64,
+    // The difference, per the summary's calc_diff.
+    pub diff: f64,
+    // Whether x and y differ in sign, after any sign change exemptions.
+    pub sign_change: bool,
+    // Whether x is over or under y, or None if equal or either is nan.
+    pub direction: Option<Direction>,
+    pub outcome: ItemStatus,
+    // The histogram bucket the difference was counted in.
+    pub bucket: BucketKey,
+}
+
+// A summary's headline results, as returned by snapshot and passed to the
+// publisher set by with_publisher, for dashboards that update as results
+// change rather than scraping Display output.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SummarySnapshot {
+    pub count: usize,
+    pub fail_count: usize,
+    pub sign_count: usize,
+    pub worst_diff: f64,
+    pub is_ok: bool,
+}
+
+// A function receiving snapshots, and the changes in worst difference and
+// failure count beyond which it's called: (publish, worst_delta, fail_delta).
+type Publisher<'a> = (&'a dyn Fn(&SummarySnapshot), f64, usize);
+
+// A named alternative tolerance, evaluated alongside a summary's own, and
+// its failing items: (name, allow_diff, failures).
+type Profile<'a> = (&'a str, f64, DiffPartSummary);
+
+// A secondary comparator and the histogram of its differences: (label, calc_diff, histogram).
+type View<'a> = (&'a str, &'a dyn Fn(f64, f64) -> (f64, bool), LogHistogram);
+
+// A convergence estimate and the function giving each index's step size,
+// or None to use 1 / index: (convergence, step).
+type ConvergenceCheck<'a> = (Convergence, Option<&'a dyn Fn(usize) -> f64>);
+
+// A summary's configuration, in the tuple form used by new_vec:
+// (name, allow_diff, allow_sign, calc_diff)
+pub type SummaryInfo<'a> = (&'a str, f64, bool, &'a dyn Fn(f64, f64) -> (f64, bool));
 
 // An object for tracking a series of test results for a the same measurement type,
 // recording how they compare to the expected value for the test case, and 
@@ -17,14 +376,66 @@ pub struct DiffSummary<'a>
     // The maximum allowable difference for this summary to consider an item successful.
     allow_diff: f64,
 
+    // The unit of differences, shown after differences in Display output. Empty for none.
+    unit: &'a str,
+
     // Indicates whether the summary should allow sign changes when deciding whether an item is successful.
     allow_sign: bool,
 
     // The total number of items added to this summary.
     num_total: usize,
 
-    // The number of items that have failed based on difference (ignoring sign change).
-    num_diff_fail: usize,
+    // The number of items with finite differences, and the sum and sum of
+    // squares of those differences, for mean and RMS reporting.
+    num_finite: usize,
+    sum_diff: f64,
+    sum_sq_diff: f64,
+
+    // The number of items where x and y have identical bit patterns.
+    num_identical: usize,
+
+    // The number of items excluded by filter, which aren't included in num_total.
+    num_skipped: usize,
+
+    // If set, which items to evaluate. Others are only counted, in num_unsampled.
+    sampling: Option<Sampling>,
+
+    // The number of items excluded by sampling, which aren't included in num_total.
+    num_unsampled: usize,
+
+    // The number of x and y items left over from longer streams and read by add_streams.
+    num_unpaired: (usize, usize),
+
+    // Watchdog limits. Once either is reached, later items are ignored and
+    // truncation records which limit stopped ingestion.
+    deadline: Option<Instant>,
+    max_items: Option<usize>,
+
+    // The number of items offered before any limit was reached, including
+    // items excluded by sampling or filter.
+    num_offered: usize,
+    truncation: Option<Truncation>,
+
+    // How add_results handles items where only one of x and y is an error.
+    error_policy: ErrorPolicy,
+
+    // The number of add_results items where both x and y, only x, or only y
+    // were errors. Only mismatches recorded under ErrorPolicy::Fail are
+    // included in num_total.
+    num_both_errors: usize,
+    num_x_errors: usize,
+    num_y_errors: usize,
+
+    // Count of items that have failed based on difference (ignoring sign change),
+    // and information about the first such item.
+    summary_fail: DiffPartSummary,
+
+    // If set, a softer threshold below allow_diff. Items above it that are
+    // still within tolerance are counted as warnings, without failing.
+    warn_diff: Option<f64>,
+
+    // Count of items with warnings, and information about the first such item.
+    summary_warn: DiffPartSummary,
 
     // Count of items with non-zero diffs, and information about the item with the worst diff.
     summary_diff: DiffPartSummary,
@@ -32,202 +443,3068 @@ pub struct DiffSummary<'a>
     // Count of items with sign changes, and information about the first such item.
     summary_sign: DiffPartSummary,
 
+    // If set, sign changes where both |x| and |y| are below this floor are
+    // exempt from sign change counting, such as -1e-300 vs 1e-300.
+    sign_epsilon: Option<f64>,
+
+    // The number of sign changes exempted by sign_epsilon.
+    num_sign_exempt: usize,
+
+    // The number of items with non-zero differences where x was over,
+    // or under, y.
+    num_over: usize,
+    num_under: usize,
+
+    // If set, the number of items outside tolerance to accept.
+    fail_budget: Option<FailBudget>,
+
+    // Indices of items expected to fail, in ascending order.
+    expected_failures: Vec<usize>,
+
+    // Count of expected failures that failed, and information about the first.
+    // These items are excluded from all other statistics.
+    summary_xfail: DiffPartSummary,
+
+    // The number of expected failures that passed.
+    num_xpass: usize,
+
+    // How to compare zeros of differing sign.
+    signed_zero_policy: SignedZeroPolicy,
+
+    // The number of pairs of zeros of differing sign compared as equal,
+    // under SignedZeroPolicy::Ignore.
+    num_signed_zero: usize,
+
+    // If set, pairs where both |x| and |y| are below this floor are compared
+    // as zeros, such as 0.0 vs 5e-324.
+    zero_epsilon: Option<f64>,
+
+    // The number of pairs compared as zeros under zero_epsilon.
+    num_near_zero: usize,
+
+    // Whether any pair with a nan or infinite value fails, per with_strict_mode.
+    strict: bool,
+
+    // The number of passing pairs with a nan or infinite value, which
+    // strict mode would have failed.
+    num_nonfinite_passed: usize,
+
+    // If set, how to treat items whose (x, y) bit patterns were already seen.
+    duplicate_policy: Option<DuplicatePolicy>,
+
+    // The distinct (x, y) bit patterns seen, with duplicate detection.
+    seen_pairs: HashSet<(u64, u64)>,
+
+    // The number of items whose (x, y) bit patterns were already seen.
+    num_duplicates: usize,
+
+    // If set, and sign changes aren't allowed, the number or fraction of sign changes to accept.
+    sign_limit: Option<SignLimit>,
+
+    // If set, how to choose the worst sign change item to track in summary_sign_worst.
+    sign_worst_metric: Option<SignWorstMetric>,
+
+    // Information about the worst sign change item, per sign_worst_metric.
+    summary_sign_worst: DiffPartSummary,
+
+    // The sign_worst_metric value of the item in summary_sign_worst.
+    sign_worst: f64,
+
+    // What worst_sample and worst_score consider worst.
+    worst_metric: WorstMetric,
+
+    // With WorstMetric::Ulps, information about the item with the most ULPs,
+    // and that item's ULPs.
+    summary_ulps: DiffPartSummary,
+    worst_ulps: f64,
+
     // A partially logarithmic breakdown of differences.
     histo: LogHistogram,
 
+    // Secondary comparators whose differences feed additional histograms,
+    // without affecting pass/fail status.
+    views: Vec<View<'a>>,
+
+    // The (index, x, y, diff) of failing items, in the order added.
+    // Collection stops once failure_capacity items have been collected.
+    failures: Vec<(usize, f64, f64, f64)>,
+
+    // The maximum number of failing items to collect. Zero disables collection.
+    failure_capacity: usize,
+
+    // If enabled, a least-squares fit of x (computed) against y (expected),
+    // and the allowed deviation of the fitted slope from 1.
+    fit: Option<(LinearFit, f64)>,
+
+    // If enabled, per-segment statistics over the index range, for drift reporting.
+    drift: Option<DriftSegments>,
+
+    // If enabled, bootstrap replicates for confidence intervals on the mean
+    // difference and failure rate.
+    bootstrap: Option<Bootstrap>,
+
+    // If enabled, the estimated order of convergence, and how to find each index's step size.
+    convergence: Option<ConvergenceCheck<'a>>,
+
+    // If enabled, an identifier of the expected values, checked when comparing runs.
+    fingerprint: Option<Fingerprint>,
+
+    // If enabled, the range of expected magnitudes seen.
+    extremes: Option<Extremes>,
+
+    // If enabled, the worst differences under other comparators, for
+    // suggesting tolerances.
+    suggestion: Option<ToleranceSuggestion>,
+
+    // If enabled, a fit of the errors in ulps of the expected values.
+    error_model: Option<ErrorModel>,
+
+    // Alternative tolerances, evaluated against every item.
+    profiles: Vec<Profile<'a>>,
+
+    // Sub-summaries of the items in each declared index range, for isolating regional behavior.
+    ranges: Vec<(Range<usize>, DiffSummary<'a>)>,
+
+    // If enabled, a summary per IEEE class and sign of the expected value.
+    classes: Option<BTreeMap<ValueClass, DiffSummary<'a>>>,
+
+    // Elapsed-time statistics, once items are added with add_timed.
+    timing: Option<Timing>,
+
+    // If enabled, a breakdown of failing items by cause and magnitude band.
+    triage: Option<Triage>,
+
+    // If set, how to round x and y before calculating their difference.
+    quantization: Option<Quantization>,
+
+    // If set, how to handle subnormal values, which are only counted when set.
+    subnormal_policy: Option<SubnormalPolicy>,
+
+    // Count of items with subnormal x, and information about the first such item.
+    summary_subnormal_x: DiffPartSummary,
+
+    // The number of items with subnormal y.
+    num_subnormal_y: usize,
+
+    // The number of items with normal x and y whose difference x - y is subnormal.
+    num_subnormal_cancel: usize,
+
+    // The items with the worst differences, whether or not they fail.
+    // Has zero capacity unless enabled.
+    top: TopN,
+
+    // A uniform random sample of items, whether or not they fail.
+    // Has zero capacity unless enabled.
+    reservoir: Reservoir,
+
+    // If enabled, the kind of passing example, and a sample of passing
+    // items to choose it from.
+    passing: Option<(PassingExample, Reservoir)>,
+
+    // If enabled, the (index, x, y, diff) of the item with the worst
+    // difference in each unreduced histogram bucket.
+    bucket_samples: Option<BTreeMap<BucketKey, (usize, f64, f64, f64)>>,
+
+    // The (index, x, y) of an item staged for addition, pending commit or discard.
+    staged: Option<(usize, f64, f64)>,
+
+    // Key/value pairs describing the run, such as a seed or commit, in the order added.
+    metadata: Vec<(String, String)>,
+
+    // Context prefixed to assert failure messages. Empty for none.
+    assert_context: String,
+
+    // Context attached to sample records of items added, per set_sample_context.
+    sample_context: Option<Rc<str>>,
+
+    // How to format x and y values in Display output and assert messages.
+    float_format: FloatFormat,
+
+    // Which items spot_check prints.
+    spot_check: Sampling,
+
+    // Options used by Display.
+    display_options: DisplayOptions,
+
+    // If set, a predicate given (index, x, y) that returns false for items to skip.
+    filter: Option<&'a dyn Fn(usize, f64, f64) -> bool>,
+
+    // If set, called with a formatted line for every item recorded.
+    trace: Option<&'a dyn Fn(&str)>,
+
+    // If set, called with a structured record of every item recorded.
+    observer: Option<&'a dyn Fn(&ItemRecord)>,
+
+    // If set, called with a snapshot when results change enough.
+    publisher: Option<Publisher<'a>>,
+
+    // The (worst difference, failure count) when last published.
+    published: (f64, usize),
+
+    // If set, a CSV sink for every item recorded.
+    csv_tee: Option<CsvTee>,
+
+    // If enabled, a file-backed store of every recorded item.
+    spill: Option<Spill>,
+
+    // If enabled, the (index, x, y) of every recorded item, in the order recorded.
+    retained: Option<Vec<(usize, f64, f64)>>,
+
+    // The tracing span that failure and report events are emitted within.
+    #[cfg(feature = "tracing")]
+    span: tracing::Span,
+
     // The function to use when calculating the difference and sign change status of a value pair.
     pub calc_diff: &'a dyn Fn(f64, f64) -> (f64, bool),
+
+    // The function to use when deciding whether x is over or under y, which
+    // calc_diff doesn't report. Defaults to diff::direction.
+    calc_direction: &'a dyn Fn(f64, f64) -> Option<Direction>,
 }
 
 impl<'a> DiffSummary<'a> {
     pub fn new(name: &'a str, allow_diff: f64, allow_sign: bool, bucket_count: usize, calc_diff: &'a dyn Fn(f64, f64) -> (f64, bool)) -> Self {
+        assert!(allow_diff >= 0.0, "summary {} tolerance must be non-negative, got {}", name, allow_diff);
         DiffSummary {
-            name: name,
-            allow_diff: allow_diff,
-            allow_sign: allow_sign,
+            name,
+            allow_diff,
+            allow_sign,
+            unit: "",
             diff: 0.0,
             num_total: 0,
-            num_diff_fail: 0,
+            num_finite: 0,
+            sum_diff: 0.0,
+            sum_sq_diff: 0.0,
+            num_identical: 0,
+            num_skipped: 0,
+            sampling: None,
+            num_unsampled: 0,
+            num_unpaired: (0, 0),
+            deadline: None,
+            max_items: None,
+            num_offered: 0,
+            truncation: None,
+            error_policy: ErrorPolicy::Fail,
+            num_both_errors: 0,
+            num_x_errors: 0,
+            num_y_errors: 0,
+            summary_fail: DiffPartSummary::new(),
+            warn_diff: None,
+            summary_warn: DiffPartSummary::new(),
             summary_diff: DiffPartSummary::new(),
             summary_sign: DiffPartSummary::new(),
+            sign_epsilon: None,
+            num_sign_exempt: 0,
+            num_over: 0,
+            num_under: 0,
+            fail_budget: None,
+            expected_failures: Vec::new(),
+            summary_xfail: DiffPartSummary::new(),
+            num_xpass: 0,
+            signed_zero_policy: SignedZeroPolicy::Strict,
+            num_signed_zero: 0,
+            zero_epsilon: None,
+            num_near_zero: 0,
+            strict: false,
+            num_nonfinite_passed: 0,
+            duplicate_policy: None,
+            seen_pairs: HashSet::new(),
+            num_duplicates: 0,
+            sign_limit: None,
+            sign_worst_metric: None,
+            summary_sign_worst: DiffPartSummary::new(),
+            sign_worst: 0.0,
+            worst_metric: WorstMetric::Diff,
+            summary_ulps: DiffPartSummary::new(),
+            worst_ulps: 0.0,
             histo: LogHistogram::new(bucket_count),
-            calc_diff: calc_diff,
+            views: Vec::new(),
+            failures: Vec::new(),
+            failure_capacity: 0,
+            fit: None,
+            bootstrap: None,
+            convergence: None,
+            fingerprint: None,
+            extremes: None,
+            suggestion: None,
+            error_model: None,
+            drift: None,
+            profiles: Vec::new(),
+            ranges: Vec::new(),
+            classes: None,
+            timing: None,
+            triage: None,
+            quantization: None,
+            subnormal_policy: None,
+            summary_subnormal_x: DiffPartSummary::new(),
+            num_subnormal_y: 0,
+            num_subnormal_cancel: 0,
+            top: TopN::new(0),
+            reservoir: Reservoir::new(0, 0),
+            passing: None,
+            bucket_samples: None,
+            staged: None,
+            metadata: Vec::new(),
+            assert_context: String::new(),
+            sample_context: None,
+            float_format: FloatFormat::Exp,
+            spot_check: DEFAULT_SPOT_CHECK,
+            display_options: DisplayOptions::new(),
+            filter: None,
+            trace: None,
+            observer: None,
+            publisher: None,
+            published: (0.0, 0),
+            csv_tee: None,
+            spill: None,
+            retained: None,
+            #[cfg(feature = "tracing")]
+            span: tracing::info_span!("diff_summary", name),
+            calc_diff,
+            calc_direction: &diff::direction,
         }
     }
 
-    // Create a vector of DiffSummary based on a slice of tuples with the form:
-    // (name, allow_diff, allow_sign, calc_diff)
-    pub fn new_vec(bucket_count: usize, infos: &'a [(&str, f64, bool, &'a dyn Fn(f64, f64) -> (f64, bool))]) -> Vec<Self> {
-        infos.iter().map(|(name, allow_diff, allow_sign, calc_diff)| {
-            DiffSummary {
-                name: name,
-                allow_diff: *allow_diff,
-                allow_sign: *allow_sign,
-                diff: 0.0,
-                num_total: 0,
-                num_diff_fail: 0,
-                summary_diff: DiffPartSummary::new(),
-                summary_sign: DiffPartSummary::new(),
-                histo: LogHistogram::new(bucket_count),
-                calc_diff: calc_diff,
+    // Check that this summary's tolerance is plausible for kind, the kind of
+    // its calc_diff, per ComparatorKind::check, handling an implausible
+    // tolerance per check. ToleranceCheck::Off opts out explicitly.
+    pub fn with_tolerance_check(self, kind: ComparatorKind, check: ToleranceCheck) -> Self {
+        if let Err(err) = kind.check(self.allow_diff) {
+            match check {
+                ToleranceCheck::Off => {}
+                ToleranceCheck::Warn => eprintln!("float-diff: warning: {}: {}", self.name, err),
+                ToleranceCheck::Error => panic!("{}: {}", self.name, err),
             }
-        }).collect()
+        }
+        self
     }
 
-    // Given x and y, calculate their difference and sign change status,
-    // then check whether any of those values is the worst seen so far
-    // for comparable operations. If it is, record the iteration
-    // information and the new worst difference.
-    // For purposes of deciding "worst", infinity is worse than any
-    // finite number, and nan is worse than infinity.
-    pub fn add(&mut self, x: f64, y: f64, index: usize) {
-        self.num_total += 1;
-        let (diff, sign_change) = (*self.calc_diff)(x, y);
-        let is_diff_worst = crate::diff::is_diff_worse(diff, self.diff);
-        // Funky negation on next line is intentional, to get desired nan behavior.
-        if !(diff == 0.0) {
-            self.summary_diff.add(x, y, index, is_diff_worst);
-            if is_diff_worst {
-                self.diff = diff;
-            }
-            // Funky negation on next line is intentional, to get desired nan behavior.
-            if !(diff <= self.allow_diff) {
-                self.num_diff_fail += 1;
-            }
-        }
-        // For the sign change check, allow (NAN vs NAN), but not (0.0 vs -0.0) or (NAN vs -NAN).
-        if sign_change {
-            self.summary_sign.add(x, y, index, false);
-        }
-        self.histo.add(diff);
+    // Collect up to capacity failing items, retrievable via failures().
+    // Intended for feeding failing cases back into a focused rerun.
+    pub fn with_failure_capacity(mut self, capacity: usize) -> Self {
+        self.failure_capacity = capacity;
+        self
     }
 
-    // Indicate whether data currently satisfies allowed tolerance and sign change acceptance.
-    pub fn is_ok(&self) -> bool {
-        self.diff <= self.allow_diff && (self.allow_sign || self.summary_sign.count == 0)
+    // Fit x (computed) against y (expected) by least squares, to detect scaling
+    // errors like x = 1.0000003 * y, and flag fitted slopes that deviate from
+    // 1 by more than allow_slope_deviation. Reported in Display.
+    pub fn with_linear_fit(mut self, allow_slope_deviation: f64) -> Self {
+        self.fit = Some((LinearFit::new(), allow_slope_deviation));
+        self
     }
 
-    // Assert that worst diff is within tolerance,
-    // then assert that sign change status is allowed.
-    pub fn assert(&self) {
-        assert!(
-            self.diff <= self.allow_diff,
-            "assert failed item {}, {}: {}{:e} vs {}{:e} diff abs {:e} outside inclusive {:e}",
-            self.summary_diff.sample_index,
-            self.name,
-            util::help_sign(self.summary_diff.sample_x),
-            self.summary_diff.sample_x,
-            util::help_sign(self.summary_diff.sample_y),
-            self.summary_diff.sample_y,
-            self.diff,
-            self.allow_diff
-        );
-        assert!(
-            self.allow_sign || self.summary_sign.count == 0,
-            "assert failed item {}, {}: {}{:e} vs {}{:e} sign difference disallowed.",
-            self.summary_sign.sample_index,
-            self.name,
-            util::help_sign(self.summary_sign.sample_x),
-            self.summary_sign.sample_x,
-            util::help_sign(self.summary_sign.sample_y),
-            self.summary_sign.sample_y
-        );
+    // Split indices [0, index_count) into segment_count contiguous segments,
+    // tracking the worst and mean difference of each, to report whether
+    // differences grow over a series. Reported in Display.
+    pub fn with_drift(mut self, segment_count: usize, index_count: usize) -> Self {
+        self.drift = Some(DriftSegments::new(segment_count, index_count));
+        self
     }
-}
 
-impl Clone for DiffSummary<'_> {
-        fn clone(&self) -> Self {
-            DiffSummary {
-                name: self.name,
-                diff: self.diff,
-                allow_diff: self.allow_diff,
-                allow_sign: self.allow_sign,
-                num_total: self.num_total,
-                num_diff_fail: self.num_diff_fail,
-                summary_diff: self.summary_diff.clone(),
-                summary_sign: self.summary_sign.clone(),
-                histo: self.histo.clone(),
-                calc_diff: self.calc_diff,
-            }
-        }
-}
+    // Track the worst difference each of the abs, rel and ulps comparators
+    // would give, for suggesting the tightest tolerance each would pass,
+    // widened by margin, e.g. 2.0 for twice the worst difference seen.
+    // Print tolerance_suggestion for a configuration block to paste into
+    // a config file, when the achievable accuracy of a new channel isn't known.
+    pub fn with_tolerance_suggestion(mut self, margin: f64) -> Self {
+        self.suggestion = Some(ToleranceSuggestion::new(self.name, margin));
+        self
+    }
 
-impl Display for DiffSummary<'_> {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::result::Result<(), std::fmt::Error> {
-        assert!(self.num_diff_fail <= self.num_total);
-        write!(
-            f,
-            "{}{}count {}",
-            self.name,
-            if self.name.len() > 0 { ": " } else { "" },
-            self.num_total
-        )?;
-        if self.summary_diff.count > 0 {
-            write!(
-                f,
-                ", worst index {} {}{:e} vs {}{:e} diff {:e}, {}% failed tolerance {:e}, {}",
-                self.summary_diff.sample_index,
-                util::help_sign(self.summary_diff.sample_x),
-                self.summary_diff.sample_x,
-                util::help_sign(self.summary_diff.sample_y),
-                self.summary_diff.sample_y,
-                self.diff,
-                util::to_percent(self.num_diff_fail, self.num_total),
-                self.allow_diff,
-                self.histo,
-            )?;
-        } else if self.num_total > 0 {
-            write!(f, ", zero 100%, 0% failed tolerance {:e}", self.allow_diff)?;
-        }
-        if self.num_total > 0 {
-            write!(
-                f,
-                ", sign diffs {}%",
-                util::to_percent(self.summary_sign.count, self.num_total),
-            )?;
-            if self.summary_sign.count > 0 {
-                write!(f,
-                    " first index {} {}{:e} vs {}{:e}",
-                    self.summary_sign.sample_index,
-                    util::help_sign(self.summary_sign.sample_x),
-                    self.summary_sign.sample_x,
-                    util::help_sign(self.summary_sign.sample_y),
-                    self.summary_sign.sample_y,
-                )?;
-            }
-        }
-        Ok(())
+    // Fit an ErrorModel to each item's signed error in ulps of its expected
+    // value, whatever the comparator, shown in verbose Display, for
+    // validating that a math library's errors are consistent with rounding noise.
+    pub fn with_error_model(mut self) -> Self {
+        self.error_model = Some(ErrorModel::new());
+        self
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::{DiffSummary};
-    use crate::diff;
-    use std::f64;
+    // Track the smallest non-zero and largest finite expected (y) magnitudes,
+    // and the number of subnormal and huge expected values, shown in Display,
+    // for checking that the items cover the intended dynamic range.
+    pub fn with_extremes(mut self) -> Self {
+        self.extremes = Some(Extremes::new());
+        self
+    }
 
-    #[test]
-    fn test1() {
-        let data = &[
-            (0.0, 1.0),
-            (2.0, 1.0),
-            (1.0, 10.0),
-            (0.1, -0.1),
-            (f64::NAN, f64::NAN),
-        ];
-        let mut summary = DiffSummary::new("simple", 1.0, false, 4, &diff::diff_abs);
-        for (i, item) in data.iter().enumerate() {
-            summary.add(item.0, item.1, i);
-        }
-        println!();
-        println!("{}", summary);
-        assert!(!summary.is_ok());
+    // Estimate 95% confidence intervals for the mean difference and failure
+    // rate, reported in verbose Display, using a streaming bootstrap with
+    // the given number of replicates, such as 1000, seeded for reproducibility.
+    // Each item costs time proportional to the number of replicates.
+    pub fn with_bootstrap(mut self, replicate_count: usize, seed: u64) -> Self {
+        self.bootstrap = Some(Bootstrap::new(replicate_count, seed));
+        self
     }
 
-    #[test]
-    fn test2() {
-        let data = &[
-            (0.0, 0.0, 1.0, 1.0),
+    // Fingerprint the expected (y) values by their count and a hash of each
+    // value and index, including filtered items, so that compare_to refuses
+    // baselines of different data.
+    pub fn with_fingerprint(mut self) -> Self {
+        self.fingerprint = Some(Fingerprint::values());
+        self
+    }
+
+    // Identify the dataset by a user-supplied id, such as a file name and
+    // version, so that compare_to refuses baselines with a different id.
+    // Cheaper than with_fingerprint, but relies on the id being kept current.
+    pub fn with_dataset_id(mut self, id: &str) -> Self {
+        self.fingerprint = Some(Fingerprint::Id(id.to_string()));
+        self
+    }
+
+    // Check that differences shrink at a rate of at least min_order as index
+    // grows, where index counts refinement, such as grid points per unit,
+    // so that differences are expected to shrink as index^-order.
+    // Fails is_ok if the estimated order, reported in Display, is lower.
+    pub fn with_convergence(mut self, min_order: f64) -> Self {
+        self.convergence = Some((Convergence::new(min_order), None));
+        self
+    }
+
+    // As with_convergence, where step gives each index's refinement
+    // parameter, such as grid spacing, so that differences are expected to
+    // shrink as step(index)^order.
+    pub fn with_convergence_by(mut self, min_order: f64, step: &'a dyn Fn(usize) -> f64) -> Self {
+        self.convergence = Some((Convergence::new(min_order), Some(step)));
+        self
+    }
+
+    // Also evaluate items against an alternative tolerance, named e.g. "dev"
+    // or "ci", so that one run answers the gating questions of several
+    // platforms, such as a looser tolerance for machines with FMA variations.
+    // Profiles share this summary's sign policy and fail budget. Check one
+    // with is_profile_ok or assert_profile. Reported in Display.
+    pub fn with_profile(mut self, name: &'a str, allow_diff: f64) -> Self {
+        assert!(allow_diff >= 0.0, "profile {} tolerance must be non-negative, got {}", name, allow_diff);
+        assert!(self.profiles.iter().all(|profile| profile.0 != name), "Duplicate tolerance profile {}", name);
+        self.profiles.push((name, allow_diff, DiffPartSummary::new()));
+        self
+    }
+
+    // Also summarize the items in each index range separately, e.g. where a
+    // mesh refines, retrievable via range_summaries. Each range summary takes
+    // this summary's tolerance, warning level, sign policy and unit as
+    // configured so far. Ranges may overlap. Reported in Display.
+    pub fn with_ranges(mut self, ranges: &[Range<usize>]) -> Self {
+        for range in ranges {
+            let summary = self.sub_summary();
+            self.ranges.push((range.clone(), summary));
+        }
+        self
+    }
+
+    // Also summarize items separately by the IEEE class and sign of their
+    // expected value (zero, subnormal, normal, huge, infinite or nan), so
+    // that weakness in one regime isn't averaged away, retrievable via
+    // class_summaries. Each class summary takes this summary's tolerance,
+    // warning level, sign policy and unit. Reported in Display.
+    pub fn with_class_strata(mut self) -> Self {
+        self.classes = Some(BTreeMap::new());
+        self
+    }
+
+    // An empty summary with this summary's tolerance, warning level,
+    // sign policy, unit and sample context, for summarizing a subset of
+    // its items.
+    fn sub_summary(&self) -> DiffSummary<'a> {
+        let mut summary = DiffSummary::new(self.name, self.allow_diff, self.allow_sign, self.histo.max_display_buckets(), self.calc_diff);
+        summary.histo.negligible = self.histo.negligible;
+        summary.histo.signed = self.histo.signed;
+        summary.histo.boundary = self.histo.boundary;
+        summary.unit = self.unit;
+        summary.calc_direction = self.calc_direction;
+        summary.warn_diff = self.warn_diff;
+        summary.sign_epsilon = self.sign_epsilon;
+        summary.fail_budget = self.fail_budget;
+        summary.signed_zero_policy = self.signed_zero_policy;
+        summary.zero_epsilon = self.zero_epsilon;
+        summary.strict = self.strict;
+        summary.sign_limit = self.sign_limit;
+        summary.subnormal_policy = self.subnormal_policy;
+        summary.sample_context = self.sample_context.clone();
+        summary
+    }
+
+    // Break failing items down by likely cause (nan, infinite, sign change,
+    // near-zero or large expected value) and by the magnitude band of the
+    // expected value, ranked in Display to show where failures concentrate.
+    // Expected values below near_zero or above large in magnitude are
+    // classified as such.
+    pub fn with_triage(mut self, near_zero: f64, large: f64) -> Self {
+        self.triage = Some(Triage::new(near_zero, large));
+        self
+    }
+
+    // Count items whose difference exceeds warn_diff but is still within
+    // tolerance, so that creeping degradation is visible before it fails.
+    pub fn with_warn_diff(mut self, warn_diff: f64) -> Self {
+        assert!(warn_diff <= self.allow_diff, "warn_diff {:e} must not exceed allow_diff {:e}", warn_diff, self.allow_diff);
+        self.warn_diff = Some(warn_diff);
+        self
+    }
+
+    // Retain the n items with the worst differences, for outlier reporting.
+    pub fn with_top_n(mut self, n: usize) -> Self {
+        self.top = TopN::new(n);
+        self
+    }
+
+    // Retain a uniform random sample of up to capacity items, chosen
+    // reproducibly from seed, for scatter plots and sanity checks.
+    pub fn with_reservoir(mut self, capacity: usize, seed: u64) -> Self {
+        self.reservoir = Reservoir::new(capacity, seed);
+        self
+    }
+
+    // Retain a representative passing item, such as the one with the
+    // median difference, for showing what typical agreement looks like
+    // alongside the failures. Reported in Display output with samples,
+    // unless turned off with DisplayOptions::with_passing_example.
+    pub fn with_passing_example(mut self, example: PassingExample) -> Self {
+        let reservoir = match example {
+            PassingExample::Median => Reservoir::new(PASSING_POOL, 0),
+            PassingExample::Random { seed } => Reservoir::new(1, seed),
+        };
+        self.passing = Some((example, reservoir));
+        self
+    }
+
+    // Track the item with the worst difference in each histogram bucket,
+    // so that each region of the distribution has a concrete example.
+    // Reported in verbose Display output with samples.
+    pub fn with_bucket_samples(mut self) -> Self {
+        self.bucket_samples = Some(BTreeMap::new());
+        self
+    }
+
+    // Choose how add_results handles items where only one of x and y is an
+    // error. The default is ErrorPolicy::Fail.
+    pub fn with_error_policy(mut self, policy: ErrorPolicy) -> Self {
+        self.error_policy = policy;
+        self
+    }
+
+    // Round x and y before calculating their difference.
+    // Samples still report the values as added.
+    pub fn with_quantization(mut self, quantization: Quantization) -> Self {
+        self.quantization = Some(quantization);
+        self
+    }
+
+    // Count subnormal x values, subnormal y values, and cancellations of normal
+    // x and y to a subnormal difference, which are often a red flag even when
+    // values are close. With SubnormalPolicy::Fail, subnormal x values also fail.
+    pub fn with_subnormal_policy(mut self, policy: SubnormalPolicy) -> Self {
+        self.subnormal_policy = Some(policy);
+        self
+    }
+
+    // Exempt sign changes where both |x| and |y| are below sign_epsilon from
+    // sign change counting, since tiny values flipping sign is rarely meaningful.
+    // Exempted sign changes are counted separately.
+    pub fn with_sign_epsilon(mut self, sign_epsilon: f64) -> Self {
+        assert!(sign_epsilon >= 0.0, "sign_epsilon must be non-negative, got {:e}", sign_epsilon);
+        self.sign_epsilon = Some(sign_epsilon);
+        self
+    }
+
+    // Detect items whose (x, y) pair repeats an earlier item's bit for bit,
+    // such as from generated inputs that overlap, counting them, and per
+    // DuplicatePolicy, optionally skipping them. Filtered items aren't checked.
+    // Memory grows with the number of distinct pairs.
+    pub fn with_duplicate_detection(mut self, policy: DuplicatePolicy) -> Self {
+        self.duplicate_policy = Some(policy);
+        self
+    }
+
+    // Choose whether 0.0 vs -0.0 is a sign change, per SignedZeroPolicy.
+    // Applies to every comparator, since the zeros are made positive before
+    // calc_diff sees them.
+    pub fn with_signed_zero_policy(mut self, policy: SignedZeroPolicy) -> Self {
+        self.signed_zero_policy = policy;
+        self
+    }
+
+    // Compare pairs where both |x| and |y| are below zero_epsilon as zeros,
+    // so that values such as 0.0 vs 5e-324 don't produce subnormal-scale
+    // differences that dominate relative or ulps rankings. Applies to every
+    // comparator, since the values are replaced by 0.0 before calc_diff sees
+    // them. Such pairs are counted separately, and aren't also counted as
+    // ignored signed zeros.
+    pub fn with_zero_epsilon(mut self, zero_epsilon: f64) -> Self {
+        assert!(zero_epsilon >= 0.0, "zero_epsilon must be non-negative, got {:e}", zero_epsilon);
+        self.zero_epsilon = Some(zero_epsilon);
+        self
+    }
+
+    // Fail every pair where x or y is nan or infinite, with a nan
+    // difference, rather than following the comparator, which treats
+    // pairs such as (nan, nan) or (inf, inf) as equal. For audits that
+    // need a guarantee that no such pair passed silently. Verbose Display
+    // output states whether strict mode is on. See also registry::STRICT_ENV.
+    pub fn with_strict_mode(mut self) -> Self {
+        self.strict = true;
+        self
+    }
+
+    // Accept up to count items outside tolerance, including nan differences,
+    // for reference data with known-bad items. is_ok and assert pass within
+    // the budget, and Display reports how much of it was used.
+    pub fn with_allow_fail_count(mut self, count: usize) -> Self {
+        self.fail_budget = Some(FailBudget::Count(count));
+        self
+    }
+
+    // As with_allow_fail_count, for up to a fraction of items, from 0 to 1.
+    pub fn with_allow_fail_fraction(mut self, fraction: f64) -> Self {
+        assert!((0.0..=1.0).contains(&fraction), "allowed fail fraction must be within [0, 1], got {}", fraction);
+        self.fail_budget = Some(FailBudget::Fraction(fraction));
+        self
+    }
+
+    // Quarantine the items at the given indices as known issues. Those that
+    // fail tolerance, or change sign where that's disallowed, are counted as
+    // expected failures, and are otherwise excluded from the summary, so
+    // they don't fail is_ok or assert. Those that pass are recorded as usual,
+    // and counted as unexpectedly passing, as a cue to remove them.
+    pub fn with_expected_failures(mut self, indices: &[usize]) -> Self {
+        self.expected_failures.extend_from_slice(indices);
+        self.expected_failures.sort_unstable();
+        self.expected_failures.dedup();
+        self
+    }
+
+    // Accept sign changes up to a count or fraction of items, rather than none.
+    // Has no effect if the summary allows sign changes outright.
+    pub fn with_sign_limit(mut self, limit: SignLimit) -> Self {
+        if let SignLimit::Fraction(fraction) = limit {
+            assert!((0.0..=1.0).contains(&fraction), "SignLimit::Fraction must be within [0, 1], got {}", fraction);
+        }
+        self.sign_limit = Some(limit);
+        self
+    }
+
+    // Also track the worst sign change, in addition to the first.
+    // The first sign change is often a harmless case like 0.0 vs -0.0,
+    // while a later one may be a large value flipping sign.
+    pub fn with_sign_worst(mut self, metric: SignWorstMetric) -> Self {
+        self.sign_worst_metric = Some(metric);
+        self
+    }
+
+    // Choose what worst_sample and worst_score consider worst, such as
+    // WorstMetric::ToleranceRatio for ranking channels with different
+    // tolerances in reports. Pass/fail status and worst_diff are unaffected.
+    pub fn with_worst_metric(mut self, metric: WorstMetric) -> Self {
+        self.worst_metric = metric;
+        self
+    }
+
+    // Count non-zero diffs below the threshold in the histogram's near-zero
+    // bucket, rather than in extreme low-exponent buckets.
+    pub fn with_negligible_diff(mut self, negligible: f64) -> Self {
+        self.histo = self.histo.with_negligible(negligible);
+        self
+    }
+
+    // Split the histogram bucket containing allow_diff at allow_diff, and
+    // keep it apart during reduction, so that Display always shows how much
+    // of the distribution lies on either side of the tolerance.
+    // Requires a positive, finite tolerance.
+    pub fn with_tolerance_boundary(mut self) -> Self {
+        self.histo = self.histo.with_boundary(self.allow_diff);
+        self
+    }
+
+    // Attach a key/value pair, such as a random seed or commit, to report with
+    // this summary so that failing runs can be reproduced.
+    // Replaces any existing value for the key.
+    pub fn with_metadata<K: Into<String>, V: ToString>(mut self, key: K, value: V) -> Self {
+        let key = key.into();
+        let value = value.to_string();
+        match self.metadata.iter_mut().find(|(existing, _)| *existing == key) {
+            Some(entry) => entry.1 = value,
+            None => self.metadata.push((key, value)),
+        }
+        self
+    }
+
+    // The attached key/value metadata, in the order added.
+    pub fn metadata(&self) -> &[(String, String)] {
+        &self.metadata
+    }
+
+    // Also histogram differences per view_diff, labeled in Display output,
+    // for example to see the ULPs distribution while pass/fail uses absolute
+    // differences. May be called more than once to add several views.
+    pub fn with_view(mut self, label: &'a str, view_diff: &'a dyn Fn(f64, f64) -> (f64, bool)) -> Self {
+        let histo = LogHistogram::new(self.histo.max_display_buckets());
+        self.views.push((label, view_diff, histo));
+        self
+    }
+
+    // Skip items for which filter, given (index, x, y), returns false,
+    // such as known-bad reference rows. Skipped items are counted separately.
+    pub fn with_filter(mut self, filter: &'a dyn Fn(usize, f64, f64) -> bool) -> Self {
+        self.filter = Some(filter);
+        self
+    }
+
+    // Only evaluate the items selected by sampling, counting the rest as
+    // unsampled, so that a quick check can share a full check's call sites.
+    pub fn with_sampling(mut self, sampling: Sampling) -> Self {
+        self.sampling = Some(sampling);
+        self
+    }
+
+    // Stop ingesting items once the deadline passes, so that a CI watchdog
+    // gets a truncated summary of what was gathered rather than a killed job.
+    // Later items are ignored, and the summary is reported as truncated.
+    pub fn with_deadline(mut self, deadline: Instant) -> Self {
+        self.deadline = Some(deadline);
+        self
+    }
+
+    // As with_deadline, for a deadline the given time from now.
+    pub fn with_timeout(self, timeout: Duration) -> Self {
+        self.with_deadline(Instant::now() + timeout)
+    }
+
+    // Stop ingesting items once max_items have been offered, counting items
+    // excluded by sampling or filter, as with_deadline does for time.
+    pub fn with_max_items(mut self, max_items: usize) -> Self {
+        self.max_items = Some(max_items);
+        self
+    }
+
+    // Pass a formatted line describing every recorded item to trace, e.g.
+    // "name: index 3 1.5e0 vs 1e0 diff 5e-1 failed bucket e-1", for seeing the
+    // full comparison trail of a small dataset. For example:
+    //   .with_trace(&|line| println!("{}", line))
+    pub fn with_trace(mut self, trace: &'a dyn Fn(&str)) -> Self {
+        self.trace = Some(trace);
+        self
+    }
+
+    // Pass a structured ItemRecord for every recorded item to observer,
+    // whatever its outcome, for building pipelines on the comparison stream
+    // without a custom ingestion loop. Items excluded by filter, as expected
+    // failures or as skipped duplicates aren't recorded, so aren't observed.
+    pub fn with_observer(mut self, observer: &'a dyn Fn(&ItemRecord)) -> Self {
+        self.observer = Some(observer);
+        self
+    }
+
+    // Pass a SummarySnapshot to publish whenever the worst difference
+    // changes by more than worst_delta, or the failure count grows by more
+    // than fail_delta, since the last snapshot published, including through
+    // merge, e.g. to send over a channel to a dashboard:
+    //   let publish = |snapshot: &SummarySnapshot| { sender.send(*snapshot).ok(); };
+    //   .with_publisher(&publish, 1e-12, 0)
+    pub fn with_publisher(mut self, publish: &'a dyn Fn(&SummarySnapshot), worst_delta: f64, fail_delta: usize) -> Self {
+        assert!(worst_delta >= 0.0, "worst_delta must be non-negative, got {:e}", worst_delta);
+        self.publisher = Some((publish, worst_delta, fail_delta));
+        self
+    }
+
+    // Write every recorded item to writer as a CSV row of
+    // index,x,y,diff,sign_change, after a header row, for deep-dive analysis
+    // of the full comparison stream. Values use this summary's float format.
+    // Rows are buffered, so call flush_csv_tee to write them out and check
+    // for errors, e.g.
+    //   .with_csv_tee(File::create("pressure.csv")?)
+    // Clones of the summary share the writer.
+    pub fn with_csv_tee<W: io::Write + 'static>(mut self, writer: W) -> Self {
+        self.csv_tee = Some(CsvTee::new(writer));
+        self
+    }
+
+    // Flush rows buffered for with_csv_tee, returning the first write error
+    // since the last flush, if any. Does nothing if not enabled.
+    pub fn flush_csv_tee(&self) -> io::Result<()> {
+        match &self.csv_tee {
+            Some(tee) => tee.flush(),
+            None => Ok(()),
+        }
+    }
+
+    // Retain the (index, x, y) of every recorded item in memory, for
+    // secondary analysis such as rescore. See with_spill for datasets too
+    // large to keep in memory.
+    pub fn with_raw_retention(mut self) -> Self {
+        self.retained = Some(Vec::new());
+        self
+    }
+
+    // Retain the (index, x, y) of every recorded item in chunk files of up
+    // to chunk_items items in dir, such as a directory under
+    // std::env::temp_dir(), for datasets too large to keep in memory.
+    // Chunk files left in dir by earlier runs are removed. Call spilled to
+    // stream the items back, or SpillReader::open to read them in a later run.
+    // Clones of the summary share the store. The directory isn't removed
+    // when the summary is dropped.
+    pub fn with_spill<P: AsRef<Path>>(mut self, dir: P, chunk_items: usize) -> Self {
+        self.spill = Some(Spill::new(dir.as_ref(), chunk_items));
+        self
+    }
+
+    // Write out items buffered for with_spill, returning the first write
+    // error since the last flush, if any. Does nothing if not enabled.
+    pub fn flush_spill(&self) -> io::Result<()> {
+        match &self.spill {
+            Some(spill) => spill.flush(),
+            None => Ok(()),
+        }
+    }
+
+    // Flush items spilled per with_spill, then iterate over every spilled
+    // item, in the order recorded. Panics if with_spill wasn't enabled.
+    pub fn spilled(&self) -> io::Result<SpillReader> {
+        let spill = self.spill.as_ref().expect("spilled requires with_spill");
+        spill.flush()?;
+        SpillReader::open(spill.dir())
+    }
+
+    // The (index, x, y) of every recorded item, in the order recorded,
+    // followed by those of merged summaries, if enabled via with_raw_retention.
+    pub fn retained_items(&self) -> Option<&[(usize, f64, f64)]> {
+        self.retained.as_deref()
+    }
+
+    // Replay the retained items through a different difference function and
+    // tolerance, without rerunning the computations that produced them, such
+    // as to see whether a channel would pass under relative rather than
+    // absolute comparison. The returned summary has this summary's name,
+    // sign change acceptance and bucket count, but no other options.
+    // Uses items retained via with_raw_retention if enabled, otherwise
+    // those spilled via with_spill, returning any error reading them.
+    // Either includes the items of merged summaries. Panics if neither is enabled.
+    pub fn rescore(&self, calc_diff: &'a dyn Fn(f64, f64) -> (f64, bool), allow_diff: f64) -> io::Result<DiffSummary<'a>> {
+        let mut summary = DiffSummary::new(self.name, allow_diff, self.allow_sign, self.histo.max_display_buckets, calc_diff);
+        match (&self.retained, &self.spill) {
+            (Some(retained), _) => {
+                for &(index, x, y) in retained {
+                    summary.add(x, y, index);
+                }
+            }
+            (None, Some(_)) => {
+                for item in self.spilled()? {
+                    let (index, x, y) = item?;
+                    summary.add(x, y, index);
+                }
+            }
+            (None, None) => panic!("rescore requires with_raw_retention or with_spill"),
+        }
+        Ok(summary)
+    }
+
+    // Set context to prefix to assert failure messages, such as a test phase.
+    pub fn with_assert_context<S: Into<String>>(mut self, context: S) -> Self {
+        self.assert_context = context.into();
+        self
+    }
+
+    // Set the function used to decide whether x is over or under y, for
+    // over_count, under_count, and item records. Comparators that measure the
+    // short way around a circle need a matching direction, such as
+    // diff::direction_periodic_degrees, since (359, 1) is under, not over.
+    pub fn with_direction(mut self, calc_direction: &'a dyn Fn(f64, f64) -> Option<Direction>) -> Self {
+        self.calc_direction = calc_direction;
+        self
+    }
+
+    // Set the unit of differences, such as "m" or "ms", shown after the worst diff,
+    // tolerance, and histogram bucket labels in Display output, e.g. "diff 3.2e-3 m".
+    pub fn with_unit(mut self, unit: &'a str) -> Self {
+        self.unit = unit;
+        self
+    }
+
+    // Set the options used when displaying this summary via Display.
+    pub fn with_display_options(mut self, display_options: DisplayOptions) -> Self {
+        self.display_options = display_options;
+        self
+    }
+
+    // Set how x and y values are formatted in Display output and assert messages.
+    // FloatFormat::Hex or FloatFormat::Bits allow exact reproduction of failing cases.
+    pub fn with_float_format(mut self, float_format: FloatFormat) -> Self {
+        self.float_format = float_format;
+        self
+    }
+
+    // Set which items spot_check, and the spot_check! macro, print.
+    // By default, DEFAULT_SPOT_CHECK prints about 1 item in 100.
+    pub fn with_spot_check(mut self, sampling: Sampling) -> Self {
+        self.spot_check = sampling;
+        self
+    }
+
+    // Create a summary comparing percent differences from the expected value,
+    // using diff::diff_percent, with differences displayed with a % unit.
+    pub fn new_percent(name: &'a str, allow_percent: f64, allow_sign: bool, bucket_count: usize) -> Self {
+        DiffSummary {
+            unit: "%",
+            ..DiffSummary::new(name, allow_percent, allow_sign, bucket_count, &diff::diff_percent)
+        }
+    }
+
+    // Create a summary comparing x to y as a ratio, using diff::diff_ratio,
+    // for gain or scale channels. allow_ratio bounds |x / y - 1|, so an
+    // allow_ratio of 0.01 accepts x within 1% of y as a factor, without
+    // dividing values beforehand.
+    pub fn new_ratio(name: &'a str, allow_ratio: f64, allow_sign: bool, bucket_count: usize) -> Self {
+        DiffSummary::new(name, allow_ratio, allow_sign, bucket_count, &diff::diff_ratio)
+    }
+
+    // Create a summary comparing values under IEEE 754 totalOrder, where any
+    // pair that isn't bit-identical fails, such as for validating sorting or
+    // searching. The worst diff is the largest distance in totalOrder steps.
+    pub fn new_total_order(name: &'a str, allow_sign: bool, bucket_count: usize) -> Self {
+        DiffSummary::new(name, 0.0, allow_sign, bucket_count, &diff::diff_total_order)
+    }
+
+    // Presets for common workflows, each choosing a comparator, sign policy
+    // and bucket count, for a readable report without further configuration.
+
+    // Compare by the number of representable f64 values between x and y,
+    // allowing up to max_ulps. Sign changes fail.
+    pub fn ulps(name: &'a str, max_ulps: u64) -> Self {
+        DiffSummary::new(name, max_ulps as f64, false, PRESET_BUCKETS, &diff::diff_ulps)
+    }
+
+    // Compare by difference relative to the mean of the two magnitudes (see
+    // diff::diff_rel), allowing up to rel_tol. Sign changes fail.
+    pub fn relative(name: &'a str, rel_tol: f64) -> Self {
+        DiffSummary::new(name, rel_tol, false, PRESET_BUCKETS, &diff::diff_rel)
+    }
+
+    // Compare by absolute difference, allowing up to abs_tol. Sign changes
+    // near zero are expected at this scale, so values below abs_tol in
+    // magnitude are exempt from sign change checks.
+    pub fn absolute(name: &'a str, abs_tol: f64) -> Self {
+        DiffSummary::new(name, abs_tol, false, PRESET_BUCKETS, &diff::diff_abs).with_sign_epsilon(abs_tol)
+    }
+
+    // Compare angles in degrees the short way around the circle, allowing
+    // up to tol degrees, and reporting differences in degrees. Sign changes
+    // are allowed, since they include crossing the wrap point.
+    pub fn angle_degrees(name: &'a str, tol: f64) -> Self {
+        DiffSummary::new(name, tol, true, PRESET_BUCKETS, &diff::diff_degrees)
+            .with_unit("deg")
+            .with_direction(&diff::direction_periodic_degrees)
+    }
+
+    // Compare angles in radians the short way around the circle, per
+    // diff::diff_periodic_radians, allowing up to tol radians. Sign changes
+    // are never reported, since either sign names the same angle.
+    pub fn angle_radians(name: &'a str, tol: f64) -> Self {
+        DiffSummary::new(name, tol, false, PRESET_BUCKETS, &diff::diff_periodic_radians)
+            .with_unit("rad")
+            .with_direction(&diff::direction_periodic_radians)
+    }
+
+    // Create a vector of DiffSummary based on a slice of tuples with the form:
+    // (name, allow_diff, allow_sign, calc_diff)
+    pub fn new_vec(bucket_count: usize, infos: &'a [SummaryInfo<'a>]) -> Vec<Self> {
+        infos.iter().map(|&(name, allow_diff, allow_sign, calc_diff)| {
+            DiffSummary::new(name, allow_diff, allow_sign, bucket_count, calc_diff)
+        }).collect()
+    }
+
+    // Given x and y, calculate their difference and sign change status,
+    // then check whether any of those values is the worst seen so far
+    // for comparable operations. If it is, record the iteration
+    // information and the new worst difference.
+    // For purposes of deciding "worst", infinity is worse than any
+    // finite number, and nan is worse than infinity.
+    // x and y may be any primitive numeric type, and are converted to f64.
+    pub fn add<X: ToPrimitive, Y: ToPrimitive>(&mut self, x: X, y: Y, index: usize) {
+        self.add_f64(util::to_f64(x), util::to_f64(y), index);
+    }
+
+    // Add an item along with the time taken to compute x and y, accumulating
+    // timing statistics reported alongside accuracy, including the ratio of
+    // x time to y time. Items excluded by sampling or filter are still timed.
+    pub fn add_timed<X: ToPrimitive, Y: ToPrimitive>(&mut self, x: X, y: Y, index: usize, dur_x: Duration, dur_y: Duration) {
+        self.timing.get_or_insert_with(Timing::new).add(dur_x, dur_y);
+        self.add(x, y, index);
+    }
+
+    // Compute x and y by calling compute_x and compute_y, timing each, then
+    // add them as add_timed does.
+    pub fn add_timed_with<X, Y, FX, FY>(&mut self, compute_x: FX, compute_y: FY, index: usize)
+    where
+        X: ToPrimitive,
+        Y: ToPrimitive,
+        FX: FnOnce() -> X,
+        FY: FnOnce() -> Y,
+    {
+        let start = Instant::now();
+        let x = compute_x();
+        let dur_x = start.elapsed();
+        let start = Instant::now();
+        let y = compute_y();
+        let dur_y = start.elapsed();
+        self.add_timed(x, y, index, dur_x, dur_y);
+    }
+
+    // Add an item from fallible computations, such as when the computed or
+    // expected value comes from a function returning Result. Items where x
+    // and y are both errors are counted, as are items where only one is,
+    // which are also recorded as failures under ErrorPolicy::Fail.
+    pub fn add_results<X: ToPrimitive, Y: ToPrimitive, EX, EY>(&mut self, x: Result<X, EX>, y: Result<Y, EY>, index: usize) {
+        let (x, y) = match (x, y) {
+            (Ok(x), Ok(y)) => return self.add(x, y, index),
+            (x, y) => (x.ok().map(util::to_f64), y.ok().map(util::to_f64)),
+        };
+        // Check sampling first, so that error counts cover evaluated items only.
+        if self.skip_item(index) {
+            return;
+        }
+        match (x, y) {
+            (None, None) => {
+                self.num_both_errors += 1;
+                return;
+            }
+            (None, _) => self.num_x_errors += 1,
+            _ => self.num_y_errors += 1,
+        }
+        let (x, y) = (x.unwrap_or(f64::NAN), y.unwrap_or(f64::NAN));
+        if self.error_policy == ErrorPolicy::Fail && self.record(x, y, f64::NAN, false, index) {
+            self.histo.add(f64::NAN);
+        }
+    }
+
+    // Add each (x, y) pair of results from an iterator, as add_results does,
+    // with each item's position as its index.
+    pub fn add_results_iter<X: ToPrimitive, Y: ToPrimitive, EX, EY, I>(&mut self, items: I)
+    where
+        I: IntoIterator<Item = (Result<X, EX>, Result<Y, EY>)>,
+    {
+        for (index, (x, y)) in items.into_iter().enumerate() {
+            self.add_results(x, y, index);
+        }
+    }
+
+    // Add an item whose expected value is a guaranteed interval [lo, hi].
+    // An x within the interval has zero difference. Otherwise the difference
+    // is calculated between x and the nearest bound, which is recorded as y.
+    // A nan bound guarantees nothing, so the item fails with a nan
+    // difference, with that bound recorded as y.
+    pub fn add_interval(&mut self, x: f64, lo: f64, hi: f64, index: usize) {
+        if lo.is_nan() || hi.is_nan() {
+            self.add_diff(x, if lo.is_nan() { lo } else { hi }, f64::NAN, false, index);
+            return;
+        }
+        assert!(lo <= hi, "add_interval requires lo <= hi, got [{}, {}]", lo, hi);
+        if lo <= x && x <= hi {
+            self.add_diff(x, x, 0.0, false, index);
+        } else {
+            // A nan x is compared against lo.
+            let nearest = if x > hi { hi } else { lo };
+            self.add_f64(x, nearest, index);
+        }
+    }
+
+    // Add the angle between computed (x) and expected (y) 3-vectors as the difference.
+    // The angle is recorded as x, with y as 0.0.
+    pub fn add_vec3(&mut self, x: &[f64; 3], y: &[f64; 3], unit: AngleUnit, index: usize) {
+        let angle = unit.from_radians(diff::diff_angle_vec3(x, y));
+        self.add_diff(angle, 0.0, angle, false, index);
+    }
+
+    // Add the geodesic angle between computed (x) and expected (y) rotation
+    // quaternions as the difference. The angle is recorded as x, with y as 0.0.
+    pub fn add_quat(&mut self, x: &[f64; 4], y: &[f64; 4], unit: AngleUnit, index: usize) {
+        let angle = unit.from_radians(diff::diff_angle_quat(x, y));
+        self.add_diff(angle, 0.0, angle, false, index);
+    }
+
+    // Stage an item for addition, to be added by commit or excluded by discard,
+    // for driver loops that may find a case invalid after comparing it.
+    // Staged items don't affect any results until committed.
+    // Staging an item commits any item that was already staged.
+    pub fn stage<X: ToPrimitive, Y: ToPrimitive>(&mut self, x: X, y: Y, index: usize) {
+        self.commit();
+        self.staged = Some((index, util::to_f64(x), util::to_f64(y)));
+    }
+
+    // Add the staged item, if any.
+    pub fn commit(&mut self) {
+        if let Some((index, x, y)) = self.staged.take() {
+            self.add_f64(x, y, index);
+        }
+    }
+
+    // Exclude the staged item, if any, returning its (index, x, y).
+    pub fn discard(&mut self) -> Option<(usize, f64, f64)> {
+        self.staged.take()
+    }
+
+    // The (index, x, y) of the staged item, if any.
+    pub fn staged(&self) -> Option<(usize, f64, f64)> {
+        self.staged
+    }
+
+    // Add each (x, y) pair from a pair of equal length slices, with each
+    // item's position as its index.
+    // Histogram insertion is batched, so that runs of differences in the
+    // same bucket are counted together.
+    pub fn add_slices<X: ToPrimitive + Copy, Y: ToPrimitive + Copy>(&mut self, xs: &[X], ys: &[Y]) {
+        assert_eq!(xs.len(), ys.len(), "add_slices requires equal length slices");
+        let mut batch = [0.0; HISTO_BATCH];
+        let mut len = 0;
+        for (index, (&x, &y)) in xs.iter().zip(ys).enumerate() {
+            let (x, y) = (util::to_f64(x), util::to_f64(y));
+            if self.skip_item(index) {
+                if self.truncation.is_some() {
+                    break;
+                }
+                continue;
+            }
+            let (diff, sign_change) = self.calc(x, y);
+            if self.record(x, y, diff, sign_change, index) {
+                batch[len] = diff;
+                len += 1;
+                if len == HISTO_BATCH {
+                    self.histo.add_batch(&batch);
+                    len = 0;
+                }
+            }
+        }
+        self.histo.add_batch(&batch[..len]);
+    }
+
+    // Add each (x, y) pair as add_slices does, but for slices that may differ
+    // in length, such as values loaded from files. Returns
+    // DiffInputError::LengthMismatch without adding any items if they differ.
+    pub fn try_add_slices<X: ToPrimitive + Copy, Y: ToPrimitive + Copy>(&mut self, xs: &[X], ys: &[Y]) -> Result<(), DiffInputError> {
+        if xs.len() != ys.len() {
+            return Err(DiffInputError::LengthMismatch { name: self.name.to_string(), x_len: xs.len(), y_len: ys.len() });
+        }
+        self.add_slices(xs, ys);
+        Ok(())
+    }
+
+    // Add each (x, y) pair from an iterator, with each item's position as its index.
+    // Stops without consuming further items once a watchdog limit is reached.
+    pub fn add_iter<X: ToPrimitive, Y: ToPrimitive, I: IntoIterator<Item = (X, Y)>>(&mut self, items: I) {
+        let mut items = items.into_iter().enumerate();
+        while !self.is_stopped() {
+            match items.next() {
+                Some((index, (x, y))) => self.add(x, y, index),
+                None => break,
+            }
+        }
+    }
+
+    // Add each (x, y) pair from a pair of streams that may differ in length,
+    // such as output records from two runs, with each item's position as its
+    // index. Items left over from the longer stream are handled per policy.
+    // Under LengthPolicy::Error, pairs before the shorter stream ended have
+    // already been added when the error is returned.
+    pub fn add_streams<X, Y, IX, IY>(&mut self, xs: IX, ys: IY, policy: LengthPolicy) -> Result<(), DiffInputError>
+    where
+        X: ToPrimitive,
+        Y: ToPrimitive,
+        IX: IntoIterator<Item = X>,
+        IY: IntoIterator<Item = Y>,
+    {
+        let (mut xs, mut ys) = (xs.into_iter(), ys.into_iter());
+        let mut index = 0;
+        while !self.is_stopped() {
+            match (xs.next(), ys.next()) {
+                (Some(x), Some(y)) => self.add(x, y, index),
+                (Some(x), None) => return self.add_unpaired(std::iter::once(x).chain(xs).map(util::to_f64), true, index, policy),
+                (None, Some(y)) => return self.add_unpaired(std::iter::once(y).chain(ys).map(util::to_f64), false, index, policy),
+                (None, None) => break,
+            }
+            index += 1;
+        }
+        Ok(())
+    }
+
+    // Handle the items left over in add_streams, starting at index, from
+    // the x stream if is_x, or else the y stream.
+    fn add_unpaired(&mut self, rest: impl Iterator<Item = f64>, is_x: bool, index: usize, policy: LengthPolicy) -> Result<(), DiffInputError> {
+        let mut count = 0;
+        match policy {
+            LengthPolicy::Error => {
+                return Err(DiffInputError::UnpairedStream { name: self.name.to_string(), longer: if is_x { "x" } else { "y" }, paired: index });
+            }
+            LengthPolicy::Truncate => count = 1,
+            LengthPolicy::PadNan => {
+                for value in rest {
+                    if self.is_stopped() {
+                        break;
+                    }
+                    // A nan difference, since the comparator would pass a nan
+                    // leftover against its nan padding.
+                    let (x, y) = if is_x { (value, f64::NAN) } else { (f64::NAN, value) };
+                    self.add_diff(x, y, f64::NAN, false, index + count);
+                    count += 1;
+                }
+            }
+        }
+        if is_x {
+            self.num_unpaired.0 += count;
+        } else {
+            self.num_unpaired.1 += count;
+        }
+        Ok(())
+    }
+
+    // If a watchdog limit has been reached, or sampling excludes the item
+    // with the given index, count it and return true.
+    fn skip_item(&mut self, index: usize) -> bool {
+        if self.is_stopped() {
+            return true;
+        }
+        self.num_offered += 1;
+        match self.sampling {
+            Some(sampling) if !sampling.includes(index) => {
+                self.num_unsampled += 1;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    // Indicate whether a watchdog limit has been reached, recording which.
+    fn is_stopped(&mut self) -> bool {
+        if self.truncation.is_none() {
+            if self.max_items.is_some_and(|max_items| self.num_offered >= max_items) {
+                self.truncation = Some(Truncation::MaxItems);
+            } else if self.deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+                self.truncation = Some(Truncation::Deadline);
+            }
+        }
+        self.truncation.is_some()
+    }
+
+    fn add_f64(&mut self, x: f64, y: f64, index: usize) {
+        // Check sampling before calc_diff, which may be expensive.
+        if self.skip_item(index) {
+            return;
+        }
+        let (diff, sign_change) = self.calc(x, y);
+        if self.record(x, y, diff, sign_change, index) {
+            self.histo.add(diff);
+        }
+    }
+
+    // Indicate whether a pair is below sign_epsilon, so that any sign change is exempt.
+    fn is_sign_exempt(&self, x: f64, y: f64) -> bool {
+        match self.sign_epsilon {
+            Some(sign_epsilon) => x.abs() < sign_epsilon && y.abs() < sign_epsilon,
+            None => false,
+        }
+    }
+
+    // Indicate whether a pair is zeros of differing sign to be compared as equal.
+    fn is_signed_zero_ignored(&self, x: f64, y: f64) -> bool {
+        self.signed_zero_policy == SignedZeroPolicy::Ignore
+            && x == 0.0
+            && y == 0.0
+            && x.is_sign_negative() != y.is_sign_negative()
+    }
+
+    // Indicate whether strict mode fails a pair, for having a nan or infinite value.
+    fn is_strict_failure(&self, x: f64, y: f64) -> bool {
+        self.strict && !(x.is_finite() && y.is_finite())
+    }
+
+    // Indicate whether a pair is below zero_epsilon, so that it's compared as zeros.
+    fn is_near_zero(&self, x: f64, y: f64) -> bool {
+        self.zero_epsilon.is_some_and(|zero_epsilon| x.abs() < zero_epsilon && y.abs() < zero_epsilon)
+    }
+
+    // The difference function, without this summary's zero handling or quantization.
+    pub(crate) fn calc_diff(&self) -> &'a dyn Fn(f64, f64) -> (f64, bool) {
+        self.calc_diff
+    }
+
+    // Calculate the difference and sign change status of a pair, after any quantization.
+    pub(crate) fn calc(&self, x: f64, y: f64) -> (f64, bool) {
+        if self.is_strict_failure(x, y) {
+            return (f64::NAN, (*self.calc_diff)(x, y).1);
+        }
+        if self.is_near_zero(x, y) || self.is_signed_zero_ignored(x, y) {
+            return (*self.calc_diff)(0.0, 0.0);
+        }
+        match self.quantization {
+            Some(quantization) => (*self.calc_diff)(quantization.apply(x), quantization.apply(y)),
+            None => (*self.calc_diff)(x, y),
+        }
+    }
+
+    // Add an item, first printing how it compares if it's selected for spot
+    // checking, e.g.
+    //   spot check solver: index 7 f(t) = 1.5e0 vs exact(t) = 1e0 diff 5e-1 failed
+    // for readable traces of a few items in a huge run. x_label and y_label
+    // describe x and y, and are usually their source text, per spot_check!.
+    pub fn spot_check<X: ToPrimitive, Y: ToPrimitive>(&mut self, x_label: &str, y_label: &str, x: X, y: Y, index: usize) {
+        let (x, y) = (util::to_f64(x), util::to_f64(y));
+        if let Some(line) = self.spot_check_line(x_label, y_label, x, y, index) {
+            println!("{}", line);
+        }
+        self.add_f64(x, y, index);
+    }
+
+    // Format an item for spot_check, if it's selected.
+    fn spot_check_line(&self, x_label: &str, y_label: &str, x: f64, y: f64, index: usize) -> Option<String> {
+        if !self.spot_check.includes(index) {
+            return None;
+        }
+        let outcome = self.evaluate(x, y);
+        Some(format!(
+            "spot check {}{}index {} {} = {} vs {} = {} diff {}{} {}",
+            self.name,
+            if !self.name.is_empty() { ": " } else { "" },
+            index,
+            x_label,
+            self.fmt_value(x),
+            y_label,
+            self.fmt_value(y),
+            WithUnit(FmtF64(outcome.diff, FloatFormat::Exp), self.unit),
+            if outcome.sign_change { " sign diff" } else { "" },
+            if outcome.is_ok() { "ok" } else { "failed" },
+        ))
+    }
+
+    // Report how a pair would score if added, without adding it,
+    // for example to decide whether to log extra detail about it.
+    pub fn evaluate<X: ToPrimitive, Y: ToPrimitive>(&self, x: X, y: Y) -> ItemOutcome {
+        let (x, y) = (util::to_f64(x), util::to_f64(y));
+        let (diff, sign_change) = self.calc(x, y);
+        let sign_change = sign_change && !self.is_sign_exempt(x, y);
+        ItemOutcome {
+            diff,
+            sign_change,
+            direction: (self.calc_direction)(x, y),
+            within_tolerance: diff <= self.allow_diff,
+            sign_ok: !sign_change || self.is_sign_count_ok(self.summary_sign.count + 1, self.num_total + 1),
+            is_worst: diff != 0.0 && crate::diff::is_diff_worse(diff, self.diff),
+            bucket: self.histo.bucket_key(diff),
+        }
+    }
+
+    // Record an item whose difference and sign change status were already
+    // calculated, for comparisons made outside of calc_diff.
+    pub(crate) fn add_diff(&mut self, x: f64, y: f64, diff: f64, sign_change: bool, index: usize) {
+        if self.skip_item(index) {
+            return;
+        }
+        // Strict mode applies to precomputed differences too, such as those of add_interval.
+        let diff = if self.is_strict_failure(x, y) { f64::NAN } else { diff };
+        if self.record(x, y, diff, sign_change, index) {
+            self.histo.add(diff);
+        }
+    }
+
+    // Indicate whether any enabled option needs to see every item,
+    // including exact matches.
+    fn tracks_every_item(&self) -> bool {
+        self.top.capacity() > 0
+            || self.reservoir.capacity() > 0
+            || self.passing.is_some()
+            || self.bucket_samples.is_some()
+            || self.fit.is_some()
+            || self.drift.is_some()
+            || self.subnormal_policy.is_some()
+            || !self.views.is_empty()
+            || self.trace.is_some()
+            || self.observer.is_some()
+            || self.csv_tee.is_some()
+            || self.spill.is_some()
+            || self.retained.is_some()
+            || self.worst_metric == WorstMetric::Ulps
+            || self.bootstrap.is_some()
+    }
+
+    // Record a sampled item in everything but the histogram, which callers
+    // update, so that they can batch insertions.
+    // Returns false if the item was excluded by filter, as an expected
+    // failure, or as a duplicate.
+    #[allow(clippy::neg_cmp_op_on_partial_ord)]
+    fn record(&mut self, x: f64, y: f64, diff: f64, sign_change: bool, index: usize) -> bool {
+        if let Some(fingerprint) = &mut self.fingerprint {
+            fingerprint.add(index, y);
+        }
+        if let Some(filter) = self.filter {
+            if !filter(index, x, y) {
+                self.num_skipped += 1;
+                return false;
+            }
+        }
+        if let Some(policy) = self.duplicate_policy {
+            if !self.seen_pairs.insert((x.to_bits(), y.to_bits())) {
+                self.num_duplicates += 1;
+                if policy == DuplicatePolicy::Skip {
+                    return false;
+                }
+            }
+        }
+        if !self.expected_failures.is_empty() && self.expected_failures.binary_search(&index).is_ok() {
+            // Funky negation on next line is intentional, to get desired nan behavior.
+            let fails = !(diff <= self.allow_diff) || (sign_change && !self.allow_sign && !self.is_sign_exempt(x, y));
+            if fails {
+                let context = self.sample_context.as_ref();
+                self.summary_xfail.add(false, || SampleRecord::new(index, x, y, diff, context));
+                return false;
+            }
+            self.num_xpass += 1;
+        }
+        self.num_total += 1;
+        if x.to_bits() == y.to_bits() {
+            self.num_identical += 1;
+        }
+        if let Some(extremes) = &mut self.extremes {
+            extremes.add(y);
+        }
+        if let Some(suggestion) = &mut self.suggestion {
+            suggestion.add(x, y);
+        }
+        if let Some(error_model) = &mut self.error_model {
+            error_model.add(x, y);
+        }
+        if self.is_near_zero(x, y) {
+            self.num_near_zero += 1;
+        } else if self.is_signed_zero_ignored(x, y) {
+            self.num_signed_zero += 1;
+        }
+        if !(x.is_finite() && y.is_finite()) && diff <= self.allow_diff && (!sign_change || self.allow_sign || self.is_sign_exempt(x, y)) {
+            self.num_nonfinite_passed += 1;
+        }
+        for (range, summary) in &mut self.ranges {
+            if range.contains(&index) {
+                summary.add_diff(x, y, diff, sign_change, index);
+            }
+        }
+        if let Some(mut classes) = self.classes.take() {
+            let summary = classes.entry(ValueClass::of(y)).or_insert_with(|| self.sub_summary());
+            summary.add_diff(x, y, diff, sign_change, index);
+            self.classes = Some(classes);
+        }
+        // Fast path for exact matches, the common case in large runs, which
+        // can't be the worst item, a failure, a warning, or a sign change.
+        if diff == 0.0 && !sign_change && !self.tracks_every_item() {
+            self.num_finite += 1;
+            return true;
+        }
+        let is_diff_worst = crate::diff::is_diff_worse(diff, self.diff);
+        let context = self.sample_context.as_ref();
+        let sample = || SampleRecord::new(index, x, y, diff, context);
+        // Funky negation on next line is intentional, to get desired nan behavior.
+        if !(diff == 0.0) {
+            self.summary_diff.add(is_diff_worst, sample);
+            if is_diff_worst {
+                self.diff = diff;
+            }
+            match (self.calc_direction)(x, y) {
+                Some(Direction::Over) => self.num_over += 1,
+                Some(Direction::Under) => self.num_under += 1,
+                None => (),
+            }
+            // Funky negation on next line is intentional, to get desired nan behavior.
+            if !(diff <= self.allow_diff) {
+                self.summary_fail.add(false, sample);
+                if self.failures.len() < self.failure_capacity {
+                    self.failures.push((index, x, y, diff));
+                }
+                if let Some(triage) = &mut self.triage {
+                    triage.add(x, y, sign_change);
+                }
+                #[cfg(feature = "tracing")]
+                tracing::warn!(parent: &self.span, index, x, y, diff, tolerance = self.allow_diff, "diff outside tolerance");
+            } else if let Some(warn_diff) = self.warn_diff {
+                if diff > warn_diff {
+                    self.summary_warn.add(false, sample);
+                }
+            }
+            for (_, allow_diff, failures) in &mut self.profiles {
+                // Funky negation on next line is intentional, to get desired nan behavior.
+                if !(diff <= *allow_diff) {
+                    failures.add(false, sample);
+                }
+            }
+        }
+        // For the sign change check, allow (NAN vs NAN), but not (0.0 vs -0.0) or (NAN vs -NAN).
+        let sign_change = if sign_change && self.is_sign_exempt(x, y) {
+            self.num_sign_exempt += 1;
+            false
+        } else {
+            sign_change
+        };
+        if sign_change {
+            self.summary_sign.add(false, sample);
+            if let Some(metric) = self.sign_worst_metric {
+                let value = match metric {
+                    SignWorstMetric::Magnitude => x.abs() + y.abs(),
+                    SignWorstMetric::Diff => diff,
+                };
+                let is_sign_worst = self.summary_sign_worst.count == 0
+                    || crate::diff::is_diff_worse(value, self.sign_worst);
+                self.summary_sign_worst.add(is_sign_worst, sample);
+                if is_sign_worst {
+                    self.sign_worst = value;
+                }
+            }
+        }
+        if let Some((_, reservoir)) = &mut self.passing {
+            if diff <= self.allow_diff && (self.allow_sign || !sign_change) {
+                reservoir.add(index, x, y, diff);
+            }
+        }
+        if self.subnormal_policy.is_some() {
+            if x.is_subnormal() {
+                self.summary_subnormal_x.add(false, sample);
+            }
+            if y.is_subnormal() {
+                self.num_subnormal_y += 1;
+            }
+            if x.is_normal() && y.is_normal() && (x - y).is_subnormal() {
+                self.num_subnormal_cancel += 1;
+            }
+        }
+        if self.worst_metric == WorstMetric::Ulps {
+            let ulps = diff::diff_ulps(x, y).0;
+            let is_ulps_worst = self.summary_ulps.count == 0 || crate::diff::is_diff_worse(ulps, self.worst_ulps);
+            self.summary_ulps.add(is_ulps_worst, sample);
+            if is_ulps_worst {
+                self.worst_ulps = ulps;
+            }
+        }
+        self.top.add(diff, sample);
+        self.reservoir.add(index, x, y, diff);
+        if let Some(samples) = &mut self.bucket_samples {
+            let key = self.histo.bucket_key(diff);
+            #[allow(clippy::unnecessary_map_or)]
+            let is_bucket_worst = samples.get(&key).map_or(true, |sample| crate::diff::is_diff_worse(diff, sample.3));
+            if is_bucket_worst {
+                samples.insert(key, (index, x, y, diff));
+            }
+        }
+        if let Some((fit, _)) = &mut self.fit {
+            fit.add(x, y);
+        }
+        if let Some(drift) = &mut self.drift {
+            drift.add(index, diff);
+        }
+        if let Some((convergence, step)) = &mut self.convergence {
+            let step = match step {
+                Some(step) => step(index),
+                None => 1.0 / index as f64,
+            };
+            convergence.add(step, diff);
+        }
+        if diff.is_finite() {
+            self.num_finite += 1;
+            self.sum_diff += diff;
+            self.sum_sq_diff += diff * diff;
+        }
+        if let Some(bootstrap) = &mut self.bootstrap {
+            // Funky negation on next line is intentional, to get desired nan behavior.
+            bootstrap.add(index, diff, !(diff <= self.allow_diff));
+        }
+        if !self.views.is_empty() {
+            let (qx, qy) = match self.quantization {
+                Some(quantization) => (quantization.apply(x), quantization.apply(y)),
+                None => (x, y),
+            };
+            for (_, view_diff, histo) in &mut self.views {
+                histo.add(view_diff(qx, qy).0);
+            }
+        }
+        if let Some(trace) = self.trace {
+            trace(&self.trace_line(x, y, diff, sign_change, index));
+        }
+        if let Some(observer) = self.observer {
+            observer(&self.item_record(x, y, diff, sign_change, index));
+        }
+        self.publish_changes();
+        if let Some(tee) = &self.csv_tee {
+            tee.add(index, self.fmt_value(x), self.fmt_value(y), diff, sign_change);
+        }
+        if let Some(spill) = &self.spill {
+            spill.add(index, x, y);
+        }
+        if let Some(retained) = &mut self.retained {
+            retained.push((index, x, y));
+        }
+        true
+    }
+
+    // Describe an item for with_observer.
+    #[allow(clippy::neg_cmp_op_on_partial_ord)]
+    fn item_record(&self, x: f64, y: f64, diff: f64, sign_change: bool, index: usize) -> ItemRecord {
+        let outcome = if !(diff <= self.allow_diff) || (sign_change && !self.allow_sign) {
+            ItemStatus::Failed
+        } else if diff == 0.0 {
+            ItemStatus::Exact
+        } else if self.warn_diff.is_some_and(|warn_diff| diff > warn_diff) {
+            ItemStatus::Warned
+        } else {
+            ItemStatus::Ok
+        };
+        let direction = (self.calc_direction)(x, y);
+        ItemRecord { index, x, y, diff, sign_change, direction, outcome, bucket: self.histo.bucket_key(diff) }
+    }
+
+    // Format an item for with_trace.
+    fn trace_line(&self, x: f64, y: f64, diff: f64, sign_change: bool, index: usize) -> String {
+        let is_ok = diff <= self.allow_diff && (self.allow_sign || !sign_change);
+        format!(
+            "{}{}index {} {} vs {} diff {}{} {} bucket {}",
+            self.name,
+            if !self.name.is_empty() { ": " } else { "" },
+            index,
+            self.fmt_value(x),
+            self.fmt_value(y),
+            WithUnit(FmtF64(diff, FloatFormat::Exp), self.unit),
+            if sign_change { " sign diff" } else { "" },
+            if is_ok { "ok" } else { "failed" },
+            self.histo.bucket_key(diff),
+        )
+    }
+
+    // Combine the results of another summary into this one, for example
+    // when a dataset is split across threads or runs.
+    // Both summaries must have the same tolerance and sign change policy,
+    // and are assumed to use equivalent calc_diff functions.
+    // Where "first" items are tracked, the item with the lower index is kept.
+    pub fn merge(&mut self, other: &DiffSummary) {
+        assert!(
+            self.allow_diff == other.allow_diff && self.allow_sign == other.allow_sign,
+            "Cannot merge summaries {} and {} with different tolerances",
+            self.name,
+            other.name
+        );
+        self.num_total += other.num_total;
+        self.num_finite += other.num_finite;
+        self.sum_diff += other.sum_diff;
+        self.sum_sq_diff += other.sum_sq_diff;
+        self.num_identical += other.num_identical;
+        self.num_skipped += other.num_skipped;
+        self.num_unsampled += other.num_unsampled;
+        self.num_offered += other.num_offered;
+        self.num_unpaired = (self.num_unpaired.0 + other.num_unpaired.0, self.num_unpaired.1 + other.num_unpaired.1);
+        self.truncation = self.truncation.or(other.truncation);
+        self.num_both_errors += other.num_both_errors;
+        self.num_x_errors += other.num_x_errors;
+        self.num_y_errors += other.num_y_errors;
+        self.num_sign_exempt += other.num_sign_exempt;
+        self.num_over += other.num_over;
+        self.num_under += other.num_under;
+        self.summary_xfail.merge(&other.summary_xfail, other.summary_xfail.sample.index < self.summary_xfail.sample.index);
+        self.num_xpass += other.num_xpass;
+        self.num_signed_zero += other.num_signed_zero;
+        self.num_near_zero += other.num_near_zero;
+        // The merged results are only strict if both summaries' were.
+        self.strict = self.strict && other.strict;
+        self.num_nonfinite_passed += other.num_nonfinite_passed;
+        // Pairs seen by both summaries count as duplicates, though under
+        // DuplicatePolicy::Skip, both summaries' statistics already include them.
+        let num_seen = self.seen_pairs.len() + other.seen_pairs.len();
+        self.seen_pairs.extend(&other.seen_pairs);
+        self.num_duplicates += other.num_duplicates + (num_seen - self.seen_pairs.len());
+        let is_diff_worst = crate::diff::is_diff_worse(other.diff, self.diff)
+            || (other.diff == self.diff && other.summary_diff.sample.index < self.summary_diff.sample.index);
+        self.summary_diff.merge(&other.summary_diff, is_diff_worst);
+        if is_diff_worst {
+            self.diff = other.diff;
+        }
+        let is_fail_first = other.summary_fail.sample.index < self.summary_fail.sample.index;
+        self.summary_fail.merge(&other.summary_fail, is_fail_first);
+        let is_warn_first = other.summary_warn.sample.index < self.summary_warn.sample.index;
+        self.summary_warn.merge(&other.summary_warn, is_warn_first);
+        let is_sign_first = other.summary_sign.sample.index < self.summary_sign.sample.index;
+        self.summary_sign.merge(&other.summary_sign, is_sign_first);
+        if self.sign_worst_metric.is_some() {
+            let is_sign_worst = other.summary_sign_worst.count > 0
+                && crate::diff::is_diff_worse(other.sign_worst, self.sign_worst);
+            self.summary_sign_worst.merge(&other.summary_sign_worst, is_sign_worst);
+            if is_sign_worst {
+                self.sign_worst = other.sign_worst;
+            }
+        }
+        if self.worst_metric == WorstMetric::Ulps {
+            let is_ulps_worst = other.summary_ulps.count > 0
+                && (self.summary_ulps.count == 0 || crate::diff::is_diff_worse(other.worst_ulps, self.worst_ulps));
+            self.summary_ulps.merge(&other.summary_ulps, is_ulps_worst);
+            if is_ulps_worst {
+                self.worst_ulps = other.worst_ulps;
+            }
+        }
+        let is_subnormal_first = other.summary_subnormal_x.sample.index < self.summary_subnormal_x.sample.index;
+        self.summary_subnormal_x.merge(&other.summary_subnormal_x, is_subnormal_first);
+        self.num_subnormal_y += other.num_subnormal_y;
+        self.num_subnormal_cancel += other.num_subnormal_cancel;
+        for (key, value) in &other.metadata {
+            if !self.metadata.iter().any(|(existing, _)| existing == key) {
+                self.metadata.push((key.clone(), value.clone()));
+            }
+        }
+        self.histo.merge(&other.histo);
+        assert_eq!(self.views.len(), other.views.len(), "Cannot merge summaries with different views");
+        for (view, other_view) in self.views.iter_mut().zip(&other.views) {
+            assert_eq!(view.0, other_view.0, "Cannot merge summaries with different views");
+            view.2.merge(&other_view.2);
+        }
+        self.top.merge(&other.top);
+        self.reservoir.merge(&other.reservoir);
+        if let (Some((_, reservoir)), Some((_, other_reservoir))) = (&mut self.passing, &other.passing) {
+            reservoir.merge(other_reservoir);
+        }
+        if let (Some(samples), Some(other_samples)) = (&mut self.bucket_samples, &other.bucket_samples) {
+            for (&key, &other_sample) in other_samples {
+                #[allow(clippy::unnecessary_map_or)]
+                let is_bucket_worst = samples.get(&key).map_or(true, |sample| {
+                    crate::diff::is_diff_worse(other_sample.3, sample.3) || (other_sample.3 == sample.3 && other_sample.0 < sample.0)
+                });
+                if is_bucket_worst {
+                    samples.insert(key, other_sample);
+                }
+            }
+        }
+        if let (Some((fit, _)), Some((other_fit, _))) = (&mut self.fit, &other.fit) {
+            fit.merge(other_fit);
+        }
+        if let (Some(drift), Some(other_drift)) = (&mut self.drift, &other.drift) {
+            drift.merge(other_drift);
+        }
+        if let (Some(bootstrap), Some(other_bootstrap)) = (&mut self.bootstrap, &other.bootstrap) {
+            bootstrap.merge(other_bootstrap);
+        }
+        if let (Some((convergence, _)), Some((other_convergence, _))) = (&mut self.convergence, &other.convergence) {
+            convergence.merge(other_convergence);
+        }
+        if let (Some(extremes), Some(other_extremes)) = (&mut self.extremes, &other.extremes) {
+            extremes.merge(other_extremes);
+        }
+        if let (Some(error_model), Some(other_error_model)) = (&mut self.error_model, &other.error_model) {
+            error_model.merge(other_error_model);
+        }
+        if let (Some(suggestion), Some(other_suggestion)) = (&mut self.suggestion, &other.suggestion) {
+            suggestion.merge(other_suggestion);
+        }
+        if let (Some(fingerprint), Some(other_fingerprint)) = (&mut self.fingerprint, &other.fingerprint) {
+            fingerprint.merge(other_fingerprint);
+        }
+        if let (Some(triage), Some(other_triage)) = (&mut self.triage, &other.triage) {
+            triage.merge(other_triage);
+        }
+        if let Some(other_timing) = &other.timing {
+            self.timing.get_or_insert_with(Timing::new).merge(other_timing);
+        }
+        assert!(
+            self.profiles.len() == other.profiles.len()
+                && self.profiles.iter().zip(&other.profiles).all(|(profile, other)| profile.0 == other.0 && profile.1 == other.1),
+            "Cannot merge summaries with different tolerance profiles"
+        );
+        for ((_, _, failures), (_, _, other_failures)) in self.profiles.iter_mut().zip(&other.profiles) {
+            let is_first = other_failures.sample.index < failures.sample.index;
+            failures.merge(other_failures, is_first);
+        }
+        assert_eq!(self.ranges.len(), other.ranges.len(), "Cannot merge summaries with different ranges");
+        for ((range, summary), (other_range, other_summary)) in self.ranges.iter_mut().zip(&other.ranges) {
+            assert_eq!(*range, *other_range, "Cannot merge summaries with different ranges");
+            summary.merge(other_summary);
+        }
+        if let (Some(other_classes), true) = (&other.classes, self.classes.is_some()) {
+            let mut classes = self.classes.take().unwrap();
+            for (class, other_summary) in other_classes {
+                classes.entry(*class).or_insert_with(|| self.sub_summary()).merge(other_summary);
+            }
+            self.classes = Some(classes);
+        }
+        self.merge_raw_items(other);
+        if self.failure_capacity > 0 {
+            self.failures.extend_from_slice(&other.failures);
+            self.failures.sort_by_key(|item| item.0);
+            self.failures.truncate(self.failure_capacity);
+        }
+        self.publish_changes();
+    }
+
+    // Copy other's raw items into this summary's retained items and spill
+    // store, whichever are enabled, so that rescore covers both summaries.
+    // Panics if other has items but retains neither, since rescore would
+    // silently leave them out, or if other's spill store can't be read for
+    // retained items. Errors reading it for the spill store are reported by
+    // flush_spill, spilled and rescore instead.
+    fn merge_raw_items(&mut self, other: &DiffSummary) {
+        if self.retained.is_none() && self.spill.is_none() {
+            return;
+        }
+        assert!(
+            other.retained.is_some() || other.spill.is_some() || other.num_total == 0,
+            "Cannot merge summary {} without raw retention or spill into {}, which retains raw items",
+            other.name,
+            self.name
+        );
+        if let Some(retained) = &mut self.retained {
+            match (&other.retained, &other.spill) {
+                (Some(other_retained), _) => retained.extend_from_slice(other_retained),
+                (None, Some(_)) => {
+                    let items = other.spilled().and_then(|reader| reader.collect::<io::Result<Vec<_>>>());
+                    match items {
+                        Ok(items) => retained.extend(items),
+                        Err(err) => panic!("Cannot merge summary {}: reading its spill failed: {}", other.name, err),
+                    }
+                }
+                (None, None) => (),
+            }
+        }
+        if let Some(spill) = &self.spill {
+            match (&other.spill, &other.retained) {
+                (Some(other_spill), _) => spill.extend_from(other_spill),
+                (None, Some(other_retained)) => {
+                    for &(index, x, y) in other_retained {
+                        spill.add(index, x, y);
+                    }
+                }
+                (None, None) => (),
+            }
+        }
+    }
+
+    // The summary's headline results.
+    pub fn snapshot(&self) -> SummarySnapshot {
+        SummarySnapshot {
+            count: self.num_total,
+            fail_count: self.summary_fail.count,
+            sign_count: self.summary_sign.count,
+            worst_diff: self.diff,
+            is_ok: self.is_ok(),
+        }
+    }
+
+    // Pass a snapshot to any publisher if the worst difference or failure
+    // count changed by more than its deltas since the last one published.
+    fn publish_changes(&mut self) {
+        if let Some((publish, worst_delta, fail_delta)) = self.publisher {
+            let (worst, fail_count) = self.published;
+            let is_worst_same = self.diff == worst || (self.diff.is_nan() && worst.is_nan()) || (self.diff - worst).abs() <= worst_delta;
+            if !is_worst_same || self.summary_fail.count - fail_count > fail_delta {
+                self.published = (self.diff, self.summary_fail.count);
+                publish(&self.snapshot());
+            }
+        }
+    }
+
+    // The number of items excluded by sampling.
+    pub fn unsampled_count(&self) -> usize {
+        self.num_unsampled
+    }
+
+    // The number of (x, y) items left over from the longer of the streams
+    // passed to add_streams and read: all of them under LengthPolicy::PadNan,
+    // but only the first under Truncate. At most one is non-zero for a single call.
+    pub fn unpaired_counts(&self) -> (usize, usize) {
+        self.num_unpaired
+    }
+
+    // The number of items offered so far, including items excluded by
+    // sampling or filter, but not items ignored after a watchdog limit.
+    pub fn offered_count(&self) -> usize {
+        self.num_offered
+    }
+
+    // Which watchdog limit stopped ingestion early, if any.
+    pub fn truncation(&self) -> Option<Truncation> {
+        self.truncation
+    }
+
+    pub fn is_truncated(&self) -> bool {
+        self.truncation.is_some()
+    }
+
+    // The (both, x only, y only) counts of add_results items with errors.
+    pub fn error_counts(&self) -> (usize, usize, usize) {
+        (self.num_both_errors, self.num_x_errors, self.num_y_errors)
+    }
+
+    // Print this summary to stdout. With the tracing feature, also emit an
+    // event with its key statistics, within the summary's span.
+    pub fn report(&self) {
+        self.report_with(&|text| println!("{}", text));
+    }
+
+    // Report as with report, but pass the summary text to output rather than
+    // printing it, for targets without a usable stdout such as wasm32.
+    pub fn report_with(&self, output: &dyn Fn(&str)) {
+        output(&self.to_string());
+        #[cfg(feature = "tracing")]
+        tracing::info!(
+            parent: &self.span,
+            count = self.num_total,
+            fail_count = self.summary_fail.count,
+            sign_count = self.summary_sign.count,
+            worst_diff = self.diff,
+            tolerance = self.allow_diff,
+            ok = self.is_ok(),
+            "summary report"
+        );
+    }
+
+    // Indicate whether data currently satisfies allowed tolerance and sign change acceptance.
+    pub fn is_ok(&self) -> bool {
+        self.is_tolerance_ok() && self.is_sign_ok() && self.is_subnormal_ok() && self.is_convergence_ok()
+    }
+
+    // Indicate whether differences are within tolerance, apart from any
+    // failures allowed by the fail budget.
+    fn is_tolerance_ok(&self) -> bool {
+        self.is_within(self.allow_diff, &self.summary_fail)
+    }
+
+    // Indicate whether differences are within allow_diff, with the given
+    // failures, apart from any failures allowed by the fail budget.
+    fn is_within(&self, allow_diff: f64, failures: &DiffPartSummary) -> bool {
+        match self.fail_budget {
+            Some(budget) => failures.count <= budget.allowed(self.num_total),
+            None => self.diff <= allow_diff,
+        }
+    }
+
+    // Indicate whether the summary would be ok under the named tolerance
+    // profile, per with_profile, in place of its own tolerance.
+    pub fn is_profile_ok(&self, name: &str) -> bool {
+        let (_, allow_diff, failures) = self.profile(name);
+        self.is_within(*allow_diff, failures) && self.is_sign_ok() && self.is_subnormal_ok() && self.is_convergence_ok()
+    }
+
+    // The number of items outside the named profile's tolerance.
+    pub fn profile_fail_count(&self, name: &str) -> usize {
+        self.profile(name).2.count
+    }
+
+    // The names of the tolerance profiles, in declaration order.
+    pub fn profile_names(&self) -> Vec<&'a str> {
+        self.profiles.iter().map(|profile| profile.0).collect()
+    }
+
+    fn profile(&self, name: &str) -> &Profile<'a> {
+        match self.profiles.iter().find(|profile| profile.0 == name) {
+            Some(profile) => profile,
+            None => panic!("{} has no tolerance profile {}", self.name, name),
+        }
+    }
+
+    // The number of expected failures, per with_expected_failures, that failed.
+    pub fn expected_fail_count(&self) -> usize {
+        self.summary_xfail.count
+    }
+
+    // The number of expected failures, per with_expected_failures, that passed.
+    pub fn unexpected_pass_count(&self) -> usize {
+        self.num_xpass
+    }
+
+    // The fail budget, if set, as (failures used, failures allowed).
+    pub fn fail_budget_used(&self) -> Option<(usize, usize)> {
+        self.fail_budget.map(|budget| (self.summary_fail.count, budget.allowed(self.num_total)))
+    }
+
+    // Which criteria this summary failed or warned about, with the relevant
+    // counts. status().is_ok() matches is_ok().
+    #[allow(clippy::neg_cmp_op_on_partial_ord)]
+    pub fn status(&self) -> SummaryStatus {
+        let mut failures = Vec::new();
+        let mut warnings = Vec::new();
+        // Failures within the fail budget are only warnings.
+        let tolerance_reasons = if self.is_tolerance_ok() { &mut warnings } else { &mut failures };
+        // Funky negation on next line is intentional, to get desired nan behavior.
+        if !(self.diff <= self.allow_diff) {
+            tolerance_reasons.push(StatusReason::Tolerance { fail_count: self.summary_fail.count, worst_diff: self.diff, allow_diff: self.allow_diff });
+        }
+        if self.histo.count_nan() > 0 {
+            tolerance_reasons.push(StatusReason::Nan { nan_count: self.histo.count_nan() });
+        }
+        if self.summary_sign.count > 0 {
+            let sign = StatusReason::Sign { sign_count: self.summary_sign.count, allow_sign: self.allow_sign, limit: self.sign_limit };
+            if self.is_sign_ok() { warnings.push(sign) } else { failures.push(sign) }
+        }
+        if !self.is_subnormal_ok() {
+            failures.push(StatusReason::Subnormal { subnormal_count: self.summary_subnormal_x.count });
+        }
+        if let Some(convergence) = self.convergence().filter(|convergence| !convergence.is_ok()) {
+            failures.push(StatusReason::Convergence { order: convergence.order(), min_order: convergence.min_order() });
+        }
+        if let Some(warn_diff) = self.warn_diff {
+            if self.summary_warn.count > 0 {
+                warnings.push(StatusReason::Warn { warn_count: self.summary_warn.count, warn_diff });
+            }
+        }
+        if let Some(truncation) = self.truncation {
+            warnings.push(StatusReason::Truncated { truncation, offered_count: self.num_offered });
+        }
+        SummaryStatus { count: self.num_total, failures, warnings }
+    }
+
+    // Indicate whether sign changes are acceptable under allow_sign and any sign limit.
+    fn is_sign_ok(&self) -> bool {
+        self.is_sign_count_ok(self.summary_sign.count, self.num_total)
+    }
+
+    // Indicate whether num_sign sign changes among num_total items would be acceptable.
+    fn is_sign_count_ok(&self, num_sign: usize, num_total: usize) -> bool {
+        if self.allow_sign {
+            return true;
+        }
+        match self.sign_limit {
+            None => num_sign == 0,
+            Some(SignLimit::Count(count)) => num_sign <= count,
+            Some(SignLimit::Fraction(fraction)) => num_sign as f64 <= fraction * num_total as f64,
+        }
+    }
+
+    // Indicate whether subnormal x values are acceptable under the subnormal policy.
+    fn is_subnormal_ok(&self) -> bool {
+        self.subnormal_policy != Some(SubnormalPolicy::Fail) || self.summary_subnormal_x.count == 0
+    }
+
+    // The (x, y, cancellation) subnormal counts, if tracked via with_subnormal_policy.
+    pub fn subnormal_counts(&self) -> Option<(usize, usize, usize)> {
+        self.subnormal_policy.map(|_| (self.summary_subnormal_x.count, self.num_subnormal_y, self.num_subnormal_cancel))
+    }
+
+    // The total number of items added.
+    pub fn count(&self) -> usize {
+        self.num_total
+    }
+
+    // The number of items that failed tolerance, ignoring sign changes.
+    pub fn fail_count(&self) -> usize {
+        self.summary_fail.count
+    }
+
+    // The percentage of items that failed tolerance, or 0 if no items were added.
+    pub fn fail_percent(&self) -> f64 {
+        if self.num_total == 0 {
+            0.0
+        } else {
+            100.0 * self.summary_fail.count as f64 / self.num_total as f64
+        }
+    }
+
+    // The number of items skipped by the filter set with with_filter.
+    pub fn skipped_count(&self) -> usize {
+        self.num_skipped
+    }
+
+    // The number of items where x and y are bit-identical.
+    // Unlike a zero difference, this excludes cases like 0.0 vs -0.0.
+    pub fn identical_count(&self) -> usize {
+        self.num_identical
+    }
+
+    // The percentage of items where x and y are bit-identical, or 0 if no items were added.
+    pub fn identical_percent(&self) -> f64 {
+        if self.num_total == 0 {
+            0.0
+        } else {
+            100.0 * self.num_identical as f64 / self.num_total as f64
+        }
+    }
+
+    // The number of items above the warn threshold but within tolerance.
+    pub fn warn_count(&self) -> usize {
+        self.summary_warn.count
+    }
+
+    // The percentage of items above the warn threshold but within tolerance,
+    // or 0 if no items were added.
+    pub fn warn_percent(&self) -> f64 {
+        if self.num_total == 0 {
+            0.0
+        } else {
+            100.0 * self.summary_warn.count as f64 / self.num_total as f64
+        }
+    }
+
+    // The number of items with sign changes.
+    pub fn sign_count(&self) -> usize {
+        self.summary_sign.count
+    }
+
+    // The number of sign changes exempted by sign_epsilon.
+    pub fn sign_exempt_count(&self) -> usize {
+        self.num_sign_exempt
+    }
+
+    // The number of items with non-zero differences where x was over y,
+    // for seeing whether errors are biased in one direction.
+    pub fn over_count(&self) -> usize {
+        self.num_over
+    }
+
+    // The number of items with non-zero differences where x was under y.
+    pub fn under_count(&self) -> usize {
+        self.num_under
+    }
+
+    // The number of pairs of zeros of differing sign compared as equal,
+    // under SignedZeroPolicy::Ignore.
+    pub fn signed_zero_count(&self) -> usize {
+        self.num_signed_zero
+    }
+
+    // The number of pairs compared as zeros under with_zero_epsilon.
+    pub fn near_zero_count(&self) -> usize {
+        self.num_near_zero
+    }
+
+    // Whether strict mode is on, per with_strict_mode.
+    pub fn is_strict(&self) -> bool {
+        self.strict
+    }
+
+    // The number of passing pairs where x or y is nan or infinite, such as
+    // (nan, nan) under a comparator that treats them as equal. Always 0 in
+    // strict mode.
+    pub fn nonfinite_pass_count(&self) -> usize {
+        self.num_nonfinite_passed
+    }
+
+    // The number of items whose (x, y) pair repeated an earlier item's,
+    // with duplicate detection enabled via with_duplicate_detection.
+    pub fn duplicate_count(&self) -> usize {
+        self.num_duplicates
+    }
+
+    // The number of distinct (x, y) pairs, with duplicate detection enabled.
+    pub fn unique_count(&self) -> usize {
+        self.seen_pairs.len()
+    }
+
+    // The worst difference seen so far.
+    pub fn worst_diff(&self) -> f64 {
+        self.diff
+    }
+
+    // The maximum allowable difference for an item to be considered successful.
+    pub fn allow_diff(&self) -> f64 {
+        self.allow_diff
+    }
+
+    // Whether sign changes are allowed.
+    pub fn allow_sign(&self) -> bool {
+        self.allow_sign
+    }
+
+    // The mean of all finite differences, or 0 if there were none.
+    pub fn mean_diff(&self) -> f64 {
+        if self.num_finite == 0 { 0.0 } else { self.sum_diff / self.num_finite as f64 }
+    }
+
+    // The root mean square of all finite differences, or 0 if there were none.
+    pub fn rms_diff(&self) -> f64 {
+        if self.num_finite == 0 { 0.0 } else { (self.sum_sq_diff / self.num_finite as f64).sqrt() }
+    }
+
+    // The linear fit of x (computed) against y (expected), if enabled via with_linear_fit.
+    pub fn linear_fit(&self) -> Option<&LinearFit> {
+        self.fit.as_ref().map(|(fit, _)| fit)
+    }
+
+    // The bootstrap replicates, if enabled via with_bootstrap.
+    pub fn bootstrap(&self) -> Option<&Bootstrap> {
+        self.bootstrap.as_ref()
+    }
+
+    // The suggested tolerances, if enabled via with_tolerance_suggestion.
+    pub fn tolerance_suggestion(&self) -> Option<&ToleranceSuggestion> {
+        self.suggestion.as_ref()
+    }
+
+    // The fitted error model, if enabled via with_error_model.
+    pub fn error_model(&self) -> Option<&ErrorModel> {
+        self.error_model.as_ref()
+    }
+
+    // The range of expected magnitudes, if enabled via with_extremes.
+    pub fn extremes(&self) -> Option<&Extremes> {
+        self.extremes.as_ref()
+    }
+
+    // The dataset fingerprint, if enabled via with_fingerprint or with_dataset_id.
+    pub fn fingerprint(&self) -> Option<&Fingerprint> {
+        self.fingerprint.as_ref()
+    }
+
+    // The estimated order of convergence, if enabled via with_convergence.
+    pub fn convergence(&self) -> Option<&Convergence> {
+        self.convergence.as_ref().map(|(convergence, _)| convergence)
+    }
+
+    // Indicate whether the estimated order of convergence is at least the
+    // minimum, or true if convergence checking isn't enabled.
+    #[allow(clippy::unnecessary_map_or)]
+    pub fn is_convergence_ok(&self) -> bool {
+        self.convergence().map_or(true, |convergence| convergence.is_ok())
+    }
+
+    // Indicate whether the fitted slope is within the allowed deviation
+    // from 1, or true if fitting isn't enabled.
+    pub fn is_slope_ok(&self) -> bool {
+        match &self.fit {
+            Some((fit, allow)) => fit.count() < 2 || (fit.slope() - 1.0).abs() <= *allow,
+            None => true,
+        }
+    }
+
+    // The breakdown of failing items, if enabled.
+    pub fn triage(&self) -> Option<&Triage> {
+        self.triage.as_ref()
+    }
+
+    // The per-segment drift statistics, if enabled.
+    pub fn drift(&self) -> Option<&DriftSegments> {
+        self.drift.as_ref()
+    }
+
+    // The timing statistics, if any items were added with add_timed.
+    pub fn timing(&self) -> Option<&Timing> {
+        self.timing.as_ref()
+    }
+
+    // The summary of each range declared with with_ranges, in declaration order.
+    pub fn range_summaries(&self) -> &[(Range<usize>, DiffSummary<'a>)] {
+        &self.ranges
+    }
+
+    // The summary of each class of expected value seen, in class order,
+    // if enabled via with_class_strata.
+    pub fn class_summaries(&self) -> Option<&BTreeMap<ValueClass, DiffSummary<'a>>> {
+        self.classes.as_ref()
+    }
+
+    // The histogram of differences.
+    pub fn histogram(&self) -> &LogHistogram {
+        &self.histo
+    }
+
+    // The histogram of differences for the view with the given label, if any.
+    pub fn view_histogram(&self, label: &str) -> Option<&LogHistogram> {
+        self.views.iter().find(|view| view.0 == label).map(|view| &view.2)
+    }
+
+    // Compare this summary's results against a baseline run of the same
+    // measurement, reporting whether each metric improved or regressed.
+    // Panics if both summaries have fingerprints and they differ.
+    pub fn compare_to(&self, baseline: &DiffSummary) -> RunComparison {
+        self.try_compare_to(baseline).unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    // As compare_to, but returns DiffInputError::DatasetMismatch if both
+    // summaries have fingerprints and they differ. A summary without a
+    // fingerprint is assumed to match any dataset.
+    pub fn try_compare_to(&self, baseline: &DiffSummary) -> Result<RunComparison, DiffInputError> {
+        match (&baseline.fingerprint, &self.fingerprint) {
+            (Some(before), Some(after)) if before != after => Err(DiffInputError::DatasetMismatch {
+                name: self.name.to_string(),
+                baseline: before.clone(),
+                current: after.clone(),
+            }),
+            _ => Ok(RunComparison::new(baseline, self)),
+        }
+    }
+
+    // What worst_sample and worst_score consider worst.
+    pub fn worst_metric(&self) -> WorstMetric {
+        self.worst_metric
+    }
+
+    // How bad the worst item is, per the worst metric: its difference, its
+    // difference over the tolerance, or its ULPs. 0 if no item differed.
+    // Scores of summaries with the same metric can be ranked against each other.
+    pub fn worst_score(&self) -> f64 {
+        match self.worst_metric {
+            WorstMetric::Diff => self.diff,
+            WorstMetric::ToleranceRatio if self.diff == 0.0 => 0.0,
+            WorstMetric::ToleranceRatio => self.diff / self.allow_diff,
+            WorstMetric::Ulps => self.worst_ulps,
+        }
+    }
+
+    // The worst item per the worst metric, if any item had a non-zero
+    // difference, or for WorstMetric::Ulps, non-zero ULPs.
+    pub fn worst_sample(&self) -> Option<&SampleRecord> {
+        if self.worst_metric == WorstMetric::Ulps {
+            self.summary_ulps.sample().filter(|_| self.worst_ulps != 0.0)
+        } else {
+            self.summary_diff.sample()
+        }
+    }
+
+    // The first item to fail tolerance, if any.
+    pub fn first_failure(&self) -> Option<&SampleRecord> {
+        self.summary_fail.sample()
+    }
+
+    // The first item with a sign change, if any.
+    pub fn first_sign_change(&self) -> Option<&SampleRecord> {
+        self.summary_sign.sample()
+    }
+
+    // The worst item with a sign change, if enabled via with_sign_worst
+    // and any sign change has occurred.
+    pub fn worst_sign_change(&self) -> Option<&SampleRecord> {
+        self.summary_sign_worst.sample()
+    }
+
+    // Set context to attach to the sample records of items added from now
+    // on, such as the fuzzer seed or input file producing them, or None to
+    // clear it. Applies to retained samples: the worst item, first failure,
+    // first and worst sign changes, and top-N items.
+    pub fn set_sample_context(&mut self, context: Option<&str>) {
+        self.sample_context = context.map(Rc::from);
+        for (_, summary) in &mut self.ranges {
+            summary.set_sample_context(context);
+        }
+        for summary in self.classes.iter_mut().flat_map(|classes| classes.values_mut()) {
+            summary.set_sample_context(context);
+        }
+    }
+
+    // The (index, x, y, diff) of the passing example chosen per
+    // with_passing_example, if enabled and any item passed.
+    pub fn passing_example(&self) -> Option<(usize, f64, f64, f64)> {
+        let (example, reservoir) = self.passing.as_ref()?;
+        let mut items = reservoir.sorted();
+        match example {
+            PassingExample::Median => {
+                // Stable, so equal diffs keep index order.
+                items.sort_by(|a, b| a.3.total_cmp(&b.3));
+                items.get(items.len().saturating_sub(1) / 2).copied()
+            }
+            PassingExample::Random { .. } => items.first().copied(),
+        }
+    }
+
+    // The (index, x, y, diff) of the items sampled per with_reservoir,
+    // in index order. Empty unless enabled.
+    pub fn reservoir(&self) -> Vec<(usize, f64, f64, f64)> {
+        self.reservoir.sorted()
+    }
+
+    // The (index, x, y, diff) of the item with the worst difference in each
+    // unreduced histogram bucket, in bucket order. Empty unless enabled via
+    // with_bucket_samples.
+    pub fn bucket_samples(&self) -> Vec<(BucketKey, (usize, f64, f64, f64))> {
+        self.bucket_samples.iter().flatten().map(|(&key, &sample)| (key, sample)).collect()
+    }
+
+    // The items with the worst differences, from worst to least bad.
+    // Empty unless enabled via with_top_n.
+    pub fn top_n(&self) -> Vec<SampleRecord> {
+        self.top.sorted()
+    }
+
+    // Identify outliers among the items retained via with_top_n: items whose
+    // diff exceeds q75 + k * (q75 - q25), with the quartiles estimated from
+    // the histogram. A k of 1.5 is typical, with 3 for more extreme outliers.
+    pub fn outliers(&self, k: f64) -> OutlierReport {
+        let q25 = self.histo.approx_quantile(0.25);
+        let q75 = self.histo.approx_quantile(0.75);
+        let threshold = q75 + k * (q75 - q25);
+        let items: Vec<SampleRecord> = self.top.sorted().into_iter()
+            .filter(|item| crate::diff::is_diff_worse(item.diff, threshold))
+            .collect();
+        let truncated = self.top.capacity() > 0 && items.len() == self.top.capacity();
+        OutlierReport { k, threshold, items, truncated }
+    }
+
+    // The (index, x, y, diff) of collected failing items, in the order added.
+    // Empty unless enabled via with_failure_capacity. May hold fewer items
+    // than the failure count, if the capacity was reached.
+    pub fn failures(&self) -> &[(usize, f64, f64, f64)] {
+        &self.failures
+    }
+
+    // Write collected failing items as CSV, with a header row.
+    // Values use this summary's float format.
+    pub fn write_failures_csv<W: io::Write>(&self, writer: &mut W) -> io::Result<()> {
+        writeln!(writer, "index,x,y,diff")?;
+        for &(index, x, y, diff) in &self.failures {
+            writeln!(writer, "{},{},{},{:e}", index, self.fmt_value(x), self.fmt_value(y), diff)?;
+        }
+        Ok(())
+    }
+
+    // Wrap a value for display using this summary's float format.
+    fn fmt_value(&self, x: f64) -> FmtF64 {
+        FmtF64(x, self.float_format)
+    }
+
+    // Perform any pending histogram bucket reduction now, rather than
+    // waiting for the next Display.
+    pub fn reduce(&self) {
+        self.histo.reduce();
+    }
+
+    // Assert that worst diff is within tolerance,
+    // then assert that sign change status is allowed.
+    // The tolerance failure message also reports the first failing item.
+    // Failure messages are prefixed with any context set by with_assert_context,
+    // and followed by the summary's full Display output.
+    pub fn assert(&self) {
+        self.assert_with("");
+    }
+
+    // Assert as with assert, prefixing failure messages with context,
+    // for example the phase of a long test that produced the data.
+    pub fn assert_with(&self, context: &str) {
+        let prefix = self.assert_prefix(context);
+        if !self.is_tolerance_ok() {
+            panic!(
+                "{}assert failed item {}, {}: {} vs {} diff abs {:e} outside inclusive {:e}{}{}\n{}",
+                prefix,
+                self.summary_diff.sample.index,
+                self.name,
+                self.fmt_value(self.summary_diff.sample.x),
+                self.fmt_value(self.summary_diff.sample.y),
+                self.diff,
+                self.allow_diff,
+                // Only present if an item was recorded as failing.
+                match self.first_failure().map(SampleRecord::as_tuple) {
+                    Some((index, x, y, diff)) => {
+                        format!(", first failed item {}: {} vs {} diff abs {:e}", index, self.fmt_value(x), self.fmt_value(y), diff)
+                    }
+                    None => String::new(),
+                },
+                match self.fail_budget_used() {
+                    Some((used, allowed)) => format!(", {} failures exceed budget {}", used, allowed),
+                    None => String::new(),
+                },
+                self
+            );
+        }
+        self.assert_sign_and_subnormal(&prefix);
+    }
+
+    // Assert that the summary is ok under the named tolerance profile,
+    // per with_profile, e.g. assert_profile("ci") on the reference platform.
+    pub fn assert_profile(&self, name: &str) {
+        let prefix = self.assert_prefix("");
+        let (_, allow_diff, failures) = self.profile(name);
+        if !self.is_within(*allow_diff, failures) {
+            let (first_index, first_x, first_y, first_diff) = failures.sample.as_tuple();
+            panic!(
+                "{}assert failed profile {} item {}, {}: {} vs {} diff abs {:e} outside inclusive {:e}, first failed item {}: {} vs {} diff abs {:e}{}\n{}",
+                prefix,
+                name,
+                self.summary_diff.sample.index,
+                self.name,
+                self.fmt_value(self.summary_diff.sample.x),
+                self.fmt_value(self.summary_diff.sample.y),
+                self.diff,
+                allow_diff,
+                first_index,
+                self.fmt_value(first_x),
+                self.fmt_value(first_y),
+                first_diff,
+                match self.fail_budget {
+                    Some(budget) => format!(", {} failures exceed budget {}", failures.count, budget.allowed(self.num_total)),
+                    None => String::new(),
+                },
+                self
+            );
+        }
+        self.assert_sign_and_subnormal(&prefix);
+    }
+
+    // The prefix for assert failure messages, from the summary's assert
+    // context and the given context.
+    fn assert_prefix(&self, context: &str) -> String {
+        match (self.assert_context.as_str(), context) {
+            ("", "") => String::new(),
+            (outer, "") | ("", outer) => format!("{}: ", outer),
+            (outer, inner) => format!("{}: {}: ", outer, inner),
+        }
+    }
+
+    // Assert the sign change and subnormal checks, as part of assert.
+    fn assert_sign_and_subnormal(&self, prefix: &str) {
+        assert!(
+            self.is_sign_ok(),
+            "{}assert failed item {}, {}: {} vs {} sign difference disallowed{}.\n{}",
+            prefix,
+            self.summary_sign.sample.index,
+            self.name,
+            self.fmt_value(self.summary_sign.sample.x),
+            self.fmt_value(self.summary_sign.sample.y),
+            match self.sign_limit {
+                Some(limit) => format!(", {} sign differences exceed {}", self.summary_sign.count, limit),
+                None => String::new(),
+            },
+            self
+        );
+        assert!(
+            self.is_subnormal_ok(),
+            "{}assert failed item {}, {}: {} vs {} subnormal result disallowed.\n{}",
+            prefix,
+            self.summary_subnormal_x.sample.index,
+            self.name,
+            self.fmt_value(self.summary_subnormal_x.sample.x),
+            self.fmt_value(self.summary_subnormal_x.sample.y),
+            self
+        );
+    }
+
+    // Assert that every summary is ok, so that one failing channel doesn't
+    // hide the results of the others. The failure message lists the full
+    // Display output of each failing summary.
+    pub fn assert_all(summaries: &[DiffSummary]) {
+        let failed: Vec<String> = summaries.iter().filter(|summary| !summary.is_ok()).map(|summary| summary.to_string()).collect();
+        assert!(
+            failed.is_empty(),
+            "assert failed for {} of {} summaries:\n{}",
+            failed.len(),
+            summaries.len(),
+            failed.join("\n")
+        );
+    }
+}
+
+impl Clone for DiffSummary<'_> {
+        fn clone(&self) -> Self {
+            DiffSummary {
+                name: self.name,
+                diff: self.diff,
+                allow_diff: self.allow_diff,
+                unit: self.unit,
+                allow_sign: self.allow_sign,
+                num_total: self.num_total,
+                num_finite: self.num_finite,
+                sum_diff: self.sum_diff,
+                sum_sq_diff: self.sum_sq_diff,
+                num_identical: self.num_identical,
+                num_skipped: self.num_skipped,
+                sampling: self.sampling,
+                num_unsampled: self.num_unsampled,
+                num_unpaired: self.num_unpaired,
+                deadline: self.deadline,
+                max_items: self.max_items,
+                num_offered: self.num_offered,
+                truncation: self.truncation,
+                error_policy: self.error_policy,
+                num_both_errors: self.num_both_errors,
+                num_x_errors: self.num_x_errors,
+                num_y_errors: self.num_y_errors,
+                summary_fail: self.summary_fail.clone(),
+                warn_diff: self.warn_diff,
+                summary_warn: self.summary_warn.clone(),
+                summary_diff: self.summary_diff.clone(),
+                summary_sign: self.summary_sign.clone(),
+                sign_epsilon: self.sign_epsilon,
+                num_sign_exempt: self.num_sign_exempt,
+                num_over: self.num_over,
+                num_under: self.num_under,
+                fail_budget: self.fail_budget,
+                expected_failures: self.expected_failures.clone(),
+                summary_xfail: self.summary_xfail.clone(),
+                num_xpass: self.num_xpass,
+                signed_zero_policy: self.signed_zero_policy,
+                num_signed_zero: self.num_signed_zero,
+                zero_epsilon: self.zero_epsilon,
+                num_near_zero: self.num_near_zero,
+                strict: self.strict,
+                num_nonfinite_passed: self.num_nonfinite_passed,
+                duplicate_policy: self.duplicate_policy,
+                seen_pairs: self.seen_pairs.clone(),
+                num_duplicates: self.num_duplicates,
+                sign_limit: self.sign_limit,
+                sign_worst_metric: self.sign_worst_metric,
+                summary_sign_worst: self.summary_sign_worst.clone(),
+                sign_worst: self.sign_worst,
+                worst_metric: self.worst_metric,
+                summary_ulps: self.summary_ulps.clone(),
+                worst_ulps: self.worst_ulps,
+                histo: self.histo.clone(),
+                views: self.views.clone(),
+                failures: self.failures.clone(),
+                failure_capacity: self.failure_capacity,
+                fit: self.fit,
+                bootstrap: self.bootstrap.clone(),
+                convergence: self.convergence,
+                fingerprint: self.fingerprint.clone(),
+                extremes: self.extremes,
+                suggestion: self.suggestion.clone(),
+                error_model: self.error_model,
+                drift: self.drift.clone(),
+                profiles: self.profiles.clone(),
+                ranges: self.ranges.clone(),
+                classes: self.classes.clone(),
+                timing: self.timing,
+                triage: self.triage.clone(),
+                quantization: self.quantization,
+                subnormal_policy: self.subnormal_policy,
+                summary_subnormal_x: self.summary_subnormal_x.clone(),
+                num_subnormal_y: self.num_subnormal_y,
+                num_subnormal_cancel: self.num_subnormal_cancel,
+                top: self.top.clone(),
+                reservoir: self.reservoir.clone(),
+                passing: self.passing.clone(),
+                bucket_samples: self.bucket_samples.clone(),
+                staged: self.staged,
+                metadata: self.metadata.clone(),
+                assert_context: self.assert_context.clone(),
+                sample_context: self.sample_context.clone(),
+                float_format: self.float_format,
+                spot_check: self.spot_check,
+                display_options: self.display_options,
+                filter: self.filter,
+                trace: self.trace,
+                observer: self.observer,
+                publisher: self.publisher,
+                published: self.published,
+                csv_tee: self.csv_tee.clone(),
+                spill: self.spill.clone(),
+                retained: self.retained.clone(),
+                #[cfg(feature = "tracing")]
+                span: self.span.clone(),
+                calc_diff: self.calc_diff,
+                calc_direction: self.calc_direction,
+            }
+        }
+}
+
+impl DiffSummary<'_> {
+    // Format this summary using the given options, rather than the summary's own.
+    pub fn fmt_with(&self, f: &mut Formatter<'_>, options: &DisplayOptions) -> std::fmt::Result {
+        assert!(self.summary_fail.count <= self.num_total);
+        let sep = options.separator();
+        let precision = options.precision();
+        let value = |x: f64| Precise(self.fmt_value(x), precision);
+        let diff = |x: f64| Precise(FmtF64(x, FloatFormat::Exp), precision);
+        let diff_unit = |x: f64| WithUnit(diff(x), self.unit);
+        let percent = |count: usize| Percent(count, self.num_total, options.show_counts, options.percent_format);
+        let (hl_overall, hl_diff, hl_sign) = if options.use_color() {
+            let (overall, diff, sign) = self.highlights();
+            (Some(overall), Some(diff), Some(sign))
+        } else {
+            (None, None, None)
+        };
+        write!(f, "{}", Paint(self.name, hl_overall))?;
+        if !self.metadata.is_empty() {
+            if !self.name.is_empty() {
+                write!(f, " ")?;
+            }
+            write!(f, "[")?;
+            for (i, (key, value)) in self.metadata.iter().enumerate() {
+                write!(f, "{}{}={}", if i > 0 { ", " } else { "" }, key, value)?;
+            }
+            write!(f, "]")?;
+        }
+        write!(
+            f,
+            "{}count {}",
+            if !self.name.is_empty() || !self.metadata.is_empty() { ": " } else { "" },
+            self.num_total
+        )?;
+        if let Some(truncation) = self.truncation {
+            write!(f, "{}truncated by {}", sep, truncation)?;
+        }
+        match self.num_unpaired {
+            (0, 0) => (),
+            (x_count, 0) => write!(f, "{}x longer, unpaired {}", sep, x_count)?,
+            (0, y_count) => write!(f, "{}y longer, unpaired {}", sep, y_count)?,
+            (x_count, y_count) => write!(f, "{}unpaired x {} y {}", sep, x_count, y_count)?,
+        }
+        if self.num_unsampled > 0 {
+            write!(f, "{}unsampled {}", sep, self.num_unsampled)?;
+        }
+        if self.num_skipped > 0 {
+            write!(f, "{}skipped {}", sep, self.num_skipped)?;
+        }
+        if let (Some(zero_epsilon), true) = (self.zero_epsilon, self.num_near_zero > 0) {
+            write!(f, "{}near zero {} below {:e}", sep, self.num_near_zero, zero_epsilon)?;
+        }
+        if let Some(policy) = self.duplicate_policy {
+            write!(f, "{}unique {} duplicates {}", sep, self.seen_pairs.len(), self.num_duplicates)?;
+            if policy == DuplicatePolicy::Skip && self.num_duplicates > 0 {
+                write!(f, " skipped")?;
+            }
+        }
+        if self.num_both_errors + self.num_x_errors + self.num_y_errors > 0 {
+            write!(
+                f,
+                "{}errors both {} computed {} expected {}{}",
+                sep,
+                self.num_both_errors,
+                self.num_x_errors,
+                self.num_y_errors,
+                if self.error_policy == ErrorPolicy::Skip { " skipped" } else { "" },
+            )?;
+        }
+        if self.summary_xfail.count + self.num_xpass > 0 {
+            write!(f, "{}expected failures {} unexpectedly passing {}", sep, self.summary_xfail.count, self.num_xpass)?;
+            if options.show_samples && self.summary_xfail.count > 0 {
+                write!(f, " first index {}", self.summary_xfail.sample.index)?;
+            }
+        }
+        if self.num_identical > 0 {
+            write!(f, "{}identical {}", sep, percent(self.num_identical))?;
+        }
+        if options.is_verbose() {
+            write!(f, "{}status {}", sep, Paint(if self.is_ok() { "ok" } else { "failed" }, hl_overall))?;
+            write!(f, "{}nonfinite {}", sep, if self.strict { "strict" } else { "lenient" })?;
+            if self.num_nonfinite_passed > 0 {
+                write!(f, " passed {}", self.num_nonfinite_passed)?;
+            }
+        }
+        if self.summary_diff.count > 0 {
+            let worst = match self.worst_sample() {
+                Some(sample) if options.show_samples => Some(sample),
+                _ => None,
+            };
+            // With WorstMetric::Ulps, the worst sample is the item with the
+            // most ULPs, whose diff needn't be the worst diff, so label both.
+            let by_ulps = self.worst_metric == WorstMetric::Ulps;
+            match worst {
+                Some(&SampleRecord { index, x, y, diff: worst_diff, .. }) => {
+                    write!(f, "{}worst ", sep)?;
+                    if by_ulps {
+                        write!(f, "ulps {} ", self.worst_ulps)?;
+                    }
+                    write!(f, "index {} {} vs {} diff {}", index, value(x), value(y), diff_unit(worst_diff))?;
+                    if by_ulps {
+                        write!(f, "{}worst diff {}", sep, diff_unit(self.diff))?;
+                    }
+                }
+                None => {
+                    write!(f, "{}worst diff {}", sep, diff_unit(self.diff))?;
+                    if by_ulps {
+                        write!(f, "{}worst ulps {}", sep, self.worst_ulps)?;
+                    }
+                }
+            }
+            write!(
+                f,
+                "{}{} failed{}",
+                sep,
+                Paint(percent(self.summary_fail.count), hl_diff),
+                Paint(format_args!(" tolerance {}", diff_unit(self.allow_diff)), hl_diff),
+            )?;
+            if let Some((used, allowed)) = self.fail_budget_used() {
+                write!(f, " budget used {} of {}", used, allowed)?;
+            }
+            if options.show_samples {
+                if let Some(&SampleRecord { index, x, y, diff: fail_diff, .. }) = self.first_failure() {
+                    write!(f, " first index {} {} vs {} diff {}", index, value(x), value(y), diff_unit(fail_diff))?;
+                }
+            }
+            if let Some(triage) = &self.triage {
+                if triage.count() > 0 {
+                    write!(f, "{}{}", sep, triage)?;
+                }
+            }
+            if let Some(warn_diff) = self.warn_diff {
+                let hl_warn = hl_overall.map(|_| if self.summary_warn.count > 0 { Highlight::Marginal } else { Highlight::Pass });
+                write!(
+                    f,
+                    "{}{}",
+                    sep,
+                    Paint(format_args!("{} warned above {}", percent(self.summary_warn.count), diff_unit(warn_diff)), hl_warn),
+                )?;
+            }
+            if options.is_verbose() && self.num_finite > 0 {
+                write!(f, "{}mean {} rms {}", sep, diff(self.mean_diff()), diff(self.rms_diff()))?;
+            }
+            if options.is_verbose() {
+                write!(f, "{}over {} under {}", sep, percent(self.num_over), percent(self.num_under))?;
+            }
+            if let (Some(bootstrap), true) = (&self.bootstrap, options.is_verbose()) {
+                let (mean_lo, mean_hi) = bootstrap.mean_diff_interval(0.95);
+                let (fail_lo, fail_hi) = bootstrap.fail_rate_interval(0.95);
+                write!(
+                    f,
+                    "{}95% ci mean {} to {} failed {:.1}% to {:.1}%",
+                    sep,
+                    diff(mean_lo),
+                    diff(mean_hi),
+                    100.0 * fail_lo,
+                    100.0 * fail_hi
+                )?;
+            }
+            if options.show_histogram {
+                let marker = Some(self.allow_diff).filter(|_| options.show_tolerance_marker);
+                write!(f, "{}{}", sep, self.histo.display_with_options(self.unit, options, marker))?;
+            }
+            if let Some(samples) = &self.bucket_samples {
+                if options.is_verbose() && options.show_samples {
+                    write!(f, "{}bucket worst", sep)?;
+                    for (i, (key, (index, x, y, _))) in samples.iter().enumerate() {
+                        write!(f, "{} {} index {} {} vs {}", if i > 0 { "," } else { "" }, key, index, value(*x), value(*y))?;
+                    }
+                }
+            }
+            if options.show_quantiles {
+                // These are rough estimates, so avoid implying more precision than they have.
+                let quantile = |q: f64| Precise(FmtF64(self.histo.approx_quantile(q), FloatFormat::Exp), Some(precision.unwrap_or(1)));
+                write!(f, "{}p50 {} p95 {} p99 {}", sep, quantile(0.5), quantile(0.95), quantile(0.99))?;
+            }
+        } else if self.num_total > 0 {
+            if options.show_histogram {
+                write!(f, "{}zero {}", sep, percent(self.num_total))?;
+            }
+            write!(f, "{}{}", sep, Paint(format_args!("{} failed tolerance {}", percent(0), diff_unit(self.allow_diff)), hl_diff))?;
+            if let Some((used, allowed)) = self.fail_budget_used() {
+                write!(f, " budget used {} of {}", used, allowed)?;
+            }
+        }
+        if let (Some((example, _)), Some((index, x, y, passing_diff)), true) =
+            (&self.passing, self.passing_example(), options.show_samples && options.show_passing_example)
+        {
+            write!(f, "{}{} passing index {} {} vs {} diff {}", sep, example, index, value(x), value(y), diff_unit(passing_diff))?;
+        }
+        if let (Some(extremes), true) = (&self.extremes, self.num_total > 0) {
+            write!(f, "{}{}", sep, extremes)?;
+        }
+        if let (Some(error_model), true) = (&self.error_model, options.is_verbose() && self.num_total > 0) {
+            write!(f, "{}{}", sep, error_model)?;
+        }
+        if let (Some(fingerprint), true) = (&self.fingerprint, options.is_verbose()) {
+            write!(f, "{}dataset {}", sep, fingerprint)?;
+        }
+        if options.show_histogram && self.num_total > 0 {
+            for (label, _, histo) in &self.views {
+                write!(f, "{}{} {}", sep, label, histo.display_with_options("", options, None))?;
+            }
+        }
+        if let Some((fit, _)) = &self.fit {
+            if fit.count() > 1 {
+                let hl_fit = hl_overall.map(|_| if self.is_slope_ok() { Highlight::Pass } else { Highlight::Fail });
+                write!(
+                    f,
+                    "{}{}{} intercept {} r2 {} corr {}",
+                    sep,
+                    Paint(format_args!("slope {}", diff(fit.slope())), hl_fit),
+                    if self.is_slope_ok() { "" } else { " flagged" },
+                    value(fit.intercept()),
+                    diff(fit.r_squared()),
+                    value(fit.correlation()),
+                )?;
+            }
+        }
+        if let Some(convergence) = self.convergence() {
+            if convergence.count() > 1 {
+                let hl_order = hl_overall.map(|_| if convergence.is_ok() { Highlight::Pass } else { Highlight::Fail });
+                write!(f, "{}{}", sep, Paint(format_args!("order {}", diff(convergence.order())), hl_order))?;
+                if !convergence.is_ok() {
+                    write!(f, " below min {}", diff(convergence.min_order()))?;
+                }
+                if options.is_verbose() {
+                    write!(f, " r2 {}", diff(convergence.r_squared()))?;
+                }
+            }
+        }
+        if let Some(drift) = &self.drift {
+            if self.num_total > 0 {
+                write!(f, "{}drift {}", sep, diff(drift.ratio()))?;
+                if options.is_verbose() {
+                    write!(f, " segment means")?;
+                    for segment in drift.segments() {
+                        write!(f, " {}", diff(segment.mean()))?;
+                    }
+                }
+            }
+        }
+        if let (Some(timing), true) = (&self.timing, options.show_timing) {
+            write!(f, "{}{}", sep, timing)?;
+        }
+        for (range, summary) in &self.ranges {
+            let hl_range = hl_overall.map(|_| if summary.is_ok() { Highlight::Pass } else { Highlight::Fail });
+            write!(
+                f,
+                "{}{} worst {} failed {}",
+                sep,
+                Paint(format_args!("range {}..{}", range.start, range.end), hl_range),
+                diff_unit(summary.diff),
+                Percent(summary.summary_fail.count, summary.num_total, options.show_counts, options.percent_format),
+            )?;
+            if options.show_samples && summary.summary_diff.count > 0 {
+                write!(f, " worst index {}", summary.summary_diff.sample.index)?;
+            }
+        }
+        for (name, allow_diff, failures) in &self.profiles {
+            let is_ok = self.is_within(*allow_diff, failures);
+            write!(
+                f,
+                "{}{} within {} failed {}",
+                sep,
+                Paint(format_args!("profile {} {}", name, if is_ok { "pass" } else { "fail" }), hl_overall.map(|_| if is_ok { Highlight::Pass } else { Highlight::Fail })),
+                diff_unit(*allow_diff),
+                percent(failures.count),
+            )?;
+        }
+        for (class, summary) in self.classes.iter().flatten() {
+            let hl_class = hl_overall.map(|_| if summary.is_ok() { Highlight::Pass } else { Highlight::Fail });
+            write!(
+                f,
+                "{}{} worst {} failed {}",
+                sep,
+                Paint(format_args!("class {}", class), hl_class),
+                diff_unit(summary.diff),
+                Percent(summary.summary_fail.count, summary.num_total, options.show_counts, options.percent_format),
+            )?;
+            if options.show_samples && summary.summary_diff.count > 0 {
+                write!(f, " worst index {}", summary.summary_diff.sample.index)?;
+            }
+        }
+        if self.num_total > 0 {
+            write!(
+                f,
+                "{}{}",
+                sep,
+                Paint(format_args!("sign diffs {}", percent(self.summary_sign.count)), hl_sign),
+            )?;
+            if options.is_verbose() {
+                match (self.allow_sign, self.sign_limit) {
+                    (false, Some(limit)) => write!(f, " {}", limit)?,
+                    (allow_sign, _) => write!(f, " {}", if allow_sign { "allowed" } else { "disallowed" })?,
+                }
+            }
+            if self.num_sign_exempt > 0 {
+                write!(f, " exempt {}", self.num_sign_exempt)?;
+            }
+            if self.num_signed_zero > 0 {
+                write!(f, " signed zeros ignored {}", self.num_signed_zero)?;
+            }
+            if options.show_samples && self.summary_sign.count > 0 {
+                write!(f,
+                    " first index {} {} vs {}",
+                    self.summary_sign.sample.index,
+                    value(self.summary_sign.sample.x),
+                    value(self.summary_sign.sample.y),
+                )?;
+                if let Some(&SampleRecord { index, x, y, .. }) = self.worst_sign_change() {
+                    write!(f, " worst index {} {} vs {}", index, value(x), value(y))?;
+                }
+            }
+        }
+        if let Some((num_x, num_y, num_cancel)) = self.subnormal_counts() {
+            let hl_subnormal = hl_overall.map(|_| if self.is_subnormal_ok() { Highlight::Pass } else { Highlight::Fail });
+            write!(
+                f,
+                "{}{} y {} cancellation {}",
+                sep,
+                Paint(format_args!("subnormal x {}", num_x), hl_subnormal),
+                num_y,
+                num_cancel
+            )?;
+            if options.show_samples && num_x > 0 {
+                write!(
+                    f,
+                    " first index {} {} vs {}",
+                    self.summary_subnormal_x.sample.index,
+                    value(self.summary_subnormal_x.sample.x),
+                    value(self.summary_subnormal_x.sample.y),
+                )?;
+            }
+        }
+        Ok(())
+    }
+
+    // Choose highlights for (overall status, tolerance, sign change) in colored output.
+    fn highlights(&self) -> (Highlight, Highlight, Highlight) {
+        let diff = if !self.is_tolerance_ok() {
+            Highlight::Fail
+        } else if self.summary_fail.count > 0 || self.diff > self.allow_diff * MARGINAL_FRACTION {
+            Highlight::Marginal
+        } else {
+            Highlight::Pass
+        };
+        let sign = if self.summary_sign.count == 0 {
+            Highlight::Pass
+        } else if self.is_sign_ok() {
+            Highlight::Marginal
+        } else {
+            Highlight::Fail
+        };
+        // Derived from is_ok, so that every failure reason, such as a
+        // convergence order below the minimum, highlights as a failure.
+        let overall = if !self.is_ok() {
+            Highlight::Fail
+        } else if diff == Highlight::Marginal || sign == Highlight::Marginal {
+            Highlight::Marginal
+        } else {
+            Highlight::Pass
+        };
+        (overall, diff, sign)
+    }
+
+    // Wrap this summary for display with the given options, e.g. for use with format!
+    pub fn display_with<'s>(&'s self, options: &'s DisplayOptions) -> DisplayWith<'s> {
+        DisplayWith { summary: self, options }
+    }
+
+    // The summary as shown with DisplayOptions::snapshot, for comparing
+    // against a stored snapshot, e.g.
+    //   insta::assert_snapshot!(summary.snapshot_report());
+    pub fn snapshot_report(&self) -> String {
+        self.display_with(&DisplayOptions::snapshot()).to_string()
+    }
+}
+
+impl Display for DiffSummary<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::result::Result<(), std::fmt::Error> {
+        self.fmt_with(f, &self.display_options)
+    }
+}
+
+// A DiffSummary paired with display options, as returned by display_with.
+pub struct DisplayWith<'s> {
+    summary: &'s DiffSummary<'s>,
+    options: &'s DisplayOptions,
+}
+
+impl Display for DisplayWith<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        self.summary.fmt_with(f, self.options)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{DiffSummary, Direction, DuplicatePolicy, ErrorPolicy, ItemRecord, ItemStatus, LengthPolicy, PassingExample, Quantization, Sampling, SignLimit, SignWorstMetric, SignedZeroPolicy, SubnormalPolicy, SummarySnapshot, Truncation, WorstMetric};
+    use crate::log_histogram::BucketKey;
+    use crate::diff;
+    use crate::display_options::{DisplayOptions, Layout};
+    use crate::status::{StatusReason, SummaryStatus};
+    use crate::sample_record::SampleRecord;
+    use crate::tolerance_check::{ComparatorKind, ToleranceCheck};
+    use crate::util::{FloatFormat, Highlight, PercentFormat};
+    use std::f64;
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn test1() {
+        let data = &[
+            (0.0, 1.0),
+            (2.0, 1.0),
+            (1.0, 10.0),
+            (0.1, -0.1),
+            (f64::NAN, f64::NAN),
+        ];
+        let mut summary = DiffSummary::new("simple", 1.0, false, 4, &diff::diff_abs);
+        for (i, item) in data.iter().enumerate() {
+            summary.add(item.0, item.1, i);
+        }
+        println!();
+        println!("{}", summary);
+        assert!(!summary.is_ok());
+    }
+
+    #[test]
+    fn test2() {
+        let data = &[
+            (0.0, 0.0, 1.0, 1.0),
             (0.0, -0.0, 2.1, 2.1),
             (-0.0, 0.0, -5.3, -5.3),
             (-0.0, -0.0, 504.0, 504.0),
@@ -252,32 +3529,1406 @@ mod tests {
             (f64::MAX, f64::MIN, 0.0, 1e-18),
         ];
 
-        let mut summaries = DiffSummary::new_vec(4, &[
-            ("data0", 2e-8, false, &diff::diff_abs),
-            ("data1", 1e-6, true, &diff::diff_abs),
-            ("data2", 1e-9, false, &diff::diff_abs),
-            ("data3", 1e-9, false, &diff::diff_abs),
+        let mut summaries = DiffSummary::new_vec(4, &[
+            ("data0", 2e-8, false, &diff::diff_abs),
+            ("data1", 1e-6, true, &diff::diff_abs),
+            ("data2", 1e-9, false, &diff::diff_abs),
+            ("data3", 1e-9, false, &diff::diff_abs),
+        ]);
+        for (i, item) in data.iter().enumerate() {
+            summaries[0].add(item.0, item.1, i);
+            summaries[1].add(item.2, item.3, i);
+            summaries[2].add(item.0, item.0, i);
+        }
+
+        println!();
+        for summary in &summaries {
+            println!("{}", summary);
+        }
+        assert_eq!(summaries[0].num_total, data.len());
+        assert_eq!(summaries[1].num_total, data.len());
+        assert!(summaries[0].summary_fail.count > summaries[1].summary_fail.count);
+        assert_eq!(summaries[2].num_total, data.len());
+        assert_eq!(summaries[2].summary_diff.count, 0);
+        assert_eq!(summaries[2].summary_sign.count, 0);
+        assert_eq!(summaries[3].num_total, 0);
+        assert!(!summaries[0].is_ok());
+        assert!(!summaries[1].is_ok());
+        assert!(summaries[2].is_ok());
+        assert!(summaries[3].is_ok());
+    }
+
+    #[test]
+    fn test_spot_check() {
+        let summary = DiffSummary::new("solver", 0.25, false, 4, &diff::diff_abs).with_spot_check(Sampling::EveryNth(7));
+        assert_eq!(summary.spot_check_line("f(t)", "exact(t)", 1.5, 1.0, 3), None);
+        assert_eq!(
+            summary.spot_check_line("f(t)", "exact(t)", 1.5, 1.0, 7).unwrap(),
+            "spot check solver: index 7 f(t) = 1.5e0 vs exact(t) = 1e0 diff 5e-1 failed"
+        );
+        assert!(summary.spot_check_line("x", "y", -1.0, 1.0, 14).unwrap().ends_with("diff 2e0 sign diff failed"));
+        let default = DiffSummary::new("", 0.25, false, 4, &diff::diff_abs);
+        let printed = (0..10000).filter(|&i| default.spot_check_line("x", "y", 1.0, 1.0, i).is_some()).count();
+        assert!(50 < printed && printed < 150, "{}", printed);
+    }
+
+    #[test]
+    fn test_float_format() {
+        let mut summary = DiffSummary::new("hex", 0.0, false, 4, &diff::diff_abs)
+            .with_float_format(FloatFormat::Hex);
+        summary.add(-0.0, 0.5, 0);
+        let shown = format!("{}", summary);
+        assert!(shown.contains("-0x0p+0 vs 0x1p-1"), "{}", shown);
+    }
+
+    #[test]
+    fn test_display_options() {
+        let mut summary = DiffSummary::new("opts", 0.1, false, 4, &diff::diff_abs);
+        summary.add(1.0, 1.123456, 7);
+        summary.add(-1.0, 1.0, 8);
+        assert_eq!(
+            format!("{}", summary.display_with(&DisplayOptions::terse())),
+            "opts: count 2, worst diff 2e0, 100% failed tolerance 1e-1, sign diffs 50%"
+        );
+        let options = DisplayOptions::new().with_significant_digits(3).with_histogram(false).with_quantiles(false);
+        assert_eq!(
+            format!("{}", summary.display_with(&options)),
+            "opts: count 2, worst index 8 -1.00e0 vs 1.00e0 diff 2.00e0, 100% failed tolerance 1.00e-1 \
+                first index 7 1.00e0 vs 1.12e0 diff 1.23e-1, sign diffs 50% first index 8 -1.00e0 vs 1.00e0"
+        );
+        let summary = summary.with_display_options(DisplayOptions::terse().with_layout(Layout::MultiLine));
+        assert_eq!(
+            format!("{}", summary),
+            "opts: count 2\n  status failed\n  nonfinite lenient\n  worst diff 2e0\n  100% failed tolerance 1e-1\n  \
+                mean 1.061728e0 rms 1.416905322160941e0\n  over 0% under 100%\n  sign diffs 50% disallowed"
+        );
+    }
+
+    #[test]
+    fn test_counts() {
+        let mut summary = DiffSummary::new("counts", 0.1, false, 4, &diff::diff_abs);
+        summary.add_slices(&[1.0; 300], &[1.0; 300]);
+        summary.add(1.0, 1.5, 300);
+        summary.add(-1.0, -1.0, 301);
+        let options = DisplayOptions::terse().with_counts(true).with_histogram(true);
+        assert_eq!(
+            format!("{}", summary.display_with(&options)),
+            "counts: count 302, identical 301 (99%), worst diff 5e-1, 1 (1%) failed tolerance 1e-1, \
+                zero 301 (99%), e0 1 (1%), sign diffs 0 (0%)"
+        );
+        let fractional = DisplayOptions::terse().with_percent_format(PercentFormat::Fractional(2));
+        assert_eq!(
+            format!("{}", summary.display_with(&fractional)),
+            "counts: count 302, identical 99.67%, worst diff 5e-1, 0.33% failed tolerance 1e-1, sign diffs 0%"
+        );
+        let mut exact = DiffSummary::new("exact", 0.1, false, 4, &diff::diff_abs);
+        exact.add(1.0, 1.0, 0);
+        assert_eq!(
+            format!("{}", exact.display_with(&options)),
+            "exact: count 1, identical 1 (100%), zero 1 (100%), 0 (0%) failed tolerance 1e-1, sign diffs 0 (0%)"
+        );
+    }
+
+    #[test]
+    fn test_bucket_samples() {
+        let mut summary = DiffSummary::new("buckets", 1.0, false, 4, &diff::diff_abs).with_bucket_samples();
+        summary.add(1.0, 1.0, 0);
+        summary.add(1.0, 1.03125, 1);
+        summary.add(1.0, 1.0625, 2);
+        summary.add(1.0, 1.125, 3);
+        summary.add(1.0, 4.0, 4);
+        let mut other = DiffSummary::new("buckets", 1.0, false, 4, &diff::diff_abs).with_bucket_samples();
+        other.add(1.0, 1.0625, 5);
+        other.add(1.0, 1.0, 6);
+        summary.merge(&other);
+        assert_eq!(
+            summary.bucket_samples(),
+            vec![
+                (BucketKey::Zero, (0, 1.0, 1.0, 0.0)),
+                (BucketKey::Pos(-1), (2, 1.0, 1.0625, 0.0625)),
+                (BucketKey::Pos(0), (4, 1.0, 4.0, 3.0)),
+            ]
+        );
+        let options = DisplayOptions::terse().with_layout(Layout::Verbose).with_samples(true);
+        let shown = format!("{}", summary.display_with(&options));
+        assert!(
+            shown.contains(", bucket worst zero index 0 1e0 vs 1e0, e-1 index 2 1e0 vs 1.0625e0, e0 index 4 1e0 vs 4e0, "),
+            "{}",
+            shown
+        );
+        assert!(!format!("{}", summary).contains("bucket worst"));
+        assert!(DiffSummary::new("off", 1.0, false, 4, &diff::diff_abs).bucket_samples().is_empty());
+    }
+
+    #[test]
+    fn test_csv_tee() {
+        use std::cell::RefCell;
+        use std::io::{self, Write};
+        use std::rc::Rc;
+
+        #[derive(Clone, Default)]
+        struct Shared(Rc<RefCell<Vec<u8>>>);
+        impl Write for Shared {
+            fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+                self.0.borrow_mut().write(buf)
+            }
+            fn flush(&mut self) -> io::Result<()> {
+                Ok(())
+            }
+        }
+
+        let out = Shared::default();
+        let filter = |index: usize, _: f64, _: f64| index != 2;
+        let mut summary = DiffSummary::new("tee", 0.1, false, 4, &diff::diff_abs).with_csv_tee(out.clone()).with_filter(&filter);
+        summary.add(1.0, 1.0, 0);
+        summary.add_slices(&[1.5, -1.0, 2.0], &[1.0, 1.0, 2.0]);
+        assert!(out.0.borrow().is_empty());
+        summary.flush_csv_tee().unwrap();
+        assert_eq!(
+            String::from_utf8(out.0.borrow().clone()).unwrap(),
+            "index,x,y,diff,sign_change\n0,1e0,1e0,0e0,false\n0,1.5e0,1e0,5e-1,false\n1,-1e0,1e0,2e0,true\n"
+        );
+        assert!(DiffSummary::new("off", 0.1, false, 4, &diff::diff_abs).flush_csv_tee().is_ok());
+    }
+
+    #[test]
+    fn test_spill() {
+        let dir = std::env::temp_dir().join(format!("float-diff-summary-spill-{}", std::process::id()));
+        let filter = |index: usize, _: f64, _: f64| index != 2;
+        let mut summary = DiffSummary::new("spill", 0.1, false, 4, &diff::diff_abs).with_spill(&dir, 3).with_filter(&filter);
+        summary.add_slices(&[1.0, 2.0, 3.0, 4.0, 5.0], &[1.0, 2.5, 3.0, 4.0, f64::NAN]);
+        let items: Vec<(usize, f64, f64)> = summary.spilled().unwrap().map(Result::unwrap).collect();
+        assert_eq!(items.len(), 4);
+        assert_eq!(items[1], (1, 2.0, 2.5));
+        assert!(items[3].2.is_nan());
+        let rel = summary.rescore(&diff::diff_rel, 0.25).unwrap();
+        assert_eq!((rel.name, rel.count(), rel.fail_count()), ("spill", 4, 1));
+
+        // Merging copies the other summary's items into this one's store, so
+        // that rescore covers both, whether the other spills or retains them.
+        let mut spilled = DiffSummary::new("spill", 0.1, false, 4, &diff::diff_abs).with_spill(dir.join("other"), 2);
+        spilled.add_slices(&[10.0, 20.0, 30.0], &[10.0, 20.0, 33.0]);
+        let mut retained = DiffSummary::new("spill", 0.1, false, 4, &diff::diff_abs).with_raw_retention();
+        retained.add(7.0, 7.5, 8);
+        let shared = summary.clone();
+        summary.merge(&spilled);
+        summary.merge(&retained);
+        summary.merge(&DiffSummary::new("empty", 0.1, false, 4, &diff::diff_abs));
+        assert_eq!(summary.spilled().unwrap().count(), 8);
+        let merged_rel = summary.rescore(&diff::diff_rel, 0.25).unwrap();
+        assert_eq!((merged_rel.count(), merged_rel.fail_count()), (8, 1));
+        assert_eq!(shared.spilled().unwrap().count(), 8);
+        retained.merge(&spilled);
+        assert_eq!(retained.retained_items().unwrap().len(), 4);
+        let retained_rel = retained.rescore(&diff::diff_rel, 0.05).unwrap();
+        assert_eq!((retained_rel.count(), retained_rel.fail_count()), (4, 2));
+        let mut unretained = DiffSummary::new("plain", 0.1, false, 4, &diff::diff_abs);
+        unretained.add(1.0, 1.0, 0);
+        assert!(std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| retained.merge(&unretained))).is_err());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_rescore() {
+        let mut summary = DiffSummary::new("rescore", 0.1, false, 4, &diff::diff_abs).with_raw_retention();
+        let mut other = summary.clone();
+        summary.add_slices(&[100.5, 1.0, 0.0], &[100.0, 1.05, 0.0]);
+        other.add(-2.0, 2.0, 3);
+        summary.merge(&other);
+        assert_eq!(summary.retained_items().unwrap().len(), 4);
+        assert_eq!(summary.fail_count(), 2);
+        let rel = summary.rescore(&diff::diff_rel, 0.01).unwrap();
+        assert_eq!((rel.count(), rel.fail_count(), rel.sign_count()), (4, 2, 1));
+        assert_eq!(rel.first_failure().unwrap().index, 1);
+        assert!(DiffSummary::new("off", 0.1, false, 4, &diff::diff_abs).retained_items().is_none());
+    }
+
+    #[test]
+    fn test_worst_metric() {
+        let mut by_diff = DiffSummary::new("diff", 1e-3, false, 4, &diff::diff_abs);
+        let mut by_ulps = DiffSummary::new("ulps", 1e-3, false, 4, &diff::diff_abs).with_worst_metric(WorstMetric::Ulps);
+        let mut by_ratio = DiffSummary::new("ratio", 1e-3, false, 4, &diff::diff_abs).with_worst_metric(WorstMetric::ToleranceRatio);
+        for summary in [&mut by_diff, &mut by_ulps, &mut by_ratio] {
+            summary.add(1e6, 1e6 + 1e-4, 0);
+            summary.add(1e-9, 2e-9, 1);
+            summary.add(1.0, 1.0, 2);
+        }
+        assert_eq!(by_diff.worst_sample().unwrap().index, 0);
+        assert_eq!(by_diff.worst_score(), by_diff.worst_diff());
+        assert_eq!(by_ratio.worst_sample().unwrap().index, 0);
+        assert_eq!(by_ratio.worst_score(), by_ratio.worst_diff() / 1e-3);
+        let (index, _, _, worst_diff) = by_ulps.worst_sample().unwrap().as_tuple();
+        assert_eq!((index, worst_diff), (1, 1e-9));
+        assert_eq!(by_ulps.worst_score(), diff::diff_ulps(1e-9, 2e-9).0);
+        assert_eq!(by_ulps.worst_diff(), by_diff.worst_diff());
+        assert!(by_ulps.is_ok());
+        assert!(format!("{}", by_ulps.display_with(&DisplayOptions::terse().with_samples(true)))
+            .contains("worst ulps 4503599627370496 index 1 1e-9 vs 2e-9 diff 1e-9, worst diff 9.999994654208422e-5, "));
+        assert!(format!("{}", by_ulps.display_with(&DisplayOptions::terse()))
+            .contains("worst diff 9.999994654208422e-5, worst ulps 4503599627370496, "));
+
+        let mut merged = DiffSummary::new("ulps", 1e-3, false, 4, &diff::diff_abs).with_worst_metric(WorstMetric::Ulps);
+        merged.add(1.0, 1.0 + f64::EPSILON, 5);
+        merged.merge(&by_ulps);
+        assert_eq!(merged.worst_sample().unwrap().index, 1);
+        let empty = DiffSummary::new("empty", 1e-3, false, 4, &diff::diff_abs).with_worst_metric(WorstMetric::ToleranceRatio);
+        assert_eq!((empty.worst_score(), empty.worst_sample()), (0.0, None));
+    }
+
+    #[test]
+    fn test_status() {
+        let mut summary = DiffSummary::new("status", 0.5, false, 4, &diff::diff_abs)
+            .with_warn_diff(0.25)
+            .with_sign_limit(SignLimit::Count(1));
+        summary.add(1.0, 1.0, 0);
+        assert_eq!(summary.status(), SummaryStatus { count: 1, failures: vec![], warnings: vec![] });
+        assert_eq!(summary.status().to_string(), "ok");
+        summary.add(1.0, 1.375, 1);
+        summary.add(-1e-3, 1e-3, 2);
+        let status = summary.status();
+        assert!(status.is_ok() && summary.is_ok());
+        assert_eq!(
+            status.to_string(),
+            "ok, with warnings: sign: 1 sign diffs limit 1; warn: 1 warned above 2.5e-1"
+        );
+        summary.add(f64::NAN, 1.0, 3);
+        summary.add(1.0, 3.0, 4);
+        summary.add(-2.0, 2.0, 5);
+        let status = summary.status();
+        assert!(!status.is_ok() && !summary.is_ok());
+        assert!(status.failed_tolerance() && status.failed_nan() && status.failed_sign());
+        match status.failures[0] {
+            StatusReason::Tolerance { fail_count, worst_diff, allow_diff } => {
+                assert!(fail_count == 3 && worst_diff.is_nan() && allow_diff == 0.5)
+            }
+            reason => panic!("unexpected {:?}", reason),
+        }
+        assert_eq!(
+            status.to_string(),
+            "failed: tolerance: 3 failed, worst diff NaN vs allowed 5e-1; nan: 1 nan diffs; sign: 2 sign diffs limit 1, \
+                with warnings: warn: 1 warned above 2.5e-1"
+        );
+    }
+
+    #[test]
+    fn test_color() {
+        let mut summary = DiffSummary::new("color", 1.0, false, 4, &diff::diff_abs);
+        summary.add(1.0, 1.75, 0);
+        // Forced, so that the result doesn't depend on NO_COLOR.
+        let options = DisplayOptions::terse().with_color(true).with_force_color(true);
+        let shown = format!("{}", summary.display_with(&options));
+        assert!(shown.starts_with("\x1b[33mcolor\x1b[0m"), "{}", shown);
+        assert!(shown.contains("\x1b[32msign diffs 0%\x1b[0m"), "{}", shown);
+        summary.add(-1.0, 1.0, 1);
+        let shown = format!("{}", summary.display_with(&DisplayOptions::terse()));
+        assert!(!shown.contains('\x1b'));
+    }
+
+    #[test]
+    fn test_first_failure() {
+        let mut summary = DiffSummary::new("first", 0.5, true, 4, &diff::diff_abs);
+        summary.add(1.0, 1.25, 0);
+        assert_eq!(summary.first_failure(), None);
+        summary.add(1.0, 2.0, 1);
+        summary.add(1.0, 4.0, 2);
+        assert_eq!(summary.first_failure().map(SampleRecord::as_tuple), Some((1, 1.0, 2.0, 1.0)));
+        assert_eq!(summary.worst_sample().map(SampleRecord::as_tuple), Some((2, 1.0, 4.0, 3.0)));
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| summary.assert()));
+        let message = *result.unwrap_err().downcast::<String>().unwrap();
+        assert!(message.contains("assert failed item 2, first: 1e0 vs 4e0"), "{}", message);
+        assert!(message.contains("first failed item 1: 1e0 vs 2e0 diff abs 1e0"), "{}", message);
+
+        // Tolerances that no diff could be within are rejected up front.
+        for allow_diff in [-1.0, f64::NAN] {
+            let result = std::panic::catch_unwind(|| DiffSummary::new("bad", allow_diff, false, 4, &diff::diff_abs));
+            let message = *result.err().unwrap().downcast::<String>().unwrap();
+            assert!(message.starts_with("summary bad tolerance must be non-negative, got "), "{}", message);
+        }
+    }
+
+    #[test]
+    fn test_sample_context() {
+        let start = std::time::SystemTime::now();
+        let mut summary = DiffSummary::new("fuzz", 0.5, false, 4, &diff::diff_abs).with_top_n(2);
+        summary.add(1.0, 2.0, 0);
+        summary.set_sample_context(Some("seed 7"));
+        summary.add(1.0, -4.0, 1);
+        summary.set_sample_context(None);
+        summary.add(1.0, 1.25, 2);
+        let first = summary.first_failure().unwrap();
+        assert_eq!((first.index, first.context.as_deref()), (0, None));
+        let worst = summary.worst_sample().unwrap();
+        assert_eq!((worst.index, worst.diff, worst.context.as_deref()), (1, 5.0, Some("seed 7")));
+        assert!(worst.time >= start);
+        assert_eq!(summary.first_sign_change().map(|sample| sample.index), Some(1));
+        assert_eq!(summary.top_n().iter().map(|sample| sample.index).collect::<Vec<_>>(), vec![1, 0]);
+    }
+
+    #[test]
+    fn test_failures() {
+        let mut summary = DiffSummary::new("failures", 0.5, false, 4, &diff::diff_abs)
+            .with_failure_capacity(2);
+        for (i, y) in [1.0, 2.0, 1.25, -3.0, 5.0].iter().enumerate() {
+            summary.add(1.0, *y, i);
+        }
+        assert_eq!(summary.failures(), &[(1, 1.0, 2.0, 1.0), (3, 1.0, -3.0, 4.0)]);
+        let mut csv = Vec::new();
+        summary.write_failures_csv(&mut csv).unwrap();
+        assert_eq!(String::from_utf8(csv).unwrap(), "index,x,y,diff\n1,1e0,2e0,1e0\n3,1e0,-3e0,4e0\n");
+    }
+
+    #[test]
+    fn test_sign_worst() {
+        let data = [(0.0, -0.0), (-5.0, 4.0), (-100.0, 100.5), (3.0, -3.0)];
+        let mut by_magnitude = DiffSummary::new("magnitude", 1.0, true, 4, &diff::diff_rel)
+            .with_sign_worst(SignWorstMetric::Magnitude);
+        let mut by_diff = DiffSummary::new("diff", 1.0, true, 4, &diff::diff_abs)
+            .with_sign_worst(SignWorstMetric::Diff);
+        let mut untracked = DiffSummary::new("untracked", 1.0, true, 4, &diff::diff_abs);
+        for (i, item) in data.iter().enumerate() {
+            by_magnitude.add(item.0, item.1, i);
+            by_diff.add(item.0, item.1, i);
+            untracked.add(item.0, item.1, i);
+        }
+        assert_eq!(by_magnitude.first_sign_change().map(|sample| (sample.index, sample.x, sample.y)), Some((0, 0.0, -0.0)));
+        assert_eq!(by_magnitude.worst_sign_change().map(|sample| (sample.index, sample.x, sample.y)), Some((2, -100.0, 100.5)));
+        assert_eq!(by_diff.worst_sign_change().map(|sample| (sample.index, sample.x, sample.y)), Some((2, -100.0, 100.5)));
+        assert!(untracked.worst_sign_change().is_none());
+        assert!(format!("{}", by_diff).contains("first index 0 0e0 vs -0e0 worst index 2 -1e2 vs 1.005e2"));
+    }
+
+    #[test]
+    fn test_negligible_diff() {
+        let mut summary = DiffSummary::new("negligible", 1.0, false, 4, &diff::diff_abs)
+            .with_negligible_diff(1e-100);
+        summary.add(1e-300, 0.0, 0);
+        summary.add(20.0, 0.0, 1);
+        assert!(format!("{}", summary).contains("near-zero 50%, e1 50%, p50 "));
+    }
+
+    #[test]
+    fn test_merge() {
+        let data = [(1.0, 1.5), (2.0, -2.0), (0.0, 0.0), (3.0, 7.0), (-1.0, 1.0), (5.0, 5.25)];
+        let mut whole = DiffSummary::new("whole", 1.0, false, 4, &diff::diff_abs)
+            .with_failure_capacity(10);
+        let mut first = whole.clone();
+        let mut second = whole.clone();
+        for (i, item) in data.iter().enumerate() {
+            whole.add(item.0, item.1, i);
+            if i < 3 { &mut first } else { &mut second }.add(item.0, item.1, i);
+        }
+        // Merge in reverse order, to check that "first" items are by index.
+        second.merge(&first);
+        assert_eq!(format!("{}", second), format!("{}", whole));
+        assert_eq!(second.failures(), whole.failures());
+        assert_eq!(second.first_failure().map(SampleRecord::as_tuple), Some((1, 2.0, -2.0, 4.0)));
+        assert_eq!(second.worst_sample().map(SampleRecord::as_tuple), Some((1, 2.0, -2.0, 4.0)));
+    }
+
+    #[test]
+    fn test_mean_rms() {
+        let mut summary = DiffSummary::new("mean", 1.0, false, 4, &diff::diff_abs);
+        assert_eq!(summary.mean_diff(), 0.0);
+        summary.add(1.0, 2.0, 0);
+        summary.add(1.0, 4.0, 1);
+        summary.add(1.0, f64::INFINITY, 2);
+        assert_eq!(summary.count(), 3);
+        assert_eq!(summary.mean_diff(), 2.0);
+        assert_eq!(summary.rms_diff(), 5f64.sqrt());
+    }
+
+    #[test]
+    fn test_outliers() {
+        let mut summary = DiffSummary::new("outliers", 1.0, false, 4, &diff::diff_abs).with_top_n(3);
+        for i in 0..1000 {
+            let y = match i {
+                123 => 0.5,
+                456 => 3.0,
+                _ => 1.0 + 1e-6 * (1 + i % 10) as f64,
+            };
+            summary.add(1.0, y, i);
+        }
+        assert_eq!(summary.top_n().len(), 3);
+        assert_eq!(summary.top_n()[0].index, 456);
+        let report = summary.outliers(3.0);
+        let indices: Vec<usize> = report.items.iter().map(|item| item.index).collect();
+        assert_eq!(indices, vec![456, 123]);
+        assert!(!report.truncated);
+        assert!(format!("{}", report).starts_with("2 outliers above "));
+        assert!(DiffSummary::new("none", 1.0, false, 4, &diff::diff_abs).outliers(1.5).items.is_empty());
+    }
+
+    #[test]
+    fn test_linear_fit() {
+        let mut summary = DiffSummary::new("fit", 1e-3, false, 4, &diff::diff_rel)
+            .with_linear_fit(1e-6)
+            .with_display_options(DisplayOptions::terse().with_significant_digits(6));
+        for i in 1..=100 {
+            let y = i as f64;
+            summary.add(y * 1.0001, y, i);
+        }
+        assert!(summary.is_ok());
+        assert!(!summary.is_slope_ok());
+        let fit = summary.linear_fit().unwrap();
+        assert!((fit.slope() - 1.0001).abs() < 1e-12);
+        assert!(format!("{}", summary).contains(", slope 1.00010e0 flagged intercept "));
+        assert!(format!("{}", summary).contains(" corr 1.00000e0"));
+    }
+
+    #[test]
+    fn test_mixed_types() {
+        let mut summary = DiffSummary::new("mixed", 0.5, false, 4, &diff::diff_abs);
+        summary.add(1.5f32, 1.0, 0);
+        summary.add(10i64, 10.25, 1);
+        summary.add(7u32, 8i16, 2);
+        assert_eq!(summary.count(), 3);
+        assert_eq!(summary.fail_count(), 1);
+        assert_eq!(summary.worst_diff(), 1.0);
+        assert_eq!(summary.worst_sample().map(SampleRecord::as_tuple), Some((2, 7.0, 8.0, 1.0)));
+    }
+
+    #[test]
+    fn test_interval() {
+        let mut summary = DiffSummary::new("interval", 0.1, false, 4, &diff::diff_abs);
+        summary.add_interval(1.5, 1.0, 2.0, 0);
+        summary.add_interval(2.0, 2.0, 2.0, 1);
+        assert_eq!(summary.worst_sample(), None);
+        summary.add_interval(2.25, 1.0, 2.0, 2);
+        summary.add_interval(0.5, 1.0, 2.0, 3);
+        assert_eq!(summary.worst_sample().map(SampleRecord::as_tuple), Some((3, 0.5, 1.0, 0.5)));
+        summary.add_interval(-0.5, 0.0, 1.0, 4);
+        assert_eq!(summary.sign_count(), 1);
+        summary.add_interval(f64::NAN, 0.0, 1.0, 5);
+        assert!(summary.worst_diff().is_nan());
+        assert_eq!(summary.count(), 6);
+
+        let mut unbounded = DiffSummary::new("interval", 0.1, false, 4, &diff::diff_abs);
+        unbounded.add_interval(1.0, f64::NAN, 2.0, 0);
+        unbounded.add_interval(1.0, 0.0, f64::NAN, 1);
+        unbounded.add_interval(f64::NAN, f64::NAN, f64::NAN, 2);
+        assert_eq!((unbounded.count(), unbounded.fail_count()), (3, 3));
+        assert!(unbounded.worst_diff().is_nan());
+    }
+
+    #[test]
+    fn test_angles() {
+        use crate::diff::AngleUnit;
+        let mut summary = DiffSummary::new("attitude", 1.0, false, 4, &diff::diff_abs);
+        summary.add_vec3(&[1.0, 0.0, 0.0], &[1.0, 0.0, 0.0], AngleUnit::Degrees, 0);
+        summary.add_vec3(&[1.0, 0.0, 0.0], &[0.0, 1.0, 0.0], AngleUnit::Degrees, 1);
+        summary.add_quat(&[1.0, 0.0, 0.0, 0.0], &[-1.0, 0.0, 0.0, 0.0], AngleUnit::Radians, 2);
+        assert_eq!(summary.count(), 3);
+        assert_eq!(summary.worst_sample().map(SampleRecord::as_tuple), Some((1, 90.0, 0.0, 90.0)));
+        assert_eq!(summary.fail_count(), 1);
+    }
+
+    #[test]
+    fn test_identical() {
+        let mut summary = DiffSummary::new("same", 0.1, true, 4, &diff::diff_abs);
+        summary.add(1.5, 1.5, 0);
+        summary.add(f64::NAN, f64::NAN, 1);
+        summary.add(0.0, -0.0, 2);
+        summary.add(1.0, 1.05, 3);
+        assert_eq!(summary.identical_count(), 2);
+        assert_eq!(summary.identical_percent(), 50.0);
+        assert!(format!("{}", summary.display_with(&DisplayOptions::terse()))
+            .starts_with("same: count 4, identical 50%, worst diff "));
+    }
+
+    #[test]
+    fn test_subnormal() {
+        let tiny = f64::MIN_POSITIVE / 4.0;
+        let mut summary = DiffSummary::new("sub", 1e-3, false, 4, &diff::diff_abs)
+            .with_subnormal_policy(SubnormalPolicy::Count);
+        summary.add(1.0, 1.0, 0);
+        summary.add(tiny, 0.0, 1);
+        summary.add(0.0, tiny, 2);
+        summary.add(f64::MIN_POSITIVE * 1.5, f64::MIN_POSITIVE, 3);
+        assert_eq!(summary.subnormal_counts(), Some((1, 1, 1)));
+        assert!(summary.is_ok());
+        assert!(format!("{}", summary.display_with(&DisplayOptions::terse()))
+            .ends_with(", subnormal x 1 y 1 cancellation 1"));
+        let summary = summary.with_subnormal_policy(SubnormalPolicy::Fail);
+        assert!(!summary.is_ok());
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| summary.assert()));
+        assert!(result.is_err());
+        let untracked = DiffSummary::new("sub", 1e-3, false, 4, &diff::diff_abs);
+        assert_eq!(untracked.subnormal_counts(), None);
+    }
+
+    #[test]
+    fn test_total_order() {
+        let mut summary = DiffSummary::new_total_order("sorted", true, 4);
+        summary.add(1.0, 1.0, 0);
+        summary.add(0.0, -0.0, 1);
+        summary.add(f64::NAN, f64::NAN, 2);
+        summary.add(2.0, 3.0, 3);
+        assert_eq!(summary.fail_count(), 2);
+        assert_eq!(summary.first_failure().map(SampleRecord::as_tuple), Some((1, 0.0, -0.0, 1.0)));
+        assert_eq!(summary.worst_sample().unwrap().index, 3);
+        assert!(!summary.is_ok());
+    }
+
+    #[test]
+    fn test_quantization() {
+        let digits = Quantization::SignificantDigits(3);
+        assert_eq!(digits.apply(1.23456), 1.23);
+        assert_eq!(digits.apply(-98765.0), -98800.0);
+        assert!(digits.apply(f64::NAN).is_nan());
+        let ulps = Quantization::Ulps(4);
+        let one = 1.0f64.to_bits();
+        assert_eq!(ulps.apply(f64::from_bits(one + 1)), 1.0);
+        assert_eq!(ulps.apply(f64::from_bits(one + 3)), f64::from_bits(one + 4));
+        assert_eq!(ulps.apply(-f64::from_bits(one + 2)), -f64::from_bits(one + 4));
+        assert_eq!(Quantization::Ulps(1 << 60).apply(f64::MAX), f64::INFINITY);
+
+        let mut summary = DiffSummary::new("printed", 0.0, false, 4, &diff::diff_abs)
+            .with_quantization(Quantization::SignificantDigits(9));
+        summary.add(1.0 / 3.0, 0.333333333, 0);
+        assert!(summary.is_ok());
+        summary.add(0.1234567, 0.123456789, 1);
+        assert_eq!(summary.worst_sample().map(|sample| (sample.index, sample.x)), Some((1, 0.1234567)));
+    }
+
+    #[test]
+    fn test_metadata() {
+        let mut summary = DiffSummary::new("fuzz", 0.1, false, 4, &diff::diff_abs)
+            .with_metadata("seed", 42)
+            .with_metadata("commit", "abc1234")
+            .with_metadata("seed", 43);
+        summary.add(1.0, 1.0, 0);
+        assert_eq!(summary.metadata(), &[("seed".to_string(), "43".to_string()), ("commit".to_string(), "abc1234".to_string())]);
+        assert!(format!("{}", summary.display_with(&DisplayOptions::terse()))
+            .starts_with("fuzz [seed=43, commit=abc1234]: count 1, identical 100%"));
+        let other = DiffSummary::new("fuzz", 0.1, false, 4, &diff::diff_abs).with_metadata("dataset", "grid");
+        summary.merge(&other);
+        assert_eq!(summary.metadata().len(), 3);
+    }
+
+    #[test]
+    fn test_assert_context() {
+        let mut summary = DiffSummary::new("ctx", 0.1, false, 4, &diff::diff_abs).with_assert_context("phase 2");
+        summary.add(1.0, 1.5, 0);
+        let message = |result: std::thread::Result<()>| match result {
+            Err(payload) => payload.downcast_ref::<String>().cloned().unwrap(),
+            Ok(()) => String::new(),
+        };
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| summary.assert_with("refine")));
+        let shown = message(result);
+        assert!(shown.starts_with("phase 2: refine: assert failed item 0, ctx: "), "{}", shown);
+        assert!(shown.ends_with(&format!("\n{}", summary)), "{}", shown);
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| summary.assert()));
+        assert!(message(result).starts_with("phase 2: assert failed"));
+    }
+
+    #[test]
+    fn test_assert_all() {
+        let mut summaries = vec![
+            DiffSummary::new("a", 0.1, false, 4, &diff::diff_abs),
+            DiffSummary::new("b", 0.1, false, 4, &diff::diff_abs),
+            DiffSummary::new("c", 0.1, false, 4, &diff::diff_abs),
+        ];
+        summaries[0].add(1.0, 1.5, 0);
+        summaries[1].add(1.0, 1.0, 0);
+        summaries[2].add(-1.0, 1.0, 0);
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| DiffSummary::assert_all(&summaries)));
+        let shown = result.unwrap_err().downcast_ref::<String>().cloned().unwrap();
+        assert_eq!(shown, format!("assert failed for 2 of 3 summaries:\n{}\n{}", summaries[0], summaries[2]));
+        DiffSummary::assert_all(&summaries[1..2]);
+    }
+
+    #[test]
+    fn test_evaluate() {
+        let mut summary = DiffSummary::new("peek", 0.1, false, 4, &diff::diff_abs);
+        summary.add(1.0, 1.25, 0);
+        let outcome = summary.evaluate(2.0, 2.5);
+        assert_eq!(outcome.diff, 0.5);
+        assert!(!outcome.within_tolerance && outcome.sign_ok && outcome.is_worst);
+        assert!(!outcome.is_ok());
+        assert_eq!(outcome.bucket, BucketKey::Pos(0));
+        let outcome = summary.evaluate(-1.0, 1.0);
+        assert!(outcome.sign_change && !outcome.sign_ok);
+        let outcome = summary.evaluate(3.0, 3.0);
+        assert!(outcome.is_ok() && !outcome.is_worst);
+        assert_eq!(outcome.bucket, BucketKey::Zero);
+        assert_eq!(summary.count(), 1);
+    }
+
+    #[test]
+    fn test_stage() {
+        let mut summary = DiffSummary::new("staged", 0.1, false, 4, &diff::diff_abs);
+        summary.stage(1.0, 1.05, 0);
+        assert_eq!(summary.count(), 0);
+        summary.stage(1.0, 3.0, 1);
+        assert_eq!(summary.count(), 1);
+        // The reference for item 1 turned out to be unreliable.
+        assert_eq!(summary.discard(), Some((1, 1.0, 3.0)));
+        assert_eq!(summary.staged(), None);
+        summary.stage(2.0, 2.25, 2);
+        summary.commit();
+        summary.commit();
+        assert_eq!(summary.count(), 2);
+        assert_eq!(summary.worst_sample().map(SampleRecord::as_tuple), Some((2, 2.0, 2.25, 0.25)));
+    }
+
+    #[test]
+    fn test_filter() {
+        let skip = [3];
+        let filter = |index: usize, _x: f64, y: f64| !y.is_nan() && !skip.contains(&index);
+        let mut summary = DiffSummary::new("filtered", 0.1, false, 4, &diff::diff_abs).with_filter(&filter);
+        summary.add(1.0, 1.0, 0);
+        summary.add(1.0, f64::NAN, 1);
+        summary.add(2.0, 2.05, 2);
+        summary.add(5.0, 9.0, 3);
+        assert_eq!(summary.count(), 2);
+        assert_eq!(summary.skipped_count(), 2);
+        assert!(summary.is_ok());
+        assert!(format!("{}", summary).starts_with("filtered: count 2, skipped 2, "));
+    }
+
+    #[test]
+    fn test_views() {
+        let mut summary = DiffSummary::new("views", 1e-3, false, 4, &diff::diff_abs)
+            .with_view("ulps", &diff::diff_ulps)
+            .with_view("rel", &diff::diff_rel);
+        summary.add(1.0, 1.0, 0);
+        summary.add(1e6, 1e6 + 1e-9, 1);
+        let ulps = summary.view_histogram("ulps").unwrap();
+        assert_eq!(ulps.count_total(), 2);
+        assert_eq!(ulps.count_zero(), 1);
+        assert!(summary.view_histogram("abs").is_none());
+        let shown = format!("{}", summary);
+        assert!(shown.contains(", ulps "), "{}", shown);
+        assert!(shown.contains(", rel "), "{}", shown);
+        let mut doubled = summary.clone();
+        doubled.merge(&summary);
+        assert_eq!(doubled.view_histogram("rel").unwrap().count_total(), 4);
+    }
+
+    #[test]
+    fn test_warn_diff() {
+        let mut summary = DiffSummary::new("warn", 1.0, false, 4, &diff::diff_abs).with_warn_diff(0.25);
+        summary.add(1.0, 1.125, 0);
+        summary.add(1.0, 1.5, 1);
+        summary.add(1.0, 3.0, 2);
+        summary.add(1.0, 1.75, 3);
+        assert_eq!(summary.warn_count(), 2);
+        assert_eq!(summary.warn_percent(), 50.0);
+        assert_eq!(summary.fail_count(), 1);
+        assert!(format!("{}", summary.display_with(&DisplayOptions::terse()))
+            .contains(", 25% failed tolerance 1e0, 50% warned above 2.5e-1, "));
+        let mut merged = DiffSummary::new("warn", 1.0, false, 4, &diff::diff_abs).with_warn_diff(0.25);
+        merged.merge(&summary);
+        assert_eq!(merged.warn_count(), 2);
+    }
+
+    #[test]
+    fn test_sign_limit() {
+        let mut summary = DiffSummary::new("limit", 1.0, false, 4, &diff::diff_abs).with_sign_limit(SignLimit::Count(1));
+        summary.add(1e-9, -1e-9, 0);
+        summary.add(1.0, 1.0, 1);
+        assert!(summary.is_ok());
+        assert!(!summary.evaluate(-0.5, 0.5).sign_ok);
+        summary.add(-0.5, 0.5, 2);
+        assert!(!summary.is_ok());
+        let options = DisplayOptions::terse().with_layout(Layout::Verbose);
+        assert!(format!("{}", summary.display_with(&options)).ends_with("sign diffs 67% limit 1"));
+
+        let mut summary = DiffSummary::new("fraction", 1.0, false, 4, &diff::diff_abs)
+            .with_sign_limit(SignLimit::Fraction(0.25));
+        for i in 0..4 {
+            summary.add(1.0, 1.0, i);
+        }
+        summary.add(-1e-9, 1e-9, 4);
+        assert!(summary.is_ok());
+        summary.add(-1e-9, 1e-9, 5);
+        assert!(!summary.is_ok());
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| summary.assert()));
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_sign_epsilon() {
+        let mut summary = DiffSummary::new("eps", 1.0, false, 4, &diff::diff_abs).with_sign_epsilon(1e-12);
+        summary.add(-1e-300, 1e-300, 0);
+        summary.add(0.0, -0.0, 1);
+        assert_eq!(summary.sign_count(), 0);
+        assert_eq!(summary.sign_exempt_count(), 2);
+        assert!(summary.is_ok());
+        assert!(summary.evaluate(-1e-13, 1e-13).sign_ok);
+        summary.add(-1e-13, 1.0, 2);
+        assert_eq!(summary.sign_count(), 1);
+        assert!(!summary.is_ok());
+        assert!(format!("{}", summary.display_with(&DisplayOptions::terse())).ends_with("sign diffs 33% exempt 2"));
+    }
+
+    #[test]
+    fn test_error_model() {
+        let mut summary = DiffSummary::new("libm", 1e-15, false, 4, &diff::diff_abs).with_error_model();
+        let mut other = summary.clone();
+        summary.add(1.0 + f64::EPSILON, 1.0, 0);
+        summary.add(3.0, 3.0, 1);
+        other.add(0.5 - f64::EPSILON / 4.0, 0.5, 2);
+        summary.merge(&other);
+        let model = summary.error_model().unwrap();
+        assert_eq!((model.count(), model.max_ulps()), (3, 1.0));
+        assert!(summary.to_string().find("errors consistent").is_none());
+        let verbose = summary.display_with(&DisplayOptions::new().with_layout(Layout::Verbose)).to_string();
+        assert!(verbose.contains("errors consistent with 0.65 ulps RMS, bias +1.7e-1 std dev 0.76 max 1, 66.7% within 0.5 ulps"), "{}", verbose);
+    }
+
+    #[test]
+    fn test_tolerance_suggestion() {
+        let mut summary = DiffSummary::new("onboard", 1.0, false, 4, &diff::diff_abs).with_tolerance_suggestion(1.5);
+        summary.add_slices(&[1.0, 2.0, 4.0], &[1.0, 2.0 + 2.0 * f64::EPSILON, 4.0]);
+        let suggestion = summary.tolerance_suggestion().unwrap();
+        assert_eq!(suggestion.suggested(ComparatorKind::Ulps), Some(2.0));
+        assert_eq!(suggestion.suggested(ComparatorKind::Abs), Some(6.7e-16));
+        assert!(suggestion.to_string().contains("name = \"onboard\"\ncomparator = \"ulps\"\ntolerance = 2e0\n"));
+        assert!(DiffSummary::new("off", 1.0, false, 4, &diff::diff_abs).tolerance_suggestion().is_none());
+    }
+
+    #[test]
+    fn test_extremes() {
+        let mut summary = DiffSummary::new("range", 0.1, false, 4, &diff::diff_abs).with_extremes();
+        assert!(!summary.to_string().contains("magnitudes"));
+        summary.add_slices(&[0.0, 1e-310, 2.0, 3e200], &[0.0, 1e-310, -2.0, 3e200]);
+        assert_eq!(summary.extremes().unwrap().subnormal_count(), 1);
+        assert!(summary.to_string().contains(", expected magnitudes 1e-310 to 3e200 subnormal 1 huge 1"), "{}", summary);
+    }
+
+    #[test]
+    fn test_duplicates() {
+        let xs = [1.0, 2.0, 1.0, 1.0, f64::NAN, f64::NAN];
+        let ys = [1.5, 2.0, 1.5, 1.0, 1.0, 1.0];
+        let mut counted = DiffSummary::new("dup", 0.1, false, 4, &diff::diff_abs).with_duplicate_detection(DuplicatePolicy::Count);
+        counted.add_slices(&xs, &ys);
+        assert_eq!((counted.count(), counted.unique_count(), counted.duplicate_count(), counted.fail_count()), (6, 4, 2, 4));
+        let mut skipped = DiffSummary::new("dup", 0.1, false, 4, &diff::diff_abs).with_duplicate_detection(DuplicatePolicy::Skip);
+        let mut other = skipped.clone();
+        skipped.add_slices(&xs, &ys);
+        assert_eq!((skipped.count(), skipped.unique_count(), skipped.duplicate_count(), skipped.fail_count()), (4, 4, 2, 2));
+        assert!(skipped.to_string().contains(", unique 4 duplicates 2 skipped,"), "{}", skipped);
+        other.add_slices(&[2.0, 3.0], &[2.0, 3.0]);
+        skipped.merge(&other);
+        assert_eq!((skipped.unique_count(), skipped.duplicate_count()), (5, 3));
+        assert!(!DiffSummary::new("off", 0.1, false, 4, &diff::diff_abs).to_string().contains("unique"));
+    }
+
+    #[test]
+    fn test_strict_mode() {
+        let xs = [1.0, f64::NAN, f64::INFINITY, -f64::INFINITY, 2.0];
+        let ys = [1.0, f64::NAN, f64::INFINITY, -f64::INFINITY, f64::NAN];
+        let mut lenient = DiffSummary::new("lenient", 0.1, false, 4, &diff::diff_abs);
+        lenient.add_slices(&xs, &ys);
+        assert_eq!((lenient.fail_count(), lenient.nonfinite_pass_count(), lenient.is_strict()), (1, 3, false));
+        let verbose = DisplayOptions::new().with_layout(Layout::Verbose);
+        assert!(lenient.display_with(&verbose).to_string().contains(", status failed, nonfinite lenient passed 3, "));
+        let mut strict = DiffSummary::new("strict", 0.1, false, 4, &diff::diff_abs).with_strict_mode();
+        strict.add_slices(&xs, &ys);
+        assert_eq!((strict.fail_count(), strict.nonfinite_pass_count(), strict.is_strict()), (4, 0, true));
+        assert!(strict.display_with(&verbose).to_string().contains(", status failed, nonfinite strict, "));
+        strict.add_interval(f64::INFINITY, 0.0, f64::INFINITY, 5);
+        assert_eq!((strict.fail_count(), strict.nonfinite_pass_count()), (5, 0));
+        strict.merge(&lenient);
+        assert_eq!((strict.nonfinite_pass_count(), strict.is_strict()), (3, false));
+    }
+
+    #[test]
+    fn test_zero_epsilon() {
+        let mut summary = DiffSummary::new("tiny", 1e-3, false, 4, &diff::diff_rel).with_zero_epsilon(1e-300);
+        summary.add(0.0, 5e-324, 0);
+        summary.add(-1e-310, 1e-305, 1);
+        summary.add(-0.0, 0.0, 2);
+        assert_eq!((summary.worst_diff(), summary.sign_count(), summary.near_zero_count()), (0.0, 0, 3));
+        summary.add(1e-300, 2e-300, 3);
+        assert_eq!((summary.fail_count(), summary.near_zero_count()), (1, 3));
+        assert!(summary.to_string().starts_with("tiny: count 4, near zero 3 below 1e-300,"), "{}", summary);
+        let mut ulps = DiffSummary::new("ulps", 0.0, false, 4, &diff::diff_ulps).with_zero_epsilon(1e-300);
+        ulps.add(f64::NAN, 0.0, 0);
+        ulps.add(5e-324, 0.0, 1);
+        assert_eq!((ulps.fail_count(), ulps.near_zero_count()), (1, 1));
+        ulps.merge(&ulps.clone());
+        assert_eq!(ulps.near_zero_count(), 2);
+    }
+
+    #[test]
+    fn test_signed_zero() {
+        let mut strict = DiffSummary::new("strict", 4.0, false, 4, &diff::diff_ulps);
+        strict.add(0.0, -0.0, 0);
+        assert_eq!((strict.sign_count(), strict.is_ok()), (1, false));
+        let mut summary = DiffSummary::new("zeros", 4.0, false, 4, &diff::diff_ulps)
+            .with_signed_zero_policy(SignedZeroPolicy::Ignore);
+        summary.add(0.0, -0.0, 0);
+        summary.add(-0.0, 0.0, 1);
+        summary.add(-0.0, -0.0, 2);
+        assert_eq!((summary.worst_diff(), summary.sign_count(), summary.signed_zero_count()), (0.0, 0, 2));
+        assert!(summary.is_ok() && summary.evaluate(0.0, -0.0).is_ok());
+        summary.add(-1e-300, 1e-300, 3);
+        assert_eq!((summary.sign_count(), summary.signed_zero_count()), (1, 2));
+        assert!(format!("{}", summary.display_with(&DisplayOptions::terse())).ends_with("signed zeros ignored 2"));
+    }
+
+    #[test]
+    fn test_convergence() {
+        let mut summary = DiffSummary::new("grid", 1.0, false, 4, &diff::diff_abs).with_convergence(1.5);
+        for n in 1..=8 {
+            summary.add(1.0, 1.0 + 0.5 / (n * n) as f64, n);
+        }
+        let order = summary.convergence().unwrap().order();
+        assert!((order - 2.0).abs() < 1e-3, "{}", order);
+        assert!(summary.is_ok());
+        let steps = [0.1, 0.05, 0.025];
+        let step = |index: usize| steps[index];
+        let mut first_order = DiffSummary::new("spacing", 1.0, false, 4, &diff::diff_abs).with_convergence_by(1.5, &step);
+        first_order.add_slices(&[1.1, 1.05, 1.025], &[1.0, 1.0, 1.0]);
+        assert!(!first_order.is_ok() && !first_order.is_convergence_ok());
+        assert!(first_order.status().failures.iter().any(|reason| matches!(reason, StatusReason::Convergence { .. })));
+        assert!(format!("{}", first_order.display_with(&DisplayOptions::terse())).contains(" below min 1.5e0,"), "{}", first_order);
+        assert_eq!(first_order.highlights().0, Highlight::Fail);
+    }
+
+    #[test]
+    fn test_fail_budget() {
+        let mut summary = DiffSummary::new("budget", 0.1, false, 4, &diff::diff_abs).with_allow_fail_count(2);
+        summary.add_slices(&[1.0, 2.0, 3.0, 4.0], &[1.0, 2.5, 3.0, f64::NAN]);
+        assert_eq!(summary.fail_budget_used(), Some((2, 2)));
+        assert!(summary.is_ok());
+        summary.assert();
+        let status = summary.status();
+        assert!(status.is_ok() && status.warnings.len() == 2, "{}", status);
+        assert!(summary.to_string().contains("50% failed tolerance 1e-1 budget used 2 of 2"), "{}", summary);
+        summary.add(5.0, 6.0, 4);
+        assert!(!summary.is_ok() && summary.status().failed_tolerance());
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| summary.assert()));
+        assert!(result.is_err());
+
+        let mut fraction = DiffSummary::new("fraction", 0.1, false, 4, &diff::diff_abs).with_allow_fail_fraction(0.1);
+        fraction.add_iter((0..20).map(|i| (i as f64, if i == 7 { 0.0 } else { i as f64 })));
+        assert_eq!((fraction.fail_budget_used(), fraction.is_ok()), (Some((1, 2)), true));
+    }
+
+    #[test]
+    fn test_expected_failures() {
+        let mut summary = DiffSummary::new("xfail", 0.1, false, 4, &diff::diff_abs).with_expected_failures(&[3, 1, 3]);
+        summary.add_slices(&[1.0, 2.0, 3.0, 4.0], &[1.0, 2.5, 3.0, 4.0]);
+        summary.add(-5.0, 5.0, 3);
+        assert_eq!((summary.count(), summary.expected_fail_count(), summary.unexpected_pass_count()), (3, 2, 1));
+        assert!(summary.is_ok());
+        summary.assert();
+        assert!(summary.to_string().contains(", expected failures 2 unexpectedly passing 1 first index 1,"), "{}", summary);
+        summary.add(5.0, 6.0, 4);
+        assert!(!summary.is_ok());
+    }
+
+    #[test]
+    fn test_bootstrap() {
+        let mut summary = DiffSummary::new("mc", 0.75, false, 4, &diff::diff_abs).with_bootstrap(100, 3);
+        summary.add_iter((0..500).map(|i| (0.0, (i % 10) as f64 * 0.1)));
+        assert_eq!(summary.bootstrap().unwrap().replicate_count(), 100);
+        let (lo, hi) = summary.bootstrap().unwrap().fail_rate_interval(0.95);
+        assert!(lo < 0.2 && 0.2 < hi, "{} {}", lo, hi);
+        let text = summary.display_with(&DisplayOptions::new().with_layout(Layout::Verbose)).to_string();
+        assert!(text.contains(", 95% ci mean 4."), "{}", text);
+        assert!(!summary.to_string().contains("95% ci"));
+    }
+
+    #[test]
+    fn test_percent() {
+        let mut summary = DiffSummary::new_percent("revenue", 5.0, false, 4);
+        summary.add(104.0, 100.0, 0);
+        summary.add(90.0, 100.0, 1);
+        assert_eq!(summary.worst_diff(), 10.0);
+        assert_eq!(summary.fail_count(), 1);
+        assert_eq!(
+            format!("{}", summary.display_with(&DisplayOptions::terse())),
+            "revenue: count 2, worst diff 1e1%, 50% failed tolerance 5e0%, sign diffs 0%"
+        );
+    }
+
+    #[test]
+    fn test_ratio() {
+        let mut summary = DiffSummary::new_ratio("gain", 0.01, false, 4);
+        summary.add(2.01, 2.0, 0);
+        summary.add(1e-6 * 1.005, 1e-6, 1);
+        assert!(summary.is_ok());
+        summary.add(3.0, 2.0, 2);
+        summary.add(1.0, 0.0, 3);
+        assert_eq!(summary.fail_count(), 2);
+        assert_eq!(summary.worst_diff(), f64::INFINITY);
+    }
+
+    #[test]
+    fn test_unit() {
+        let mut summary = DiffSummary::new("position", 1e-2, false, 4, &diff::diff_abs).with_unit("m");
+        summary.add(1.5, 1.5 - 0.00390625, 0);
+        summary.add(2.0, 2.0, 1);
+        assert_eq!(
+            format!("{}", summary.display_with(&DisplayOptions::terse().with_histogram(true))),
+            "position: count 2, identical 50%, worst diff 3.90625e-3 m, 0% failed tolerance 1e-2 m, \
+                zero 50%, e-2 m 50%, sign diffs 0%"
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "count: tolerance 1e-9 is implausible for the ulps comparator")]
+    fn test_tolerance_check() {
+        let rel = DiffSummary::new("pressure", 1e-9, false, 4, &diff::diff_rel);
+        let _ = rel.with_tolerance_check(ComparatorKind::Rel, ToleranceCheck::Error);
+        let ulps = DiffSummary::new("count", 1e-9, false, 4, &diff::diff_ulps);
+        let ulps = ulps.with_tolerance_check(ComparatorKind::Ulps, ToleranceCheck::Off);
+        let _ = ulps.with_tolerance_check(ComparatorKind::Ulps, ToleranceCheck::Error);
+    }
+
+    #[test]
+    fn test_timed() {
+        let mut summary = DiffSummary::new("solver", 0.5, false, 4, &diff::diff_abs);
+        summary.add(1.0, 1.0, 0);
+        assert_eq!(summary.timing(), None);
+        summary.add_timed(1.0, 1.25, 1, Duration::from_millis(3), Duration::from_millis(2));
+        let mut other = DiffSummary::new("solver", 0.5, false, 4, &diff::diff_abs);
+        other.add_timed_with(|| 2.0, || 2.0, 2);
+        other.add_timed(1.0, 1.0, 3, Duration::from_millis(6), Duration::from_millis(4));
+        summary.merge(&other);
+        let timing = summary.timing().unwrap();
+        assert_eq!((timing.count, timing.max_x, timing.max_y), (3, Duration::from_millis(6), Duration::from_millis(4)));
+        assert_eq!(summary.count(), 4);
+
+        let mut fixed = DiffSummary::new("solver", 0.5, false, 4, &diff::diff_abs);
+        fixed.add_timed(1.0, 1.0, 0, Duration::from_micros(1500), Duration::from_millis(1));
+        fixed.add_timed(1.0, 1.0, 1, Duration::from_micros(1500), Duration::from_millis(1));
+        assert!(fixed.to_string().contains(", time x 1.5 ms y 1 ms ratio 1.5e0,"), "{}", fixed);
+    }
+
+    #[test]
+    fn test_presets() {
+        let mut ulps = DiffSummary::ulps("ulps", 2);
+        ulps.add(1.0, 1.0 + 2.0 * f64::EPSILON, 0);
+        assert!(ulps.is_ok());
+        ulps.add(1.0, 1.0 + 3.0 * f64::EPSILON, 1);
+        assert_eq!((ulps.worst_diff(), ulps.is_ok()), (3.0, false));
+
+        let mut relative = DiffSummary::relative("relative", 1e-2);
+        relative.add(100.0, 100.5, 0);
+        assert!(relative.is_ok());
+        relative.add(-1.0, 1.0, 1);
+        assert!(!relative.is_ok());
+
+        let mut absolute = DiffSummary::absolute("absolute", 1e-3);
+        absolute.add(-1e-4, 1e-4, 0);
+        assert_eq!((absolute.sign_count(), absolute.sign_exempt_count(), absolute.is_ok()), (0, 1, true));
+        absolute.add(1.0, 1.01, 1);
+        assert!(!absolute.is_ok());
+
+        let mut angle = DiffSummary::angle_degrees("heading", 1.0);
+        angle.add(179.75, -179.75, 0);
+        assert_eq!((angle.worst_diff(), angle.is_ok()), (0.5, true));
+        assert!(angle.to_string().contains("diff 5e-1 deg"), "{}", angle);
+
+        let mut radians = DiffSummary::angle_radians("phase", 1e-9);
+        radians.add(std::f64::consts::PI, -std::f64::consts::PI, 0);
+        radians.add(0.5, 0.5 + std::f64::consts::TAU, 1);
+        assert_eq!((radians.sign_count(), radians.is_ok()), (0, true));
+    }
+
+    #[test]
+    fn test_results() {
+        let results = vec![(Ok(1.0), Ok(1.0)), (Err("diverged"), Err("singular")), (Err("diverged"), Ok(2.0)), (Ok(3.0), Err("missing"))];
+        let mut summary = DiffSummary::new("solve", 0.5, false, 4, &diff::diff_abs);
+        summary.add_results_iter(results.clone());
+        assert_eq!(summary.error_counts(), (1, 1, 1));
+        assert_eq!((summary.count(), summary.fail_count()), (3, 2));
+        assert_eq!(summary.first_failure().map(|sample| (sample.index, sample.y)), Some((2, 2.0)));
+        assert!(!summary.is_ok());
+        assert!(summary.to_string().contains(", errors both 1 computed 1 expected 1, "));
+
+        let mut skip = DiffSummary::new("solve", 0.5, false, 4, &diff::diff_abs).with_error_policy(ErrorPolicy::Skip);
+        skip.add_results_iter(results);
+        assert_eq!((skip.error_counts(), skip.count()), ((1, 1, 1), 1));
+        assert!(skip.is_ok());
+        assert!(skip.to_string().contains(", errors both 1 computed 1 expected 1 skipped, "));
+        summary.merge(&skip);
+        assert_eq!(summary.error_counts(), (2, 2, 2));
+    }
+
+    #[test]
+    fn test_class_strata() {
+        let mut summary = DiffSummary::new("libm", 0.25, false, 4, &diff::diff_abs).with_class_strata();
+        let mut other = summary.clone();
+        summary.add_slices(&[0.0, 1.5, 2.0, 1e-310], &[0.0, 1.0, 2.125, 0.0]);
+        other.add(-3.0, -3.0, 4);
+        other.add(f64::NAN, 1e-310, 5);
+        summary.merge(&other);
+        let classes = summary.class_summaries().unwrap();
+        let counts: Vec<(String, usize, usize)> =
+            classes.iter().map(|(class, summary)| (class.to_string(), summary.count(), summary.fail_count())).collect();
+        assert_eq!(counts, vec![
+            ("+zero".to_string(), 2, 0),
+            ("+subnormal".to_string(), 1, 1),
+            ("+normal".to_string(), 2, 1),
+            ("-normal".to_string(), 1, 0),
         ]);
-        for (i, item) in data.iter().enumerate() {
-            summaries[0].add(item.0, item.1, i);
-            summaries[1].add(item.2, item.3, i);
-            summaries[2].add(item.0, item.0, i);
+        assert_eq!(classes.values().map(DiffSummary::count).sum::<usize>(), summary.count());
+        let text = summary.to_string();
+        assert!(text.contains("class +normal worst 5e-1 failed 50%"), "{}", text);
+        assert!(text.contains("class -normal worst 0e0 failed 0%"), "{}", text);
+        assert!(DiffSummary::new("off", 0.25, false, 4, &diff::diff_abs).class_summaries().is_none());
+    }
+
+    #[test]
+    fn test_profiles() {
+        let mut summary = DiffSummary::new("fma", 1e-12, false, 4, &diff::diff_abs)
+            .with_profile("dev", 1e-9)
+            .with_profile("ci", 1e-15);
+        let mut other = summary.clone();
+        summary.add_slices(&[1.0, 2.0, 3.0], &[1.0, 2.0 + 1e-13, 3.0]);
+        other.add(4.0 + 1e-10, 4.0, 3);
+        summary.merge(&other);
+        assert_eq!(summary.profile_names(), vec!["dev", "ci"]);
+        assert!(!summary.is_ok() && summary.is_profile_ok("dev") && !summary.is_profile_ok("ci"));
+        assert_eq!((summary.fail_count(), summary.profile_fail_count("dev"), summary.profile_fail_count("ci")), (1, 0, 2));
+        summary.assert_profile("dev");
+        let text = summary.to_string();
+        assert!(text.contains("profile dev pass within 1e-9 failed 0%, profile ci fail within 1e-15 failed 50%"), "{}", text);
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| summary.assert_profile("ci")));
+        let message = result.unwrap_err().downcast::<String>().unwrap();
+        assert!(message.starts_with("assert failed profile ci item 3, fma: "), "{}", message);
+        assert!(message.contains("first failed item 1: "), "{}", message);
+        assert!(std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| summary.is_profile_ok("release"))).is_err());
+    }
+
+    #[test]
+    fn test_ranges() {
+        let mut summary = DiffSummary::new("mesh", 0.25, false, 4, &diff::diff_abs)
+            .with_warn_diff(0.125)
+            .with_ranges(&[2..4, 3..6]);
+        let mut other = summary.clone();
+        summary.add_slices(&[1.0; 4], &[1.0, 1.5, 1.0, 1.1875]);
+        other.add(1.0, 1.0, 4);
+        other.add(-1.0, 1.5, 5);
+        summary.merge(&other);
+        let ranges = summary.range_summaries();
+        assert_eq!(ranges.len(), 2);
+        assert_eq!(ranges[0].0, 2..4);
+        assert_eq!((ranges[0].1.count(), ranges[0].1.warn_count(), ranges[0].1.worst_diff()), (2, 1, 0.1875));
+        assert!(ranges[0].1.is_ok());
+        assert_eq!((ranges[1].1.count(), ranges[1].1.fail_count(), ranges[1].1.sign_count()), (3, 1, 1));
+        assert!(!ranges[1].1.is_ok() && !summary.is_ok());
+        let text = summary.to_string();
+        assert!(text.contains("range 2..4 worst 1.875e-1 failed 0%"), "{}", text);
+        assert!(text.contains("range 3..6 worst 2.5e0 failed 33%"), "{}", text);
+    }
+
+    #[test]
+    fn test_drift() {
+        let mut summary = DiffSummary::new("series", 1.0, false, 4, &diff::diff_abs).with_drift(2, 8);
+        for i in 0..8 {
+            let err = if i < 4 { 0.125 } else { 0.5 };
+            summary.add(1.0 + err, 1.0, i);
         }
+        let drift = summary.drift().unwrap();
+        assert_eq!(drift.segments()[1].worst, 0.5);
+        assert_eq!(drift.ratio(), 4.0);
+        let options = DisplayOptions::terse().with_layout(Layout::Verbose);
+        assert!(
+            format!("{}", summary.display_with(&options)).contains(", drift 4e0 segment means 1.25e-1 5e-1,"),
+            "{}",
+            summary.display_with(&options)
+        );
+    }
 
-        println!();
-        for summary in &summaries {
-            println!("{}", summary);
+    #[test]
+    fn test_sampling() {
+        let xs: Vec<f64> = (0..100).map(|i| i as f64 + 0.5).collect();
+        let ys: Vec<f64> = (0..100).map(|i| i as f64).collect();
+        let mut summary = DiffSummary::new("nth", 1.0, false, 4, &diff::diff_abs).with_sampling(Sampling::EveryNth(10));
+        summary.add_slices(&xs, &ys);
+        assert_eq!(summary.count(), 10);
+        assert_eq!(summary.unsampled_count(), 90);
+        assert!(format!("{}", summary.display_with(&DisplayOptions::terse())).starts_with("nth: count 10, unsampled 90, worst"));
+
+        let sampling = Sampling::Random { probability: 0.25, seed: 7 };
+        let mut random = DiffSummary::new("random", 1.0, false, 4, &diff::diff_abs).with_sampling(sampling);
+        random.add_iter(xs.iter().zip(&ys).map(|(&x, &y)| (x, y)));
+        assert_eq!(random.count() + random.unsampled_count(), 100);
+        assert!(10 < random.count() && random.count() < 40, "{}", random.count());
+        // Selection depends only on index, so split halves merge to the same result.
+        let mut first = DiffSummary::new("random", 1.0, false, 4, &diff::diff_abs).with_sampling(sampling);
+        let mut second = first.clone();
+        for i in 0..100 {
+            if i < 50 { &mut first } else { &mut second }.add(xs[i], ys[i], i);
         }
-        assert_eq!(summaries[0].num_total, data.len());
-        assert_eq!(summaries[1].num_total, data.len());
-        assert!(summaries[0].num_diff_fail > summaries[1].num_diff_fail);
-        assert_eq!(summaries[2].num_total, data.len());
-        assert_eq!(summaries[2].summary_diff.count, 0);
-        assert_eq!(summaries[2].summary_sign.count, 0);
-        assert_eq!(summaries[3].num_total, 0);
-        assert!(!summaries[0].is_ok());
-        assert!(!summaries[1].is_ok());
-        assert!(summaries[2].is_ok());
-        assert!(summaries[3].is_ok());
+        first.merge(&second);
+        assert_eq!(first.count(), random.count());
+    }
+
+    #[test]
+    fn test_watchdog() {
+        let mut consumed = 0;
+        let mut summary = DiffSummary::new("watch", 0.1, false, 4, &diff::diff_abs).with_max_items(30);
+        summary.add_iter((0..100).map(|i| {
+            consumed += 1;
+            (i as f64 + 0.5, i as f64)
+        }));
+        assert_eq!((consumed, summary.count(), summary.fail_count()), (30, 30, 30));
+        assert_eq!(summary.truncation(), Some(Truncation::MaxItems));
+        summary.add(1.0, 1.0, 100);
+        assert_eq!(summary.count(), 30);
+        assert!(summary.to_string().starts_with("watch: count 30, truncated by max items"));
+        assert!(summary.status().warnings.contains(&StatusReason::Truncated { truncation: Truncation::MaxItems, offered_count: 30 }));
+
+        let mut untruncated = DiffSummary::new("watch", 0.1, false, 4, &diff::diff_abs);
+        untruncated.add_slices(&[1.0, 2.0], &[1.0, 2.0]);
+        assert!(!untruncated.is_truncated());
+        untruncated.merge(&summary);
+        assert_eq!(untruncated.truncation(), Some(Truncation::MaxItems));
+
+        let mut late = DiffSummary::new("late", 0.1, false, 4, &diff::diff_abs).with_deadline(Instant::now());
+        late.add_slices(&[1.0, 2.0], &[1.0, 2.0]);
+        assert_eq!((late.count(), late.truncation()), (0, Some(Truncation::Deadline)));
+        assert!(late.is_ok());
+        let mut timely = DiffSummary::new("timely", 0.1, false, 4, &diff::diff_abs).with_timeout(Duration::from_secs(3600));
+        timely.add_slices(&[1.0, 2.0], &[1.0, 2.0]);
+        assert_eq!((timely.count(), timely.is_truncated()), (2, false));
+    }
+
+    #[test]
+    fn test_streams() {
+        let xs = [1.0, 2.0, 3.0, 4.0, 5.0];
+        let mut summary = DiffSummary::new("streams", 0.1, false, 4, &diff::diff_abs);
+        let err = summary.add_streams(xs.iter().cloned(), vec![1.0, 2.0, 3.0], LengthPolicy::Error).unwrap_err();
+        assert_eq!(err.to_string(), "streams: length mismatch, y ended after 3 values but x continued");
+        assert_eq!((summary.count(), summary.unpaired_counts()), (3, (0, 0)));
+
+        // Neither Error nor Truncate reads an unbounded stream to its end.
+        let mut endless = DiffSummary::new("streams", 0.1, false, 4, &diff::diff_abs);
+        assert!(endless.add_streams(std::iter::repeat(1.0), vec![1.0, 1.0], LengthPolicy::Error).is_err());
+        let mut truncated = DiffSummary::new("streams", 0.1, false, 4, &diff::diff_abs);
+        truncated.add_streams(std::iter::repeat(1.0), vec![1.0, 2.0, 3.0], LengthPolicy::Truncate).unwrap();
+        assert_eq!((truncated.count(), truncated.fail_count(), truncated.unpaired_counts()), (3, 2, (1, 0)));
+        assert!(truncated.to_string().starts_with("streams: count 3, x longer, unpaired 1,"), "{}", truncated);
+
+        let mut padded = DiffSummary::new("streams", 0.1, false, 4, &diff::diff_abs);
+        padded.add_streams(vec![1, 2], xs.iter().cloned(), LengthPolicy::PadNan).unwrap();
+        assert_eq!((padded.count(), padded.fail_count(), padded.unpaired_counts()), (5, 3, (0, 3)));
+        assert!(padded.worst_diff().is_nan());
+        padded.merge(&truncated);
+        assert_eq!(padded.unpaired_counts(), (1, 3));
+        assert!(padded.to_string().contains(", unpaired x 1 y 3,"));
+
+        let mut nan_padded = DiffSummary::new("streams", 0.1, false, 4, &diff::diff_abs);
+        nan_padded.add_streams(vec![1.0, f64::NAN], vec![1.0], LengthPolicy::PadNan).unwrap();
+        assert_eq!((nan_padded.count(), nan_padded.fail_count(), nan_padded.unpaired_counts()), (2, 1, (1, 0)));
+    }
+
+    #[test]
+    fn test_reservoir() {
+        let mut summary = DiffSummary::new("sample", 1.0, false, 4, &diff::diff_abs).with_reservoir(5, 3);
+        assert!(summary.reservoir().is_empty());
+        for i in 0..50 {
+            summary.add(i as f64 + 0.25, i as f64, i);
+        }
+        let sample = summary.reservoir();
+        assert_eq!(sample.len(), 5);
+        assert!(sample.windows(2).all(|pair| pair[0].0 < pair[1].0));
+        assert!(sample.iter().all(|&(index, x, y, diff)| x == index as f64 + 0.25 && y == index as f64 && diff == 0.25));
+        assert!(DiffSummary::new("off", 1.0, false, 4, &diff::diff_abs).reservoir().is_empty());
+    }
+
+    #[test]
+    fn test_passing_example() {
+        let mut summary = DiffSummary::new("typical", 0.1, false, 4, &diff::diff_abs).with_passing_example(PassingExample::Median);
+        assert_eq!(summary.passing_example(), None);
+        for i in 0..41 {
+            summary.add(1.0 + (40 - i) as f64 * 1e-3, 1.0, i);
+        }
+        summary.add(2.0, 1.0, 41);
+        summary.add(-1e-3, 1e-3, 42);
+        let mut other = summary.clone();
+        other.add(f64::NAN, 1.0, 43);
+        summary.merge(&other);
+        let (index, _, _, median) = summary.passing_example().unwrap();
+        assert_eq!((index, median), (20, 1.0 + 20.0 * 1e-3 - 1.0));
+        let shown = summary.to_string();
+        assert!(shown.contains(", median passing index 20 1.02e0 vs 1e0 diff 2.0000000000000018e-2, sign diffs"), "{}", shown);
+        assert!(!summary.display_with(&DisplayOptions::new().with_passing_example(false)).to_string().contains("passing"));
+        let mut random = DiffSummary::new("typical", 0.1, false, 4, &diff::diff_abs).with_passing_example(PassingExample::Random { seed: 5 });
+        random.add_iter((0..10).map(|i| (i as f64 * 0.02, 0.0)));
+        let (index, ..) = random.passing_example().unwrap();
+        assert!(index < 6, "{}", index);
+        assert!(random.to_string().contains(&format!("random passing index {} ", index)));
+    }
+
+    #[test]
+    fn test_add_slices_matches_add() {
+        // Enough items to span several histogram batches, with exact matches,
+        // sign changes, and nans mixed in.
+        let ys: Vec<f64> = (0..300).map(|i| (i as f64 - 150.0) * 0.37).collect();
+        let xs: Vec<f64> = ys
+            .iter()
+            .enumerate()
+            .map(|(i, &y)| match i % 5 {
+                0 => y + 1e-9 * i as f64,
+                1 => -y,
+                2 if i % 50 == 2 => f64::NAN,
+                _ => y,
+            })
+            .collect();
+        let mut single = DiffSummary::new("same", 1e-7, false, 5, &diff::diff_abs);
+        for (i, (&x, &y)) in xs.iter().zip(&ys).enumerate() {
+            single.add(x, y, i);
+        }
+        let mut sliced = DiffSummary::new("same", 1e-7, false, 5, &diff::diff_abs);
+        sliced.add_slices(&xs, &ys);
+        assert_eq!(sliced.histogram().bucket_counts(), single.histogram().bucket_counts());
+        assert_eq!(format!("{}", sliced), format!("{}", single));
+        assert_eq!(sliced.mean_diff().to_bits(), single.mean_diff().to_bits());
+    }
+
+    #[test]
+    fn test_triage() {
+        let mut summary = DiffSummary::new("triage", 1e-3, true, 4, &diff::diff_rel).with_triage(1e-6, 1e6);
+        let items = [(1.0, 1.0), (f64::NAN, 2.0), (3e-9, 1e-9), (2e-9, 1e-9), (1.1, 1.0)];
+        for (i, &(x, y)) in items.iter().enumerate() {
+            summary.add(x, y, i);
+        }
+        assert_eq!(summary.triage().unwrap().count(), summary.fail_count());
+        let text = format!("{}", summary.display_with(&DisplayOptions::terse()));
+        assert!(text.contains(", triage near-zero 2, nan 1, other 1, y band e-9 2, e0 2, sign"), "{}", text);
+        assert!(DiffSummary::new("off", 1e-3, true, 4, &diff::diff_rel).triage().is_none());
+    }
+
+    #[test]
+    fn test_direction() {
+        let mut summary = DiffSummary::new("bias", 0.25, false, 4, &diff::diff_abs);
+        let mut other = summary.clone();
+        summary.add_slices(&[1.0, 2.5, 3.0, f64::NAN], &[1.0, 2.0, 3.125, 4.0]);
+        other.add(5.5, 5.0, 4);
+        summary.merge(&other);
+        assert_eq!((summary.over_count(), summary.under_count()), (2, 1));
+        assert_eq!(summary.evaluate(0.5, 1.0).direction, Some(Direction::Under));
+        let text = summary.display_with(&DisplayOptions::new().with_layout(Layout::Verbose)).to_string();
+        assert!(text.contains("over 40% under 20%"), "{}", text);
+
+        let mut angles = DiffSummary::angle_degrees("heading", 5.0);
+        angles.add_slices(&[359.0, 2.0, 10.0], &[1.0, 358.0, 10.0]);
+        assert_eq!((angles.over_count(), angles.under_count()), (1, 1));
+        assert_eq!(angles.evaluate(359.0, 1.0).direction, Some(Direction::Under));
+        assert_eq!(angles.sub_summary().evaluate(359.0, 1.0).direction, Some(Direction::Under));
+    }
+
+    #[test]
+    fn test_publisher() {
+        let snapshots = std::cell::RefCell::new(Vec::new());
+        let publish = |snapshot: &SummarySnapshot| snapshots.borrow_mut().push(*snapshot);
+        let mut summary = DiffSummary::new("live", 0.5, false, 4, &diff::diff_abs).with_publisher(&publish, 0.1, 1);
+        summary.add(1.0, 1.0, 0);
+        summary.add(1.05, 1.0, 1);
+        summary.add(1.3, 1.0, 2);
+        assert_eq!(snapshots.borrow().len(), 1);
+        assert_eq!(snapshots.borrow()[0], SummarySnapshot { count: 3, fail_count: 0, sign_count: 0, worst_diff: summary.worst_diff(), is_ok: true });
+        summary.add(2.0, 1.0, 3);
+        summary.add(2.0, 1.0, 4);
+        summary.add(2.0, 1.0, 5);
+        assert_eq!(snapshots.borrow().iter().map(|snapshot| snapshot.fail_count).collect::<Vec<_>>(), vec![0, 1, 3]);
+        summary.add(f64::NAN, 1.0, 6);
+        summary.add(f64::NAN, 1.0, 7);
+        assert_eq!((snapshots.borrow().len(), snapshots.borrow()[3].fail_count), (4, 4));
+        let other = summary.clone();
+        summary.merge(&other);
+        assert_eq!(snapshots.borrow().last().unwrap().count, 16);
+        assert!(snapshots.borrow()[4].worst_diff.is_nan());
+        assert!(!summary.snapshot().is_ok);
+    }
+
+    #[test]
+    fn test_observer() {
+        let records = std::cell::RefCell::new(Vec::new());
+        let observer = |record: &ItemRecord| records.borrow_mut().push(*record);
+        let mut summary = DiffSummary::new("observe", 0.25, false, 4, &diff::diff_abs)
+            .with_warn_diff(0.1)
+            .with_observer(&observer);
+        summary.add_slices(&[1.0, 1.5, -0.125, 2.0, 3.0], &[1.0, 1.0, 0.0, 2.0625, 2.875]);
+        let records = records.borrow();
+        assert_eq!(
+            records.iter().map(|record| (record.index, record.outcome)).collect::<Vec<_>>(),
+            vec![(0, ItemStatus::Exact), (1, ItemStatus::Failed), (2, ItemStatus::Failed), (3, ItemStatus::Ok), (4, ItemStatus::Warned)]
+        );
+        assert!(records[2].sign_change && !records[1].sign_change);
+        assert_eq!((records[3].x, records[3].y, records[3].diff), (2.0, 2.0625, 0.0625));
+        assert_eq!(records[1].bucket.to_string(), "e0");
+    }
+
+    #[test]
+    fn test_trace() {
+        let lines = std::cell::RefCell::new(Vec::new());
+        let trace = |line: &str| lines.borrow_mut().push(line.to_string());
+        let mut summary = DiffSummary::new("trace", 0.25, false, 4, &diff::diff_abs).with_trace(&trace);
+        summary.add(1.0, 1.0, 0);
+        summary.add(1.5, 1.0, 1);
+        summary.add(-0.125, 0.0, 2);
+        summary.add_slices(&[2.0], &[2.0625]);
+        assert_eq!(*lines.borrow(), vec![
+            "trace: index 0 1e0 vs 1e0 diff 0e0 ok bucket zero",
+            "trace: index 1 1.5e0 vs 1e0 diff 5e-1 failed bucket e0",
+            "trace: index 2 -1.25e-1 vs 0e0 diff 1.25e-1 sign diff failed bucket e0",
+            "trace: index 0 2e0 vs 2.0625e0 diff 6.25e-2 ok bucket e-1",
+        ]);
     }
-}
\ No newline at end of file
+
+    #[cfg(feature = "tracing")]
+    #[test]
+    fn test_tracing() {
+        use std::sync::{Arc, Mutex};
+        use tracing::field::{Field, Visit};
+
+        // Records each event as its message followed by its field names, and
+        // the names of spans created.
+        #[derive(Clone, Default)]
+        struct Recorder(Arc<Mutex<Vec<String>>>);
+
+        struct Fields(String);
+
+        impl Visit for Fields {
+            fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+                if field.name() == "message" {
+                    self.0.insert_str(0, &format!("{:?}", value));
+                } else {
+                    self.0.push_str(&format!(" {}", field.name()));
+                }
+            }
+        }
+
+        impl tracing::Subscriber for Recorder {
+            fn enabled(&self, _: &tracing::Metadata<'_>) -> bool {
+                true
+            }
+            fn new_span(&self, span: &tracing::span::Attributes<'_>) -> tracing::span::Id {
+                self.0.lock().unwrap().push(format!("span {}", span.metadata().name()));
+                tracing::span::Id::from_u64(1)
+            }
+            fn record(&self, _: &tracing::span::Id, _: &tracing::span::Record<'_>) {}
+            fn record_follows_from(&self, _: &tracing::span::Id, _: &tracing::span::Id) {}
+            fn event(&self, event: &tracing::Event<'_>) {
+                let mut fields = Fields(String::new());
+                event.record(&mut fields);
+                self.0.lock().unwrap().push(fields.0);
+            }
+            fn enter(&self, _: &tracing::span::Id) {}
+            fn exit(&self, _: &tracing::span::Id) {}
+        }
+
+        let recorder = Recorder::default();
+        tracing::subscriber::with_default(recorder.clone(), || {
+            let mut summary = DiffSummary::new("traced", 0.1, false, 4, &diff::diff_abs);
+            summary.add(1.0, 1.0, 0);
+            summary.add(2.0, 1.0, 1);
+            summary.report();
+        });
+        assert_eq!(*recorder.0.lock().unwrap(), vec![
+            "span diff_summary",
+            "diff outside tolerance index x y diff tolerance",
+            "summary report count fail_count sign_count worst_diff tolerance ok",
+        ]);
+    }
+}
@@ -0,0 +1,220 @@
+use crate::log_histogram::BucketLabels;
+use crate::util::{self, PercentFormat};
+
+// Overall shape of a summary's Display output.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Layout {
+    // A single line containing the core statistics.
+    Compact,
+    // A single line with additional configuration and status details.
+    Verbose,
+    // The same content as Verbose, with one section per line.
+    MultiLine,
+}
+
+// Options controlling how a DiffSummary is displayed, allowing the same
+// summary to produce terse CI lines or detailed debug dumps.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DisplayOptions {
+    // The number of significant digits to show for values and diffs.
+    // None uses the shortest representation that round-trips.
+    pub significant_digits: Option<usize>,
+
+    // The overall output layout.
+    pub layout: Layout,
+
+    // Whether to include the histogram breakdown of differences.
+    pub show_histogram: bool,
+
+    // Whether to include approximate p50, p95, and p99 differences,
+    // estimated from the histogram.
+    pub show_quantiles: bool,
+
+    // Whether to include the index and values of sample items,
+    // such as the item with the worst difference.
+    pub show_samples: bool,
+
+    // Whether to show counts alongside percentages, e.g. "3 (1%) failed"
+    // rather than "1% failed", for failures, sign changes, and histogram
+    // buckets, since a small percent of a large dataset can be many items.
+    pub show_counts: bool,
+
+    // How to show percentages, such as with fractional precision for rare events.
+    pub percent_format: PercentFormat,
+
+    // How to label histogram buckets, by exponent or by value range.
+    pub bucket_labels: BucketLabels,
+
+    // Whether to mark where the tolerance falls in the histogram with "|",
+    // separating the buckets within tolerance from those beyond it.
+    pub show_tolerance_marker: bool,
+
+    // Whether to highlight pass/fail status using ANSI terminal colors:
+    // red for failures, yellow for marginal passes, green for passes.
+    // Ignored when the NO_COLOR environment variable is set, unless force_color is.
+    pub color: bool,
+
+    // Whether color applies despite NO_COLOR, such as in tests of colored output.
+    pub force_color: bool,
+
+    // Whether to include the passing example retained per
+    // DiffSummary::with_passing_example, when showing samples.
+    pub show_passing_example: bool,
+
+    // Whether to include elapsed-time statistics from add_timed, which vary
+    // from run to run and machine to machine.
+    pub show_timing: bool,
+}
+
+impl DisplayOptions {
+    // The default options, matching the standard Display output.
+    pub fn new() -> Self {
+        DisplayOptions {
+            significant_digits: None,
+            layout: Layout::Compact,
+            show_histogram: true,
+            show_quantiles: true,
+            show_samples: true,
+            show_counts: false,
+            percent_format: PercentFormat::Whole,
+            bucket_labels: BucketLabels::Exponent,
+            show_tolerance_marker: false,
+            color: false,
+            force_color: false,
+            show_passing_example: true,
+            show_timing: true,
+        }
+    }
+
+    // A short single line, without histogram or sample details.
+    pub fn terse() -> Self {
+        DisplayOptions {
+            show_histogram: false,
+            show_quantiles: false,
+            show_samples: false,
+            ..DisplayOptions::new()
+        }
+    }
+
+    // Everything available, one section per line.
+    pub fn detailed() -> Self {
+        DisplayOptions {
+            layout: Layout::MultiLine,
+            show_counts: true,
+            show_tolerance_marker: true,
+            ..DisplayOptions::new()
+        }
+    }
+
+    // Deterministic output for snapshot tests, e.g. with insta: every
+    // section one per line, values and diffs at a fixed 6 significant
+    // digits, and nothing that depends on the machine or run, such as
+    // timings or terminal colors. Output only changes when the compared
+    // values or the summary's configuration do.
+    pub fn snapshot() -> Self {
+        DisplayOptions {
+            significant_digits: Some(6),
+            layout: Layout::MultiLine,
+            show_counts: true,
+            show_tolerance_marker: true,
+            show_timing: false,
+            ..DisplayOptions::new()
+        }
+    }
+
+    pub fn with_significant_digits(mut self, digits: usize) -> Self {
+        assert!(digits > 0, "significant_digits must be at least 1");
+        self.significant_digits = Some(digits);
+        self
+    }
+
+    pub fn with_layout(mut self, layout: Layout) -> Self {
+        self.layout = layout;
+        self
+    }
+
+    pub fn with_histogram(mut self, show_histogram: bool) -> Self {
+        self.show_histogram = show_histogram;
+        self
+    }
+
+    pub fn with_quantiles(mut self, show_quantiles: bool) -> Self {
+        self.show_quantiles = show_quantiles;
+        self
+    }
+
+    pub fn with_samples(mut self, show_samples: bool) -> Self {
+        self.show_samples = show_samples;
+        self
+    }
+
+    pub fn with_counts(mut self, show_counts: bool) -> Self {
+        self.show_counts = show_counts;
+        self
+    }
+
+    pub fn with_percent_format(mut self, percent_format: PercentFormat) -> Self {
+        if let PercentFormat::Fractional(digits) | PercentFormat::Scientific(digits) = percent_format {
+            assert!(digits > 0, "percent format digits must be at least 1");
+        }
+        self.percent_format = percent_format;
+        self
+    }
+
+    pub fn with_bucket_labels(mut self, bucket_labels: BucketLabels) -> Self {
+        self.bucket_labels = bucket_labels;
+        self
+    }
+
+    pub fn with_tolerance_marker(mut self, show_tolerance_marker: bool) -> Self {
+        self.show_tolerance_marker = show_tolerance_marker;
+        self
+    }
+
+    pub fn with_color(mut self, color: bool) -> Self {
+        self.color = color;
+        self
+    }
+
+    pub fn with_force_color(mut self, force_color: bool) -> Self {
+        self.force_color = force_color;
+        self
+    }
+
+    pub fn with_passing_example(mut self, show_passing_example: bool) -> Self {
+        self.show_passing_example = show_passing_example;
+        self
+    }
+
+    pub fn with_timing(mut self, show_timing: bool) -> Self {
+        self.show_timing = show_timing;
+        self
+    }
+
+    // The precision to use with exponent formatting, if any.
+    pub(crate) fn precision(&self) -> Option<usize> {
+        self.significant_digits.map(|digits| digits - 1)
+    }
+
+    // The separator to write between sections of output.
+    pub(crate) fn separator(&self) -> &'static str {
+        match self.layout {
+            Layout::MultiLine => "\n  ",
+            _ => ", ",
+        }
+    }
+
+    pub(crate) fn use_color(&self) -> bool {
+        self.color && (self.force_color || util::color_allowed())
+    }
+
+    pub(crate) fn is_verbose(&self) -> bool {
+        self.layout != Layout::Compact
+    }
+}
+
+impl Default for DisplayOptions {
+    fn default() -> Self {
+        DisplayOptions::new()
+    }
+}
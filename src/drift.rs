@@ -0,0 +1,136 @@
+// Per-segment difference statistics over an index range, for telling whether
+// differences grow over a series (drift) rather than being uniform.
+// The index range [0, index_count) is split into contiguous segments of
+// near-equal size. Indices at or beyond index_count fall in the last segment.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DriftSegments {
+    // The number of indices the segments span.
+    index_count: usize,
+
+    // The statistics of each segment, in index order.
+    segments: Vec<Segment>,
+}
+
+// Statistics for the differences of a single segment.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Segment {
+    // The number of items in this segment.
+    pub count: usize,
+
+    // The worst difference in this segment, or 0 if none are non-zero.
+    pub worst: f64,
+
+    // The number of items with finite differences, and their sum.
+    num_finite: usize,
+    sum: f64,
+}
+
+impl Segment {
+    fn new() -> Self {
+        Segment { count: 0, worst: 0.0, num_finite: 0, sum: 0.0 }
+    }
+
+    // The mean finite difference in this segment, or nan if there are none.
+    pub fn mean(&self) -> f64 {
+        if self.num_finite > 0 {
+            self.sum / self.num_finite as f64
+        } else {
+            f64::NAN
+        }
+    }
+}
+
+impl DriftSegments {
+    pub fn new(segment_count: usize, index_count: usize) -> Self {
+        assert!(segment_count > 0, "segment_count must be positive");
+        assert!(index_count >= segment_count, "index_count {} must be at least segment_count {}", index_count, segment_count);
+        DriftSegments { index_count, segments: vec![Segment::new(); segment_count] }
+    }
+
+    // The segment containing index.
+    fn segment_of(&self, index: usize) -> usize {
+        let count = self.segments.len();
+        ((index as u128 * count as u128 / self.index_count as u128) as usize).min(count - 1)
+    }
+
+    pub fn add(&mut self, index: usize, diff: f64) {
+        let segment = self.segment_of(index);
+        let segment = &mut self.segments[segment];
+        segment.count += 1;
+        if crate::diff::is_diff_worse(diff, segment.worst) {
+            segment.worst = diff;
+        }
+        if diff.is_finite() {
+            segment.num_finite += 1;
+            segment.sum += diff;
+        }
+    }
+
+    // Combine another set of segments' data into this one.
+    // Both must have the same segment and index counts.
+    pub fn merge(&mut self, other: &DriftSegments) {
+        assert!(
+            self.index_count == other.index_count && self.segments.len() == other.segments.len(),
+            "Cannot merge drift segments with different layouts"
+        );
+        for (segment, other) in self.segments.iter_mut().zip(&other.segments) {
+            segment.count += other.count;
+            if crate::diff::is_diff_worse(other.worst, segment.worst) {
+                segment.worst = other.worst;
+            }
+            segment.num_finite += other.num_finite;
+            segment.sum += other.sum;
+        }
+    }
+
+    // The statistics of each segment, in index order.
+    pub fn segments(&self) -> &[Segment] {
+        &self.segments
+    }
+
+    // The drift indicator: the ratio of the last segment's mean difference to
+    // the first segment's. Values well above 1 indicate differences growing
+    // over the series. Two zero means give 1, and nan means give nan.
+    pub fn ratio(&self) -> f64 {
+        let first = self.segments[0].mean();
+        let last = self.segments[self.segments.len() - 1].mean();
+        if first == 0.0 && last == 0.0 {
+            1.0
+        } else {
+            last / first
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::DriftSegments;
+
+    #[test]
+    fn test_drift() {
+        let mut drift = DriftSegments::new(4, 100);
+        let mut first = DriftSegments::new(4, 100);
+        let mut second = DriftSegments::new(4, 100);
+        for i in 0..100 {
+            let diff = (i / 25 + 1) as f64 * 1e-3;
+            drift.add(i, diff);
+            if i % 2 == 0 { &mut first } else { &mut second }.add(i, diff);
+        }
+        drift.add(150, f64::NAN);
+        let segments = drift.segments();
+        assert_eq!(segments.iter().map(|s| s.count).collect::<Vec<_>>(), vec![25, 25, 25, 26]);
+        assert_eq!(segments[1].worst, 2e-3);
+        assert!(segments[3].worst.is_nan());
+        assert!((segments[3].mean() - 4e-3).abs() < 1e-15);
+        assert!((drift.ratio() - 4.0).abs() < 1e-12);
+
+        first.merge(&second);
+        assert_eq!(first.segments()[2].count, 25);
+        assert!((first.ratio() - 4.0).abs() < 1e-12);
+
+        let mut flat = DriftSegments::new(2, 2);
+        flat.add(0, 0.0);
+        flat.add(1, 0.0);
+        assert_eq!(flat.ratio(), 1.0);
+    }
+}
@@ -0,0 +1,167 @@
+use std::fmt::Display;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use crate::diff;
+use crate::diff_summary_f64::DiffSummary;
+use crate::sample_record::SampleRecord;
+use crate::util::{Percent, PercentFormat};
+
+// A DiffSummary for comparing durations or timestamps, such as simulation
+// times against reference times.
+// Differences are calculated exactly in nanoseconds, then recorded in seconds.
+// Display reports times in human-readable units, rather than exponents.
+pub struct DurationDiffSummary<'a> {
+    summary: DiffSummary<'a>,
+}
+
+impl<'a> DurationDiffSummary<'a> {
+    // Create a summary allowing absolute differences up to allow_diff.
+    pub fn new(name: &'a str, allow_diff: Duration, bucket_count: usize) -> Self {
+        DurationDiffSummary {
+            summary: DiffSummary::new(name, allow_diff.as_secs_f64(), true, bucket_count, &diff::diff_abs),
+        }
+    }
+
+    // Add a pair of durations.
+    pub fn add(&mut self, x: Duration, y: Duration, index: usize) {
+        self.add_nanos(nanos(x), nanos(y), index);
+    }
+
+    // Add a pair of timestamps. Timestamps before the epoch are supported.
+    pub fn add_time(&mut self, x: SystemTime, y: SystemTime, index: usize) {
+        self.add_nanos(epoch_nanos(x), epoch_nanos(y), index);
+    }
+
+    fn add_nanos(&mut self, x: i128, y: i128, index: usize) {
+        let diff = (x - y).abs() as f64 * 1e-9;
+        let (x, y) = (x as f64 * 1e-9, y as f64 * 1e-9);
+        self.summary.add_diff(x, y, diff, x.is_sign_negative() != y.is_sign_negative(), index);
+    }
+
+    // The underlying summary, with values and differences in seconds.
+    pub fn summary(&self) -> &DiffSummary<'a> {
+        &self.summary
+    }
+
+    // The worst difference, if any item had a non-zero difference.
+    pub fn worst_diff(&self) -> Option<Duration> {
+        self.summary.worst_sample().map(|_| Duration::from_secs_f64(self.summary.worst_diff()))
+    }
+
+    // Indicate whether data currently satisfies allowed tolerance.
+    pub fn is_ok(&self) -> bool {
+        self.summary.is_ok()
+    }
+
+    // Assert that worst diff is within tolerance.
+    pub fn assert(&self) {
+        assert!(self.is_ok(), "assert failed {}", self);
+    }
+}
+
+// The signed number of nanoseconds in a duration.
+fn nanos(duration: Duration) -> i128 {
+    duration.as_nanos() as i128
+}
+
+// The signed number of nanoseconds from the epoch to a timestamp.
+fn epoch_nanos(time: SystemTime) -> i128 {
+    match time.duration_since(UNIX_EPOCH) {
+        Ok(after) => nanos(after),
+        Err(before) => -nanos(before.duration()),
+    }
+}
+
+// Formats a number of seconds using the largest of ns, µs, ms, or s
+// that keeps the value at least 1, e.g. 0.0015 as "1.5 ms".
+pub struct FmtSeconds(pub f64);
+
+impl Display for FmtSeconds {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let abs = self.0.abs();
+        let (scale, unit) = if abs == 0.0 || !abs.is_finite() || abs >= 1.0 {
+            (1.0, "s")
+        } else if abs >= 1e-3 {
+            (1e3, "ms")
+        } else if abs >= 1e-6 {
+            (1e6, "µs")
+        } else {
+            (1e9, "ns")
+        };
+        let value = self.0 * scale;
+        match f.precision() {
+            Some(precision) => write!(f, "{:.*} {}", precision, value, unit),
+            // Round away binary noise from scaling, such as 1.5000000000000002.
+            None => write!(f, "{} {}", (value * 1e9).round() / 1e9, unit),
+        }
+    }
+}
+
+impl Display for DurationDiffSummary<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let summary = &self.summary;
+        write!(
+            f,
+            "{}{}count {}",
+            summary.name,
+            if !summary.name.is_empty() { ": " } else { "" },
+            summary.count()
+        )?;
+        if let Some((index, x, y, diff)) = summary.worst_sample().map(SampleRecord::as_tuple) {
+            write!(
+                f,
+                ", worst {} item {}: {} vs {}",
+                FmtSeconds(diff),
+                index,
+                FmtSeconds(x),
+                FmtSeconds(y)
+            )?;
+        }
+        write!(
+            f,
+            ", {} failed tolerance {}",
+            Percent(summary.fail_count(), summary.count(), false, PercentFormat::Whole),
+            FmtSeconds(summary.allow_diff())
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, UNIX_EPOCH};
+    use super::{DurationDiffSummary, FmtSeconds};
+
+    #[test]
+    fn test_fmt_seconds() {
+        assert_eq!(format!("{}", FmtSeconds(0.0015)), "1.5 ms");
+        assert_eq!(format!("{}", FmtSeconds(2e-8)), "20 ns");
+        assert_eq!(format!("{}", FmtSeconds(-3.5e-5)), "-35 µs");
+        assert_eq!(format!("{}", FmtSeconds(90.0)), "90 s");
+        assert_eq!(format!("{:.2}", FmtSeconds(0.0012345)), "1.23 ms");
+    }
+
+    #[test]
+    fn test_durations() {
+        let mut summary = DurationDiffSummary::new("steps", Duration::from_micros(1), 4);
+        summary.add(Duration::from_nanos(1_000_000_250), Duration::from_secs(1), 0);
+        summary.add(Duration::from_millis(5), Duration::from_micros(4998), 1);
+        assert_eq!(summary.worst_diff(), Some(Duration::from_micros(2)));
+        assert!(!summary.is_ok());
+        assert_eq!(format!("{}", summary), "steps: count 2, worst 2 µs item 1: 5 ms vs 4.998 ms, 50% failed tolerance 1 µs");
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| summary.assert()));
+        assert!(result.is_err());
+        summary.add(Duration::from_millis(2), Duration::from_millis(2), 2);
+        assert!(format!("{}", summary).ends_with(", 33% failed tolerance 1 µs"), "{}", summary);
+    }
+
+    #[test]
+    fn test_timestamps() {
+        // Nanosecond differences between large timestamps are kept exactly.
+        let reference = UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        let mut summary = DurationDiffSummary::new("clock", Duration::from_nanos(10), 4);
+        summary.add_time(reference + Duration::from_nanos(7), reference, 0);
+        summary.add_time(UNIX_EPOCH - Duration::from_nanos(3), UNIX_EPOCH + Duration::from_nanos(2), 1);
+        assert_eq!(summary.worst_diff(), Some(Duration::from_nanos(7)));
+        assert_eq!(summary.summary().sign_count(), 1);
+        assert!(summary.is_ok());
+    }
+}
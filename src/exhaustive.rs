@@ -0,0 +1,155 @@
+// Exhaustive testing of single-precision functions of one variable: with
+// only about 4 billion finite f32 inputs, a function can be compared
+// against a reference at every one of them, as for libm-style validation, e.g.
+//   let options = ExhaustiveOptions::new("sinf").with_allow_ulps(1.0);
+//   let summary = exhaustive::exhaustive_f32(&|x| my_sinf(x), &|x| (x as f64).sin() as f32, &options)?;
+//   println!("{:?}", exhaustive::worst_inputs(&summary));
+//   summary.assert();
+// Each input's index in the summary is its bit pattern, so samples such as
+// worst_sample identify the input as f32::from_bits(sample.index as u32).
+use std::fmt::Display;
+use crate::diff;
+use crate::diff_summary_f64::DiffSummary;
+
+// The settings for an exhaustive run.
+#[derive(Clone, Copy, Debug)]
+pub struct ExhaustiveOptions<'a> {
+    // The name of the resulting summary.
+    pub name: &'a str,
+
+    // The maximum allowable difference, in f32 ULPs.
+    pub allow_ulps: f64,
+
+    // Whether sign changes are allowed.
+    pub allow_sign: bool,
+
+    // The maximum number of histogram buckets to display.
+    pub bucket_count: usize,
+
+    // The number of worst-offending inputs to retain.
+    pub worst_count: usize,
+
+    // Visit only the bit patterns congruent to offset modulo stride,
+    // for a quicker run over an evenly spread subset of inputs.
+    // stride must be positive, and offset less than stride.
+    pub stride: u32,
+    pub offset: u32,
+}
+
+impl<'a> ExhaustiveOptions<'a> {
+    // Options for an exact comparison of every finite input, disallowing
+    // sign changes, retaining the 10 worst inputs.
+    pub fn new(name: &'a str) -> Self {
+        ExhaustiveOptions { name, allow_ulps: 0.0, allow_sign: false, bucket_count: 5, worst_count: 10, stride: 1, offset: 0 }
+    }
+
+    pub fn with_allow_ulps(mut self, allow_ulps: f64) -> Self {
+        self.allow_ulps = allow_ulps;
+        self
+    }
+
+    pub fn with_allow_sign(mut self, allow_sign: bool) -> Self {
+        self.allow_sign = allow_sign;
+        self
+    }
+
+    pub fn with_bucket_count(mut self, bucket_count: usize) -> Self {
+        self.bucket_count = bucket_count;
+        self
+    }
+
+    pub fn with_worst_count(mut self, worst_count: usize) -> Self {
+        self.worst_count = worst_count;
+        self
+    }
+
+    // Visit every stride-th bit pattern, starting from offset, e.g.
+    // with_stripe(1024, 0) for a quick check of about 4 million inputs.
+    // Running each offset from 0 to stride - 1 covers every input once,
+    // for example spread across processes. An invalid stripe is reported
+    // by exhaustive_f32.
+    pub fn with_stripe(mut self, stride: u32, offset: u32) -> Self {
+        self.stride = stride;
+        self.offset = offset;
+        self
+    }
+
+    // Check that the stripe is valid, since the fields can be set directly.
+    pub fn validated(self) -> Result<Self, InvalidStripe> {
+        if self.stride == 0 || self.offset >= self.stride {
+            return Err(InvalidStripe { stride: self.stride, offset: self.offset });
+        }
+        Ok(self)
+    }
+}
+
+// A stripe with a stride of 0, or an offset not less than its stride.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct InvalidStripe {
+    pub stride: u32,
+    pub offset: u32,
+}
+
+impl Display for InvalidStripe {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "stripe offset {} must be less than stride {}", self.offset, self.stride)
+    }
+}
+
+impl std::error::Error for InvalidStripe {}
+
+// Compare f against the reference g at every finite f32 input selected by
+// options, in f32 ULPs, per diff::diff_ulps_f32. Non-finite inputs are
+// skipped, though non-finite outputs are compared as usual. Returns
+// InvalidStripe, without running, if the stripe is invalid.
+pub fn exhaustive_f32<'a>(f: &dyn Fn(f32) -> f32, g: &dyn Fn(f32) -> f32, options: &ExhaustiveOptions<'a>) -> Result<DiffSummary<'a>, InvalidStripe> {
+    let options = options.validated()?;
+    let mut summary = DiffSummary::new(options.name, options.allow_ulps, options.allow_sign, options.bucket_count, &diff::diff_ulps_f32)
+        .with_top_n(options.worst_count);
+    for bits in (options.offset..=u32::MAX).step_by(options.stride as usize) {
+        let input = f32::from_bits(bits);
+        if input.is_finite() {
+            summary.add(f(input), g(input), bits as usize);
+        }
+    }
+    Ok(summary)
+}
+
+// The inputs with the worst differences in a summary from exhaustive_f32,
+// worst first, with their differences in ULPs.
+pub fn worst_inputs(summary: &DiffSummary) -> Vec<(f32, f64)> {
+    summary.top_n().iter().map(|sample| (f32::from_bits(sample.index as u32), sample.diff)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{exhaustive_f32, worst_inputs, ExhaustiveOptions, InvalidStripe};
+
+    #[test]
+    fn test_exhaustive_f32() {
+        // A reciprocal that's off by one ULP for inputs whose lowest bit is set,
+        // and exact otherwise.
+        let reference = |x: f32| 1.0 / x;
+        let approx = |x: f32| {
+            let y = 1.0 / x;
+            if x.to_bits() & 1 == 1 && y.is_normal() { f32::from_bits(y.to_bits() + 1) } else { y }
+        };
+        let options = ExhaustiveOptions::new("recip").with_stripe(1 << 20, 3).with_worst_count(2);
+        let summary = exhaustive_f32(&approx, &reference, &options).unwrap();
+        // 4096 patterns, less the 16 with all-ones exponents.
+        assert_eq!(summary.count(), 4080);
+        assert!(summary.fail_count() > 0);
+        assert_eq!(summary.worst_diff(), 1.0);
+        let worst = worst_inputs(&summary);
+        assert_eq!(worst.len(), 2);
+        assert!(worst.iter().all(|&(input, diff)| input.to_bits() % (1 << 20) == 3 && diff == 1.0));
+        assert!(exhaustive_f32(&approx, &reference, &options.with_allow_ulps(1.0)).unwrap().is_ok());
+        let even = exhaustive_f32(&approx, &reference, &options.with_stripe(1 << 20, 2)).unwrap();
+        assert_eq!((even.count(), even.fail_count()), (4080, 0));
+
+        let unstriped = ExhaustiveOptions { stride: 0, ..options };
+        let err = exhaustive_f32(&approx, &reference, &unstriped).err().unwrap();
+        assert_eq!(err.to_string(), "stripe offset 3 must be less than stride 0");
+        assert_eq!(exhaustive_f32(&approx, &reference, &options.with_stripe(4, 4)).err(), Some(InvalidStripe { stride: 4, offset: 4 }));
+    }
+}
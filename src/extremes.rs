@@ -0,0 +1,109 @@
+use std::fmt::Display;
+
+// The threshold above which magnitudes count as huge: the square root of
+// f64::MAX, above which squaring overflows.
+pub(crate) const HUGE: f64 = 1.3407807929942596e154;
+
+// The range of expected (y) magnitudes seen, for checking that a test set
+// exercises the dynamic range it's meant to.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Extremes {
+    // The smallest non-zero and largest finite |y|, or nan if there were none.
+    min_abs: f64,
+    max_abs: f64,
+    // The number of subnormal y values.
+    num_subnormal: usize,
+    // The number of finite y values with magnitudes above sqrt(f64::MAX).
+    num_huge: usize,
+}
+
+impl Default for Extremes {
+    fn default() -> Self {
+        Extremes { min_abs: f64::NAN, max_abs: f64::NAN, num_subnormal: 0, num_huge: 0 }
+    }
+}
+
+impl Extremes {
+    pub fn new() -> Self {
+        Extremes::default()
+    }
+
+    pub fn add(&mut self, y: f64) {
+        let y = y.abs();
+        if !y.is_finite() {
+            return;
+        }
+        // f64::min and max ignore the initial nans.
+        if y != 0.0 {
+            self.min_abs = self.min_abs.min(y);
+        }
+        self.max_abs = self.max_abs.max(y);
+        if y.is_subnormal() {
+            self.num_subnormal += 1;
+        } else if y > HUGE {
+            self.num_huge += 1;
+        }
+    }
+
+    // Combine another set of extremes into this one.
+    pub fn merge(&mut self, other: &Extremes) {
+        self.min_abs = self.min_abs.min(other.min_abs);
+        self.max_abs = self.max_abs.max(other.max_abs);
+        self.num_subnormal += other.num_subnormal;
+        self.num_huge += other.num_huge;
+    }
+
+    // The smallest non-zero finite |y|, or nan if there were none.
+    pub fn min_abs(&self) -> f64 {
+        self.min_abs
+    }
+
+    // The largest finite |y|, or nan if there were none.
+    pub fn max_abs(&self) -> f64 {
+        self.max_abs
+    }
+
+    // The number of subnormal y values.
+    pub fn subnormal_count(&self) -> usize {
+        self.num_subnormal
+    }
+
+    // The number of y values too large to square without overflow.
+    pub fn huge_count(&self) -> usize {
+        self.num_huge
+    }
+}
+
+// Shows the range and counts, e.g.
+//   expected magnitudes 1e-300 to 2.5e3 subnormal 0 huge 0
+impl Display for Extremes {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "expected magnitudes {:e} to {:e} subnormal {} huge {}",
+            self.min_abs, self.max_abs, self.num_subnormal, self.num_huge
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Extremes;
+
+    #[test]
+    fn test_extremes() {
+        let mut extremes = Extremes::new();
+        assert!(extremes.min_abs().is_nan() && extremes.max_abs().is_nan());
+        for &y in [0.0, -2.5e3, 1e-300, f64::INFINITY, f64::NAN].iter() {
+            extremes.add(y);
+        }
+        let mut other = Extremes::new();
+        other.add(5e-324);
+        other.add(-1e200);
+        other.merge(&Extremes::new());
+        extremes.merge(&other);
+        assert_eq!((extremes.min_abs(), extremes.max_abs()), (5e-324, 1e200));
+        assert_eq!((extremes.subnormal_count(), extremes.huge_count()), (1, 1));
+        assert_eq!(extremes.to_string(), "expected magnitudes 5e-324 to 1e200 subnormal 1 huge 1");
+    }
+}
@@ -0,0 +1,185 @@
+// A C interface to DiffSummary, for test harnesses in other languages.
+// include/float_diff.h declares these functions. To link from C or C++,
+// build a static or dynamic library, e.g.
+//   cargo rustc --release --features ffi --crate-type staticlib
+// Summaries are created with float_diff_summary_new and must be released
+// with float_diff_summary_free. Report strings are returned as
+// NUL-terminated UTF-8, and must be released with float_diff_string_free.
+// Every pointer argument must be null or valid, as described per function.
+// A panic, such as from an invalid bucket_count, never unwinds into the
+// caller. It's caught, and reported by a null pointer or FLOAT_DIFF_PANICKED.
+#![allow(clippy::missing_safety_doc)]
+use std::ffi::{CStr, CString};
+use std::os::raw::{c_char, c_int};
+use std::panic::{self, AssertUnwindSafe};
+use std::ptr;
+use crate::diff;
+use crate::diff_summary_f64::DiffSummary;
+
+// The comparator used to calculate differences, per the diff module's diff_* functions.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FloatDiffComparator {
+    Abs = 0,
+    Rel = 1,
+    Ulps = 2,
+    Lesser = 3,
+    Log = 4,
+    Percent = 5,
+    TotalOrder = 6,
+}
+
+impl FloatDiffComparator {
+    fn calc_diff(self) -> &'static dyn Fn(f64, f64) -> (f64, bool) {
+        match self {
+            FloatDiffComparator::Abs => &diff::diff_abs,
+            FloatDiffComparator::Rel => &diff::diff_rel,
+            FloatDiffComparator::Ulps => &diff::diff_ulps,
+            FloatDiffComparator::Lesser => &diff::diff_lesser,
+            FloatDiffComparator::Log => &diff::diff_log,
+            FloatDiffComparator::Percent => &diff::diff_percent,
+            FloatDiffComparator::TotalOrder => &diff::diff_total_order,
+        }
+    }
+}
+
+// The status returned by functions that succeeded.
+pub const FLOAT_DIFF_OK: c_int = 0;
+
+// The status returned by functions that panicked. The summary may have been
+// left partially updated, but remains safe to release.
+pub const FLOAT_DIFF_PANICKED: c_int = -1;
+
+// Run body, returning on_panic instead if it panics, so that no panic
+// unwinds across the C interface.
+fn guard<T>(on_panic: T, body: impl FnOnce() -> T) -> T {
+    panic::catch_unwind(AssertUnwindSafe(body)).unwrap_or(on_panic)
+}
+
+// An opaque handle to a summary and the name it owns.
+pub struct FloatDiffSummary {
+    summary: DiffSummary<'static>,
+    // Leaked by float_diff_summary_new, and reclaimed by float_diff_summary_free.
+    name: *mut str,
+}
+
+// Create a summary. name must be null or a NUL-terminated string, and is
+// copied, with invalid UTF-8 replaced. A null name is treated as "".
+// Returns null if the settings are invalid, such as a bucket_count of 2 or less.
+#[no_mangle]
+pub unsafe extern "C" fn float_diff_summary_new(
+    name: *const c_char,
+    comparator: FloatDiffComparator,
+    allow_diff: f64,
+    allow_sign: bool,
+    bucket_count: usize,
+) -> *mut FloatDiffSummary {
+    let name = if name.is_null() { String::new() } else { CStr::from_ptr(name).to_string_lossy().into_owned() };
+    let name: &'static mut str = Box::leak(name.into_boxed_str());
+    let name_ptr: *mut str = name;
+    let summary = guard(None, || Some(DiffSummary::new(&*name_ptr, allow_diff, allow_sign, bucket_count, comparator.calc_diff())));
+    match summary {
+        Some(summary) => Box::into_raw(Box::new(FloatDiffSummary { summary, name: name_ptr })),
+        None => {
+            drop(Box::from_raw(name_ptr));
+            ptr::null_mut()
+        }
+    }
+}
+
+// Release a summary. summary must be null or from float_diff_summary_new,
+// and not already released.
+#[no_mangle]
+pub unsafe extern "C" fn float_diff_summary_free(summary: *mut FloatDiffSummary) {
+    if !summary.is_null() {
+        let handle = Box::from_raw(summary);
+        let name = handle.name;
+        guard((), || drop(handle));
+        drop(Box::from_raw(name));
+    }
+}
+
+// Add an item, as DiffSummary::add does, returning FLOAT_DIFF_OK or
+// FLOAT_DIFF_PANICKED. summary must be valid.
+#[no_mangle]
+pub unsafe extern "C" fn float_diff_summary_add(summary: *mut FloatDiffSummary, x: f64, y: f64, index: usize) -> c_int {
+    guard(FLOAT_DIFF_PANICKED, || {
+        (*summary).summary.add(x, y, index);
+        FLOAT_DIFF_OK
+    })
+}
+
+// Add len items from a pair of arrays, as DiffSummary::add_slices does.
+// summary must be valid, and xs and ys must each hold len values.
+// Either array may be null if len is 0. Returns FLOAT_DIFF_OK or FLOAT_DIFF_PANICKED.
+#[no_mangle]
+pub unsafe extern "C" fn float_diff_summary_add_slices(summary: *mut FloatDiffSummary, xs: *const f64, ys: *const f64, len: usize) -> c_int {
+    guard(FLOAT_DIFF_PANICKED, || {
+        if len > 0 {
+            let (xs, ys) = (std::slice::from_raw_parts(xs, len), std::slice::from_raw_parts(ys, len));
+            (*summary).summary.add_slices(xs, ys);
+        }
+        FLOAT_DIFF_OK
+    })
+}
+
+// Indicate whether the summary's items are ok, as DiffSummary::is_ok does,
+// returning 1 if so, 0 if not, or FLOAT_DIFF_PANICKED. summary must be valid.
+#[no_mangle]
+pub unsafe extern "C" fn float_diff_summary_is_ok(summary: *const FloatDiffSummary) -> c_int {
+    guard(FLOAT_DIFF_PANICKED, || c_int::from((*summary).summary.is_ok()))
+}
+
+// The summary's Display output. summary must be valid. The result must be
+// released with float_diff_string_free. Returns null if formatting panicked.
+#[no_mangle]
+pub unsafe extern "C" fn float_diff_summary_report(summary: *const FloatDiffSummary) -> *mut c_char {
+    // Display output never contains NUL, short of a summary name containing one, which C can't pass.
+    guard(ptr::null_mut(), || CString::new((*summary).summary.to_string()).map_or(ptr::null_mut(), CString::into_raw))
+}
+
+// Release a string from float_diff_summary_report. text must be null or
+// from float_diff_summary_report, and not already released.
+#[no_mangle]
+pub unsafe extern "C" fn float_diff_string_free(text: *mut c_char) {
+    if !text.is_null() {
+        drop(CString::from_raw(text));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ffi() {
+        unsafe {
+            let name = CString::new("c side").unwrap();
+            let summary = float_diff_summary_new(name.as_ptr(), FloatDiffComparator::Rel, 0.1, false, 4);
+            assert_eq!(float_diff_summary_add(summary, 1.0, 1.05, 0), FLOAT_DIFF_OK);
+            assert_eq!(float_diff_summary_is_ok(summary), 1);
+            let xs = [2.0, 3.0];
+            let ys = [2.0, 4.0];
+            assert_eq!(float_diff_summary_add_slices(summary, xs.as_ptr(), ys.as_ptr(), 2), FLOAT_DIFF_OK);
+            assert_eq!(float_diff_summary_add_slices(summary, ptr::null(), ptr::null(), 0), FLOAT_DIFF_OK);
+            assert_eq!(float_diff_summary_is_ok(summary), 0);
+            assert_eq!((*summary).summary.count(), 3);
+
+            let text = float_diff_summary_report(summary);
+            assert_eq!(CStr::from_ptr(text).to_str().unwrap(), (*summary).summary.to_string());
+            assert!(CStr::from_ptr(text).to_str().unwrap().starts_with("c side"));
+            float_diff_string_free(text);
+            float_diff_summary_free(summary);
+
+            let unnamed = float_diff_summary_new(ptr::null(), FloatDiffComparator::Abs, 0.0, false, 4);
+            assert_eq!((*unnamed).summary.name, "");
+            float_diff_summary_free(unnamed);
+            float_diff_summary_free(ptr::null_mut());
+            float_diff_string_free(ptr::null_mut());
+
+            // Panics are caught rather than unwinding into C.
+            assert!(float_diff_summary_new(name.as_ptr(), FloatDiffComparator::Abs, 0.0, false, 2).is_null());
+            assert_eq!(guard(FLOAT_DIFF_PANICKED, || panic!("unwinding")), FLOAT_DIFF_PANICKED);
+        }
+    }
+}
@@ -0,0 +1,78 @@
+use std::fmt::Display;
+use crate::util::mix64;
+
+// An identifier for the dataset a summary compared against, so that
+// comparisons between runs can refuse to match results from different data.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Fingerprint {
+    // The number of expected (y) values, and a hash of each value's bits
+    // combined with its index. The hash is independent of the order items
+    // are added in, so summaries of split datasets merge to the same
+    // fingerprint as a summary of the whole.
+    Values { count: usize, hash: u64 },
+    // A user-supplied dataset identifier, such as a file name and version.
+    Id(String),
+}
+
+impl Fingerprint {
+    // A fingerprint of no values, to be extended with add.
+    pub fn values() -> Self {
+        Fingerprint::Values { count: 0, hash: 0 }
+    }
+
+    // Include an expected value at the given index. Does nothing for Id.
+    pub fn add(&mut self, index: usize, y: f64) {
+        if let Fingerprint::Values { count, hash } = self {
+            *count += 1;
+            *hash = hash.wrapping_add(mix64(mix64(index as u64) ^ y.to_bits()));
+        }
+    }
+
+    // Combine another fingerprint of the same kind into this one.
+    // Id fingerprints must be equal.
+    pub fn merge(&mut self, other: &Fingerprint) {
+        match (self, other) {
+            (Fingerprint::Values { count, hash }, Fingerprint::Values { count: other_count, hash: other_hash }) => {
+                *count += other_count;
+                *hash = hash.wrapping_add(*other_hash);
+            }
+            (this, other) => assert!(this == other, "Cannot merge fingerprints {} and {}", this, other),
+        }
+    }
+}
+
+// Shows the count and hash, e.g. "1000 values #3f2a9c0d1e5b7a64", or the id.
+impl Display for Fingerprint {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Fingerprint::Values { count, hash } => write!(f, "{} values #{:016x}", count, hash),
+            Fingerprint::Id(id) => write!(f, "{}", id),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Fingerprint;
+
+    #[test]
+    fn test_fingerprint() {
+        let (mut whole, mut first, mut second) = (Fingerprint::values(), Fingerprint::values(), Fingerprint::values());
+        for i in 0..10 {
+            whole.add(i, i as f64);
+            if i < 4 { &mut first } else { &mut second }.add(i, i as f64);
+        }
+        second.merge(&first);
+        assert_eq!(second, whole);
+        let mut swapped = Fingerprint::values();
+        for i in 0..10 {
+            swapped.add(9 - i, i as f64);
+        }
+        assert_ne!(swapped, whole);
+        let mut id = Fingerprint::Id("grid v2".to_string());
+        id.add(0, 1.0);
+        id.merge(&Fingerprint::Id("grid v2".to_string()));
+        assert_eq!(id.to_string(), "grid v2");
+        assert!(whole.to_string().starts_with("10 values #"));
+    }
+}
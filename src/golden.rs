@@ -0,0 +1,159 @@
+// Golden file support: record computed values to a file on a first run,
+// then compare later runs' values against the recorded ones through a
+// DiffSummary, so tolerances are applied as for any other comparison, e.g.
+//   let mut summary = DiffSummary64::new("solver", 1e-12, false, 5, &diff::diff_rel);
+//   golden::update("tests/golden/solver.txt", &values, &mut summary).unwrap();
+//   summary.assert();
+// Setting the FLOAT_DIFF_UPDATE_GOLDENS environment variable to anything
+// other than "" or "0" makes update re-record goldens rather than compare.
+// Golden files are text, with one value per line in shortest round-trip
+// scientific notation. Blank lines and lines starting with # are ignored.
+// Golden files with binary::EXTENSION are instead written in the binary
+// module's format, a header, the value count, then each value's bits.
+use std::fs;
+use std::io;
+use std::path::Path;
+use crate::binary::{self, Reader, Writer};
+use crate::diff_summary_f64::DiffSummary;
+use crate::input_error::DiffInputError;
+
+// The environment variable that requests updating goldens.
+pub const UPDATE_ENV: &str = "FLOAT_DIFF_UPDATE_GOLDENS";
+
+// What update did with a golden file.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GoldenAction {
+    // The golden file was written, because it didn't exist or updating was requested.
+    Recorded,
+    // The values were compared against the existing golden file.
+    Compared,
+}
+
+// Indicate whether the environment requests updating goldens.
+pub fn update_requested() -> bool {
+    match std::env::var_os(UPDATE_ENV) {
+        Some(value) => !value.is_empty() && value != "0",
+        None => false,
+    }
+}
+
+// Write values to a golden file, replacing any existing file and creating
+// parent directories as needed.
+pub fn record<P: AsRef<Path>>(path: P, values: &[f64]) -> io::Result<()> {
+    let path = path.as_ref();
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    if binary::has_extension(path) {
+        let mut writer = Writer::with_header();
+        writer.usize(values.len());
+        for &value in values {
+            writer.f64(value);
+        }
+        return fs::write(path, writer.bytes);
+    }
+    let mut text = format!("# float-diff golden, {} values\n", values.len());
+    for value in values {
+        text.push_str(&format!("{:e}\n", value));
+    }
+    fs::write(path, text)
+}
+
+// Read the values from a text or binary golden file.
+pub fn load<P: AsRef<Path>>(path: P) -> Result<Vec<f64>, DiffInputError> {
+    let bytes = fs::read(path.as_ref())?;
+    if binary::is_binary(&bytes) {
+        let source = path.as_ref().display().to_string();
+        let mut reader = Reader::new(&bytes, &source)?;
+        let count = reader.usize()?;
+        let values = (0..count).map(|_| reader.f64()).collect::<Result<Vec<f64>, _>>()?;
+        reader.finish()?;
+        return Ok(values);
+    }
+    let text = String::from_utf8(bytes).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+    let mut values = Vec::new();
+    for (line_index, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let value = line.parse().map_err(|_| DiffInputError::Parse {
+            source: path.as_ref().display().to_string(),
+            line: line_index + 1,
+            text: line.to_string(),
+        })?;
+        values.push(value);
+    }
+    Ok(values)
+}
+
+// Add each computed value to summary, as x, with the corresponding golden
+// value as y and each value's position as its index.
+// Returns DiffInputError::LengthMismatch, without adding any values, if
+// the number of values differs from the golden file's.
+pub fn compare<P: AsRef<Path>>(path: P, values: &[f64], summary: &mut DiffSummary) -> Result<(), DiffInputError> {
+    let golden = load(path)?;
+    summary.try_add_slices(values, &golden)
+}
+
+// Record values if the golden file doesn't exist yet or updating is
+// requested via UPDATE_ENV, otherwise compare them as compare does.
+pub fn update<P: AsRef<Path>>(path: P, values: &[f64], summary: &mut DiffSummary) -> Result<GoldenAction, DiffInputError> {
+    update_if(path.as_ref(), values, summary, update_requested())
+}
+
+fn update_if(path: &Path, values: &[f64], summary: &mut DiffSummary, force: bool) -> Result<GoldenAction, DiffInputError> {
+    if force || !path.exists() {
+        record(path, values)?;
+        Ok(GoldenAction::Recorded)
+    } else {
+        compare(path, values, summary)?;
+        Ok(GoldenAction::Compared)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{load, update_if, GoldenAction};
+    use crate::diff;
+    use crate::diff_summary_f64::DiffSummary;
+    use crate::input_error::DiffInputError;
+
+    #[test]
+    fn test_golden() {
+        let dir = std::env::temp_dir().join(format!("float-diff-golden-{}", std::process::id()));
+        let path = dir.join("values.txt");
+        let values = [0.1, -2.5e-300, 1.0 / 3.0, f64::INFINITY];
+        let mut summary = DiffSummary::new("golden", 1e-9, false, 4, &diff::diff_rel);
+        assert_eq!(update_if(&path, &values, &mut summary, false).unwrap(), GoldenAction::Recorded);
+        assert_eq!(summary.count(), 0);
+        assert_eq!(load(&path).unwrap(), values);
+
+        let changed = [0.1, -2.5e-300, 0.3334, f64::INFINITY];
+        assert_eq!(update_if(&path, &changed, &mut summary, false).unwrap(), GoldenAction::Compared);
+        assert_eq!(summary.count(), 4);
+        assert_eq!(summary.fail_count(), 1);
+        assert_eq!(summary.worst_sample().unwrap().index, 2);
+
+        assert_eq!(update_if(&path, &changed, &mut summary, true).unwrap(), GoldenAction::Recorded);
+        assert_eq!(load(&path).unwrap()[2], 0.3334);
+
+        let binary_path = dir.join("values.fdb");
+        assert_eq!(update_if(&binary_path, &values, &mut summary, false).unwrap(), GoldenAction::Recorded);
+        assert!(crate::binary::is_binary(&std::fs::read(&binary_path).unwrap()));
+        assert_eq!(load(&binary_path).unwrap(), values);
+        std::fs::write(&binary_path, &std::fs::read(&binary_path).unwrap()[..20]).unwrap();
+        assert!(matches!(load(&binary_path).unwrap_err(), DiffInputError::Binary { offset: 14, .. }));
+
+        std::fs::write(&path, "1.5\n\n# note\nbogus\n").unwrap();
+        let err = load(&path).unwrap_err();
+        assert!(err.to_string().ends_with("line 4: invalid value \"bogus\""), "{}", err);
+        assert!(matches!(err, DiffInputError::Parse { line: 4, .. }));
+
+        std::fs::write(&path, "1.5\n").unwrap();
+        let err = update_if(&path, &changed, &mut summary, false).unwrap_err();
+        assert_eq!(err.to_string(), "golden: length mismatch, 4 x values vs 1 y values");
+        assert_eq!(summary.count(), 4);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
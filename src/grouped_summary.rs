@@ -0,0 +1,147 @@
+use std::collections::BTreeMap;
+use std::fmt::Display;
+use crate::diff_summary_f64::DiffSummary;
+use crate::registry::RegistryEntry;
+
+// An object for breaking down comparison statistics by a group key,
+// such as a solver configuration or input category.
+// A sub-summary is created for each new key as a copy of a template summary,
+// so all groups share the same tolerance, comparator and options.
+pub struct GroupedDiffSummary<'a, K> {
+    // The name of this summary.
+    pub name: &'a str,
+
+    // The summary copied for each new group.
+    template: DiffSummary<'a>,
+
+    // One summary per group key.
+    groups: BTreeMap<K, DiffSummary<'a>>,
+}
+
+impl<'a, K: Ord + Clone> GroupedDiffSummary<'a, K> {
+    // Create a grouped summary, using template as the configuration for each group.
+    // The template must not have had any items added.
+    pub fn new(name: &'a str, template: DiffSummary<'a>) -> Self {
+        assert_eq!(template.count(), 0, "GroupedDiffSummary template must be empty");
+        GroupedDiffSummary {
+            name,
+            template,
+            groups: BTreeMap::new(),
+        }
+    }
+
+    // Add an item to the summary for the given group, creating it if needed.
+    pub fn add(&mut self, group: K, x: f64, y: f64, index: usize) {
+        let template = &self.template;
+        self.groups.entry(group).or_insert_with(|| template.clone()).add(x, y, index);
+    }
+
+    // The summary for the given group, if any items have been added to it.
+    pub fn group(&self, group: &K) -> Option<&DiffSummary<'a>> {
+        self.groups.get(group)
+    }
+
+    // The number of groups.
+    pub fn group_count(&self) -> usize {
+        self.groups.len()
+    }
+
+    // The total number of items added across all groups.
+    pub fn count(&self) -> usize {
+        self.groups.values().map(|summary| summary.count()).sum()
+    }
+
+    // A summary combining all groups.
+    pub fn combined(&self) -> DiffSummary<'a> {
+        let mut combined = self.template.clone();
+        for summary in self.groups.values() {
+            combined.merge(summary);
+        }
+        combined
+    }
+
+    // The groups and their summaries, ordered from highest to lowest failure rate.
+    // Groups with equal failure rates are ordered by key.
+    pub fn by_fail_rate(&self) -> Vec<(&K, &DiffSummary<'a>)> {
+        let mut sorted: Vec<(&K, &DiffSummary<'a>)> = self.groups.iter().collect();
+        // The sort is stable, so equal rates keep key order.
+        sorted.sort_by(|a, b| b.1.fail_percent().partial_cmp(&a.1.fail_percent()).unwrap());
+        sorted
+    }
+
+    // Indicate whether every group satisfies tolerance and sign change acceptance.
+    pub fn is_ok(&self) -> bool {
+        self.groups.values().all(|summary| summary.is_ok())
+    }
+}
+
+impl<'a, K: Ord + Clone + Display> GroupedDiffSummary<'a, K> {
+    // A snapshot of each group's results, named "name[group]", in key order,
+    // for registering or appending to trend files per run.
+    pub fn snapshots(&self) -> Vec<RegistryEntry> {
+        self.groups
+            .iter()
+            .map(|(group, summary)| {
+                let mut entry = RegistryEntry { name: format!("{}[{}]", self.name, group), ..RegistryEntry::new(summary) };
+                entry.summary = entry.line();
+                entry
+            })
+            .collect()
+    }
+
+    // Assert that every group is ok, reporting all groups on failure.
+    pub fn assert(&self) {
+        assert!(self.is_ok(), "assert failed {}", self);
+    }
+}
+
+impl<K: Ord + Clone + Display> Display for GroupedDiffSummary<'_, K> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let num_failed = self.groups.values().filter(|summary| !summary.is_ok()).count();
+        write!(
+            f,
+            "{}{}groups {}, failed groups {}",
+            self.name,
+            if !self.name.is_empty() { ": " } else { "" },
+            self.groups.len(),
+            num_failed
+        )?;
+        for (group, summary) in self.by_fail_rate() {
+            write!(f, "\n  [{}] {}", group, summary)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::GroupedDiffSummary;
+    use crate::diff;
+    use crate::diff_summary_f64::DiffSummary;
+
+    #[test]
+    fn test_grouped() {
+        let template = DiffSummary::new("solver", 0.1, false, 4, &diff::diff_abs);
+        let mut grouped = GroupedDiffSummary::new("configs", template);
+        grouped.add("fast", 1.0, 1.5, 0);
+        grouped.add("fast", 2.0, 2.0, 1);
+        grouped.add("exact", 1.0, 1.0, 2);
+        grouped.add("exact", 2.0, 2.0, 3);
+        grouped.add("rough", 1.0, 2.0, 4);
+        assert_eq!(grouped.group_count(), 3);
+        assert_eq!(grouped.count(), 5);
+        assert_eq!(grouped.group(&"fast").unwrap().fail_count(), 1);
+        assert!(grouped.group(&"other").is_none());
+        let order: Vec<&str> = grouped.by_fail_rate().iter().map(|(key, _)| **key).collect();
+        assert_eq!(order, vec!["rough", "fast", "exact"]);
+        let combined = grouped.combined();
+        assert_eq!(combined.count(), 5);
+        assert_eq!(combined.fail_count(), 2);
+        assert_eq!(combined.worst_diff(), 1.0);
+        assert!(!grouped.is_ok());
+        assert!(format!("{}", grouped).starts_with("configs: groups 3, failed groups 2\n  [rough] solver: "));
+        let snapshots = grouped.snapshots();
+        assert_eq!(snapshots.len(), 3);
+        assert_eq!(snapshots[0].summary, "configs[exact]: count 2, fail 0, sign 0, worst diff 0e0, allow 1e-1");
+    }
+}
@@ -0,0 +1,284 @@
+use num_traits::ToPrimitive;
+use std::collections::HashMap;
+use std::fmt::Display;
+use crate::diff_summary_f64::DiffSummary;
+use crate::input_error::DiffInputError;
+use crate::util::{self, FloatFormat, FmtF64, Percent, PercentFormat};
+
+// How one case's error changed between two versions of a computation.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Improvement {
+    Improved,
+    Regressed,
+    Unchanged,
+}
+
+// A second-order comparison: for each case, the error of an old version
+// (a) and of a new version (b) against the same reference, summarized by
+// the signed change in error, b's minus a's, for seeing per case whether
+// tuning helped.
+// Changes within negligible either way count as unchanged. A case whose
+// comparison becomes nan has regressed, and one that stops being nan has
+// improved. Otherwise a case with an infinite error is classified by
+// comparing the errors. Cases whose change isn't finite, such as those
+// involving a nan or infinite error, are left out of the change statistics
+// (mean, best and worst).
+#[derive(Clone)]
+pub struct ImprovementSummary<'a> {
+    // The name of this summary.
+    pub name: &'a str,
+
+    negligible: f64,
+    calc_diff: &'a dyn Fn(f64, f64) -> (f64, bool),
+    num_total: usize,
+    num_improved: usize,
+    num_regressed: usize,
+
+    // The count and sum of finite changes.
+    num_finite: usize,
+    sum_change: f64,
+
+    // The (index, change) of the most negative and most positive changes.
+    best: Option<(usize, f64)>,
+    worst: Option<(usize, f64)>,
+}
+
+impl<'a> ImprovementSummary<'a> {
+    // Create a summary computing each version's error with calc_diff, such
+    // as diff::diff_rel, and ignoring changes of at most negligible.
+    pub fn new(name: &'a str, negligible: f64, calc_diff: &'a dyn Fn(f64, f64) -> (f64, bool)) -> Self {
+        assert!(negligible >= 0.0, "negligible change must be non-negative");
+        ImprovementSummary {
+            name,
+            negligible,
+            calc_diff,
+            num_total: 0,
+            num_improved: 0,
+            num_regressed: 0,
+            num_finite: 0,
+            sum_change: 0.0,
+            best: None,
+            worst: None,
+        }
+    }
+
+    // Compare the summaries of the old (before) and new (after) versions of
+    // a computation, case by case, pairing their retained items by index.
+    // Each version's error is recomputed with its own summary's difference
+    // function and options, so the summaries should share them.
+    // Returns an error if the summaries retained different numbers of items,
+    // or an index of after has no counterpart in before. Panics if either
+    // summary lacks with_raw_retention.
+    pub fn from_summaries(name: &'a str, negligible: f64, before: &DiffSummary<'a>, after: &DiffSummary<'a>) -> Result<Self, DiffInputError> {
+        let items_a = before.retained_items().expect("from_summaries requires with_raw_retention");
+        let items_b = after.retained_items().expect("from_summaries requires with_raw_retention");
+        if items_a.len() != items_b.len() {
+            return Err(DiffInputError::LengthMismatch { name: name.to_string(), x_len: items_a.len(), y_len: items_b.len() });
+        }
+        let errors_a: HashMap<usize, f64> = items_a.iter().map(|&(index, x, y)| (index, before.calc(x, y).0)).collect();
+        let mut summary = ImprovementSummary::new(name, negligible, before.calc_diff());
+        for &(index, x, y) in items_b {
+            let err_a = *errors_a.get(&index).ok_or_else(|| DiffInputError::UnmatchedIndex { name: name.to_string(), index })?;
+            summary.add_errors(err_a, after.calc(x, y).0, index);
+        }
+        Ok(summary)
+    }
+
+    // Add a case, given the old (a) and new (b) computed values and the
+    // reference they're both compared against.
+    pub fn add<A: ToPrimitive, B: ToPrimitive, R: ToPrimitive>(&mut self, a: A, b: B, reference: R, index: usize) -> Improvement {
+        let reference = util::to_f64(reference);
+        let err_a = (*self.calc_diff)(util::to_f64(a), reference).0;
+        let err_b = (*self.calc_diff)(util::to_f64(b), reference).0;
+        self.add_errors(err_a, err_b, index)
+    }
+
+    // Add a case given its already computed old and new errors.
+    #[allow(clippy::unnecessary_map_or)]
+    pub fn add_errors(&mut self, err_a: f64, err_b: f64, index: usize) -> Improvement {
+        self.num_total += 1;
+        let change = err_b - err_a;
+        let improvement = match (err_a.is_nan(), err_b.is_nan()) {
+            (true, true) => Improvement::Unchanged,
+            (true, false) => Improvement::Improved,
+            (false, true) => Improvement::Regressed,
+            // Such as inf - inf, or a change to or from an infinite error.
+            (false, false) if !change.is_finite() => match err_b.partial_cmp(&err_a) {
+                Some(std::cmp::Ordering::Less) => Improvement::Improved,
+                Some(std::cmp::Ordering::Greater) => Improvement::Regressed,
+                _ => Improvement::Unchanged,
+            },
+            (false, false) => {
+                self.num_finite += 1;
+                self.sum_change += change;
+                if self.best.map_or(true, |(_, best)| change < best) {
+                    self.best = Some((index, change));
+                }
+                if self.worst.map_or(true, |(_, worst)| change > worst) {
+                    self.worst = Some((index, change));
+                }
+                if change < -self.negligible {
+                    Improvement::Improved
+                } else if change > self.negligible {
+                    Improvement::Regressed
+                } else {
+                    Improvement::Unchanged
+                }
+            }
+        };
+        match improvement {
+            Improvement::Improved => self.num_improved += 1,
+            Improvement::Regressed => self.num_regressed += 1,
+            Improvement::Unchanged => (),
+        }
+        improvement
+    }
+
+    // Combine another summary's cases into this one.
+    #[allow(clippy::unnecessary_map_or)]
+    pub fn merge(&mut self, other: &ImprovementSummary) {
+        self.num_total += other.num_total;
+        self.num_improved += other.num_improved;
+        self.num_regressed += other.num_regressed;
+        self.num_finite += other.num_finite;
+        self.sum_change += other.sum_change;
+        if let Some((index, change)) = other.best {
+            if self.best.map_or(true, |(_, best)| change < best) {
+                self.best = Some((index, change));
+            }
+        }
+        if let Some((index, change)) = other.worst {
+            if self.worst.map_or(true, |(_, worst)| change > worst) {
+                self.worst = Some((index, change));
+            }
+        }
+    }
+
+    // The number of cases added.
+    pub fn count(&self) -> usize {
+        self.num_total
+    }
+
+    pub fn improved_count(&self) -> usize {
+        self.num_improved
+    }
+
+    pub fn regressed_count(&self) -> usize {
+        self.num_regressed
+    }
+
+    pub fn unchanged_count(&self) -> usize {
+        self.num_total - self.num_improved - self.num_regressed
+    }
+
+    // The mean change in error, negative for an overall improvement, or nan
+    // if no case had a finite change.
+    pub fn mean_change(&self) -> f64 {
+        self.sum_change / self.num_finite as f64
+    }
+
+    // The (index, change) of the case whose error dropped most, if any.
+    pub fn best_improvement(&self) -> Option<(usize, f64)> {
+        self.best
+    }
+
+    // The (index, change) of the case whose error grew most, if any.
+    pub fn worst_regression(&self) -> Option<(usize, f64)> {
+        self.worst
+    }
+
+    // Whether any case regressed by more than negligible.
+    pub fn is_regression(&self) -> bool {
+        self.num_regressed > 0
+    }
+}
+
+// Shows counts by outcome, then the change statistics, e.g.
+//   tuning: count 5, improved 2 (40%) regressed 1 (20%) unchanged 2 (40%), mean change -1e-3, best index 3 -4e-3, worst index 1 2e-3
+impl Display for ImprovementSummary<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let percent = |count: usize| Percent(count, self.num_total, true, PercentFormat::Whole);
+        write!(f, "{}{}count {}", self.name, if !self.name.is_empty() { ": " } else { "" }, self.num_total)?;
+        if self.num_total == 0 {
+            return Ok(());
+        }
+        write!(
+            f,
+            ", improved {} regressed {} unchanged {}",
+            percent(self.num_improved),
+            percent(self.num_regressed),
+            percent(self.unchanged_count())
+        )?;
+        if self.num_finite > 0 {
+            write!(f, ", mean change {}", FmtF64(self.mean_change(), FloatFormat::Exp))?;
+        }
+        if let (Some((best_index, best)), Some((worst_index, worst))) = (self.best, self.worst) {
+            write!(
+                f,
+                ", best index {} {} worst index {} {}",
+                best_index,
+                FmtF64(best, FloatFormat::Exp),
+                worst_index,
+                FmtF64(worst, FloatFormat::Exp)
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Improvement, ImprovementSummary};
+    use crate::diff;
+    use crate::diff_summary_f64::DiffSummary;
+    use crate::input_error::DiffInputError;
+
+    #[test]
+    fn test_improvement() {
+        let mut summary = ImprovementSummary::new("tuning", 1e-3, &diff::diff_abs);
+        assert_eq!(summary.add(1.5, 1.25, 1.0, 0), Improvement::Improved);
+        assert_eq!(summary.add(2.0, 2.5, 2.0, 1), Improvement::Regressed);
+        assert_eq!(summary.add(3.0, 3.0005, 3.0, 2), Improvement::Unchanged);
+        assert_eq!(summary.add(f64::NAN, 4.0, 4.0, 3), Improvement::Improved);
+        let mut other = ImprovementSummary::new("tuning", 1e-3, &diff::diff_abs);
+        assert_eq!(other.add_errors(0.0, f64::NAN, 4), Improvement::Regressed);
+        summary.merge(&other);
+        assert_eq!((summary.count(), summary.improved_count(), summary.regressed_count(), summary.unchanged_count()), (5, 2, 2, 1));
+        assert_eq!(summary.best_improvement(), Some((0, -0.25)));
+        assert_eq!(summary.worst_regression(), Some((1, 0.5)));
+        assert!(summary.is_regression());
+        assert_eq!(
+            summary.to_string(),
+            "tuning: count 5, improved 2 (40%) regressed 2 (40%) unchanged 1 (20%), \
+                mean change 8.350000000000006e-2, best index 0 -2.5e-1 worst index 1 5e-1"
+        );
+        assert_eq!(ImprovementSummary::new("", 0.0, &diff::diff_abs).to_string(), "count 0");
+
+        let mut infinite = ImprovementSummary::new("infinite", 0.0, &diff::diff_abs);
+        assert_eq!(infinite.add_errors(f64::INFINITY, f64::INFINITY, 0), Improvement::Unchanged);
+        assert_eq!(infinite.add_errors(f64::INFINITY, 1.0, 1), Improvement::Improved);
+        assert_eq!(infinite.add_errors(1.0, f64::INFINITY, 2), Improvement::Regressed);
+        assert_eq!(infinite.add_errors(1.0, 0.5, 3), Improvement::Improved);
+        assert_eq!((infinite.improved_count(), infinite.regressed_count(), infinite.unchanged_count()), (2, 1, 1));
+        assert_eq!((infinite.mean_change(), infinite.best_improvement(), infinite.worst_regression()), (-0.5, Some((3, -0.5)), Some((3, -0.5))));
+    }
+
+    #[test]
+    fn test_from_summaries() {
+        let mut before = DiffSummary::new("old", 0.1, false, 4, &diff::diff_abs).with_raw_retention();
+        let mut after = DiffSummary::new("new", 0.1, false, 4, &diff::diff_abs).with_raw_retention();
+        before.add_slices(&[1.0, 2.5, 3.0], &[1.0, 2.0, 3.0]);
+        after.add(3.25, 3.0, 2);
+        after.add(2.0, 2.0, 1);
+        after.add(1.0, 1.0, 0);
+        let summary = ImprovementSummary::from_summaries("tuning", 0.0, &before, &after).unwrap();
+        assert_eq!((summary.improved_count(), summary.regressed_count(), summary.unchanged_count()), (1, 1, 1));
+        assert_eq!(summary.best_improvement(), Some((1, -0.5)));
+        after.add(5.0, 5.0, 7);
+        let err = ImprovementSummary::from_summaries("tuning", 0.0, &before, &after).err().unwrap();
+        assert!(matches!(err, DiffInputError::LengthMismatch { x_len: 3, y_len: 4, .. }));
+        before.add(5.0, 5.0, 8);
+        let err = ImprovementSummary::from_summaries("tuning", 0.0, &before, &after).err().unwrap();
+        assert_eq!(err.to_string(), "tuning: index 7 has no counterpart");
+    }
+}
@@ -0,0 +1,69 @@
+use std::fmt::Display;
+use std::io;
+use crate::fingerprint::Fingerprint;
+
+// An error ingesting values or records from external data, such as golden
+// or accumulation files, slices of differing lengths, or runs of different datasets.
+#[derive(Debug)]
+pub enum DiffInputError {
+    // The data couldn't be read.
+    Io(io::Error),
+    // The named summary was given different numbers of x and y values.
+    LengthMismatch { name: String, x_len: usize, y_len: usize },
+    // The named summary's stream of "x" or "y" values, the longer one,
+    // continued after the other ended with paired values.
+    UnpairedStream { name: String, longer: &'static str, paired: usize },
+    // A field on a 1-based line of the source couldn't be parsed.
+    Parse { source: String, line: usize, text: String },
+    // A line of the source had too few fields to include the named column.
+    MissingColumn { source: String, line: usize, column: &'static str },
+    // Binary data was malformed at the given byte offset of the source.
+    Binary { source: String, offset: usize, message: &'static str },
+    // An item of the named comparison had no counterpart with the same index.
+    UnmatchedIndex { name: String, index: usize },
+    // An item was added to a SummarySet under a name it has no summary for.
+    UnknownName(String),
+    // A value couldn't be serialized for comparison, with serde's message.
+    Serialize(String),
+    // Snapshots of the named summary with different tolerances were merged.
+    ToleranceMismatch { name: String, allow_diff: f64, other_allow_diff: f64 },
+    // The named summary's run was compared against a baseline of a different dataset.
+    DatasetMismatch { name: String, baseline: Fingerprint, current: Fingerprint },
+}
+
+impl Display for DiffInputError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DiffInputError::Io(err) => write!(f, "read failed: {}", err),
+            DiffInputError::LengthMismatch { name, x_len, y_len } => {
+                write!(f, "{}: length mismatch, {} x values vs {} y values", name, x_len, y_len)
+            }
+            DiffInputError::UnpairedStream { name, longer, paired } => {
+                let shorter = if *longer == "x" { "y" } else { "x" };
+                write!(f, "{}: length mismatch, {} ended after {} values but {} continued", name, shorter, paired, longer)
+            }
+            DiffInputError::Parse { source, line, text } => write!(f, "{} line {}: invalid value {:?}", source, line, text),
+            DiffInputError::MissingColumn { source, line, column } => {
+                write!(f, "{} line {}: missing column {}", source, line, column)
+            }
+            DiffInputError::Binary { source, offset, message } => write!(f, "{} byte {}: {}", source, offset, message),
+            DiffInputError::UnmatchedIndex { name, index } => write!(f, "{}: index {} has no counterpart", name, index),
+            DiffInputError::UnknownName(name) => write!(f, "unknown summary name {:?}", name),
+            DiffInputError::Serialize(message) => write!(f, "serialize failed: {}", message),
+            DiffInputError::ToleranceMismatch { name, allow_diff, other_allow_diff } => {
+                write!(f, "{}: tolerance mismatch, {:e} vs {:e}", name, allow_diff, other_allow_diff)
+            }
+            DiffInputError::DatasetMismatch { name, baseline, current } => {
+                write!(f, "{}: dataset mismatch, baseline {} vs {}", name, baseline, current)
+            }
+        }
+    }
+}
+
+impl std::error::Error for DiffInputError {}
+
+impl From<io::Error> for DiffInputError {
+    fn from(err: io::Error) -> Self {
+        DiffInputError::Io(err)
+    }
+}
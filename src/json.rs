@@ -0,0 +1,140 @@
+// JSON export of summaries and registry reports, for consumers that can't
+// use Display output directly, such as JavaScript via wasm-bindgen, e.g.
+//   #[wasm_bindgen]
+//   pub fn check(xs: &[f64], ys: &[f64]) -> String {
+//       let mut summary = DiffSummary64::new("wasm", 1e-12, false, 5, &diff::diff_rel);
+//       summary.add_slices(xs, ys);
+//       summary.to_json()
+//   }
+// JSON has no representation for non-finite numbers, so nan and infinite
+// values are written as null. Samples are {index, x, y, diff} objects.
+use serde::Serialize;
+use crate::diff_summary_f64::DiffSummary;
+use crate::registry::{RegistryEntry, Report};
+
+#[derive(Serialize)]
+struct SampleJson {
+    index: usize,
+    x: f64,
+    y: f64,
+    diff: f64,
+}
+
+impl SampleJson {
+    fn new((index, x, y, diff): (usize, f64, f64, f64)) -> Self {
+        SampleJson { index, x, y, diff }
+    }
+}
+
+#[derive(Serialize)]
+struct BucketJson {
+    // The bucket's label as displayed, e.g. "e-3" or "nan".
+    bucket: String,
+    count: usize,
+}
+
+#[derive(Serialize)]
+struct SummaryJson<'s> {
+    name: &'s str,
+    ok: bool,
+    count: usize,
+    fail_count: usize,
+    sign_count: usize,
+    identical_count: usize,
+    skipped_count: usize,
+    unsampled_count: usize,
+    worst_diff: f64,
+    allow_diff: f64,
+    allow_sign: bool,
+    mean_diff: f64,
+    rms_diff: f64,
+    worst: Option<SampleJson>,
+    first_failure: Option<SampleJson>,
+    // Unreduced bucket counts, in ascending order.
+    histogram: Vec<BucketJson>,
+    // Samples from with_reservoir, in index order.
+    reservoir: Vec<SampleJson>,
+    // [key, value] pairs from with_metadata, in the order added.
+    metadata: &'s [(String, String)],
+}
+
+#[derive(Serialize)]
+struct ReportJson<'r> {
+    count: usize,
+    fail_count: usize,
+    entries: &'r [RegistryEntry],
+}
+
+impl DiffSummary<'_> {
+    // This summary's results as a JSON object.
+    pub fn to_json(&self) -> String {
+        let json = SummaryJson {
+            name: self.name,
+            ok: self.is_ok(),
+            count: self.count(),
+            fail_count: self.fail_count(),
+            sign_count: self.sign_count(),
+            identical_count: self.identical_count(),
+            skipped_count: self.skipped_count(),
+            unsampled_count: self.unsampled_count(),
+            worst_diff: self.worst_diff(),
+            allow_diff: self.allow_diff(),
+            allow_sign: self.allow_sign(),
+            mean_diff: self.mean_diff(),
+            rms_diff: self.rms_diff(),
+            worst: self.worst_sample().map(|sample| SampleJson::new(sample.as_tuple())),
+            first_failure: self.first_failure().map(|sample| SampleJson::new(sample.as_tuple())),
+            histogram: self
+                .histogram()
+                .bucket_counts()
+                .into_iter()
+                .map(|(key, count)| BucketJson { bucket: key.to_string(), count })
+                .collect(),
+            reservoir: self.reservoir().into_iter().map(SampleJson::new).collect(),
+            metadata: self.metadata(),
+        };
+        serde_json::to_string(&json).unwrap()
+    }
+}
+
+impl Report {
+    // This report as a JSON object, with an entries array in display order.
+    pub fn to_json(&self) -> String {
+        let json = ReportJson {
+            count: self.entries.len(),
+            fail_count: self.entries.iter().filter(|entry| !entry.is_ok).count(),
+            entries: &self.entries,
+        };
+        serde_json::to_string(&json).unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::diff;
+    use crate::diff_summary_f64::DiffSummary;
+    use crate::registry::{RegistryEntry, Report};
+
+    #[test]
+    fn test_to_json() {
+        let mut summary = DiffSummary::new("js", 0.1, false, 4, &diff::diff_abs).with_metadata("seed", 7);
+        summary.add(1.0, 1.5, 0);
+        summary.add(2.0, 2.0, 1);
+        summary.add(f64::NAN, 1.0, 2);
+        assert_eq!(
+            summary.to_json(),
+            concat!(
+                r#"{"name":"js","ok":false,"count":3,"fail_count":2,"sign_count":0,"identical_count":1,"#,
+                r#""skipped_count":0,"unsampled_count":0,"worst_diff":null,"allow_diff":0.1,"allow_sign":false,"#,
+                r#""mean_diff":0.25,"rms_diff":0.3535533905932738,"#,
+                r#""worst":{"index":2,"x":null,"y":1.0,"diff":null},"first_failure":{"index":0,"x":1.0,"y":1.5,"diff":0.5},"#,
+                r#""histogram":[{"bucket":"zero","count":1},{"bucket":"e0","count":1},{"bucket":"nan","count":1}],"#,
+                r#""reservoir":[],"metadata":[["seed","7"]]}"#
+            )
+        );
+
+        let report = Report { entries: vec![RegistryEntry::new(&summary)] };
+        let json = report.to_json();
+        assert!(json.starts_with(r#"{"count":1,"fail_count":1,"entries":[{"name":"js","count":3,"#), "{}", json);
+    }
+}
@@ -0,0 +1,133 @@
+use std::collections::btree_map::Entry;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::{Debug, Display};
+use crate::diff_summary_f64::DiffSummary;
+
+// The maximum number of keys listed per category in Display output.
+const MAX_KEYS_SHOWN: usize = 10;
+
+// The result of joining computed (x) and expected (y) values by key and
+// feeding matched pairs to a DiffSummary, as done by KeyedDiff::compare.
+// Keys present on only one side, or repeated within one side, are reported
+// rather than compared. For repeated keys, the first occurrence is used.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct KeyedDiff<K> {
+    // The number of keys present on both sides.
+    pub matched: usize,
+    // Keys present only among the x values, in ascending order.
+    pub only_x: Vec<K>,
+    // Keys present only among the y values, in ascending order.
+    pub only_y: Vec<K>,
+    // Repeated occurrences of keys among the x values, in the order encountered.
+    pub duplicate_x: Vec<K>,
+    // Repeated occurrences of keys among the y values, in the order encountered.
+    pub duplicate_y: Vec<K>,
+}
+
+impl<K: Ord + Clone> KeyedDiff<K> {
+    // Join xs and ys by key, adding each matched pair to the summary.
+    // The index passed to the summary is the item's position within xs.
+    pub fn compare<IX, IY>(summary: &mut DiffSummary, xs: IX, ys: IY) -> Self
+    where
+        IX: IntoIterator<Item = (K, f64)>,
+        IY: IntoIterator<Item = (K, f64)>,
+    {
+        let mut duplicate_y = Vec::new();
+        // Map of key to (y value, whether matched yet).
+        let mut y_map: BTreeMap<K, (f64, bool)> = BTreeMap::new();
+        for (key, y) in ys {
+            match y_map.entry(key) {
+                Entry::Occupied(entry) => duplicate_y.push(entry.key().clone()),
+                Entry::Vacant(entry) => {
+                    entry.insert((y, false));
+                }
+            }
+        }
+        let mut result = KeyedDiff {
+            matched: 0,
+            only_x: Vec::new(),
+            only_y: Vec::new(),
+            duplicate_x: Vec::new(),
+            duplicate_y,
+        };
+        let mut x_only_set: BTreeSet<K> = BTreeSet::new();
+        for (index, (key, x)) in xs.into_iter().enumerate() {
+            match y_map.get_mut(&key) {
+                Some((_, true)) => result.duplicate_x.push(key),
+                Some((y, matched)) => {
+                    *matched = true;
+                    result.matched += 1;
+                    summary.add(x, *y, index);
+                }
+                None => {
+                    if x_only_set.contains(&key) {
+                        result.duplicate_x.push(key);
+                    } else {
+                        x_only_set.insert(key);
+                    }
+                }
+            }
+        }
+        result.only_x = x_only_set.into_iter().collect();
+        result.only_y = y_map.into_iter().filter(|(_, (_, matched))| !matched).map(|(key, _)| key).collect();
+        result
+    }
+
+    // Indicate whether every key matched exactly once.
+    pub fn is_complete(&self) -> bool {
+        self.only_x.is_empty() && self.only_y.is_empty()
+            && self.duplicate_x.is_empty() && self.duplicate_y.is_empty()
+    }
+}
+
+impl<K: Debug> Display for KeyedDiff<K> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "matched {}", self.matched)?;
+        let categories = [
+            ("only x", &self.only_x),
+            ("only y", &self.only_y),
+            ("duplicate x", &self.duplicate_x),
+            ("duplicate y", &self.duplicate_y),
+        ];
+        for (label, keys) in categories.iter() {
+            if keys.is_empty() {
+                continue;
+            }
+            write!(f, ", {} {}:", label, keys.len())?;
+            for key in keys.iter().take(MAX_KEYS_SHOWN) {
+                write!(f, " {:?}", key)?;
+            }
+            if keys.len() > MAX_KEYS_SHOWN {
+                write!(f, " ...")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::KeyedDiff;
+    use crate::diff;
+    use crate::diff_summary_f64::DiffSummary;
+
+    #[test]
+    fn test_keyed() {
+        let mut summary = DiffSummary::new("keyed", 0.1, false, 4, &diff::diff_abs).with_failure_capacity(5);
+        let xs = vec![("c", 3.0), ("a", 1.0), ("x", 9.0), ("b", 2.5), ("a", 1.5)];
+        let ys = vec![("a", 1.0), ("b", 2.0), ("c", 3.0), ("d", 4.0), ("b", 2.0)];
+        let keyed = KeyedDiff::compare(&mut summary, xs, ys);
+        assert_eq!(keyed.matched, 3);
+        assert_eq!(keyed.only_x, vec!["x"]);
+        assert_eq!(keyed.only_y, vec!["d"]);
+        assert_eq!(keyed.duplicate_x, vec!["a"]);
+        assert_eq!(keyed.duplicate_y, vec!["b"]);
+        assert!(!keyed.is_complete());
+        assert_eq!(summary.count(), 3);
+        assert_eq!(summary.failures(), &[(3, 2.5, 2.0, 0.5)]);
+        assert_eq!(
+            format!("{}", keyed),
+            "matched 3, only x 1: \"x\", only y 1: \"d\", duplicate x 1: \"a\", duplicate y 1: \"b\""
+        );
+    }
+}
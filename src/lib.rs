@@ -1,10 +1,105 @@
+mod bootstrap;
+mod bounded_summary;
+mod convergence;
+mod csv_tee;
 mod diff_part_summary;
 mod diff_summary_f64;
+mod display_options;
+mod drift;
+mod fingerprint;
+mod duration_diff;
+mod extremes;
+mod grouped_summary;
+mod improvement;
+mod input_error;
+mod keyed_diff;
+mod linear_fit;
 mod log_histogram;
+mod order_summary;
+mod outliers;
+mod param_grid;
+mod record_diff;
+#[cfg(feature = "reflect")]
+mod reflect;
+mod reservoir;
+mod run_comparison;
+mod sample_record;
+mod spill;
+mod status;
+mod summary_config;
+mod summary_set;
+mod timing;
+mod tolerance_check;
+mod tolerance_suggestion;
+mod top_n;
+mod triage;
+mod triple_summary;
 mod util;
+mod value_class;
 
+pub mod accumulate;
+#[cfg(feature = "approx")]
+pub mod approx_compat;
+pub mod binary;
+pub mod combinators;
+#[cfg(feature = "serde")]
+pub mod config;
+pub mod demo;
 pub mod diff;
+pub mod exhaustive;
+pub mod golden;
+pub mod machine;
+#[cfg(feature = "json")]
+mod json;
+pub mod registry;
+pub mod report;
+#[cfg(feature = "rust_decimal")]
+pub mod diff_decimal;
+#[cfg(feature = "half")]
+pub mod diff_half;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+pub mod stats;
+pub mod trend;
+pub use crate::bootstrap::Bootstrap;
+pub use crate::bounded_summary::BoundedDiffSummary;
+pub use crate::convergence::Convergence;
 pub use crate::diff_summary_f64::DiffSummary as DiffSummary64;
+pub use crate::diff_summary_f64::{DisplayWith, DuplicatePolicy, ErrorPolicy, FailBudget, ItemOutcome, ItemRecord, ItemStatus, LengthPolicy, PassingExample, Quantization, Sampling, SignLimit, SignWorstMetric, SignedZeroPolicy, SubnormalPolicy, SummaryInfo, SummarySnapshot, Truncation, WorstMetric, DEFAULT_SPOT_CHECK, PASSING_POOL};
+pub use crate::display_options::{DisplayOptions, Layout};
+pub use crate::drift::{DriftSegments, Segment};
+pub use crate::duration_diff::{DurationDiffSummary, FmtSeconds};
+pub use crate::extremes::Extremes;
+pub use crate::fingerprint::Fingerprint;
+pub use crate::grouped_summary::GroupedDiffSummary;
+pub use crate::improvement::{Improvement, ImprovementSummary};
+pub use crate::input_error::DiffInputError;
+pub use crate::keyed_diff::KeyedDiff;
+pub use crate::linear_fit::LinearFit;
+pub use crate::log_histogram::{BucketDelta, BucketKey, BucketLabels, HistogramDelta, LogHistogram};
+pub use crate::order_summary::OrderSummary;
+pub use crate::outliers::OutlierReport;
+pub use crate::param_grid::{GridCell, ParamGrid};
+pub use crate::record_diff::RecordDiff;
+#[cfg(feature = "reflect")]
+pub use crate::reflect::{LeafDiff, StructDiff};
+pub use crate::run_comparison::{Change, MetricDelta, RunComparison};
+pub use crate::sample_record::SampleRecord;
+pub use crate::spill::SpillReader;
+pub use crate::status::{StatusReason, SummaryStatus};
+pub use crate::summary_config::DiffSummaryConfig;
+pub use crate::summary_set::SummarySet;
+pub use crate::timing::Timing;
+pub use crate::tolerance_check::{ComparatorKind, ImplausibleTolerance, ToleranceCheck};
+pub use crate::tolerance_suggestion::ToleranceSuggestion;
+pub use crate::triage::{FailureCause, Triage};
+pub use crate::triple_summary::{Pairing, TripleDiffSummary};
+pub use crate::util::{FloatFormat, FmtF64, PercentFormat};
+pub use crate::value_class::{IeeeClass, ValueClass};
+// The margin type taken by diff::diff_margin, for callers without their own float-cmp 0.8 dependency.
+pub use float_cmp::F64Margin;
+#[cfg(feature = "macros")]
+pub use float_diff_macros::test;
 
 // PLEASE NOTE that this macro is more likely than
 // average to experience breaking changes or
@@ -14,38 +109,119 @@ pub use crate::diff_summary_f64::DiffSummary as DiffSummary64;
 // A call to this function can can be thought of as a
 // more elaborate variation on the approx crate's:
 // assert_approx_eq!(x, y, allow_diff)
+// An optional trailing FloatFormat argument controls how x and y are printed.
 #[macro_export]
 macro_rules! log_assert_approx_eq {
     ($name: expr, $x: expr, $y: expr, $allow_diff: expr, $allow_sign_change: expr, $calc_diff: expr) => {
+        $crate::log_assert_approx_eq!($name, $x, $y, $allow_diff, $allow_sign_change, $calc_diff, $crate::FloatFormat::Exp)
+    };
+    ($name: expr, $x: expr, $y: expr, $allow_diff: expr, $allow_sign_change: expr, $calc_diff: expr, $float_format: expr) => {
         let (diff, sign_change) = (*($calc_diff))($x, $y);
         println!(
-            "{}: {}{:e} vs {}{:e} diff {:e}, sign diff {}",
+            "{}: {} vs {} diff {:e}, sign diff {}",
             $name,
-            util::help_sign($x),
-            $x,
-            util::help_sign($y),
-            $y,
+            $crate::FmtF64($x, $float_format),
+            $crate::FmtF64($y, $float_format),
             diff,
             sign_change
         );
         assert!(
             diff <= $allow_diff,
-            "assert failed {}: {}{:e} vs {}{:e} diff abs {:e} outside inclusive {:e}",
+            "assert failed {}: {} vs {} diff abs {:e} outside inclusive {:e}",
             $name,
-            util::help_sign($x),
-            $x,
-            util::help_sign($y),
-            $y,
+            $crate::FmtF64($x, $float_format),
+            $crate::FmtF64($y, $float_format),
             diff,
             $allow_diff
         );
         assert!($allow_sign_change || !sign_change,
-            "assert failed {}: {}{:e} vs {}{:e} sign difference disallowed.",
+            "assert failed {}: {} vs {} sign difference disallowed.",
             $name,
-            util::help_sign($x),
-            $x,
-            util::help_sign($y),
-            $y,
+            $crate::FmtF64($x, $float_format),
+            $crate::FmtF64($y, $float_format),
     );
     }
 }
+
+// Compare two equal length slices element-wise, print the resulting
+// DiffSummary, and assert that it passes, replacing the usual loop and
+// assert boilerplate. Elements may be any primitive numeric type.
+// Differences are absolute, unless a calc_diff function is given.
+// Sign changes are disallowed.
+//   assert_slices_approx_eq!("output", &xs, &ys, 1e-9);
+//   assert_slices_approx_eq!("output", &xs, &ys, 1e-9, &float_diff::diff::diff_rel);
+#[macro_export]
+macro_rules! assert_slices_approx_eq {
+    ($name: expr, $xs: expr, $ys: expr, $allow_diff: expr) => {
+        $crate::assert_slices_approx_eq!($name, $xs, $ys, $allow_diff, &$crate::diff::diff_abs)
+    };
+    ($name: expr, $xs: expr, $ys: expr, $allow_diff: expr, $calc_diff: expr) => {{
+        let mut summary = $crate::DiffSummary64::new($name, $allow_diff, false, 5, $calc_diff);
+        summary.add_slices($xs, $ys);
+        println!("{}", summary);
+        summary.assert();
+    }};
+}
+
+// As assert_slices_approx_eq, with relative differences.
+#[macro_export]
+macro_rules! assert_slices_approx_eq_rel {
+    ($name: expr, $xs: expr, $ys: expr, $allow_diff: expr) => {
+        $crate::assert_slices_approx_eq!($name, $xs, $ys, $allow_diff, &$crate::diff::diff_rel)
+    };
+}
+
+// As assert_slices_approx_eq, with differences in ULPs.
+#[macro_export]
+macro_rules! assert_slices_approx_eq_ulps {
+    ($name: expr, $xs: expr, $ys: expr, $allow_ulps: expr) => {
+        $crate::assert_slices_approx_eq!($name, $xs, $ys, $allow_ulps as f64, &$crate::diff::diff_ulps)
+    };
+}
+
+// Add an item to a summary, as DiffSummary::spot_check does, printing it
+// with the source text of x and y if it's selected for spot checking.
+// The index defaults to the number of items offered to the summary so far.
+//   spot_check!(summary, solve(t), exact(t));
+//   spot_check!(summary, solve(t), exact(t), i);
+#[macro_export]
+macro_rules! spot_check {
+    ($summary: expr, $x: expr, $y: expr) => {{
+        let summary = &mut $summary;
+        let index = summary.offered_count();
+        summary.spot_check(stringify!($x), stringify!($y), $x, $y, index)
+    }};
+    ($summary: expr, $x: expr, $y: expr, $index: expr) => {
+        $summary.spot_check(stringify!($x), stringify!($y), $x, $y, $index)
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use std::panic::{catch_unwind, AssertUnwindSafe};
+
+    #[test]
+    fn test_assert_slices_approx_eq() {
+        let xs = [1.0, 2.0, 3.0 + 1e-12];
+        let ys = [1.0, 2.0, 3.0];
+        assert_slices_approx_eq!("abs", &xs, &ys, 1e-9);
+        assert_slices_approx_eq!("ints", &[1, 2, 3], &[1u8, 2, 3], 0.0);
+        assert_slices_approx_eq_rel!("rel", &xs, &ys, 1e-12);
+        assert_slices_approx_eq_ulps!("ulps", &xs, &ys, 4096);
+        let failed = catch_unwind(AssertUnwindSafe(|| assert_slices_approx_eq_ulps!("ulps", &xs, &ys, 4)));
+        assert!(failed.is_err());
+        let sign = catch_unwind(AssertUnwindSafe(|| assert_slices_approx_eq!("sign", &[-0.0], &[0.0], 1.0)));
+        assert!(sign.is_err());
+    }
+
+    #[test]
+    fn test_spot_check() {
+        let mut summary = crate::DiffSummary64::new("spot", 1e-9, false, 4, &crate::diff::diff_abs).with_spot_check(crate::Sampling::EveryNth(2));
+        for t in 0..5 {
+            spot_check!(summary, (t as f64).sqrt().powi(2), t as f64);
+        }
+        spot_check!(&mut summary, 2.0, 1.0, 10);
+        assert_eq!((summary.count(), summary.fail_count(), summary.offered_count()), (6, 1, 6));
+        assert_eq!(summary.worst_sample().unwrap().index, 10);
+    }
+}
@@ -0,0 +1,152 @@
+// Streaming least-squares fit of computed values against expected values,
+// computed = slope * expected + intercept, for detecting scaling and offset
+// errors that per-item differences may not make obvious.
+// Uses running means and co-moments for numerical stability.
+// Pairs where either value is non-finite are ignored.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct LinearFit {
+    count: usize,
+    mean_expected: f64,
+    mean_computed: f64,
+    // Sum of squared deviations from the mean, for each side.
+    m2_expected: f64,
+    m2_computed: f64,
+    // Sum of products of deviations from the means.
+    co_moment: f64,
+}
+
+impl LinearFit {
+    pub fn new() -> Self {
+        LinearFit {
+            count: 0,
+            mean_expected: 0.0,
+            mean_computed: 0.0,
+            m2_expected: 0.0,
+            m2_computed: 0.0,
+            co_moment: 0.0,
+        }
+    }
+
+    pub fn add(&mut self, computed: f64, expected: f64) {
+        if !computed.is_finite() || !expected.is_finite() {
+            return;
+        }
+        self.count += 1;
+        let n = self.count as f64;
+        let delta_expected = expected - self.mean_expected;
+        let delta_computed = computed - self.mean_computed;
+        self.mean_expected += delta_expected / n;
+        self.mean_computed += delta_computed / n;
+        self.m2_expected += delta_expected * (expected - self.mean_expected);
+        self.m2_computed += delta_computed * (computed - self.mean_computed);
+        self.co_moment += delta_expected * (computed - self.mean_computed);
+    }
+
+    // Combine another fit's data into this one.
+    pub fn merge(&mut self, other: &LinearFit) {
+        if other.count == 0 {
+            return;
+        }
+        if self.count == 0 {
+            *self = *other;
+            return;
+        }
+        let n_a = self.count as f64;
+        let n_b = other.count as f64;
+        let n = n_a + n_b;
+        let delta_expected = other.mean_expected - self.mean_expected;
+        let delta_computed = other.mean_computed - self.mean_computed;
+        self.m2_expected += other.m2_expected + delta_expected * delta_expected * n_a * n_b / n;
+        self.m2_computed += other.m2_computed + delta_computed * delta_computed * n_a * n_b / n;
+        self.co_moment += other.co_moment + delta_expected * delta_computed * n_a * n_b / n;
+        self.mean_expected += delta_expected * n_b / n;
+        self.mean_computed += delta_computed * n_b / n;
+        self.count += other.count;
+    }
+
+    // The number of pairs included in the fit.
+    pub fn count(&self) -> usize {
+        self.count
+    }
+
+    // The fitted slope, or nan if expected values don't vary.
+    pub fn slope(&self) -> f64 {
+        if self.m2_expected > 0.0 {
+            self.co_moment / self.m2_expected
+        } else {
+            f64::NAN
+        }
+    }
+
+    // The fitted intercept, or nan if expected values don't vary.
+    pub fn intercept(&self) -> f64 {
+        self.mean_computed - self.slope() * self.mean_expected
+    }
+
+    // The coefficient of determination of the fit, or nan if either side doesn't vary.
+    pub fn r_squared(&self) -> f64 {
+        let correlation = self.correlation();
+        correlation * correlation
+    }
+
+    // The Pearson correlation coefficient between computed and expected values,
+    // or nan if either side doesn't vary. A dip in correlation can be an early
+    // sign of a broken code path, even while differences remain in tolerance.
+    pub fn correlation(&self) -> f64 {
+        if self.m2_expected > 0.0 && self.m2_computed > 0.0 {
+            (self.co_moment / (self.m2_expected * self.m2_computed).sqrt()).clamp(-1.0, 1.0)
+        } else {
+            f64::NAN
+        }
+    }
+}
+
+impl Default for LinearFit {
+    fn default() -> Self {
+        LinearFit::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::LinearFit;
+
+    #[test]
+    fn test_fit() {
+        let mut fit = LinearFit::new();
+        let mut first = LinearFit::new();
+        let mut second = LinearFit::new();
+        for i in 0..100 {
+            let expected = i as f64 * 0.25;
+            let computed = 2.0 * expected + 1.0;
+            fit.add(computed, expected);
+            if i < 30 { &mut first } else { &mut second }.add(computed, expected);
+        }
+        fit.add(f64::NAN, 1.0);
+        assert_eq!(fit.count(), 100);
+        assert!((fit.slope() - 2.0).abs() < 1e-12);
+        assert!((fit.intercept() - 1.0).abs() < 1e-12);
+        assert!((fit.r_squared() - 1.0).abs() < 1e-12);
+
+        first.merge(&second);
+        assert_eq!(first.count(), 100);
+        assert!((first.slope() - fit.slope()).abs() < 1e-12);
+        assert!((first.intercept() - fit.intercept()).abs() < 1e-12);
+
+        assert!(LinearFit::new().slope().is_nan());
+    }
+
+    #[test]
+    fn test_correlation() {
+        let mut fit = LinearFit::new();
+        for &(computed, expected) in &[(1.0, 3.0), (2.0, 2.0), (3.0, 1.0)] {
+            fit.add(computed, expected);
+        }
+        assert!((fit.correlation() + 1.0).abs() < 1e-12);
+        fit.add(2.0, 2.5);
+        fit.add(2.0, 1.5);
+        let correlation = fit.correlation();
+        assert!(-1.0 < correlation && correlation < -0.8, "{}", correlation);
+        assert!((fit.r_squared() - correlation * correlation).abs() < 1e-15);
+    }
+}
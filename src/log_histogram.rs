@@ -1,21 +1,131 @@
+use std::cell::{Cell, RefCell};
+use std::cmp::Reverse;
 use std::fmt::Display;
-use std::collections::{BTreeMap, HashMap};
-use crate::util;
+use std::collections::{BTreeMap, BinaryHeap};
+use crate::display_options::DisplayOptions;
+use crate::util::{Percent, PercentFormat, WithUnit};
+
+// Map of original exponent to (reduced_exponent_min, reduced_exponent_max, count).
+type ReducedHisto = BTreeMap<isize, (isize, isize, usize)>;
+
+// Identifies one of a LogHistogram's unreduced buckets.
+// Ordering follows the values the buckets hold, from -inf up to nan.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum BucketKey {
+    NegInf,
+    // Negative values, by the exponent of their magnitude.
+    Neg(Reverse<isize>),
+    Zero,
+    NearZero,
+    // Positive values, by exponent.
+    Pos(isize),
+    Inf,
+    Nan,
+}
+
+impl Display for BucketKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BucketKey::NegInf => write!(f, "-inf"),
+            BucketKey::Neg(Reverse(exp)) => write!(f, "-e{}", exp),
+            BucketKey::Zero => write!(f, "zero"),
+            BucketKey::NearZero => write!(f, "near-zero"),
+            BucketKey::Pos(exp) => write!(f, "e{}", exp),
+            BucketKey::Inf => write!(f, "inf"),
+            BucketKey::Nan => write!(f, "nan"),
+        }
+    }
+}
+
+// How LogHistogram labels its log buckets in Display output.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BucketLabels {
+    // By decimal exponent, e.g. "e-13 to e-7".
+    Exponent,
+    // By the range of values held, e.g. "1e-14..1e-6", which is easier to
+    // read for those not used to the exponent notation.
+    Range,
+}
+
+// One bucket's counts in two histograms, as part of a HistogramDelta.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BucketDelta {
+    pub key: BucketKey,
+    pub before: usize,
+    pub after: usize,
+    // The bucket's share of each histogram's values, from 0 to 100.
+    pub before_percent: f64,
+    pub after_percent: f64,
+}
+
+impl BucketDelta {
+    // The signed change in count.
+    pub fn count_delta(&self) -> isize {
+        self.after as isize - self.before as isize
+    }
+
+    // The signed change in share of values, in percentage points.
+    pub fn percent_delta(&self) -> f64 {
+        self.after_percent - self.before_percent
+    }
+}
+
+// The per-bucket differences between two histograms, as returned by
+// LogHistogram::delta, in bucket order.
+#[derive(Clone, Debug, PartialEq)]
+pub struct HistogramDelta {
+    pub buckets: Vec<BucketDelta>,
+}
+
+impl HistogramDelta {
+    // The buckets whose count or share of values changed.
+    pub fn changed(&self) -> impl Iterator<Item = &BucketDelta> + '_ {
+        self.buckets.iter().filter(|bucket| bucket.before != bucket.after || bucket.before_percent != bucket.after_percent)
+    }
+}
+
+// Shows the gain or loss of each changed bucket, in count and percentage
+// points, e.g. "e-3 +5 +1.2pp, e-1 -5 -1.2pp", or "unchanged".
+impl Display for HistogramDelta {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut sep = "";
+        for bucket in self.changed() {
+            write!(f, "{}{} {:+} {:+.1}pp", sep, bucket.key, bucket.count_delta(), bucket.percent_delta())?;
+            sep = ", ";
+        }
+        if sep.is_empty() {
+            write!(f, "unchanged")?;
+        }
+        Ok(())
+    }
+}
 
 // A struct for taking a set of values values, splitting into special case
 // and log10 buckets, and displaying the current distribution using a
 // specified maximum number of log10 buckets.
 // Primarily intended for getting a quick overview of expected vs calculated
-// values for a potentially large dataset.
-// Current implementation assumes that all incoming values are non-negative.
+// values for a potentially large dataset, but also useful on its own for
+// eyeballing any dataset spanning many orders of magnitude, such as timings.
+// By default, all incoming values are required to be non-negative. In signed
+// mode, negative values are tracked in a mirrored set of buckets, so that
+// signed residuals can be histogrammed as well.
 // Note that formatting for display may be relatively expensive.
 pub struct LogHistogram {
     // The number of nans added
     pub(crate) num_nan: usize,
-    // The number of infinite values added
+    // The number of positive infinite values added
     pub(crate) num_inf: usize,
+    // The number of negative infinite values added (signed mode only)
+    pub(crate) num_neg_inf: usize,
     // The number of exactly-zero values added
     pub(crate) num_zero: usize,
+    // The number of non-zero values added that were below the negligible threshold
+    pub(crate) num_negligible: usize,
+
+    // Non-zero values below this threshold are counted in num_negligible,
+    // rather than in the log buckets, so that effectively-zero values don't
+    // distort the reduction. Zero disables the near-zero bucket.
+    pub(crate) negligible: f64,
 
     // max_display_buckets is the maximum number of log buckets to display, not
     // counting the special case buckets for NAN, INF, and 0. The bucket count
@@ -24,8 +134,32 @@ pub struct LogHistogram {
     // would come up for lower caps.
     pub(crate) max_display_buckets: usize,
 
-    // The standard buckets based on log10 of the incoming value
-    pub(crate) log10_buckets: HashMap<isize, usize>,
+    // Whether negative values are accepted.
+    pub(crate) signed: bool,
+
+    // The standard buckets based on log10 of the incoming value.
+    // Ordered maps keep iteration, and so reduction, independent of
+    // insertion order and hashing.
+    pub(crate) log10_buckets: BTreeMap<isize, usize>,
+
+    // Buckets based on log10 of the magnitude of negative values (signed mode only)
+    pub(crate) log10_buckets_neg: BTreeMap<isize, usize>,
+
+    // A positive value, such as a tolerance, at which Display splits the
+    // bucket containing it in two, so that the boundary is always visible.
+    // The bucket is never combined with others during reduction.
+    pub(crate) boundary: Option<f64>,
+
+    // The number of values in the boundary's bucket above the boundary.
+    pub(crate) num_above_boundary: usize,
+
+    // Cached result of the most recent bucket reduction.
+    // None indicates that the cache is dirty, and will be recalculated on
+    // the next call to reduce or fmt.
+    reduced: RefCell<Option<ReducedHisto>>,
+
+    // Cached reduction of the negative buckets, as for reduced.
+    reduced_neg: RefCell<Option<ReducedHisto>>,
 }
 
 impl LogHistogram {
@@ -34,90 +168,451 @@ impl LogHistogram {
         LogHistogram {
             num_nan: 0,
             num_inf: 0,
+            num_neg_inf: 0,
             num_zero: 0,
-            max_display_buckets: max_display_buckets,
-            log10_buckets: HashMap::new(),
+            num_negligible: 0,
+            negligible: 0.0,
+            max_display_buckets,
+            signed: false,
+            log10_buckets: BTreeMap::new(),
+            log10_buckets_neg: BTreeMap::new(),
+            boundary: None,
+            num_above_boundary: 0,
+            reduced: RefCell::new(None),
+            reduced_neg: RefCell::new(None),
+        }
+    }
+
+    // Create a histogram accepting negative values as well as positive.
+    // Negative and positive values are bucketed and reduced separately,
+    // with each side displaying up to max_display_buckets log buckets.
+    // Zero values of either sign share the zero bucket.
+    pub fn new_signed(max_display_buckets: usize) -> Self {
+        LogHistogram {
+            signed: true,
+            ..LogHistogram::new(max_display_buckets)
         }
     }
 
+    // Count non-zero values below the threshold in a near-zero bucket,
+    // reported like the zero bucket, rather than in the log buckets.
+    pub fn with_negligible(mut self, negligible: f64) -> Self {
+        assert!(negligible >= 0.0, "negligible threshold must be non-negative");
+        self.negligible = negligible;
+        self
+    }
+
+    // Show the log bucket containing boundary as two buckets, holding values
+    // up to and above boundary, and keep it apart from other buckets during
+    // reduction, so that a threshold such as a tolerance stays visible in
+    // the distribution. Displays at most one more bucket than configured.
+    // Must be set before values are added.
+    pub fn with_boundary(mut self, boundary: f64) -> Self {
+        assert!(boundary > 0.0 && boundary.is_finite(), "boundary must be positive and finite");
+        assert_eq!(self.count_total(), 0, "boundary must be set before values are added");
+        self.boundary = Some(boundary);
+        self
+    }
+
     // Add a new item to the dataset being tracked.
+    // Outside of signed mode, negative values are not permitted.
     pub fn add(&mut self, diff: f64) {
-        assert!(diff.is_sign_positive());
-        if diff.is_nan() {
-            self.num_nan += 1;
-        } else if diff.is_infinite() {
-            self.num_inf += 1;
-        } else if diff == 0.0 {
-            self.num_zero += 1;
-        } else {
-            let exp = diff.log10() as isize;
-            let current: usize = match self.log10_buckets.get(&exp) {
-                Some(val) => *val,
-                _ => 0,
+        assert!(self.signed || diff.is_sign_positive());
+        let key = self.bucket_key(diff);
+        self.count_boundary(key, diff);
+        self.add_to_bucket(key, 1);
+    }
+
+    // Add several items at once. Runs of consecutive items in the same
+    // bucket are counted together, which is cheaper than adding them singly.
+    pub fn add_batch(&mut self, diffs: &[f64]) {
+        let mut run: Option<(BucketKey, usize)> = None;
+        for &diff in diffs {
+            assert!(self.signed || diff.is_sign_positive());
+            let key = self.bucket_key(diff);
+            self.count_boundary(key, diff);
+            run = match run {
+                Some((run_key, count)) if run_key == key => Some((key, count + 1)),
+                Some((run_key, count)) => {
+                    self.add_to_bucket(run_key, count);
+                    Some((key, 1))
+                }
+                None => Some((key, 1)),
             };
-            self.log10_buckets.insert(exp, current + 1);
+        }
+        if let Some((key, count)) = run {
+            self.add_to_bucket(key, count);
+        }
+    }
+
+    fn count_boundary(&mut self, key: BucketKey, diff: f64) {
+        if let Some(boundary) = self.boundary {
+            if diff > boundary && Some(key) == self.boundary_key() {
+                self.num_above_boundary += 1;
+            }
+        }
+    }
+
+    // The unreduced bucket containing the boundary, if it falls in a log bucket.
+    fn boundary_key(&self) -> Option<BucketKey> {
+        self.boundary.map(|boundary| self.bucket_key(boundary)).filter(|key| matches!(key, BucketKey::Pos(_)))
+    }
+
+    fn add_to_bucket(&mut self, key: BucketKey, count: usize) {
+        match key {
+            BucketKey::Nan => self.num_nan += count,
+            BucketKey::Inf => self.num_inf += count,
+            BucketKey::NegInf => self.num_neg_inf += count,
+            BucketKey::Zero => self.num_zero += count,
+            BucketKey::NearZero => self.num_negligible += count,
+            // Only log buckets take part in reduction, so special cases
+            // don't need to invalidate the cache.
+            BucketKey::Pos(exp) => {
+                *self.log10_buckets.entry(exp).or_insert(0) += count;
+                self.reduced.replace(None);
+            }
+            BucketKey::Neg(Reverse(exp)) => {
+                *self.log10_buckets_neg.entry(exp).or_insert(0) += count;
+                self.reduced_neg.replace(None);
+            }
+        }
+    }
+
+    // The unreduced bucket that a value would be counted in, without adding it.
+    pub fn bucket_key(&self, diff: f64) -> BucketKey {
+        bucket_key(diff, self.negligible)
+    }
+
+    // Add all values from another histogram into this one.
+    // Both histograms must have the same signed mode, negligible threshold and boundary.
+    // This histogram's max_display_buckets is kept.
+    pub fn merge(&mut self, other: &LogHistogram) {
+        assert_eq!(self.signed, other.signed, "Cannot merge signed and unsigned histograms");
+        assert!(
+            self.negligible == other.negligible,
+            "Cannot merge histograms with different negligible thresholds {:e} and {:e}",
+            self.negligible,
+            other.negligible
+        );
+        assert!(self.boundary == other.boundary, "Cannot merge histograms with different boundaries");
+        self.num_nan += other.num_nan;
+        self.num_above_boundary += other.num_above_boundary;
+        self.num_inf += other.num_inf;
+        self.num_neg_inf += other.num_neg_inf;
+        self.num_zero += other.num_zero;
+        self.num_negligible += other.num_negligible;
+        if !other.log10_buckets.is_empty() {
+            for (&key, &val) in &other.log10_buckets {
+                *self.log10_buckets.entry(key).or_insert(0) += val;
+            }
+            self.reduced.replace(None);
+        }
+        if !other.log10_buckets_neg.is_empty() {
+            for (&key, &val) in &other.log10_buckets_neg {
+                *self.log10_buckets_neg.entry(key).or_insert(0) += val;
+            }
+            self.reduced_neg.replace(None);
+        }
+    }
+
+    // Estimate the value at quantile q (in [0, 1]) from the bucket counts.
+    // Values are ordered -inf, negative buckets, zero, near-zero, positive
+    // buckets, inf, then nan, so that nan is treated as worse than anything.
+    // Within a log bucket, the estimate is interpolated logarithmically
+    // between the bucket's bounds. Returns nan if no values have been added.
+    pub fn approx_quantile(&self, q: f64) -> f64 {
+        assert!((0.0..=1.0).contains(&q), "quantile must be in [0, 1]");
+        let num_total = self.count_total();
+        if num_total == 0 {
+            return f64::NAN;
+        }
+        // The rank of the target value, with 0 being the first value.
+        let rank = q * (num_total - 1) as f64;
+        let mut num_before = 0usize;
+        // Check whether the target falls within the next count values, and if so
+        // return its fractional position among them.
+        let mut find = |count: usize| -> Option<f64> {
+            if count == 0 {
+                return None;
+            }
+            let start = num_before;
+            num_before += count;
+            if rank < num_before as f64 {
+                Some((rank - start as f64 + 0.5) / count as f64)
+            } else {
+                None
+            }
+        };
+        if find(self.num_neg_inf).is_some() {
+            return f64::NEG_INFINITY;
+        }
+        for (exp, count) in self.buckets_neg().rev() {
+            if let Some(t) = find(count) {
+                let (lo, hi) = self.bucket_bounds(exp);
+                return -(hi * (lo / hi).powf(t));
+            }
+        }
+        if find(self.num_zero).is_some() {
+            return 0.0;
+        }
+        if let Some(t) = find(self.num_negligible) {
+            return self.negligible * t;
+        }
+        for (exp, count) in self.buckets() {
+            if let Some(t) = find(count) {
+                let (lo, hi) = self.bucket_bounds(exp);
+                return lo * (hi / lo).powf(t);
+            }
+        }
+        if find(self.num_inf).is_some() {
+            return f64::INFINITY;
+        }
+        f64::NAN
+    }
+
+    // The range of magnitudes covered by the log bucket for an exponent.
+    // Exponents are truncated toward zero, so bucket 0 spans (0.1, 10).
+    fn bucket_bounds(&self, exp: isize) -> (f64, f64) {
+        let ten: f64 = 10.0;
+        let (lo, hi) = if exp > 0 {
+            (ten.powi(exp as i32), ten.powi(exp as i32 + 1))
+        } else if exp < 0 {
+            (ten.powi(exp as i32 - 1), ten.powi(exp as i32))
+        } else {
+            (0.1, 10.0)
+        };
+        (f64::max(lo, self.negligible), hi)
+    }
+
+    // The number of nan values added.
+    pub fn count_nan(&self) -> usize {
+        self.num_nan
+    }
+
+    // The number of positive infinite values added.
+    pub fn count_inf(&self) -> usize {
+        self.num_inf
+    }
+
+    // The number of negative infinite values added (signed mode only).
+    pub fn count_neg_inf(&self) -> usize {
+        self.num_neg_inf
+    }
+
+    // The number of zero values added, of either sign.
+    pub fn count_zero(&self) -> usize {
+        self.num_zero
+    }
+
+    // The number of non-zero values added below the negligible threshold.
+    pub fn count_negligible(&self) -> usize {
+        self.num_negligible
+    }
+
+    // The total number of values added.
+    pub fn count_total(&self) -> usize {
+        self.num_nan + self.num_inf + self.num_neg_inf + self.num_zero + self.num_negligible
+            + self.log10_buckets.values().sum::<usize>()
+            + self.log10_buckets_neg.values().sum::<usize>()
+    }
+
+    // Whether this histogram accepts negative values.
+    pub fn is_signed(&self) -> bool {
+        self.signed
+    }
+
+    // The boundary set by with_boundary, if any.
+    pub fn boundary(&self) -> Option<f64> {
+        self.boundary
+    }
+
+    // The number of values added in the boundary's log bucket that exceed
+    // the boundary. Values in higher buckets aren't included.
+    pub fn count_above_boundary(&self) -> usize {
+        self.num_above_boundary
+    }
+
+    // The maximum number of log buckets shown per sign by Display.
+    pub fn max_display_buckets(&self) -> usize {
+        self.max_display_buckets
+    }
+
+    // Iterate (exponent, count) for the unreduced log buckets of positive
+    // values, in ascending order of exponent.
+    pub fn buckets(&self) -> impl DoubleEndedIterator<Item = (isize, usize)> + '_ {
+        sorted_buckets(&self.log10_buckets)
+    }
+
+    // Iterate (exponent, count) for the unreduced log buckets of negative
+    // values, in ascending order of the exponent of their magnitude.
+    pub fn buckets_neg(&self) -> impl DoubleEndedIterator<Item = (isize, usize)> + '_ {
+        sorted_buckets(&self.log10_buckets_neg)
+    }
+
+    // The counts of all non-empty unreduced buckets, including special cases.
+    pub fn bucket_counts(&self) -> BTreeMap<BucketKey, usize> {
+        let mut counts = BTreeMap::new();
+        let specials = [
+            (BucketKey::NegInf, self.num_neg_inf),
+            (BucketKey::Zero, self.num_zero),
+            (BucketKey::NearZero, self.num_negligible),
+            (BucketKey::Inf, self.num_inf),
+            (BucketKey::Nan, self.num_nan),
+        ];
+        for &(key, count) in specials.iter() {
+            if count > 0 {
+                counts.insert(key, count);
+            }
+        }
+        for (&exp, &count) in &self.log10_buckets_neg {
+            counts.insert(BucketKey::Neg(Reverse(exp)), count);
+        }
+        for (&exp, &count) in &self.log10_buckets {
+            counts.insert(BucketKey::Pos(exp), count);
+        }
+        counts
+    }
+
+    // The per-bucket change from other to this histogram, e.g.
+    // after.delta(&before), for every unreduced bucket populated in either,
+    // to show where a distribution shifted.
+    pub fn delta(&self, other: &LogHistogram) -> HistogramDelta {
+        let (counts, other_counts) = (self.bucket_counts(), other.bucket_counts());
+        let (total, other_total) = (self.count_total(), other.count_total());
+        let percent = |count: usize, total: usize| if total == 0 { 0.0 } else { 100.0 * count as f64 / total as f64 };
+        let mut keys: Vec<BucketKey> = counts.keys().chain(other_counts.keys()).cloned().collect();
+        keys.sort();
+        keys.dedup();
+        let buckets = keys.into_iter().map(|key| {
+            let before = other_counts.get(&key).cloned().unwrap_or(0);
+            let after = counts.get(&key).cloned().unwrap_or(0);
+            BucketDelta { key, before, after, before_percent: percent(before, other_total), after_percent: percent(after, total) }
+        }).collect();
+        HistogramDelta { buckets }
+    }
+
+    // The reduced log buckets of positive values, as displayed, in ascending order.
+    // Values are (exponent_min, exponent_max, count).
+    pub fn reduced_buckets(&self) -> Vec<(isize, isize, usize)> {
+        self.reduce();
+        self.reduced.borrow().as_ref().unwrap().values().cloned().collect()
+    }
+
+    // Perform bucket reduction now if the cached reduction is out of date.
+    // Display does this on demand, but calling it explicitly allows callers
+    // to control when the cost is paid.
+    pub fn reduce(&self) {
+        if self.reduced.borrow().is_none() {
+            self.reduced.replace(Some(self.reduced_histo()));
+        }
+        if self.reduced_neg.borrow().is_none() {
+            self.reduced_neg.replace(Some(reduce_buckets(&self.log10_buckets_neg, self.max_display_buckets)));
         }
     }
 
     // Resulting map's keys are the original exponent.
     // Its values are (reduced_exponent_min, reduced_exponent_max, count).
-    fn reduced_histo(&self) -> BTreeMap<isize, (isize, isize, usize)> {
-        assert!(self.max_display_buckets > 2);
-        let mut keys_asc: Vec<isize> = Vec::new();
-        let mut histo_reduced: BTreeMap<isize, (isize, isize, usize)> = BTreeMap::new();
-        self.log10_buckets.iter().for_each(|(&key, &val)| {
-            keys_asc.push(key);
-            histo_reduced.insert(key, (key, key, val));
-        });
-        keys_asc.sort();
-        while histo_reduced.len() > self.max_display_buckets {
-            // Collapse the smallest bucket into its less-populated neighbor.
-            // Favor the less-populated neighbor, to improve odds that ending
-            // buckets are at least somewhat evenly distributed in population.
-            let mut collapse_from = isize::MIN;
-            let mut val_smallest = (collapse_from, collapse_from, usize::MAX);
-            histo_reduced.iter().for_each(|(&key, &(exp_min, exp_max, count))| {
-                if count < val_smallest.2 {
-                    collapse_from = key;
-                    val_smallest = (exp_min, exp_max, count);
-                }
-            });
-
-            let index_smallest = keys_asc.iter().position(|&val| val == collapse_from).unwrap();
-            // Note that our restriction on max_display_buckets lets us
-            // trust we stop looping before we reach the case of 2 or fewer
-            // buckets, which would require additional special case logic.
-            let (collapse_to, val_to) = if index_smallest == 0 {
-                let key_next = keys_asc[index_smallest + 1];
-                let val_next = histo_reduced.get(&key_next).unwrap();
-                (key_next, val_next)
-            } else if index_smallest >= histo_reduced.len() - 1 {
-                let key_prev = keys_asc[index_smallest - 1];
-                let val_prev = histo_reduced.get(&key_prev).unwrap();
-                (key_prev, val_prev)
-            } else {
-                // Favor collapsing into the smaller bucket, to reduce lopsided bucket sizes
-                let key_prev = keys_asc[index_smallest - 1];
-                let key_next = keys_asc[index_smallest + 1];
-                let val_prev = histo_reduced.get(&key_prev).unwrap();
-                let val_next = histo_reduced.get(&key_next).unwrap();
-                if val_next.2 < val_prev.2 {
-                    (key_next, val_next)
-                } else {
-                    (key_prev, val_prev)
-                }
-            };
+    fn reduced_histo(&self) -> ReducedHisto {
+        let pinned = match self.boundary_key() {
+            Some(BucketKey::Pos(exp)) => Some(exp),
+            _ => None,
+        };
+        reduce_buckets_pinned(&self.log10_buckets, self.max_display_buckets, pinned)
+    }
+}
 
-            let val_sum = (isize::min(val_to.0, val_smallest.0), isize::max(val_to.1, val_smallest.1), val_to.2 + val_smallest.2);
+// The unreduced bucket for a value, given a negligible threshold.
+pub(crate) fn bucket_key(diff: f64, negligible: f64) -> BucketKey {
+    let negative = diff < 0.0;
+    if diff.is_nan() {
+        BucketKey::Nan
+    } else if diff.is_infinite() {
+        if negative { BucketKey::NegInf } else { BucketKey::Inf }
+    } else if diff == 0.0 {
+        BucketKey::Zero
+    } else if diff.abs() < negligible {
+        BucketKey::NearZero
+    } else {
+        let exp = diff.abs().log10() as isize;
+        if negative { BucketKey::Neg(Reverse(exp)) } else { BucketKey::Pos(exp) }
+    }
+}
 
-            histo_reduced.remove(&collapse_from);
-            histo_reduced.insert(collapse_to, val_sum);
+fn sorted_buckets(log10_buckets: &BTreeMap<isize, usize>) -> impl DoubleEndedIterator<Item = (isize, usize)> + '_ {
+    log10_buckets.iter().map(|(&key, &val)| (key, val))
+}
 
-            keys_asc.remove(index_smallest);
-            assert_eq!(keys_asc.len(), histo_reduced.len(), "Size mismatch between key list and map");
+// Reduce a set of log buckets down to at most max_display_buckets.
+// Resulting map's keys are the original exponent.
+// Its values are (reduced_exponent_min, reduced_exponent_max, count).
+// The result depends only on the bucket counts, using these tie-break rules:
+// - Among equally small buckets, the one with the lowest exponent collapses first.
+// - A bucket whose neighbors are equally populated collapses into the lower one.
+// Takes O(k log k) time for k buckets, using a min-heap of bucket counts
+// and links between neighboring buckets.
+fn reduce_buckets(log10_buckets: &BTreeMap<isize, usize>, max_display_buckets: usize) -> ReducedHisto {
+    reduce_buckets_pinned(log10_buckets, max_display_buckets, None)
+}
+
+// As reduce_buckets, except that the bucket for the pinned exponent, if
+// any, neither collapses nor absorbs its neighbors.
+// A bucket with no neighbor besides the pinned one is left as is, so at
+// least the pinned bucket and one to either side of it may remain.
+fn reduce_buckets_pinned(log10_buckets: &BTreeMap<isize, usize>, max_display_buckets: usize, pinned: Option<isize>) -> ReducedHisto {
+    assert!(max_display_buckets > 2);
+    // Buckets in ascending key order, as (key, (exp_min, exp_max, count)).
+    // Collapsed buckets stay in place, and are skipped via the links.
+    let mut buckets: Vec<(isize, (isize, isize, usize))> =
+        log10_buckets.iter().map(|(&key, &val)| (key, (key, key, val))).collect();
+    let len = buckets.len();
+    // The positions of each bucket's remaining neighbors, if any.
+    let mut prev: Vec<Option<usize>> = (0..len).map(|i| i.checked_sub(1)).collect();
+    let mut next: Vec<Option<usize>> = (0..len).map(|i| Some(i + 1).filter(|&n| n < len)).collect();
+    let mut removed = vec![false; len];
+    // Min-heap of (count, position). Positions follow key order, so ties
+    // pop the lowest key first. Entries whose count is out of date are skipped.
+    let mut heap: BinaryHeap<Reverse<(usize, usize)>> =
+        buckets.iter().enumerate().map(|(i, bucket)| Reverse(((bucket.1).2, i))).collect();
+    let pinned = pinned.and_then(|key| buckets.iter().position(|bucket| bucket.0 == key));
+    let unpinned = |neighbor: Option<usize>| neighbor.filter(|&i| Some(i) != pinned);
+    let mut remaining = len;
+    while remaining > max_display_buckets {
+        let Reverse((count, from)) = match heap.pop() {
+            Some(entry) => entry,
+            None => break,
+        };
+        if removed[from] || (buckets[from].1).2 != count || Some(from) == pinned {
+            continue;
         }
-        histo_reduced
+        // Collapse the smallest bucket into its less-populated neighbor.
+        // Favor the less-populated neighbor, to improve odds that ending
+        // buckets are at least somewhat evenly distributed in population.
+        // Note that our restriction on max_display_buckets lets us
+        // trust we stop looping before we reach the case of 2 or fewer
+        // buckets, which would require additional special case logic.
+        let to = match (unpinned(prev[from]), unpinned(next[from])) {
+            (None, Some(n)) => n,
+            (Some(p), None) => p,
+            (Some(p), Some(n)) => {
+                if (buckets[n].1).2 < (buckets[p].1).2 { n } else { p }
+            }
+            (None, None) => continue,
+        };
+        let val_from = buckets[from].1;
+        let val_to = &mut buckets[to].1;
+        *val_to = (isize::min(val_to.0, val_from.0), isize::max(val_to.1, val_from.1), val_to.2 + val_from.2);
+        heap.push(Reverse((val_to.2, to)));
+
+        removed[from] = true;
+        if let Some(p) = prev[from] {
+            next[p] = next[from];
+        }
+        if let Some(n) = next[from] {
+            prev[n] = prev[from];
+        }
+        remaining -= 1;
     }
+    buckets.into_iter().zip(removed).filter(|(_, removed)| !removed).map(|(bucket, _)| bucket).collect()
 }
 
 impl Clone for LogHistogram {
@@ -125,71 +620,269 @@ impl Clone for LogHistogram {
         LogHistogram {
             num_nan: self.num_nan,
             num_inf: self.num_inf,
+            num_neg_inf: self.num_neg_inf,
             num_zero: self.num_zero,
+            num_negligible: self.num_negligible,
+            negligible: self.negligible,
             max_display_buckets: self.max_display_buckets,
+            signed: self.signed,
             log10_buckets: self.log10_buckets.clone(),
+            log10_buckets_neg: self.log10_buckets_neg.clone(),
+            boundary: self.boundary,
+            num_above_boundary: self.num_above_boundary,
+            reduced: self.reduced.clone(),
+            reduced_neg: self.reduced_neg.clone(),
         }
     }
 }
 
-impl Display for LogHistogram {
-    // Display a summary, reduced down to a manageable number of buckets.
+impl LogHistogram {
+    // Wrap this histogram for display with a unit after bucket labels,
+    // e.g. "e-3 m 50%" rather than "e-3 50%".
+    pub fn display_with_unit<'h>(&'h self, unit: &'h str) -> impl Display + 'h {
+        self.display_with_labels(unit, BucketLabels::Exponent)
+    }
+
+    // As display_with_unit, with log buckets labeled per labels,
+    // e.g. "1e-4..1e-2 m 50%" for BucketLabels::Range.
+    pub fn display_with_labels<'h>(&'h self, unit: &'h str, labels: BucketLabels) -> impl Display + 'h {
+        HistogramWithUnit { histo: self, style: BucketStyle::new(unit, labels) }
+    }
+
+    // As display_with_labels, with a "|" marking where marker, such as a
+    // tolerance, falls among the buckets, e.g. "e-7 90% | e-5 10%".
+    // The marker goes before the first bucket holding only larger values, so
+    // after any bucket that straddles it, unless split there by with_boundary.
+    pub fn display_with_marker<'h>(&'h self, unit: &'h str, labels: BucketLabels, marker: f64) -> impl Display + 'h {
+        HistogramWithUnit { histo: self, style: BucketStyle { marker: Some(marker), ..BucketStyle::new(unit, labels) } }
+    }
+
+    // Display per the bucket labels, counts and percent format of options,
+    // with a marker if any, as for display_with_marker.
+    pub(crate) fn display_with_options<'h>(&'h self, unit: &'h str, options: &DisplayOptions, marker: Option<f64>) -> impl Display + 'h {
+        let style = BucketStyle {
+            unit,
+            labels: options.bucket_labels,
+            show_counts: options.show_counts,
+            percent_format: options.percent_format,
+            marker,
+        };
+        HistogramWithUnit { histo: self, style }
+    }
+
+    // Display a summary, reduced down to a manageable number of buckets,
+    // with unit after bucket labels, and optionally counts before percents.
     // Note that this bucket reduction may be relatively expensive.
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::result::Result<(), std::fmt::Error> {
-        // histo_reduced map's keys are the original exponent.
-        // Its values are (reduced_exponent_min, reduced_exponent_max, count).
-        let mut histo_reduced: BTreeMap<isize, (isize, isize, usize)> = self.reduced_histo();
-        let mut num_total = self.num_inf + self.num_nan + self.num_zero;
-        self.log10_buckets.iter().for_each(|(_key, &val)| {
+    // Histograms with no more buckets than are displayed need no reduction,
+    // so they're shown directly from their buckets, without allocating.
+    fn fmt_with_unit(&self, f: &mut std::fmt::Formatter<'_>, style: BucketStyle) -> std::fmt::Result {
+        if self.log10_buckets.len() <= self.max_display_buckets && self.log10_buckets_neg.len() <= self.max_display_buckets {
+            let unreduced = |buckets| sorted_buckets(buckets).map(|(key, count)| (key, (key, key, count)));
+            let (histo, histo_neg) = (unreduced(&self.log10_buckets), unreduced(&self.log10_buckets_neg));
+            return self.fmt_buckets(f, style, histo, histo_neg);
+        }
+        self.reduce();
+        let reduced = self.reduced.borrow();
+        let reduced_neg = self.reduced_neg.borrow();
+        let histo_reduced = reduced.as_ref().unwrap().iter().map(|(&key, &bucket)| (key, bucket));
+        let histo_reduced_neg = reduced_neg.as_ref().unwrap().iter().map(|(&key, &bucket)| (key, bucket));
+        self.fmt_buckets(f, style, histo_reduced, histo_reduced_neg)
+    }
+
+    // Display the given (key, (exponent_min, exponent_max, count)) buckets,
+    // in ascending key order, along with the special case buckets.
+    fn fmt_buckets(
+        &self,
+        f: &mut std::fmt::Formatter<'_>,
+        style: BucketStyle,
+        histo_reduced: impl Iterator<Item = (isize, (isize, isize, usize))>,
+        histo_reduced_neg: impl DoubleEndedIterator<Item = (isize, (isize, isize, usize))>,
+    ) -> std::fmt::Result {
+        let BucketStyle { unit, labels, show_counts, percent_format, marker } = style;
+        let mut num_total = self.num_inf + self.num_neg_inf + self.num_nan + self.num_zero + self.num_negligible;
+        self.log10_buckets.values().chain(self.log10_buckets_neg.values()).for_each(|&val| {
             num_total += val;
         });
 
-        let mut first = true;
-        let mut pad_maybe = || {
-            if first {
-                first = false;
-                ""
-            } else {
-                ", "
+        let sep = Cell::new("");
+        let pad_maybe = || sep.replace(", ");
+        // Write the marker, if any, before the first item with values at or
+        // above lo, by replacing the separator before it.
+        let mut marked = marker.is_none();
+        let mut mark = |f: &mut std::fmt::Formatter<'_>, lo: f64| {
+            if !marked && lo >= marker.unwrap() {
+                marked = true;
+                if sep.get().is_empty() {
+                    return write!(f, "| ");
+                }
+                sep.set(" | ");
             }
+            Ok(())
         };
 
+        // Negative values are shown from most to least negative.
+        if self.num_neg_inf > 0 {
+            let percent_neg_inf = Percent(self.num_neg_inf, num_total, show_counts, percent_format);
+            write!(f, "{}-inf {}", pad_maybe(), percent_neg_inf)?;
+        }
+        for (_, (exp_min, exp_max, count)) in histo_reduced_neg.rev() {
+            assert!(count != 0, "Internal error: Bucket contains no items");
+            let percent = Percent(count, num_total, show_counts, percent_format);
+            let label = self.bucket_label(labels, true, exp_min, exp_max, None);
+            write!(f, "{}{} {}", pad_maybe(), WithUnit(label, unit), percent)?;
+        }
+
         if self.num_zero > 0 {
-            let percent_zero = util::to_percent(self.num_zero, num_total); 
-            write!(f, "{}zero {}%", pad_maybe(), percent_zero)?;
+            let percent_zero = Percent(self.num_zero, num_total, show_counts, percent_format);
+            write!(f, "{}zero {}", pad_maybe(), percent_zero)?;
+        }
+        if self.num_negligible > 0 {
+            let percent_negligible = Percent(self.num_negligible, num_total, show_counts, percent_format);
+            write!(f, "{}near-zero {}", pad_maybe(), percent_negligible)?;
         }
 
-        // Convert counts to percentages
-        histo_reduced.iter_mut().for_each(|(_key, (_exp_min, _exp_max, count))| {
-            assert!(*count != 0, "Internal error: Bucket contains no items");
-            *count = util::to_percent(*count, num_total);
-        });
-        for (key, (exp_min, exp_max, count)) in &histo_reduced {
-            if exp_min == exp_max {
-                write!(f, "{}e{} {}%", pad_maybe(), key, count)?;
-            } else {
-                write!(f, "{}e{} to e{} {}%", pad_maybe(), exp_min, exp_max, count)?;
+        let boundary_key = self.boundary_key();
+        for (key, (exp_min, exp_max, count)) in histo_reduced {
+            assert!(count != 0, "Internal error: Bucket contains no items");
+            if let (Some(BucketKey::Pos(boundary_exp)), Some(boundary)) = (boundary_key, self.boundary) {
+                if key == boundary_exp {
+                    // The boundary's bucket is never reduced, so its counts split exactly.
+                    let above = self.num_above_boundary;
+                    for &(part_count, split) in [(count - above, (false, boundary)), (above, (true, boundary))].iter() {
+                        if part_count > 0 {
+                            let percent = Percent(part_count, num_total, show_counts, percent_format);
+                            let label = self.bucket_label(labels, false, exp_min, exp_max, Some(split));
+                            mark(f, label.lo)?;
+                            write!(f, "{}{} {}", pad_maybe(), WithUnit(label, unit), percent)?;
+                        }
+                    }
+                    continue;
+                }
             }
+            let percent = Percent(count, num_total, show_counts, percent_format);
+            let label = self.bucket_label(labels, false, exp_min, exp_max, None);
+            mark(f, label.lo)?;
+            write!(f, "{}{} {}", pad_maybe(), WithUnit(label, unit), percent)?;
+        }
+        if self.num_inf > 0 || self.num_nan > 0 {
+            mark(f, f64::INFINITY)?;
         }
         if self.num_inf > 0 {
-            let percent_inf = util::to_percent(self.num_inf, num_total);
-            write!(f, "{}inf {}%", pad_maybe(), percent_inf)?;
+            let percent_inf = Percent(self.num_inf, num_total, show_counts, percent_format);
+            write!(f, "{}inf {}", pad_maybe(), percent_inf)?;
         }
         if self.num_nan > 0 {
-            let percent_nan = util::to_percent(self.num_nan, num_total);
-            write!(f, "{}nan {}%", pad_maybe(), percent_nan)?;
+            let percent_nan = Percent(self.num_nan, num_total, show_counts, percent_format);
+            write!(f, "{}nan {}", pad_maybe(), percent_nan)?;
+        }
+        // Every value is within the marker.
+        mark(f, f64::INFINITY)?;
+        if sep.get() == " | " {
+            write!(f, " |")?;
         }
         Ok(())
     }
 }
 
+impl LogHistogram {
+    // The label for a reduced log bucket spanning exponents exp_min to
+    // exp_max, of negative values if negative. split is (above, boundary)
+    // for either part of the boundary's bucket.
+    fn bucket_label(&self, labels: BucketLabels, negative: bool, exp_min: isize, exp_max: isize, split: Option<(bool, f64)>) -> BucketLabel {
+        let (mut lo, mut hi) = (self.bucket_bounds(exp_min).0, self.bucket_bounds(exp_max).1);
+        match split {
+            Some((true, boundary)) => lo = boundary,
+            Some((false, boundary)) => hi = boundary,
+            None => (),
+        }
+        BucketLabel { labels, negative, exp_min, exp_max, lo, hi, split }
+    }
+}
+
+// A log bucket's label, as built by LogHistogram::bucket_label.
+struct BucketLabel {
+    labels: BucketLabels,
+    negative: bool,
+    exp_min: isize,
+    exp_max: isize,
+    // The magnitudes of the values held.
+    lo: f64,
+    hi: f64,
+    split: Option<(bool, f64)>,
+}
+
+impl Display for BucketLabel {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let sign = if self.negative { "-" } else { "" };
+        match self.labels {
+            BucketLabels::Exponent => {
+                if self.exp_min == self.exp_max {
+                    write!(f, "{}e{}", sign, self.exp_min)?;
+                } else if self.negative {
+                    write!(f, "-e{} to -e{}", self.exp_max, self.exp_min)?;
+                } else {
+                    write!(f, "e{} to e{}", self.exp_min, self.exp_max)?;
+                }
+                match self.split {
+                    Some((true, boundary)) => write!(f, " >{:e}", boundary),
+                    Some((false, boundary)) => write!(f, " <={:e}", boundary),
+                    None => Ok(()),
+                }
+            }
+            // Negative ranges run from most to least negative.
+            BucketLabels::Range if self.negative => write!(f, "-{:e}..-{:e}", self.hi, self.lo),
+            BucketLabels::Range => write!(f, "{:e}..{:e}", self.lo, self.hi),
+        }
+    }
+}
+
+impl Display for LogHistogram {
+    // Display a summary, reduced down to a manageable number of buckets.
+    // Note that this bucket reduction may be relatively expensive.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::result::Result<(), std::fmt::Error> {
+        self.fmt_with_unit(f, BucketStyle::new("", BucketLabels::Exponent))
+    }
+}
+
+// How a LogHistogram's buckets are shown: the unit after bucket labels,
+// the style of label, whether counts are shown before percents, and the
+// value to mark among the buckets, if any.
+#[derive(Clone, Copy)]
+struct BucketStyle<'u> {
+    unit: &'u str,
+    labels: BucketLabels,
+    show_counts: bool,
+    percent_format: PercentFormat,
+    marker: Option<f64>,
+}
+
+impl<'u> BucketStyle<'u> {
+    fn new(unit: &'u str, labels: BucketLabels) -> Self {
+        BucketStyle { unit, labels, show_counts: false, percent_format: PercentFormat::Whole, marker: None }
+    }
+}
+
+// A LogHistogram paired with a unit, as returned by display_with_unit.
+struct HistogramWithUnit<'h> {
+    histo: &'h LogHistogram,
+    style: BucketStyle<'h>,
+}
+
+impl Display for HistogramWithUnit<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.histo.fmt_with_unit(f, self.style)
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use super::{LogHistogram};
+    use super::{reduce_buckets, BucketKey, BucketLabels, LogHistogram, ReducedHisto};
+    use std::collections::BTreeMap;
+    use std::cmp::Reverse;
 
     #[test]
     fn test_reduce() {
-        let mut map = std::collections::HashMap::new();
+        let mut map = std::collections::BTreeMap::new();
         map.insert(-300, 5);
         map.insert(-250, 4);
         map.insert(-100, 3);
@@ -248,4 +941,289 @@ mod tests {
         assert_eq!(*histo_reduced.get(& 1).unwrap(), ( 1  ,  1 , 500));
         assert_eq!(*histo_reduced.get(& 2).unwrap(), ( 2  ,  13, 131));
     }
+
+    #[test]
+    fn test_reduce_stable() {
+        // Every bucket has the same count, so each collapse is decided by tie-breaks.
+        let values: Vec<f64> = (-6..6).map(|exp| 10f64.powi(exp)).collect();
+        let mut forward = LogHistogram::new(4);
+        let mut backward = LogHistogram::new(4);
+        for &val in &values {
+            forward.add(val);
+        }
+        for &val in values.iter().rev() {
+            backward.add(val);
+        }
+        let expected = vec![(-6, -3, 4), (-2, 1, 4), (2, 3, 2), (4, 5, 2)];
+        assert_eq!(forward.reduced_buckets(), expected);
+        assert_eq!(backward.reduced_buckets(), expected);
+        assert_eq!(format!("{}", forward), format!("{}", backward));
+        assert_eq!(format!("{}", forward.clone()), format!("{}", forward));
+    }
+
+    // The original O(k^2) reduction, rescanning for the smallest bucket on each collapse.
+    fn reduce_buckets_rescan(log10_buckets: &BTreeMap<isize, usize>, max_display_buckets: usize) -> ReducedHisto {
+        let mut keys_asc: Vec<isize> = log10_buckets.keys().cloned().collect();
+        let mut histo_reduced: ReducedHisto = log10_buckets.iter().map(|(&key, &val)| (key, (key, key, val))).collect();
+        while histo_reduced.len() > max_display_buckets {
+            let (&collapse_from, &val_smallest) = histo_reduced.iter().min_by_key(|(_, val)| val.2).unwrap();
+            let index_smallest = keys_asc.iter().position(|&key| key == collapse_from).unwrap();
+            let collapse_to = if index_smallest == 0 {
+                keys_asc[1]
+            } else if index_smallest == keys_asc.len() - 1 {
+                keys_asc[index_smallest - 1]
+            } else {
+                let (key_prev, key_next) = (keys_asc[index_smallest - 1], keys_asc[index_smallest + 1]);
+                if histo_reduced[&key_next].2 < histo_reduced[&key_prev].2 { key_next } else { key_prev }
+            };
+            let val_to = histo_reduced[&collapse_to];
+            let val_sum = (isize::min(val_to.0, val_smallest.0), isize::max(val_to.1, val_smallest.1), val_to.2 + val_smallest.2);
+            histo_reduced.remove(&collapse_from);
+            histo_reduced.insert(collapse_to, val_sum);
+            keys_asc.remove(index_smallest);
+        }
+        histo_reduced
+    }
+
+    #[test]
+    fn test_reduce_matches_rescan() {
+        // Pseudo-random counts, with plenty of ties.
+        let mut state = 12345u64;
+        for &len in &[3, 4, 10, 57, 300] {
+            let mut buckets = BTreeMap::new();
+            for key in 0..len {
+                state = state.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+                buckets.insert(key * 3 - 100, (state >> 60) as usize + 1);
+            }
+            for &max in &[3, 5, 12, 40] {
+                assert_eq!(reduce_buckets(&buckets, max), reduce_buckets_rescan(&buckets, max), "len {} max {}", len, max);
+            }
+        }
+    }
+
+    #[test]
+    fn test_reduce_cache() {
+        let mut histo = LogHistogram::new(3);
+        histo.add(1e-3);
+        histo.add(1e-1);
+        assert!(histo.reduced.borrow().is_none());
+        histo.reduce();
+        assert_eq!(histo.reduced.borrow().as_ref().unwrap().len(), 2);
+        let shown = format!("{}", histo);
+
+        // Special case values don't affect the reduction.
+        histo.add(0.0);
+        assert!(histo.reduced.borrow().is_some());
+
+        histo.add(1e5);
+        assert!(histo.reduced.borrow().is_none());
+        assert_ne!(format!("{}", histo), shown);
+        // Histograms within max_display_buckets are shown without reducing.
+        assert!(histo.reduced.borrow().is_none());
+        histo.reduce();
+        assert_eq!(histo.reduced.borrow().as_ref().unwrap().len(), 3);
+
+        // Larger ones are reduced, and cached, by Display.
+        histo.add(1e7);
+        assert!(histo.reduced.borrow().is_none());
+        let _ = format!("{}", histo);
+        assert_eq!(histo.reduced.borrow().as_ref().unwrap().len(), 3);
+    }
+
+    #[test]
+    fn test_negligible() {
+        let mut histo = LogHistogram::new(3).with_negligible(1e-200);
+        histo.add(0.0);
+        histo.add(1e-300);
+        histo.add(1e-200);
+        histo.add(1e-3);
+        assert_eq!(histo.num_zero, 1);
+        assert_eq!(histo.num_negligible, 1);
+        assert_eq!(histo.log10_buckets.len(), 2);
+        assert_eq!(format!("{}", histo), "zero 25%, near-zero 25%, e-200 25%, e-3 25%");
+    }
+
+    #[test]
+    fn test_boundary() {
+        let mut histo = LogHistogram::new(3).with_boundary(5e-7);
+        histo.add_batch(&[3e-11, 3e-10, 3e-9, 2e-7, 8e-7]);
+        let mut other = LogHistogram::new(3).with_boundary(5e-7);
+        other.add(3e-3);
+        histo.merge(&other);
+        assert_eq!((histo.boundary(), histo.count_above_boundary()), (Some(5e-7), 1));
+        assert_eq!(histo.to_string(), "e-10 to e-8 50%, e-6 <=5e-7 17%, e-6 >5e-7 17%, e-2 17%");
+        assert_eq!(
+            histo.display_with_labels("m", BucketLabels::Range).to_string(),
+            "1e-11..1e-8 m 50%, 1e-7..5e-7 m 17%, 5e-7..1e-6 m 17%, 1e-3..1e-2 m 17%"
+        );
+    }
+
+    #[test]
+    fn test_marker() {
+        let mut histo = LogHistogram::new(3).with_boundary(5e-7);
+        histo.add_batch(&[0.0, 2e-7, 8e-7, 3e-3]);
+        let marked = |histo: &LogHistogram, marker| histo.display_with_marker("", BucketLabels::Exponent, marker).to_string();
+        assert_eq!(marked(&histo, 5e-7), "zero 25%, e-6 <=5e-7 25% | e-6 >5e-7 25%, e-2 25%");
+        assert_eq!(marked(&histo, 1e-6), "zero 25%, e-6 <=5e-7 25%, e-6 >5e-7 25% | e-2 25%");
+        assert_eq!(marked(&histo, 1.0), "zero 25%, e-6 <=5e-7 25%, e-6 >5e-7 25%, e-2 25% |");
+        histo.add(f64::NAN);
+        assert_eq!(marked(&histo, 1.0), "zero 20%, e-6 <=5e-7 20%, e-6 >5e-7 20%, e-2 20% | nan 20%");
+        let mut above = LogHistogram::new(3);
+        above.add(3e-3);
+        assert_eq!(above.display_with_marker("", BucketLabels::Exponent, 1e-6).to_string(), "| e-2 100%");
+    }
+
+    #[test]
+    fn test_signed() {
+        let mut histo = LogHistogram::new_signed(3);
+        for &val in &[-1e5, -2e5, -1e-3, -1e-7, -1e-9, -0.0, 0.0, 1e-2, f64::NEG_INFINITY, f64::NAN] {
+            histo.add(val);
+        }
+        assert_eq!(histo.num_neg_inf, 1);
+        assert_eq!(histo.num_zero, 2);
+        assert_eq!(histo.log10_buckets_neg.len(), 4);
+        assert_eq!(
+            format!("{}", histo),
+            "-inf 10%, -e5 20%, -e-3 10%, -e-7 to -e-9 20%, zero 20%, e-2 10%, nan 10%"
+        );
+        assert_eq!(
+            histo.display_with_labels("", BucketLabels::Range).to_string(),
+            "-inf 10%, -1e6..-1e5 20%, -1e-3..-1e-4 10%, -1e-7..-1e-10 20%, zero 20%, 1e-3..1e-2 10%, nan 10%"
+        );
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_unsigned_rejects_negative() {
+        let mut histo = LogHistogram::new(3);
+        histo.add(-1.0);
+    }
+
+    #[test]
+    fn test_add_batch() {
+        let values = [0.0, 0.0, 2e-3, 3e-3, 1e2, f64::NAN, 2e-3, 0.0, f64::INFINITY];
+        let mut single = LogHistogram::new(3);
+        for &val in &values {
+            single.add(val);
+        }
+        let mut batched = LogHistogram::new(3);
+        batched.add_batch(&values[..4]);
+        batched.add_batch(&values[4..]);
+        batched.add_batch(&[]);
+        assert_eq!(batched.bucket_counts(), single.bucket_counts());
+        assert_eq!(format!("{}", batched), format!("{}", single));
+    }
+
+    #[test]
+    fn test_accessors() {
+        let mut histo = LogHistogram::new(3);
+        for &val in &[0.0, 2e-3, 3e-3, 1e2, 5e6, 7e9, f64::INFINITY, f64::NAN] {
+            histo.add(val);
+        }
+        assert_eq!(histo.count_zero(), 1);
+        assert_eq!(histo.count_inf(), 1);
+        assert_eq!(histo.count_nan(), 1);
+        assert_eq!(histo.count_total(), 8);
+        assert!(!histo.is_signed());
+        assert_eq!(histo.buckets().collect::<Vec<_>>(), vec![(-2, 2), (2, 1), (6, 1), (9, 1)]);
+        assert_eq!(histo.buckets_neg().count(), 0);
+        assert_eq!(histo.reduced_buckets(), vec![(-2, -2, 2), (2, 6, 2), (9, 9, 1)]);
+    }
+
+    #[test]
+    fn test_merge() {
+        let mut histo = LogHistogram::new(3);
+        let mut other = LogHistogram::new(4);
+        for &val in &[0.0, 2e-3, 1e2] {
+            histo.add(val);
+        }
+        for &val in &[3e-3, 5e6, f64::NAN] {
+            other.add(val);
+        }
+        histo.reduce();
+        histo.merge(&other);
+        assert_eq!(histo.count_total(), 6);
+        assert_eq!(histo.count_nan(), 1);
+        assert_eq!(histo.max_display_buckets(), 3);
+        assert_eq!(histo.buckets().collect::<Vec<_>>(), vec![(-2, 2), (2, 1), (6, 1)]);
+        assert_eq!(format!("{}", histo), "zero 17%, e-2 33%, e2 17%, e6 17%, nan 17%");
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_merge_incompatible() {
+        let mut histo = LogHistogram::new(3);
+        histo.merge(&LogHistogram::new(3).with_negligible(1e-100));
+    }
+
+    #[test]
+    fn test_approx_quantile() {
+        let mut histo = LogHistogram::new(3);
+        assert!(histo.approx_quantile(0.5).is_nan());
+        for _ in 0..10 {
+            histo.add(0.0);
+        }
+        for _ in 0..80 {
+            histo.add(2e3);
+        }
+        for _ in 0..9 {
+            histo.add(f64::INFINITY);
+        }
+        histo.add(f64::NAN);
+        assert_eq!(histo.approx_quantile(0.0), 0.0);
+        let median = histo.approx_quantile(0.5);
+        assert!(1e3 < median && median < 1e4, "{}", median);
+        assert_eq!(histo.approx_quantile(0.95), f64::INFINITY);
+        assert!(histo.approx_quantile(1.0).is_nan());
+
+        let mut signed = LogHistogram::new_signed(3);
+        for &val in &[-5e2, -5e2, 0.0, 5e-2] {
+            signed.add(val);
+        }
+        let low = signed.approx_quantile(0.0);
+        assert!(-1e3 < low && low < -1e2, "{}", low);
+        let high = signed.approx_quantile(1.0);
+        assert!(1e-2 < high && high < 1e-1, "{}", high);
+    }
+
+    #[test]
+    fn test_bucket_counts() {
+        let mut histo = LogHistogram::new_signed(3);
+        for &val in &[f64::NAN, 2e3, -0.0, -5e-3, -5e2, 3e3] {
+            histo.add(val);
+        }
+        let counts: Vec<(BucketKey, usize)> = histo.bucket_counts().into_iter().collect();
+        assert_eq!(counts, vec![
+            (BucketKey::Neg(Reverse(2)), 1),
+            (BucketKey::Neg(Reverse(-2)), 1),
+            (BucketKey::Zero, 1),
+            (BucketKey::Pos(3), 2),
+            (BucketKey::Nan, 1),
+        ]);
+        assert_eq!(format!("{}", BucketKey::Neg(Reverse(-2))), "-e-2");
+    }
+
+    #[test]
+    fn test_delta() {
+        let (mut before, mut after) = (LogHistogram::new(4), LogHistogram::new(4));
+        for &val in &[0.0, 0.5, 0.5, 20.0] {
+            before.add(val);
+        }
+        for &val in &[0.0, 0.5, 0.05, 0.05, 0.05, f64::NAN] {
+            after.add(val);
+        }
+        let delta = after.delta(&before);
+        let keys: Vec<(BucketKey, isize)> = delta.buckets.iter().map(|bucket| (bucket.key, bucket.count_delta())).collect();
+        assert_eq!(keys, vec![
+            (BucketKey::Zero, 0),
+            (BucketKey::Pos(-1), 3),
+            (BucketKey::Pos(0), -1),
+            (BucketKey::Pos(1), -1),
+            (BucketKey::Nan, 1),
+        ]);
+        assert_eq!(delta.buckets[1].percent_delta(), 50.0);
+        assert_eq!(delta.changed().count(), 5);
+        assert_eq!(delta.to_string(), "zero +0 -8.3pp, e-1 +3 +50.0pp, e0 -1 -33.3pp, e1 -1 -25.0pp, nan +1 +16.7pp");
+        assert_eq!(before.delta(&before).to_string(), "unchanged");
+    }
 }
@@ -0,0 +1,75 @@
+// A versioned machine format for summary and report results, for tooling
+// that parses results, so that the human-readable Display output can change
+// without breaking parsers. Each version's layout is frozen: changes to the
+// fields will come as a new version, with earlier versions still available.
+// Format stable-v1 has one record per line, with tab-separated fields:
+//   stable-v1, name, status, count, fail_count, sign_count, worst_diff, allow_diff
+// The first field is always the version tag. status is "ok" or "fail".
+// Counts are decimal integers. Differences are in shortest round-trip
+// scientific notation, e.g. 1.5e-3 or 0e0, or nan, inf or -inf, independent
+// of locale. Tabs, newlines and backslashes in names are escaped as \t, \n
+// and \\. For example:
+//   stable-v1	pressure	fail	1000	3	0	2.5e-9	1e-9
+// Produced by DiffSummary::fmt_machine, Report::fmt_machine, and
+//   float-diff report --format=stable-v1 <dir>
+use crate::accumulate;
+use crate::diff_summary_f64::DiffSummary;
+use crate::registry::{RegistryEntry, Report};
+
+// The version tag of the stable-v1 format.
+pub const STABLE_V1: &str = "stable-v1";
+
+// Format a registry entry as a stable-v1 record, without a newline.
+pub fn format_v1(entry: &RegistryEntry) -> String {
+    format!(
+        "{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}",
+        STABLE_V1,
+        accumulate::escape(&entry.name),
+        if entry.is_ok { "ok" } else { "fail" },
+        entry.count,
+        entry.fail_count,
+        entry.sign_count,
+        format_diff(entry.worst_diff),
+        format_diff(entry.allow_diff),
+    )
+}
+
+// Format a difference per stable-v1, spelling nan consistently.
+fn format_diff(diff: f64) -> String {
+    if diff.is_nan() { "nan".to_string() } else { format!("{:e}", diff) }
+}
+
+impl DiffSummary<'_> {
+    // This summary's results as a stable-v1 record, without a newline.
+    pub fn fmt_machine(&self) -> String {
+        format_v1(&RegistryEntry::new(self))
+    }
+}
+
+impl Report {
+    // The entries as stable-v1 records, one per line, in display order.
+    pub fn fmt_machine(&self) -> String {
+        self.entries.iter().map(|entry| format_v1(entry) + "\n").collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::format_v1;
+    use crate::diff;
+    use crate::diff_summary_f64::DiffSummary;
+    use crate::registry::{RegistryEntry, Report};
+
+    #[test]
+    fn test_stable_v1() {
+        let mut summary = DiffSummary::new("pressure\tinlet", 1e-9, false, 4, &diff::diff_abs);
+        summary.add_slices(&[1.0, 2.0, -3.0], &[1.0, 2.0 + 2.5e-9, 3.0]);
+        // The layout is frozen, so these are exact.
+        assert_eq!(summary.fmt_machine(), "stable-v1\tpressure\\tinlet\tfail\t3\t2\t1\t6e0\t1e-9");
+        let mut nan = DiffSummary::new("nan", 0.5, true, 4, &diff::diff_abs);
+        nan.add(f64::NAN, 1.0, 0);
+        assert_eq!(format_v1(&RegistryEntry::new(&nan)), "stable-v1\tnan\tfail\t1\t1\t0\tnan\t5e-1");
+        let report = Report { entries: vec![RegistryEntry::new(&nan), RegistryEntry::new(&summary)] };
+        assert_eq!(report.fmt_machine(), format!("{}\n{}\n", nan.fmt_machine(), summary.fmt_machine()));
+    }
+}
@@ -0,0 +1,237 @@
+use std::fmt::Display;
+use crate::util::{self, FloatFormat, FmtF64};
+
+// The (index, x, y) of an item.
+type Item = (usize, f64, f64);
+
+// An object for checking whether computed values (x) preserve the ordering
+// of expected values (y), as is often required of approximations,
+// independent of how close each individual value is.
+// A pair of items is an inversion if their x and y values are strictly
+// ordered in opposite directions. Ties on either side are not inversions.
+// Items with nan on either side are counted, but excluded from checks.
+pub struct OrderSummary<'a> {
+    // The name of this summary.
+    pub name: &'a str,
+
+    // The total number of items added to this summary.
+    num_total: usize,
+
+    // The number of items with nan x or y.
+    num_nan: usize,
+
+    // The (index, x, y) of the most recent item without nans.
+    prev: Option<Item>,
+
+    // The number of consecutive item pairs that are inverted.
+    num_consecutive: usize,
+
+    // The number of consecutive item pairs checked.
+    num_consecutive_checked: usize,
+
+    // The (index, x, y) of each item in the first inverted consecutive pair.
+    first_inversion: Option<(Item, Item)>,
+
+    // Whether to retain all (x, y) pairs, for counting inversions between
+    // arbitrary pairs of items. Retention requires memory linear in item count.
+    retain: bool,
+
+    // Retained (x, y) pairs, excluding pairs with nans.
+    pairs: Vec<(f64, f64)>,
+}
+
+impl<'a> OrderSummary<'a> {
+    // Create a summary that checks consecutive items only, in constant memory.
+    pub fn new(name: &'a str) -> Self {
+        OrderSummary {
+            name,
+            num_total: 0,
+            num_nan: 0,
+            prev: None,
+            num_consecutive: 0,
+            num_consecutive_checked: 0,
+            first_inversion: None,
+            retain: false,
+            pairs: Vec::new(),
+        }
+    }
+
+    // Create a summary that also counts inversions between arbitrary pairs of items.
+    pub fn new_all_pairs(name: &'a str) -> Self {
+        OrderSummary {
+            retain: true,
+            ..OrderSummary::new(name)
+        }
+    }
+
+    // Add an item, checking it against the previous item.
+    pub fn add(&mut self, x: f64, y: f64, index: usize) {
+        self.num_total += 1;
+        if x.is_nan() || y.is_nan() {
+            self.num_nan += 1;
+            return;
+        }
+        if let Some(prev) = self.prev {
+            self.num_consecutive_checked += 1;
+            if is_inversion((prev.1, prev.2), (x, y)) {
+                self.num_consecutive += 1;
+                if self.first_inversion.is_none() {
+                    self.first_inversion = Some((prev, (index, x, y)));
+                }
+            }
+        }
+        self.prev = Some((index, x, y));
+        if self.retain {
+            self.pairs.push((x, y));
+        }
+    }
+
+    // The number of inverted consecutive item pairs.
+    pub fn consecutive_inversions(&self) -> usize {
+        self.num_consecutive
+    }
+
+    // The (index, x, y) of each item in the first inverted consecutive pair, if any.
+    pub fn first_inversion(&self) -> Option<(Item, Item)> {
+        self.first_inversion
+    }
+
+    // The number of inverted pairs among all pairs of items, or None if this
+    // summary wasn't created with new_all_pairs.
+    // Takes O(n log n) time.
+    pub fn all_inversions(&self) -> Option<usize> {
+        if !self.retain {
+            return None;
+        }
+        // Sort by expected value, breaking ties by computed value so that
+        // ties in expected value are never counted as inversions.
+        let mut pairs = self.pairs.clone();
+        pairs.sort_by(|a, b| a.1.partial_cmp(&b.1).unwrap().then(a.0.partial_cmp(&b.0).unwrap()));
+        let mut xs: Vec<f64> = pairs.iter().map(|pair| pair.0).collect();
+        let mut buffer = xs.clone();
+        Some(count_inversions(&mut xs, &mut buffer))
+    }
+
+    // Indicate whether no inversions have been found.
+    pub fn is_ok(&self) -> bool {
+        self.num_consecutive == 0 && self.all_inversions().unwrap_or(0) == 0
+    }
+
+    // Assert that no inversions have been found.
+    pub fn assert(&self) {
+        assert!(self.is_ok(), "assert failed {}", self);
+    }
+}
+
+// Check whether two (x, y) pairs are strictly ordered in opposite directions.
+fn is_inversion(a: (f64, f64), b: (f64, f64)) -> bool {
+    (a.0 < b.0 && a.1 > b.1) || (a.0 > b.0 && a.1 < b.1)
+}
+
+// Count pairs i < j with values[i] > values[j], sorting values in the process.
+fn count_inversions(values: &mut [f64], buffer: &mut [f64]) -> usize {
+    let len = values.len();
+    if len < 2 {
+        return 0;
+    }
+    let mid = len / 2;
+    let mut count = count_inversions(&mut values[..mid], &mut buffer[..mid])
+        + count_inversions(&mut values[mid..], &mut buffer[mid..]);
+    let (mut i, mut j, mut k) = (0, mid, 0);
+    while i < mid && j < len {
+        if values[j] < values[i] {
+            // values[j] is less than every remaining value in the left half.
+            count += mid - i;
+            buffer[k] = values[j];
+            j += 1;
+        } else {
+            buffer[k] = values[i];
+            i += 1;
+        }
+        k += 1;
+    }
+    buffer[k..k + mid - i].copy_from_slice(&values[i..mid]);
+    k += mid - i;
+    buffer[k..].copy_from_slice(&values[j..]);
+    values.copy_from_slice(buffer);
+    count
+}
+
+impl Display for OrderSummary<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}{}count {}",
+            self.name,
+            if !self.name.is_empty() { ": " } else { "" },
+            self.num_total
+        )?;
+        if self.num_nan > 0 {
+            write!(f, ", nan {}%", util::to_percent(self.num_nan, self.num_total))?;
+        }
+        if self.num_consecutive_checked > 0 {
+            write!(
+                f,
+                ", consecutive inversions {} ({}%)",
+                self.num_consecutive,
+                util::to_percent(self.num_consecutive, self.num_consecutive_checked)
+            )?;
+        }
+        if let Some((a, b)) = self.first_inversion {
+            write!(
+                f,
+                " first index {} {} vs {} then index {} {} vs {}",
+                a.0,
+                FmtF64(a.1, FloatFormat::Exp),
+                FmtF64(a.2, FloatFormat::Exp),
+                b.0,
+                FmtF64(b.1, FloatFormat::Exp),
+                FmtF64(b.2, FloatFormat::Exp),
+            )?;
+        }
+        if let Some(inversions) = self.all_inversions() {
+            let n = self.pairs.len();
+            write!(f, ", all inversions {} of {} pairs", inversions, n * n.saturating_sub(1) / 2)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{count_inversions, OrderSummary};
+
+    #[test]
+    fn test_count_inversions() {
+        let mut values = [3.0, 1.0, 2.0, 5.0, 4.0, 4.0];
+        let mut buffer = values;
+        assert_eq!(count_inversions(&mut values, &mut buffer), 4);
+        assert_eq!(values, [1.0, 2.0, 3.0, 4.0, 4.0, 5.0]);
+    }
+
+    #[test]
+    fn test_order() {
+        let mut summary = OrderSummary::new_all_pairs("order");
+        let data = [(0.0, 0.0), (1.0, 1.0), (1.0, 2.0), (0.5, 3.0), (f64::NAN, 4.0), (5.0, 5.0), (4.0, 5.0)];
+        for (i, item) in data.iter().enumerate() {
+            summary.add(item.0, item.1, i);
+        }
+        assert_eq!(summary.consecutive_inversions(), 1);
+        assert_eq!(summary.first_inversion(), Some(((2, 1.0, 2.0), (3, 0.5, 3.0))));
+        // (1, 3) and (2, 3) are inverted. Ties (1, 2) and (5, 6) are not.
+        assert_eq!(summary.all_inversions(), Some(2));
+        assert!(!summary.is_ok());
+        assert_eq!(
+            format!("{}", summary),
+            "order: count 7, nan 14%, consecutive inversions 1 (20%) first index 2 1e0 vs 2e0 \
+                then index 3 5e-1 vs 3e0, all inversions 2 of 15 pairs"
+        );
+
+        let mut sorted = OrderSummary::new("sorted");
+        for i in 0..10 {
+            sorted.add(i as f64, 2.0 * i as f64, i);
+        }
+        assert!(sorted.is_ok());
+        assert_eq!(sorted.all_inversions(), None);
+    }
+}
@@ -0,0 +1,46 @@
+use std::fmt::Display;
+use crate::sample_record::SampleRecord;
+use crate::util::{FloatFormat, FmtF64};
+
+// Items whose differences are unusually large relative to the bulk of the
+// distribution, as returned by DiffSummary::outliers.
+// This is distinct from the hard tolerance: it flags items that stand apart
+// from their peers, whether or not they pass.
+#[derive(Clone, Debug, PartialEq)]
+pub struct OutlierReport {
+    // The fence multiplier used.
+    pub k: f64,
+    // Items with differences above this value are outliers. Estimated as
+    // q75 + k * (q75 - q25), with quartiles estimated from the histogram.
+    pub threshold: f64,
+    // The outlying items among those retained by the summary's top-N
+    // tracking, from worst to least bad.
+    pub items: Vec<SampleRecord>,
+    // True if every retained item was an outlier, meaning there may be
+    // additional outliers that weren't retained.
+    pub truncated: bool,
+}
+
+impl Display for OutlierReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}{} outliers above {:e} (k {})",
+            self.items.len(),
+            if self.truncated { "+" } else { "" },
+            self.threshold,
+            self.k
+        )?;
+        for &SampleRecord { index, x, y, diff, .. } in &self.items {
+            write!(
+                f,
+                ", index {} {} vs {} diff {:e}",
+                index,
+                FmtF64(x, FloatFormat::Exp),
+                FmtF64(y, FloatFormat::Exp),
+                diff
+            )?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,317 @@
+use num_traits::ToPrimitive;
+use std::fmt::Display;
+use std::ops::RangeInclusive;
+use crate::diff;
+use crate::util;
+
+// Heatmap shading levels, from lowest to highest worst diff.
+const SHADES: [char; 4] = ['░', '▒', '▓', '█'];
+
+// Statistics for the items in one cell of a ParamGrid.
+#[derive(Clone, Copy, Debug, PartialEq, Default)]
+pub struct GridCell {
+    // The number of items, and of items outside tolerance.
+    pub count: usize,
+    pub fail_count: usize,
+    // The worst difference, nan being worse than infinity.
+    pub worst_diff: f64,
+    // The number and sum of finite differences, for the mean.
+    finite_count: usize,
+    sum_diff: f64,
+}
+
+impl GridCell {
+    #[allow(clippy::neg_cmp_op_on_partial_ord)]
+    fn add(&mut self, diff: f64, allow_diff: f64) {
+        self.count += 1;
+        // Funky negation on next line is intentional, to get desired nan behavior.
+        if !(diff <= allow_diff) {
+            self.fail_count += 1;
+        }
+        if diff::is_diff_worse(diff, self.worst_diff) {
+            self.worst_diff = diff;
+        }
+        if diff.is_finite() {
+            self.finite_count += 1;
+            self.sum_diff += diff;
+        }
+    }
+
+    fn merge(&mut self, other: &GridCell) {
+        self.count += other.count;
+        self.fail_count += other.fail_count;
+        if diff::is_diff_worse(other.worst_diff, self.worst_diff) {
+            self.worst_diff = other.worst_diff;
+        }
+        self.finite_count += other.finite_count;
+        self.sum_diff += other.sum_diff;
+    }
+
+    // The mean finite difference, or nan if there were none.
+    pub fn mean_diff(&self) -> f64 {
+        self.sum_diff / self.finite_count as f64
+    }
+}
+
+// An object for seeing how accuracy varies over the plane of two input
+// parameters, a and b, of the function being compared, such as f(a, b).
+// Items are binned into a grid of equal-width cells over the given
+// parameter ranges, tracking the worst and mean difference per cell, and
+// Display renders a text heatmap of worst difference by region.
+// Sign changes are left to the difference function; only the difference
+// is checked against tolerance.
+pub struct ParamGrid<'a> {
+    // The name of this grid.
+    pub name: &'a str,
+
+    // The maximum difference that passes.
+    allow_diff: f64,
+
+    // Calculates the difference between the computed and expected values.
+    calc_diff: &'a dyn Fn(f64, f64) -> (f64, bool),
+
+    // The range of each parameter, and the number of cells across it.
+    a_range: RangeInclusive<f64>,
+    b_range: RangeInclusive<f64>,
+    a_count: usize,
+    b_count: usize,
+
+    // The cells, in rows of increasing b, each row in order of increasing a.
+    cells: Vec<GridCell>,
+
+    // The number of items with a parameter outside its range, or nan.
+    num_outside: usize,
+}
+
+impl<'a> ParamGrid<'a> {
+    // Create a grid of a_count by b_count cells covering a_range and b_range.
+    pub fn new(
+        name: &'a str,
+        allow_diff: f64,
+        calc_diff: &'a dyn Fn(f64, f64) -> (f64, bool),
+        a_range: RangeInclusive<f64>,
+        b_range: RangeInclusive<f64>,
+        (a_count, b_count): (usize, usize),
+    ) -> Self {
+        assert!(a_count > 0 && b_count > 0, "ParamGrid requires at least one cell in each direction");
+        for range in [&a_range, &b_range].iter() {
+            assert!(
+                range.start().is_finite() && range.end().is_finite() && range.start() < range.end(),
+                "ParamGrid range must be finite and non-empty, got {:?}",
+                range
+            );
+        }
+        ParamGrid {
+            name,
+            allow_diff,
+            calc_diff,
+            a_range,
+            b_range,
+            a_count,
+            b_count,
+            cells: vec![GridCell::default(); a_count * b_count],
+            num_outside: 0,
+        }
+    }
+
+    // Add an item with parameters a and b, computed value x and expected value y.
+    // Items with a parameter outside its range are only counted.
+    pub fn add<X: ToPrimitive, Y: ToPrimitive>(&mut self, a: f64, b: f64, x: X, y: Y) {
+        let (i, j) = match (bin(a, &self.a_range, self.a_count), bin(b, &self.b_range, self.b_count)) {
+            (Some(i), Some(j)) => (i, j),
+            _ => {
+                self.num_outside += 1;
+                return;
+            }
+        };
+        let (diff, _) = (self.calc_diff)(util::to_f64(x), util::to_f64(y));
+        self.cells[j * self.a_count + i].add(diff, self.allow_diff);
+    }
+
+    // Combine another grid's results into this one. Both must cover the same cells.
+    pub fn merge(&mut self, other: &ParamGrid) {
+        assert!(
+            self.a_range == other.a_range && self.b_range == other.b_range && (self.a_count, self.b_count) == (other.a_count, other.b_count),
+            "Cannot merge ParamGrids with different cells"
+        );
+        for (cell, other_cell) in self.cells.iter_mut().zip(&other.cells) {
+            cell.merge(other_cell);
+        }
+        self.num_outside += other.num_outside;
+    }
+
+    // The number of cells across a and b.
+    pub fn dimensions(&self) -> (usize, usize) {
+        (self.a_count, self.b_count)
+    }
+
+    // The cell with the i-th a interval and j-th b interval, counting from
+    // the low end of each range.
+    pub fn cell(&self, i: usize, j: usize) -> &GridCell {
+        assert!(i < self.a_count && j < self.b_count, "cell ({}, {}) is outside the {}x{} grid", i, j, self.a_count, self.b_count);
+        &self.cells[j * self.a_count + i]
+    }
+
+    // The (a, b) ranges covered by the given cell.
+    pub fn cell_bounds(&self, i: usize, j: usize) -> ((f64, f64), (f64, f64)) {
+        (bounds(&self.a_range, self.a_count, i), bounds(&self.b_range, self.b_count, j))
+    }
+
+    // The number of items within the grid.
+    pub fn count(&self) -> usize {
+        self.cells.iter().map(|cell| cell.count).sum()
+    }
+
+    // The number of items within the grid that were outside tolerance.
+    pub fn fail_count(&self) -> usize {
+        self.cells.iter().map(|cell| cell.fail_count).sum()
+    }
+
+    // The number of items with a parameter outside the grid.
+    pub fn outside_count(&self) -> usize {
+        self.num_outside
+    }
+
+    // Indicate whether every item within the grid was within tolerance.
+    pub fn is_ok(&self) -> bool {
+        self.fail_count() == 0
+    }
+
+    // The grid as CSV, e.g. for plotting as an image, with a header row and
+    // a row per cell with items:
+    //   a_lo,a_hi,b_lo,b_hi,count,fail_count,worst_diff,mean_diff
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::from("a_lo,a_hi,b_lo,b_hi,count,fail_count,worst_diff,mean_diff\n");
+        for j in 0..self.b_count {
+            for i in 0..self.a_count {
+                let cell = self.cell(i, j);
+                if cell.count > 0 {
+                    let ((a_lo, a_hi), (b_lo, b_hi)) = self.cell_bounds(i, j);
+                    csv.push_str(&format!(
+                        "{:e},{:e},{:e},{:e},{},{},{:e},{:e}\n",
+                        a_lo,
+                        a_hi,
+                        b_lo,
+                        b_hi,
+                        cell.count,
+                        cell.fail_count,
+                        cell.worst_diff,
+                        cell.mean_diff()
+                    ));
+                }
+            }
+        }
+        csv
+    }
+}
+
+// The index of the cell holding value, of count equal cells over range,
+// or None if it's outside the range or nan. The range's end is in the last cell.
+fn bin(value: f64, range: &RangeInclusive<f64>, count: usize) -> Option<usize> {
+    if !range.contains(&value) {
+        return None;
+    }
+    let fraction = (value - range.start()) / (range.end() - range.start());
+    Some(((fraction * count as f64) as usize).min(count - 1))
+}
+
+// The (low, high) bounds of the given cell, of count equal cells over range.
+fn bounds(range: &RangeInclusive<f64>, count: usize, index: usize) -> (f64, f64) {
+    let width = (range.end() - range.start()) / count as f64;
+    (range.start() + width * index as f64, range.start() + width * (index + 1) as f64)
+}
+
+// Shows a header line, then one line per b interval, highest b first, with
+// a character per a interval, lowest a first, then a key, e.g.
+//   drag: worst diff over a 0e0 to 1e0, b 0e0 to 2e0, 1 of 12 failed
+//     b 2e0 |·░▓!|
+//           | ░▒▓|
+//     b 0e0 |··░█|
+//     ░ 1e-9 to █ 1e-3, · exact, ! non-finite, 2 outside grid
+// Worst diffs are shaded on a log scale between the lowest and highest
+// nonzero finite worst diffs. Empty cells are blank.
+impl Display for ParamGrid<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}: worst diff over a {:e} to {:e}, b {:e} to {:e}, {} of {} failed",
+            self.name,
+            self.a_range.start(),
+            self.a_range.end(),
+            self.b_range.start(),
+            self.b_range.end(),
+            self.fail_count(),
+            self.count()
+        )?;
+        let shaded = self.cells.iter().filter(|cell| cell.count > 0 && cell.worst_diff > 0.0 && cell.worst_diff.is_finite());
+        let min = shaded.clone().map(|cell| cell.worst_diff).fold(f64::INFINITY, f64::min);
+        let max = shaded.map(|cell| cell.worst_diff).fold(0.0, f64::max);
+        let shade = |cell: &GridCell| {
+            if cell.count == 0 {
+                ' '
+            } else if cell.worst_diff == 0.0 {
+                '·'
+            } else if !cell.worst_diff.is_finite() {
+                '!'
+            } else if max > min {
+                let level = (cell.worst_diff / min).log10() / (max / min).log10() * (SHADES.len() - 1) as f64;
+                SHADES[level.round() as usize]
+            } else {
+                SHADES[SHADES.len() - 1]
+            }
+        };
+        let label_width = format!("{:e}", self.b_range.end()).len().max(format!("{:e}", self.b_range.start()).len());
+        for j in (0..self.b_count).rev() {
+            let label = if j == self.b_count - 1 {
+                format!("b {:>width$e}", self.b_range.end(), width = label_width)
+            } else if j == 0 {
+                format!("b {:>width$e}", self.b_range.start(), width = label_width)
+            } else {
+                " ".repeat(label_width + 2)
+            };
+            let row: String = (0..self.a_count).map(|i| shade(self.cell(i, j))).collect();
+            write!(f, "\n  {} |{}|", label, row)?;
+        }
+        write!(f, "\n  ")?;
+        if max > 0.0 {
+            write!(f, "{} {:e} to {} {:e}, ", SHADES[0], min, SHADES[SHADES.len() - 1], max)?;
+        }
+        write!(f, "· exact, ! non-finite")?;
+        if self.num_outside > 0 {
+            write!(f, ", {} outside grid", self.num_outside)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ParamGrid;
+    use crate::diff;
+
+    #[test]
+    fn test_param_grid() {
+        let mut grid = ParamGrid::new("drag", 1e-3, &diff::diff_abs, 0.0..=1.0, 0.0..=2.0, (4, 2));
+        for &(a, b, x) in [(0.1, 0.5, 1.0), (0.9, 0.5, 1.1), (1.0, 2.0, 1.001), (0.6, 1.5, f64::NAN), (1.5, 0.5, 1.0), (0.4, 1.0, 1.00001)].iter() {
+            grid.add(a, b, x, 1.0);
+        }
+        assert_eq!(grid.dimensions(), (4, 2));
+        assert_eq!((grid.count(), grid.fail_count(), grid.outside_count()), (5, 2, 1));
+        assert_eq!(grid.cell(3, 1).count, 1);
+        assert!((grid.cell(3, 0).worst_diff - 0.1).abs() < 1e-12);
+        assert_eq!(grid.cell_bounds(1, 1), ((0.25, 0.5), (1.0, 2.0)));
+        assert!(!grid.is_ok());
+        let mut other = ParamGrid::new("drag", 1e-3, &diff::diff_abs, 0.0..=1.0, 0.0..=2.0, (4, 2));
+        other.add(0.0, 0.0, 1.0, 1.0);
+        grid.merge(&other);
+        assert_eq!(grid.cell(0, 0).count, 2);
+        assert_eq!(
+            grid.to_string(),
+            "drag: worst diff over a 0e0 to 1e0, b 0e0 to 2e0, 2 of 6 failed\n  \
+                b 2e0 | ░!▒|\n  \
+                b 0e0 |·  █|\n  \
+                ░ 1.0000000000065512e-5 to █ 1.0000000000000009e-1, · exact, ! non-finite, 1 outside grid"
+        );
+        assert!(grid.to_csv().starts_with("a_lo,a_hi,b_lo,b_hi,count,fail_count,worst_diff,mean_diff\n0e0,2.5e-1,0e0,1e0,2,0,0e0,0e0\n"));
+    }
+}
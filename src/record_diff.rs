@@ -0,0 +1,135 @@
+use std::fmt::Display;
+use crate::diff_summary_f64::{DiffSummary, SummaryInfo};
+
+// An object for comparing records with several float fields, where each
+// field has its own comparator and tolerance.
+// Each field's values are fed to a separate DiffSummary, named for the field.
+pub struct RecordDiff<'a> {
+    // The name of this comparison.
+    pub name: &'a str,
+
+    // The number of records added.
+    num_records: usize,
+
+    // One summary per field, in record order.
+    fields: Vec<DiffSummary<'a>>,
+}
+
+impl<'a> RecordDiff<'a> {
+    // Create a comparison with one field per tuple, of the form:
+    // (name, allow_diff, allow_sign, calc_diff)
+    pub fn new(name: &'a str, bucket_count: usize, infos: &'a [SummaryInfo<'a>]) -> Self {
+        RecordDiff::from_summaries(name, DiffSummary::new_vec(bucket_count, infos))
+    }
+
+    // Create a comparison from already configured per-field summaries,
+    // e.g. to use options like with_failure_capacity on some fields.
+    pub fn from_summaries(name: &'a str, fields: Vec<DiffSummary<'a>>) -> Self {
+        assert!(!fields.is_empty(), "RecordDiff requires at least one field");
+        RecordDiff {
+            name,
+            num_records: 0,
+            fields,
+        }
+    }
+
+    // Add a record, passing each field's x and y values to that field's summary.
+    // xs and ys must each have one value per field.
+    pub fn add_record(&mut self, xs: &[f64], ys: &[f64], index: usize) {
+        assert_eq!(xs.len(), self.fields.len(), "{}: computed record has wrong field count", self.name);
+        assert_eq!(ys.len(), self.fields.len(), "{}: expected record has wrong field count", self.name);
+        self.num_records += 1;
+        for ((summary, &x), &y) in self.fields.iter_mut().zip(xs).zip(ys) {
+            summary.add(x, y, index);
+        }
+    }
+
+    // The number of records added.
+    pub fn count(&self) -> usize {
+        self.num_records
+    }
+
+    // The per-field summaries, in record order.
+    pub fn fields(&self) -> &[DiffSummary<'a>] {
+        &self.fields
+    }
+
+    // The summary for the named field, if any.
+    pub fn field(&self, name: &str) -> Option<&DiffSummary<'a>> {
+        self.fields.iter().find(|summary| summary.name == name)
+    }
+
+    // The summaries of fields that don't satisfy their tolerance or sign change acceptance.
+    pub fn failed_fields(&self) -> Vec<&DiffSummary<'a>> {
+        self.fields.iter().filter(|summary| !summary.is_ok()).collect()
+    }
+
+    // Indicate whether every field satisfies its tolerance and sign change acceptance.
+    pub fn is_ok(&self) -> bool {
+        self.fields.iter().all(|summary| summary.is_ok())
+    }
+
+    // Assert that every field is ok, reporting all fields on failure.
+    pub fn assert(&self) {
+        assert!(self.is_ok(), "assert failed {}", self);
+    }
+}
+
+impl Display for RecordDiff<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let failed: Vec<&str> = self.failed_fields().iter().map(|summary| summary.name).collect();
+        write!(
+            f,
+            "{}{}records {}, failed fields {} of {}",
+            self.name,
+            if !self.name.is_empty() { ": " } else { "" },
+            self.num_records,
+            failed.len(),
+            self.fields.len()
+        )?;
+        if !failed.is_empty() {
+            write!(f, " ({})", failed.join(", "))?;
+        }
+        for summary in &self.fields {
+            write!(f, "\n  {}", summary)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::RecordDiff;
+    use crate::diff;
+    use crate::diff_summary_f64::SummaryInfo;
+
+    #[test]
+    fn test_record() {
+        let infos: &[SummaryInfo] = &[
+            ("distance", 1e-3, false, &diff::diff_rel),
+            ("azimuth", 1e-6, true, &diff::diff_abs),
+            ("elevation", 1e-6, false, &diff::diff_abs),
+        ];
+        let mut records = RecordDiff::new("sky", 4, infos);
+        records.add_record(&[100.0, 0.5, 0.25], &[100.01, 0.5, 0.25], 0);
+        records.add_record(&[200.0, -0.5, 0.1], &[200.0, 0.5, 0.1], 1);
+        assert_eq!(records.count(), 2);
+        assert_eq!(records.fields().len(), 3);
+        assert_eq!(records.field("azimuth").unwrap().sign_count(), 1);
+        assert!(records.field("range").is_none());
+        let failed: Vec<&str> = records.failed_fields().iter().map(|summary| summary.name).collect();
+        assert_eq!(failed, vec!["azimuth"]);
+        assert!(!records.is_ok());
+        assert!(format!("{}", records).starts_with("sky: records 2, failed fields 1 of 3 (azimuth)\n  distance: "));
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| records.assert()));
+        assert!(result.is_err());
+    }
+
+    #[test]
+    #[should_panic(expected = "wrong field count")]
+    fn test_record_length() {
+        let infos: &[SummaryInfo] = &[("a", 0.0, false, &diff::diff_abs)];
+        let mut records = RecordDiff::new("short", 4, infos);
+        records.add_record(&[1.0, 2.0], &[1.0], 0);
+    }
+}
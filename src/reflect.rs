@@ -0,0 +1,511 @@
+// Comparison of arbitrary serde-serializable values, without a derive or a
+// manual field list. Both values are serialized to their leaves, each with a
+// JSON-pointer-style path such as "/orbit/elements/2", then numeric leaves
+// with matching paths are compared through a summary's comparator, e.g.
+//   let mut summary = DiffSummary64::relative("state", 1e-9);
+//   let diff = summary.add_serialized(&computed, &expected).unwrap();
+//   println!("{}\n{}", summary, diff);
+// Integers are compared as f64. Non-numeric leaves, such as strings and
+// unit enum variants, must match exactly, as must the set of paths. Each
+// structural mismatch is added to the summary as a failure with a nan
+// difference, so that structurally different values are never ok.
+use serde::ser::{self, Serialize};
+use std::collections::HashMap;
+use std::fmt::Display;
+use crate::diff_summary_f64::DiffSummary;
+use crate::input_error::DiffInputError;
+use crate::util::{FloatFormat, FmtF64};
+
+// The number of worst leaves shown by StructDiff's Display.
+const DISPLAY_LIMIT: usize = 5;
+
+// A numeric leaf compared by add_serialized.
+#[derive(Clone, Debug, PartialEq)]
+pub struct LeafDiff {
+    pub path: String,
+    pub x: f64,
+    pub y: f64,
+    pub diff: f64,
+}
+
+// The result of one add_serialized call: each compared numeric leaf, and
+// each structural mismatch, such as a path present in only one value.
+#[derive(Clone, Debug, PartialEq)]
+pub struct StructDiff {
+    // Numeric leaves, in x's serialization order.
+    pub leaves: Vec<LeafDiff>,
+    // Descriptions of leaves that couldn't be compared numerically.
+    pub mismatches: Vec<String>,
+}
+
+impl StructDiff {
+    // Up to n leaves with the worst differences, worst first.
+    pub fn worst(&self, n: usize) -> Vec<&LeafDiff> {
+        let mut leaves: Vec<&LeafDiff> = self.leaves.iter().filter(|leaf| leaf.diff != 0.0).collect();
+        // Stable, so that leaves with equal differences stay in path order.
+        leaves.sort_by(|a, b| {
+            if crate::diff::is_diff_worse(a.diff, b.diff) {
+                std::cmp::Ordering::Less
+            } else if crate::diff::is_diff_worse(b.diff, a.diff) {
+                std::cmp::Ordering::Greater
+            } else {
+                std::cmp::Ordering::Equal
+            }
+        });
+        leaves.truncate(n);
+        leaves
+    }
+}
+
+// Shows counts, then the worst differing leaves and any mismatches, e.g.
+//   leaves 12, mismatched 1
+//     /orbit/elements/2 1.0000001e0 vs 1e0 diff 1e-7
+//     /frame: "J2000" vs "ICRF"
+impl Display for StructDiff {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "leaves {}, mismatched {}", self.leaves.len(), self.mismatches.len())?;
+        for leaf in self.worst(DISPLAY_LIMIT) {
+            write!(
+                f,
+                "\n  {} {} vs {} diff {}",
+                leaf.path,
+                FmtF64(leaf.x, FloatFormat::Exp),
+                FmtF64(leaf.y, FloatFormat::Exp),
+                FmtF64(leaf.diff, FloatFormat::Exp)
+            )?;
+        }
+        for mismatch in self.mismatches.iter().take(DISPLAY_LIMIT) {
+            write!(f, "\n  {}", mismatch)?;
+        }
+        if self.mismatches.len() > DISPLAY_LIMIT {
+            write!(f, "\n  ...")?;
+        }
+        Ok(())
+    }
+}
+
+impl DiffSummary<'_> {
+    // Serialize x and y, and add each pair of numeric leaves with matching
+    // paths to this summary, indexed by the leaf's position in x.
+    // Mismatched leaves are added as failures, with their numeric values
+    // if any, or nan, indexed by position in x, or for leaves missing from
+    // x, by x's leaf count plus position in y.
+    // Returns DiffInputError::Serialize if either value fails to serialize.
+    pub fn add_serialized<T: Serialize + ?Sized>(&mut self, x: &T, y: &T) -> Result<StructDiff, DiffInputError> {
+        let x_leaves = leaves(x)?;
+        let y_leaves = leaves(y)?;
+        let mut y_by_path: HashMap<&str, &Leaf> = y_leaves.iter().map(|(path, leaf)| (path.as_str(), leaf)).collect();
+        let mut diff = StructDiff { leaves: Vec::new(), mismatches: Vec::new() };
+        for (index, (path, x_leaf)) in x_leaves.iter().enumerate() {
+            match (x_leaf, y_by_path.remove(path.as_str())) {
+                (Leaf::Num(x), Some(Leaf::Num(y))) => {
+                    let leaf_diff = self.evaluate(*x, *y).diff;
+                    self.add(*x, *y, index);
+                    diff.leaves.push(LeafDiff { path: path.clone(), x: *x, y: *y, diff: leaf_diff });
+                }
+                (x_leaf, Some(y_leaf)) if x_leaf != y_leaf => {
+                    self.add_diff(x_leaf.value(), y_leaf.value(), f64::NAN, false, index);
+                    diff.mismatches.push(format!("{}: {} vs {}", path, x_leaf, y_leaf));
+                }
+                (_, Some(_)) => {}
+                (x_leaf, None) => {
+                    self.add_diff(x_leaf.value(), f64::NAN, f64::NAN, false, index);
+                    diff.mismatches.push(format!("{}: missing from y", path));
+                }
+            }
+        }
+        for (y_index, (path, y_leaf)) in y_leaves.iter().enumerate() {
+            if y_by_path.contains_key(path.as_str()) {
+                self.add_diff(f64::NAN, y_leaf.value(), f64::NAN, false, x_leaves.len() + y_index);
+                diff.mismatches.push(format!("{}: missing from x", path));
+            }
+        }
+        Ok(diff)
+    }
+}
+
+// A serialized leaf value.
+#[derive(Clone, Debug, PartialEq)]
+enum Leaf {
+    Num(f64),
+    // Any other value, as text, e.g. "true", "\"J2000\"", or "None".
+    Other(String),
+}
+
+impl Leaf {
+    // The numeric value, or nan for other values.
+    fn value(&self) -> f64 {
+        match self {
+            Leaf::Num(value) => *value,
+            Leaf::Other(_) => f64::NAN,
+        }
+    }
+}
+
+impl Display for Leaf {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Leaf::Num(value) => write!(f, "{}", FmtF64(*value, FloatFormat::Exp)),
+            Leaf::Other(text) => write!(f, "{}", text),
+        }
+    }
+}
+
+// Serialize value to its (path, leaf) pairs, in serialization order.
+fn leaves<T: Serialize + ?Sized>(value: &T) -> Result<Vec<(String, Leaf)>, DiffInputError> {
+    let mut flattener = Flattener { path: String::new(), leaves: Vec::new() };
+    value.serialize(&mut flattener).map_err(|err| DiffInputError::Serialize(err.0))?;
+    Ok(flattener.leaves)
+}
+
+#[derive(Debug)]
+struct FlattenError(String);
+
+impl Display for FlattenError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl std::error::Error for FlattenError {}
+
+impl ser::Error for FlattenError {
+    fn custom<M: Display>(msg: M) -> Self {
+        FlattenError(msg.to_string())
+    }
+}
+
+// A serializer collecting leaves, with the path of the value being serialized.
+struct Flattener {
+    path: String,
+    leaves: Vec<(String, Leaf)>,
+}
+
+impl Flattener {
+    fn leaf(&mut self, leaf: Leaf) -> Result<(), FlattenError> {
+        self.leaves.push((self.path.clone(), leaf));
+        Ok(())
+    }
+
+    fn other(&mut self, text: impl Display) -> Result<(), FlattenError> {
+        self.leaf(Leaf::Other(text.to_string()))
+    }
+
+    // Serialize value under path segment, escaped per JSON pointer.
+    fn nested<T: Serialize + ?Sized>(&mut self, segment: &str, value: &T) -> Result<(), FlattenError> {
+        let len = self.path.len();
+        self.path.push('/');
+        self.path.push_str(&segment.replace('~', "~0").replace('/', "~1"));
+        let result = value.serialize(&mut *self);
+        self.path.truncate(len);
+        result
+    }
+}
+
+impl<'f> ser::Serializer for &'f mut Flattener {
+    type Ok = ();
+    type Error = FlattenError;
+    type SerializeSeq = Compound<'f>;
+    type SerializeTuple = Compound<'f>;
+    type SerializeTupleStruct = Compound<'f>;
+    type SerializeTupleVariant = Compound<'f>;
+    type SerializeMap = Compound<'f>;
+    type SerializeStruct = Compound<'f>;
+    type SerializeStructVariant = Compound<'f>;
+
+    fn serialize_bool(self, v: bool) -> Result<(), FlattenError> {
+        self.other(v)
+    }
+    fn serialize_i8(self, v: i8) -> Result<(), FlattenError> {
+        self.leaf(Leaf::Num(v.into()))
+    }
+    fn serialize_i16(self, v: i16) -> Result<(), FlattenError> {
+        self.leaf(Leaf::Num(v.into()))
+    }
+    fn serialize_i32(self, v: i32) -> Result<(), FlattenError> {
+        self.leaf(Leaf::Num(v.into()))
+    }
+    fn serialize_i64(self, v: i64) -> Result<(), FlattenError> {
+        self.leaf(Leaf::Num(v as f64))
+    }
+    fn serialize_i128(self, v: i128) -> Result<(), FlattenError> {
+        self.leaf(Leaf::Num(v as f64))
+    }
+    fn serialize_u8(self, v: u8) -> Result<(), FlattenError> {
+        self.leaf(Leaf::Num(v.into()))
+    }
+    fn serialize_u16(self, v: u16) -> Result<(), FlattenError> {
+        self.leaf(Leaf::Num(v.into()))
+    }
+    fn serialize_u32(self, v: u32) -> Result<(), FlattenError> {
+        self.leaf(Leaf::Num(v.into()))
+    }
+    fn serialize_u64(self, v: u64) -> Result<(), FlattenError> {
+        self.leaf(Leaf::Num(v as f64))
+    }
+    fn serialize_u128(self, v: u128) -> Result<(), FlattenError> {
+        self.leaf(Leaf::Num(v as f64))
+    }
+    fn serialize_f32(self, v: f32) -> Result<(), FlattenError> {
+        self.leaf(Leaf::Num(v.into()))
+    }
+    fn serialize_f64(self, v: f64) -> Result<(), FlattenError> {
+        self.leaf(Leaf::Num(v))
+    }
+    fn serialize_char(self, v: char) -> Result<(), FlattenError> {
+        self.other(format_args!("{:?}", v))
+    }
+    fn serialize_str(self, v: &str) -> Result<(), FlattenError> {
+        self.other(format_args!("{:?}", v))
+    }
+    fn serialize_bytes(self, v: &[u8]) -> Result<(), FlattenError> {
+        self.other(format_args!("{:?}", v))
+    }
+    fn serialize_none(self) -> Result<(), FlattenError> {
+        self.other("None")
+    }
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<(), FlattenError> {
+        value.serialize(self)
+    }
+    fn serialize_unit(self) -> Result<(), FlattenError> {
+        self.other("()")
+    }
+    fn serialize_unit_struct(self, name: &'static str) -> Result<(), FlattenError> {
+        self.other(name)
+    }
+    fn serialize_unit_variant(self, _name: &'static str, _index: u32, variant: &'static str) -> Result<(), FlattenError> {
+        self.other(variant)
+    }
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(self, _name: &'static str, value: &T) -> Result<(), FlattenError> {
+        value.serialize(self)
+    }
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> Result<(), FlattenError> {
+        self.nested(variant, value)
+    }
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Compound<'f>, FlattenError> {
+        Ok(Compound::new(self, None))
+    }
+    fn serialize_tuple(self, _len: usize) -> Result<Compound<'f>, FlattenError> {
+        Ok(Compound::new(self, None))
+    }
+    fn serialize_tuple_struct(self, _name: &'static str, _len: usize) -> Result<Compound<'f>, FlattenError> {
+        Ok(Compound::new(self, None))
+    }
+    fn serialize_tuple_variant(self, _name: &'static str, _index: u32, variant: &'static str, _len: usize) -> Result<Compound<'f>, FlattenError> {
+        Ok(Compound::new(self, Some(variant)))
+    }
+    fn serialize_map(self, _len: Option<usize>) -> Result<Compound<'f>, FlattenError> {
+        Ok(Compound::new(self, None))
+    }
+    fn serialize_struct(self, _name: &'static str, _len: usize) -> Result<Compound<'f>, FlattenError> {
+        Ok(Compound::new(self, None))
+    }
+    fn serialize_struct_variant(self, _name: &'static str, _index: u32, variant: &'static str, _len: usize) -> Result<Compound<'f>, FlattenError> {
+        Ok(Compound::new(self, Some(variant)))
+    }
+}
+
+// The elements, fields or entries of a compound value, serialized under
+// the path of the value, followed by the variant name for enum variants.
+struct Compound<'f> {
+    flattener: &'f mut Flattener,
+    // The length of the path before any variant name, restored on end.
+    path_len: usize,
+    // The next element position, for sequences and tuples.
+    next: usize,
+    // The pending map key, as a path segment.
+    key: Option<String>,
+}
+
+impl<'f> Compound<'f> {
+    fn new(flattener: &'f mut Flattener, variant: Option<&str>) -> Self {
+        let path_len = flattener.path.len();
+        if let Some(variant) = variant {
+            flattener.path.push('/');
+            flattener.path.push_str(variant);
+        }
+        Compound { flattener, path_len, next: 0, key: None }
+    }
+
+    fn element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), FlattenError> {
+        let segment = self.next.to_string();
+        self.next += 1;
+        self.flattener.nested(&segment, value)
+    }
+
+    fn end(self) -> Result<(), FlattenError> {
+        self.flattener.path.truncate(self.path_len);
+        Ok(())
+    }
+}
+
+impl ser::SerializeSeq for Compound<'_> {
+    type Ok = ();
+    type Error = FlattenError;
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), FlattenError> {
+        self.element(value)
+    }
+    fn end(self) -> Result<(), FlattenError> {
+        Compound::end(self)
+    }
+}
+
+impl ser::SerializeTuple for Compound<'_> {
+    type Ok = ();
+    type Error = FlattenError;
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), FlattenError> {
+        self.element(value)
+    }
+    fn end(self) -> Result<(), FlattenError> {
+        Compound::end(self)
+    }
+}
+
+impl ser::SerializeTupleStruct for Compound<'_> {
+    type Ok = ();
+    type Error = FlattenError;
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), FlattenError> {
+        self.element(value)
+    }
+    fn end(self) -> Result<(), FlattenError> {
+        Compound::end(self)
+    }
+}
+
+impl ser::SerializeTupleVariant for Compound<'_> {
+    type Ok = ();
+    type Error = FlattenError;
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), FlattenError> {
+        self.element(value)
+    }
+    fn end(self) -> Result<(), FlattenError> {
+        Compound::end(self)
+    }
+}
+
+impl ser::SerializeMap for Compound<'_> {
+    type Ok = ();
+    type Error = FlattenError;
+    // Keys must serialize to a single leaf, which becomes the path segment.
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> Result<(), FlattenError> {
+        let mut key_flattener = Flattener { path: String::new(), leaves: Vec::new() };
+        key.serialize(&mut key_flattener)?;
+        let segment = match key_flattener.leaves.as_slice() {
+            [(path, Leaf::Num(value))] if path.is_empty() => value.to_string(),
+            // Use strings as is, rather than quoted.
+            [(path, Leaf::Other(text))] if path.is_empty() => text.strip_prefix('"').and_then(|text| text.strip_suffix('"')).unwrap_or(text).to_string(),
+            _ => return Err(FlattenError(format!("{}: map key is not a single value", self.flattener.path))),
+        };
+        self.key = Some(segment);
+        Ok(())
+    }
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), FlattenError> {
+        let segment = self.key.take().expect("serialize_value called before serialize_key");
+        self.flattener.nested(&segment, value)
+    }
+    fn end(self) -> Result<(), FlattenError> {
+        Compound::end(self)
+    }
+}
+
+impl ser::SerializeStruct for Compound<'_> {
+    type Ok = ();
+    type Error = FlattenError;
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, key: &'static str, value: &T) -> Result<(), FlattenError> {
+        self.flattener.nested(key, value)
+    }
+    fn end(self) -> Result<(), FlattenError> {
+        Compound::end(self)
+    }
+}
+
+impl ser::SerializeStructVariant for Compound<'_> {
+    type Ok = ();
+    type Error = FlattenError;
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, key: &'static str, value: &T) -> Result<(), FlattenError> {
+        self.flattener.nested(key, value)
+    }
+    fn end(self) -> Result<(), FlattenError> {
+        Compound::end(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::Serialize;
+    use std::collections::BTreeMap;
+    use crate::diff;
+    use crate::diff_summary_f64::DiffSummary;
+
+    #[derive(Serialize)]
+    enum Frame {
+        J2000,
+        Rotating { rate: f64 },
+    }
+
+    #[derive(Serialize)]
+    struct State {
+        position: [f64; 3],
+        mass: f32,
+        steps: u32,
+        frame: Frame,
+        label: String,
+        extra: BTreeMap<String, f64>,
+        tag: Option<(u8, f64)>,
+    }
+
+    #[test]
+    fn test_add_serialized() {
+        let x = State {
+            position: [1.0, 2.5, -3.0],
+            mass: 10.0,
+            steps: 4,
+            frame: Frame::Rotating { rate: 0.5 },
+            label: "a".to_string(),
+            extra: vec![("drag/area".to_string(), 1.0), ("x".to_string(), 1.0)].into_iter().collect(),
+            tag: Some((1, f64::NAN)),
+        };
+        let y = State {
+            position: [1.0, 2.0, -3.0],
+            mass: 10.0,
+            steps: 4,
+            frame: Frame::Rotating { rate: 0.75 },
+            label: "b".to_string(),
+            extra: vec![("drag/area".to_string(), 1.0), ("y".to_string(), 1.0)].into_iter().collect(),
+            tag: Some((1, f64::NAN)),
+        };
+        let mut summary = DiffSummary::new("state", 0.1, false, 4, &diff::diff_abs);
+        let diff = summary.add_serialized(&x, &y).unwrap();
+        let paths: Vec<&str> = diff.leaves.iter().map(|leaf| leaf.path.as_str()).collect();
+        assert_eq!(
+            paths,
+            vec!["/position/0", "/position/1", "/position/2", "/mass", "/steps", "/frame/Rotating/rate", "/extra/drag~1area", "/tag/0", "/tag/1"]
+        );
+        // Two numeric failures, and three structural mismatches.
+        assert_eq!((summary.count(), summary.fail_count()), (12, 5));
+        assert_eq!(summary.worst_sample().map(|sample| sample.index), Some(6));
+        assert_eq!(
+            diff.to_string(),
+            "leaves 9, mismatched 3\n  \
+                /position/1 2.5e0 vs 2e0 diff 5e-1\n  \
+                /frame/Rotating/rate 5e-1 vs 7.5e-1 diff 2.5e-1\n  \
+                /label: \"a\" vs \"b\"\n  \
+                /extra/x: missing from y\n  \
+                /extra/y: missing from x"
+        );
+
+        let mut unit = DiffSummary::new("unit", 0.1, false, 4, &diff::diff_abs);
+        let diff = unit.add_serialized(&Frame::J2000, &Frame::Rotating { rate: 1.0 }).unwrap();
+        assert_eq!(diff.mismatches, vec![": missing from y", "/Rotating/rate: missing from x"]);
+        assert_eq!((unit.count(), unit.fail_count(), unit.is_ok()), (2, 2, false));
+        let mut same = DiffSummary::new("same", 0.1, false, 4, &diff::diff_abs);
+        same.add_serialized(&Frame::J2000, &Frame::J2000).unwrap();
+        assert!(same.is_ok());
+    }
+}
@@ -0,0 +1,402 @@
+// A process-wide registry of summary results, so that results from many
+// tests can be reported together once they have all run.
+// Names can form a hierarchy, with segments separated by PATH_SEPARATOR,
+// e.g. "geodesic/inverse/azimuth", so that reports can be filtered to a
+// subtree and aggregated by subtree.
+// Summaries borrow their names and comparators, so the registry keeps a
+// snapshot of each summary's results rather than the summary itself.
+use std::collections::BTreeMap;
+use std::fmt::Display;
+use std::sync::Mutex;
+use crate::accumulate;
+use crate::binary;
+use crate::diff_summary_f64::DiffSummary;
+use crate::input_error::DiffInputError;
+
+static REGISTRY: Mutex<Vec<RegistryEntry>> = Mutex::new(Vec::new());
+
+// The environment variable that turns on strict mode for every registered
+// summary, when set to anything but "" or "0": each summary's passing pairs
+// with a nan or infinite value are counted as failures, as though it had
+// been created with DiffSummary::with_strict_mode.
+pub const STRICT_ENV: &str = "FLOAT_DIFF_STRICT";
+
+// The separator between segments of hierarchical summary names.
+pub const PATH_SEPARATOR: char = '/';
+
+// A snapshot of a registered summary's results.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct RegistryEntry {
+    pub name: String,
+    pub count: usize,
+    pub fail_count: usize,
+    pub sign_count: usize,
+    pub worst_diff: f64,
+    pub allow_diff: f64,
+    // The summary's worst_score, per its worst metric, for ranking.
+    pub worst_score: f64,
+    pub is_ok: bool,
+    // The summary's Display output when registered.
+    pub summary: String,
+}
+
+impl RegistryEntry {
+    pub fn new(summary: &DiffSummary) -> Self {
+        RegistryEntry {
+            name: summary.name.to_string(),
+            count: summary.count(),
+            fail_count: summary.fail_count(),
+            sign_count: summary.sign_count(),
+            worst_diff: summary.worst_diff(),
+            allow_diff: summary.allow_diff(),
+            worst_score: summary.worst_score(),
+            is_ok: summary.is_ok(),
+            summary: summary.to_string(),
+        }
+    }
+
+    // A snapshot of a summary's results under strict mode, per STRICT_ENV.
+    // Passing pairs with a nan or infinite value become failures with a nan
+    // difference, and the summary text states that strict mode applied.
+    pub fn new_strict(summary: &DiffSummary) -> Self {
+        let mut entry = RegistryEntry::new(summary);
+        let num_nonfinite = summary.nonfinite_pass_count();
+        if num_nonfinite > 0 {
+            entry.fail_count += num_nonfinite;
+            entry.worst_diff = f64::NAN;
+            entry.worst_score = f64::NAN;
+            entry.is_ok = false;
+        }
+        entry.summary = if summary.is_strict() {
+            format!("{}, nonfinite strict", entry.summary)
+        } else {
+            format!("{}, nonfinite strict by {} failed {}", entry.summary, STRICT_ENV, num_nonfinite)
+        };
+        entry
+    }
+
+    // Combine another snapshot of the same channel into this one, following
+    // DiffSummary::merge for the counts and worst difference. The summary
+    // text is replaced by a one-line description of the combined results.
+    // Returns DiffInputError::ToleranceMismatch, leaving this snapshot
+    // unchanged, if the tolerances differ, such as for snapshots from runs
+    // before and after a tolerance change.
+    pub fn merge(&mut self, other: &RegistryEntry) -> Result<(), DiffInputError> {
+        if self.allow_diff != other.allow_diff {
+            return Err(DiffInputError::ToleranceMismatch {
+                name: self.name.clone(),
+                allow_diff: self.allow_diff,
+                other_allow_diff: other.allow_diff,
+            });
+        }
+        self.count += other.count;
+        self.fail_count += other.fail_count;
+        self.sign_count += other.sign_count;
+        if crate::diff::is_diff_worse(other.worst_diff, self.worst_diff) {
+            self.worst_diff = other.worst_diff;
+        }
+        if crate::diff::is_diff_worse(other.worst_score, self.worst_score) {
+            self.worst_score = other.worst_score;
+        }
+        self.is_ok = self.is_ok && other.is_ok;
+        self.summary = self.line();
+        Ok(())
+    }
+
+    // A one-line description of the snapshot's results.
+    pub fn line(&self) -> String {
+        format!(
+            "{}: count {}, fail {}, sign {}, worst diff {:e}, allow {:e}",
+            self.name, self.count, self.fail_count, self.sign_count, self.worst_diff, self.allow_diff
+        )
+    }
+}
+
+// Record a snapshot of a summary's current results.
+// If the ACCUMULATE_DIR_ENV environment variable names a directory, the
+// snapshot is also appended to this process's accumulation file there, so
+// results from tests run in separate processes can be reported together.
+// The file is binary if binary::ACCUMULATE_FORMAT_ENV is "binary".
+// The snapshot is taken per RegistryEntry::new_strict if STRICT_ENV is set.
+// Panics if the accumulation file can't be written.
+pub fn register(summary: &DiffSummary) {
+    let entry = if is_strict_env() { RegistryEntry::new_strict(summary) } else { RegistryEntry::new(summary) };
+    if let Some(dir) = std::env::var_os(accumulate::ACCUMULATE_DIR_ENV) {
+        let is_binary = std::env::var_os(binary::ACCUMULATE_FORMAT_ENV).is_some_and(|format| format == "binary");
+        let result = if is_binary { accumulate::append_binary(&dir, &entry) } else { accumulate::append(&dir, &entry) };
+        result.unwrap_or_else(|err| panic!("failed to append {} to accumulation dir {:?}: {}", entry.name, dir, err));
+    }
+    lock().push(entry);
+}
+
+// Whether STRICT_ENV turns on strict mode.
+pub fn is_strict_env() -> bool {
+    std::env::var_os(STRICT_ENV).is_some_and(|value| !value.is_empty() && value != "0")
+}
+
+// The registered snapshots, in the order registered.
+pub fn entries() -> Vec<RegistryEntry> {
+    lock().clone()
+}
+
+// Remove all registered snapshots.
+pub fn clear() {
+    lock().clear();
+}
+
+// A table of all registered snapshots, one line each, failures first.
+pub fn report() -> Report {
+    let mut entries = entries();
+    rank(&mut entries);
+    Report { entries }
+}
+
+// Order entries for reports: failures first, then by worst score, from
+// worst to least bad. A stable sort keeps registration order for ties.
+// Scores are only comparable between summaries with the same worst metric.
+pub fn rank(entries: &mut [RegistryEntry]) {
+    entries.sort_by(|a, b| {
+        a.is_ok.cmp(&b.is_ok).then_with(|| {
+            if crate::diff::is_diff_worse(a.worst_score, b.worst_score) {
+                std::cmp::Ordering::Less
+            } else if crate::diff::is_diff_worse(b.worst_score, a.worst_score) {
+                std::cmp::Ordering::Greater
+            } else {
+                std::cmp::Ordering::Equal
+            }
+        })
+    });
+}
+
+// A snapshot of the registry's contents, for display.
+pub struct Report {
+    pub entries: Vec<RegistryEntry>,
+}
+
+impl Report {
+    // The entries whose names match pattern, in the same order.
+    // Patterns are matched segment by segment, where "*" matches any one
+    // segment, except that a final "*" matches one or more segments, so
+    // "geodesic/inverse/*" matches every entry under geodesic/inverse.
+    pub fn filter(&self, pattern: &str) -> Report {
+        let entries = self.entries.iter().filter(|entry| path_matches(&entry.name, pattern)).cloned().collect();
+        Report { entries }
+    }
+
+    // Indicate whether every entry is ok.
+    pub fn is_ok(&self) -> bool {
+        self.entries.iter().all(|entry| entry.is_ok)
+    }
+
+    // Assert that every entry is ok, with the report as the failure message, e.g.
+    //   registry::report().filter("geodesic/inverse/*").assert();
+    pub fn assert(&self) {
+        assert!(self.is_ok(), "{}", self);
+    }
+
+    // Totals for every subtree of the entries' hierarchical names, that is,
+    // every name prefix ending before a separator, in path order.
+    pub fn subtrees(&self) -> Vec<SubtreeTotals> {
+        let mut subtrees: BTreeMap<&str, SubtreeTotals> = BTreeMap::new();
+        for entry in &self.entries {
+            for (end, _) in entry.name.match_indices(PATH_SEPARATOR) {
+                let path = &entry.name[..end];
+                subtrees.entry(path).or_insert_with(|| SubtreeTotals::new(path)).add(entry);
+            }
+        }
+        subtrees.into_values().collect()
+    }
+}
+
+// Shows a count line and a line per entry, followed by a line per subtree
+// if any names are hierarchical, e.g.
+//   3 summaries, 1 failed
+//     FAILED geodesic/inverse/azimuth: ...
+//     ok     geodesic/inverse/distance: ...
+//     ok     geodesic/direct/lat: ...
+//   subtrees
+//     FAILED geodesic: 3 channels, 1 failed, count 300, fail 2, sign 0
+//     ...
+impl Display for Report {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let num_failed = self.entries.iter().filter(|entry| !entry.is_ok).count();
+        write!(f, "{} summaries, {} failed", self.entries.len(), num_failed)?;
+        for entry in &self.entries {
+            write!(f, "\n  {} {}", status_label(entry.is_ok), entry.summary)?;
+        }
+        let subtrees = self.subtrees();
+        if !subtrees.is_empty() {
+            write!(f, "\nsubtrees")?;
+            for subtree in &subtrees {
+                write!(f, "\n  {} {}", status_label(subtree.is_ok()), subtree)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+fn status_label(is_ok: bool) -> &'static str {
+    if is_ok { "ok    " } else { "FAILED" }
+}
+
+// Totals for the entries under one subtree of hierarchical names, as
+// returned by Report::subtrees.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SubtreeTotals {
+    // The subtree's path, e.g. "geodesic/inverse".
+    pub path: String,
+    // The number of entries under the subtree, and of those that failed.
+    pub channel_count: usize,
+    pub failed_channel_count: usize,
+    // Item counts summed over the entries.
+    pub count: usize,
+    pub fail_count: usize,
+    pub sign_count: usize,
+}
+
+impl SubtreeTotals {
+    fn new(path: &str) -> Self {
+        SubtreeTotals { path: path.to_string(), channel_count: 0, failed_channel_count: 0, count: 0, fail_count: 0, sign_count: 0 }
+    }
+
+    fn add(&mut self, entry: &RegistryEntry) {
+        self.channel_count += 1;
+        if !entry.is_ok {
+            self.failed_channel_count += 1;
+        }
+        self.count += entry.count;
+        self.fail_count += entry.fail_count;
+        self.sign_count += entry.sign_count;
+    }
+
+    // Indicate whether every entry under the subtree is ok.
+    pub fn is_ok(&self) -> bool {
+        self.failed_channel_count == 0
+    }
+}
+
+impl Display for SubtreeTotals {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}: {} channels, {} failed, count {}, fail {}, sign {}",
+            self.path, self.channel_count, self.failed_channel_count, self.count, self.fail_count, self.sign_count
+        )
+    }
+}
+
+// Indicate whether a hierarchical name matches a pattern, per Report::filter.
+fn path_matches(name: &str, pattern: &str) -> bool {
+    let segments: Vec<&str> = name.split(PATH_SEPARATOR).collect();
+    let patterns: Vec<&str> = pattern.split(PATH_SEPARATOR).collect();
+    let segment_matches = |(pattern, segment): (&&str, &&str)| *pattern == "*" || pattern == segment;
+    match patterns.split_last() {
+        Some((&"*", parents)) => segments.len() > parents.len() && parents.iter().zip(&segments).all(segment_matches),
+        _ => segments.len() == patterns.len() && patterns.iter().zip(&segments).all(segment_matches),
+    }
+}
+
+// Recover from poisoning, since a panicking test shouldn't hide other results.
+fn lock() -> std::sync::MutexGuard<'static, Vec<RegistryEntry>> {
+    REGISTRY.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{entries, path_matches, rank, register, report, RegistryEntry, Report};
+    use crate::diff;
+    use crate::diff_summary_f64::{DiffSummary, WorstMetric};
+
+    #[test]
+    fn test_registry() {
+        // Other tests may register concurrently, so only look at our own entries.
+        let mut passing = DiffSummary::new("registry pass", 0.1, false, 4, &diff::diff_abs);
+        passing.add(1.0, 1.0, 0);
+        let mut failing = DiffSummary::new("registry fail", 0.1, false, 4, &diff::diff_abs);
+        failing.add(2.0, 1.0, 0);
+        register(&passing);
+        register(&failing);
+        let ours: Vec<_> = entries().into_iter().filter(|entry| entry.name.starts_with("registry ")).collect();
+        assert_eq!(ours.len(), 2);
+        assert!(ours[0].is_ok && !ours[1].is_ok);
+        assert_eq!(ours[1].worst_diff, 1.0);
+        assert_eq!(ours[1].summary, failing.to_string());
+        let text = report().to_string();
+        assert!(text.find("FAILED registry fail").unwrap() < text.find("ok     registry pass").unwrap(), "{}", text);
+    }
+
+    #[test]
+    fn test_new_strict() {
+        let mut lenient = DiffSummary::new("lenient", 0.1, false, 4, &diff::diff_abs);
+        lenient.add_slices(&[1.0, f64::NAN, f64::INFINITY], &[1.0, f64::NAN, f64::INFINITY]);
+        assert!(RegistryEntry::new(&lenient).is_ok);
+        let entry = RegistryEntry::new_strict(&lenient);
+        assert_eq!((entry.fail_count, entry.is_ok), (2, false));
+        assert!(entry.worst_diff.is_nan());
+        assert!(entry.summary.ends_with(", nonfinite strict by FLOAT_DIFF_STRICT failed 2"), "{}", entry.summary);
+        let mut strict = DiffSummary::new("strict", 0.1, false, 4, &diff::diff_abs).with_strict_mode();
+        strict.add(1.0, 1.0, 0);
+        let entry = RegistryEntry::new_strict(&strict);
+        assert!(entry.is_ok && entry.summary.ends_with("sign diffs 0%, nonfinite strict"), "{}", entry.summary);
+    }
+
+    #[test]
+    fn test_rank() {
+        let entry = |name, worst_diff, allow_diff, metric| {
+            let mut summary = DiffSummary::new(name, allow_diff, false, 4, &diff::diff_abs).with_worst_metric(metric);
+            summary.add(1.0, 1.0 + worst_diff, 0);
+            RegistryEntry::new(&summary)
+        };
+        let mut entries = vec![
+            entry("tight", 0.25, 1.0, WorstMetric::ToleranceRatio),
+            entry("loose", 1.0, 8.0, WorstMetric::ToleranceRatio),
+            entry("failing", 0.5, 0.25, WorstMetric::ToleranceRatio),
+            entry("tight twin", 0.25, 1.0, WorstMetric::ToleranceRatio),
+        ];
+        rank(&mut entries);
+        let names: Vec<&str> = entries.iter().map(|entry| entry.name.as_str()).collect();
+        assert_eq!(names, vec!["failing", "tight", "tight twin", "loose"]);
+        assert_eq!(entries[3].worst_score, 0.125);
+    }
+
+    #[test]
+    fn test_subtrees() {
+        assert!(path_matches("geodesic/inverse/azimuth", "geodesic/inverse/*"));
+        assert!(path_matches("geodesic/inverse/azimuth/deg", "geodesic/*"));
+        assert!(path_matches("geodesic/inverse/azimuth", "geodesic/*/azimuth"));
+        assert!(!path_matches("geodesic/inverse", "geodesic/inverse/*"));
+        assert!(!path_matches("geodesic/direct/lat", "geodesic/inverse/*"));
+        assert!(!path_matches("geodesic/inverse/azimuth", "geodesic/inverse"));
+
+        let entry = |name, y| {
+            let mut summary = DiffSummary::new(name, 0.1, false, 4, &diff::diff_abs);
+            summary.add(1.0, y, 0);
+            RegistryEntry::new(&summary)
+        };
+        let report = Report {
+            entries: vec![
+                entry("geodesic/inverse/azimuth", 2.0),
+                entry("geodesic/inverse/distance", 1.0),
+                entry("geodesic/direct/lat", 1.0),
+                entry("flat", 1.0),
+            ],
+        };
+        let subtrees = report.subtrees();
+        let paths: Vec<&str> = subtrees.iter().map(|subtree| subtree.path.as_str()).collect();
+        assert_eq!(paths, vec!["geodesic", "geodesic/direct", "geodesic/inverse"]);
+        assert_eq!((subtrees[0].channel_count, subtrees[0].failed_channel_count, subtrees[0].fail_count), (3, 1, 1));
+        assert!(subtrees[1].is_ok() && !subtrees[2].is_ok());
+        let text = report.to_string();
+        assert!(text.ends_with("\nsubtrees\n  FAILED geodesic: 3 channels, 1 failed, count 3, fail 1, sign 0\n  \
+            ok     geodesic/direct: 1 channels, 0 failed, count 1, fail 0, sign 0\n  \
+            FAILED geodesic/inverse: 2 channels, 1 failed, count 2, fail 1, sign 0"), "{}", text);
+
+        let inverse = report.filter("geodesic/inverse/*");
+        assert_eq!(inverse.entries.len(), 2);
+        assert!(!inverse.is_ok());
+        assert!(report.filter("geodesic/direct/*").is_ok());
+        report.filter("geodesic/direct/*").assert();
+        assert!(!report.filter("flat").to_string().contains("subtrees"));
+    }
+}
@@ -0,0 +1,150 @@
+// Reports spanning collections of summaries.
+use std::fmt::Display;
+use crate::diff_summary_f64::DiffSummary;
+use crate::run_comparison::{Change, RunComparison};
+
+// One channel of an A/B comparison.
+#[derive(Clone, Debug, PartialEq)]
+pub enum AbRow {
+    // The channel was in both collections.
+    Both(RunComparison),
+    // The named channel was only in the before collection.
+    Removed(String),
+    // The named channel was only in the after collection.
+    Added(String),
+    // The named channel was in both collections, but with different dataset
+    // fingerprints, so its runs weren't compared.
+    Mismatched(String),
+}
+
+impl AbRow {
+    // The row's overall change: Regressed if any metric regressed, otherwise
+    // Improved if any improved. None for channels in only one collection.
+    pub fn change(&self) -> Option<Change> {
+        match self {
+            AbRow::Both(comparison) => {
+                let changes: Vec<Change> = comparison.metrics().iter().map(|(_, metric)| metric.change()).collect();
+                Some(if changes.contains(&Change::Regressed) {
+                    Change::Regressed
+                } else if changes.contains(&Change::Improved) {
+                    Change::Improved
+                } else {
+                    Change::Unchanged
+                })
+            }
+            AbRow::Removed(_) | AbRow::Added(_) | AbRow::Mismatched(_) => None,
+        }
+    }
+}
+
+// A per-channel comparison of two collections of summaries, as returned by ab_compare.
+#[derive(Clone, Debug, PartialEq)]
+pub struct AbTable {
+    // Channels in after's order, followed by channels only in before, in before's order.
+    pub rows: Vec<AbRow>,
+}
+
+impl AbTable {
+    // The comparisons of channels with any regressed metric.
+    pub fn regressions(&self) -> Vec<&RunComparison> {
+        self.rows
+            .iter()
+            .filter_map(|row| match row {
+                AbRow::Both(comparison) if comparison.is_regression() => Some(comparison),
+                _ => None,
+            })
+            .collect()
+    }
+
+    // Indicate whether any channel regressed.
+    pub fn is_regression(&self) -> bool {
+        !self.regressions().is_empty()
+    }
+}
+
+// Compare "before" and "after" collections of summaries, pairing summaries
+// by name, as with DiffSummary::compare_to for each pair.
+// Names are expected to be unique within each collection. If a name
+// repeats, its first summary is used. Pairs with different dataset
+// fingerprints become Mismatched rows rather than comparisons.
+pub fn ab_compare(before: &[DiffSummary], after: &[DiffSummary]) -> AbTable {
+    let mut rows = Vec::new();
+    for (i, summary) in after.iter().enumerate() {
+        if after[..i].iter().any(|earlier| earlier.name == summary.name) {
+            continue;
+        }
+        rows.push(match before.iter().find(|baseline| baseline.name == summary.name) {
+            Some(baseline) => match summary.try_compare_to(baseline) {
+                Ok(comparison) => AbRow::Both(comparison),
+                Err(_) => AbRow::Mismatched(summary.name.to_string()),
+            },
+            None => AbRow::Added(summary.name.to_string()),
+        });
+    }
+    for (i, baseline) in before.iter().enumerate() {
+        let is_repeat = before[..i].iter().any(|earlier| earlier.name == baseline.name);
+        if !is_repeat && !after.iter().any(|summary| summary.name == baseline.name) {
+            rows.push(AbRow::Removed(baseline.name.to_string()));
+        }
+    }
+    AbTable { rows }
+}
+
+// Shows a count line, then one line per channel, labeled with its overall
+// change, e.g.
+//   A/B 3 channels, 1 regressed
+//     regressed pressure: worst diff 1e-9 -> 4e-9 regressed, ...
+//     unchanged phase: ...
+//     added     flux
+impl Display for AbTable {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "A/B {} channels, {} regressed", self.rows.len(), self.regressions().len())?;
+        for row in &self.rows {
+            match row {
+                AbRow::Both(comparison) => write!(f, "\n  {:<9} {}", row.change().unwrap().to_string(), comparison)?,
+                AbRow::Removed(name) => write!(f, "\n  {:<9} {}", "removed", name)?,
+                AbRow::Added(name) => write!(f, "\n  {:<9} {}", "added", name)?,
+                AbRow::Mismatched(name) => write!(f, "\n  {:<9} {}", "mismatch", name)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ab_compare, AbRow};
+    use crate::diff;
+    use crate::diff_summary_f64::DiffSummary;
+    use crate::run_comparison::Change;
+
+    #[test]
+    fn test_ab_compare() {
+        let new = |name| DiffSummary::new(name, 0.5, false, 4, &diff::diff_abs);
+        let mut before = vec![new("pressure"), new("phase"), new("flux")];
+        let mut after = vec![new("phase"), new("pressure"), new("temp")];
+        before[0].add(1.0, 1.25, 0);
+        after[1].add(1.0, 2.0, 0);
+        before[1].add(1.0, 1.0, 0);
+        after[0].add(1.0, 1.0, 0);
+
+        let table = ab_compare(&before, &after);
+        assert_eq!(table.rows.len(), 4);
+        assert_eq!(table.rows[0].change(), Some(Change::Unchanged));
+        assert_eq!(table.rows[1].change(), Some(Change::Regressed));
+        assert_eq!(table.rows[2], AbRow::Added("temp".to_string()));
+        assert_eq!(table.rows[3], AbRow::Removed("flux".to_string()));
+        assert!(table.is_regression());
+        assert_eq!(table.regressions()[0].name, "pressure");
+        let text = table.to_string();
+        assert!(text.starts_with("A/B 4 channels, 1 regressed\n  unchanged phase: "), "{}", text);
+        assert!(text.contains("\n  regressed pressure: worst diff 2.5e-1 -> 1e0 regressed"), "{}", text);
+        assert!(text.ends_with("\n  added     temp\n  removed   flux"), "{}", text);
+        assert!(!ab_compare(&after, &after).is_regression());
+
+        let dataset = |id| vec![new("pressure").with_dataset_id(id)];
+        let table = ab_compare(&dataset("grid v1"), &dataset("grid v2"));
+        assert_eq!(table.rows, vec![AbRow::Mismatched("pressure".to_string())]);
+        assert_eq!(table.to_string(), "A/B 1 channels, 0 regressed\n  mismatch  pressure");
+    }
+}
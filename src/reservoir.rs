@@ -0,0 +1,111 @@
+use crate::util::mix64;
+
+// Retains a uniform random sample of the (index, x, y, diff) of up to
+// capacity items seen so far, for scatter plots and sanity checks.
+// Each item gets a pseudo-random key from the seed, its index, and the
+// number of items seen before it, and the items with the smallest keys are
+// kept. This makes the sample reproducible for a given seed and order of
+// additions, and lets samples from split datasets merge uniformly.
+#[derive(Clone, Debug)]
+pub(crate) struct Reservoir {
+    capacity: usize,
+    seed: u64,
+    // The number of items offered to this reservoir.
+    seen: usize,
+    // The (key, (index, x, y, diff)) of retained items, in no particular order.
+    items: Vec<(u64, (usize, f64, f64, f64))>,
+    // Position in items of the item with the largest key, once at capacity.
+    largest: usize,
+}
+
+impl Reservoir {
+    pub fn new(capacity: usize, seed: u64) -> Self {
+        Reservoir {
+            capacity,
+            seed,
+            seen: 0,
+            items: Vec::with_capacity(capacity),
+            largest: 0,
+        }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    pub fn add(&mut self, index: usize, x: f64, y: f64, diff: f64) {
+        if self.capacity == 0 {
+            return;
+        }
+        let key = mix64(mix64(self.seed ^ index as u64) ^ self.seen as u64);
+        self.seen += 1;
+        self.offer(key, (index, x, y, diff));
+    }
+
+    fn offer(&mut self, key: u64, item: (usize, f64, f64, f64)) {
+        if self.items.len() < self.capacity {
+            self.items.push((key, item));
+            if self.items.len() == self.capacity {
+                self.find_largest();
+            }
+        } else if self.capacity > 0 && key < self.items[self.largest].0 {
+            self.items[self.largest] = (key, item);
+            self.find_largest();
+        }
+    }
+
+    // Combine another reservoir's sample into this one.
+    pub fn merge(&mut self, other: &Reservoir) {
+        self.seen += other.seen;
+        for &(key, item) in &other.items {
+            self.offer(key, item);
+        }
+    }
+
+    // The sampled items, in index order.
+    pub fn sorted(&self) -> Vec<(usize, f64, f64, f64)> {
+        let mut items: Vec<_> = self.items.iter().map(|&(_, item)| item).collect();
+        items.sort_by_key(|item| item.0);
+        items
+    }
+
+    fn find_largest(&mut self) {
+        let mut largest = 0;
+        for (i, item) in self.items.iter().enumerate() {
+            if item.0 > self.items[largest].0 {
+                largest = i;
+            }
+        }
+        self.largest = largest;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Reservoir;
+
+    #[test]
+    fn test_reservoir() {
+        let mut reservoir = Reservoir::new(10, 1);
+        let mut repeat = Reservoir::new(10, 1);
+        let mut first = Reservoir::new(10, 1);
+        let mut second = Reservoir::new(10, 1);
+        let mut reseeded = Reservoir::new(10, 2);
+        for i in 0..1000 {
+            reservoir.add(i, i as f64, 0.0, 0.0);
+            repeat.add(i, i as f64, 0.0, 0.0);
+            reseeded.add(i, i as f64, 0.0, 0.0);
+            if i < 500 { &mut first } else { &mut second }.add(i, i as f64, 0.0, 0.0);
+        }
+        let sample = reservoir.sorted();
+        assert_eq!(sample.len(), 10);
+        assert_eq!(sample, repeat.sorted());
+        // A uniform sample of 10 from 1000 is very unlikely to be clustered.
+        assert!(sample[0].0 < 500 && sample[9].0 >= 500, "{:?}", sample);
+        assert_ne!(sample, reseeded.sorted());
+
+        first.merge(&second);
+        assert_eq!(first.sorted().len(), 10);
+        assert!(Reservoir::new(0, 1).sorted().is_empty());
+    }
+}
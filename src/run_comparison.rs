@@ -0,0 +1,182 @@
+use std::fmt::Display;
+use crate::diff_summary_f64::DiffSummary;
+use crate::log_histogram::BucketKey;
+
+// The direction of change in a metric between two runs.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Change {
+    Improved,
+    Regressed,
+    Unchanged,
+}
+
+impl Display for Change {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Change::Improved => write!(f, "improved"),
+            Change::Regressed => write!(f, "regressed"),
+            Change::Unchanged => write!(f, "unchanged"),
+        }
+    }
+}
+
+// A metric's value in a baseline run and a later run.
+// For all metrics, lower values are better.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct MetricDelta {
+    pub before: f64,
+    pub after: f64,
+}
+
+impl MetricDelta {
+    // The change from before to after. May be nan if either is nan.
+    pub fn delta(&self) -> f64 {
+        self.after - self.before
+    }
+
+    // Whether the metric improved, using the same ordering as for
+    // worst diffs: nan is worse than infinity is worse than anything finite.
+    pub fn change(&self) -> Change {
+        if crate::diff::is_diff_worse(self.after, self.before) {
+            Change::Regressed
+        } else if crate::diff::is_diff_worse(self.before, self.after) {
+            Change::Improved
+        } else {
+            Change::Unchanged
+        }
+    }
+}
+
+// A comparison between a baseline run of a DiffSummary and a later run
+// of the same measurement, as returned by DiffSummary::compare_to.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RunComparison {
+    // The name of the later run's summary.
+    pub name: String,
+    pub worst_diff: MetricDelta,
+    // Percentage of items failing tolerance.
+    pub fail_percent: MetricDelta,
+    pub mean_diff: MetricDelta,
+    pub rms_diff: MetricDelta,
+    // For every histogram bucket populated in either run,
+    // (bucket, percent of items before, percent of items after).
+    pub bucket_shift: Vec<(BucketKey, f64, f64)>,
+}
+
+impl RunComparison {
+    pub fn new(before: &DiffSummary, after: &DiffSummary) -> Self {
+        let bucket_shift = after.histogram().delta(before.histogram()).buckets.into_iter()
+            .map(|bucket| (bucket.key, bucket.before_percent, bucket.after_percent))
+            .collect();
+        RunComparison {
+            name: after.name.to_string(),
+            worst_diff: MetricDelta { before: before.worst_diff(), after: after.worst_diff() },
+            fail_percent: MetricDelta { before: before.fail_percent(), after: after.fail_percent() },
+            mean_diff: MetricDelta { before: before.mean_diff(), after: after.mean_diff() },
+            rms_diff: MetricDelta { before: before.rms_diff(), after: after.rms_diff() },
+            bucket_shift,
+        }
+    }
+
+    // Indicate whether any metric regressed.
+    pub fn is_regression(&self) -> bool {
+        self.metrics().iter().any(|(_, metric)| metric.change() == Change::Regressed)
+    }
+
+    // The labeled metrics, in display order.
+    pub fn metrics(&self) -> [(&'static str, MetricDelta); 4] {
+        [
+            ("worst diff", self.worst_diff),
+            ("failed", self.fail_percent),
+            ("mean", self.mean_diff),
+            ("rms", self.rms_diff),
+        ]
+    }
+}
+
+impl Display for RunComparison {
+    // Display each metric's before and after values and direction of change,
+    // followed by the buckets whose share of items changed, in percentage points.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}{}", self.name, if !self.name.is_empty() { ": " } else { "" })?;
+        for (i, (label, metric)) in self.metrics().iter().enumerate() {
+            if i > 0 {
+                write!(f, ", ")?;
+            }
+            if *label == "failed" {
+                write!(f, "{} {:.2}% -> {:.2}% {}", label, metric.before, metric.after, metric.change())?;
+            } else {
+                write!(f, "{} {:e} -> {:e} {}", label, metric.before, metric.after, metric.change())?;
+            }
+        }
+        let mut first = true;
+        for (key, before, after) in &self.bucket_shift {
+            if before != after {
+                write!(f, "{}{} {:+.1}pp", if first { ", shift " } else { " " }, key, after - before)?;
+                first = false;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Change, MetricDelta};
+    use crate::diff;
+    use crate::diff_summary_f64::DiffSummary;
+    use crate::log_histogram::BucketKey;
+
+    #[test]
+    fn test_change() {
+        assert_eq!(MetricDelta { before: 1.0, after: 0.5 }.change(), Change::Improved);
+        assert_eq!(MetricDelta { before: 1.0, after: f64::INFINITY }.change(), Change::Regressed);
+        assert_eq!(MetricDelta { before: f64::NAN, after: 3.0 }.change(), Change::Improved);
+        assert_eq!(MetricDelta { before: 2.0, after: 2.0 }.change(), Change::Unchanged);
+    }
+
+    #[test]
+    fn test_compare_to() {
+        let mut before = DiffSummary::new("run", 0.5, false, 4, &diff::diff_abs);
+        let mut after = before.clone();
+        for (i, &(x, y)) in [(1.0, 1.0), (1.0, 3.0), (2.0, 2.25), (3.0, 3.0)].iter().enumerate() {
+            before.add(x, y, i);
+        }
+        for (i, &(x, y)) in [(1.0, 1.0), (1.0, 1.25), (2.0, 2.25), (3.0, 3.0)].iter().enumerate() {
+            after.add(x, y, i);
+        }
+        let comparison = after.compare_to(&before);
+        assert_eq!(comparison.worst_diff, MetricDelta { before: 2.0, after: 0.25 });
+        assert_eq!(comparison.fail_percent.change(), Change::Improved);
+        assert!(!comparison.is_regression());
+        assert_eq!(comparison.bucket_shift, vec![(BucketKey::Zero, 50.0, 50.0), (BucketKey::Pos(0), 50.0, 50.0)]);
+        assert_eq!(
+            format!("{}", comparison),
+            "run: worst diff 2e0 -> 2.5e-1 improved, failed 25.00% -> 0.00% improved, \
+                mean 5.625e-1 -> 1.25e-1 improved, rms 1.0077822185373186e0 -> 1.767766952966369e-1 improved"
+        );
+        assert!(before.compare_to(&after).is_regression());
+    }
+
+    #[test]
+    fn test_compare_fingerprint() {
+        let mut before = DiffSummary::new("run", 0.5, false, 4, &diff::diff_abs).with_fingerprint();
+        let (mut after, mut other) = (before.clone(), before.clone());
+        before.add_slices(&[1.0, 2.0], &[1.0, 2.0]);
+        after.add_slices(&[1.5, 2.0], &[1.0, 2.0]);
+        other.add_slices(&[1.0, 2.0], &[1.0, 2.5]);
+        assert!(after.try_compare_to(&before).is_ok());
+        let err = other.try_compare_to(&before).unwrap_err();
+        assert!(err.to_string().starts_with("run: dataset mismatch, baseline 2 values #"), "{}", err);
+        let unfingerprinted = DiffSummary::new("run", 0.5, false, 4, &diff::diff_abs);
+        assert!(unfingerprinted.try_compare_to(&other).is_ok());
+    }
+
+    #[test]
+    #[should_panic(expected = "dataset mismatch, baseline v1 vs v2")]
+    fn test_compare_dataset_id() {
+        let before = DiffSummary::new("run", 0.5, false, 4, &diff::diff_abs).with_dataset_id("v1");
+        let after = DiffSummary::new("run", 0.5, false, 4, &diff::diff_abs).with_dataset_id("v2");
+        after.compare_to(&before);
+    }
+}
@@ -0,0 +1,36 @@
+use std::rc::Rc;
+use std::time::SystemTime;
+
+// A retained item, such as a summary's worst item, first failure, or first
+// sign change, with what was known when it was added.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SampleRecord {
+    pub index: usize,
+    // The computed and expected values.
+    pub x: f64,
+    pub y: f64,
+    // The item's difference, per the summary's difference function.
+    pub diff: f64,
+    // When the item was added.
+    pub time: SystemTime,
+    // The summary's context when the item was added, per DiffSummary::set_sample_context.
+    pub context: Option<Rc<str>>,
+}
+
+impl SampleRecord {
+    // A record of an item added now, with the given context.
+    pub fn new(index: usize, x: f64, y: f64, diff: f64, context: Option<&Rc<str>>) -> Self {
+        SampleRecord { index, x, y, diff, time: SystemTime::now(), context: context.cloned() }
+    }
+
+    // The (index, x, y, diff) of the item, as for unretained samples
+    // such as those from DiffSummary::reservoir.
+    pub fn as_tuple(&self) -> (usize, f64, f64, f64) {
+        (self.index, self.x, self.y, self.diff)
+    }
+
+    // A placeholder for before any item is recorded.
+    pub(crate) fn empty() -> Self {
+        SampleRecord { index: 0, x: f64::NAN, y: f64::NAN, diff: f64::NAN, time: SystemTime::UNIX_EPOCH, context: None }
+    }
+}
@@ -0,0 +1,257 @@
+use std::cell::RefCell;
+use std::fs::{self, File};
+use std::io::{self, BufReader, Read, Write};
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+// The size of one spilled item: its index, then the bits of x and y, each
+// as a little-endian u64.
+const ITEM_BYTES: usize = 24;
+
+// A file-backed store of every (index, x, y) a summary records, for
+// retaining raw data larger than memory for later analysis.
+// Items are buffered in memory, and each full buffer is written to the
+// directory as a chunk file, named spill-000000.bin and so on. Values are
+// stored as raw bits, so they're read back exactly. The directory is
+// created, and chunk files left in it by earlier runs removed, before the
+// first chunk is written.
+// Items are recorded without a way to report errors, so the first write
+// error is kept, and later items are dropped, until flush reports it.
+// Clones of a summary share the store, as with CsvTee.
+#[derive(Clone)]
+pub(crate) struct Spill {
+    inner: Rc<RefCell<SpillState>>,
+}
+
+struct SpillState {
+    dir: PathBuf,
+    chunk_items: usize,
+    buffer: Vec<u8>,
+    chunk_count: usize,
+    // Whether the directory has been created and cleared of earlier chunks.
+    is_prepared: bool,
+    error: Option<io::Error>,
+}
+
+impl Spill {
+    // Create a store writing chunks of up to chunk_items items to dir,
+    // which is created when the first chunk is written.
+    pub fn new(dir: &Path, chunk_items: usize) -> Self {
+        assert!(chunk_items > 0, "spill chunks must hold at least one item");
+        let state = SpillState {
+            dir: dir.to_path_buf(),
+            chunk_items,
+            buffer: Vec::with_capacity(chunk_items * ITEM_BYTES),
+            chunk_count: 0,
+            is_prepared: false,
+            error: None,
+        };
+        Spill { inner: Rc::new(RefCell::new(state)) }
+    }
+
+    pub fn add(&self, index: usize, x: f64, y: f64) {
+        let mut state = self.inner.borrow_mut();
+        if state.error.is_some() {
+            return;
+        }
+        for value in [index as u64, x.to_bits(), y.to_bits()].iter() {
+            state.buffer.extend_from_slice(&value.to_le_bytes());
+        }
+        if state.buffer.len() == state.chunk_items * ITEM_BYTES {
+            state.write_chunk();
+        }
+    }
+
+    // Write any buffered items as a chunk, returning the first error since
+    // the last flush, if any. The directory is prepared even if no items
+    // were recorded, so that it reads back as empty.
+    pub fn flush(&self) -> io::Result<()> {
+        let mut state = self.inner.borrow_mut();
+        if !state.buffer.is_empty() {
+            state.write_chunk();
+        } else if let Err(err) = state.prepare() {
+            state.error.get_or_insert(err);
+        }
+        match state.error.take() {
+            Some(err) => Err(err),
+            None => Ok(()),
+        }
+    }
+
+    pub fn dir(&self) -> PathBuf {
+        self.inner.borrow().dir.clone()
+    }
+
+    // Add every item in other, such as a merged summary's store, after those
+    // already added. Does nothing if other is this store or a clone of it,
+    // since it already holds other's items. An error flushing or reading
+    // other is kept, as for a write error, until flush reports it.
+    pub fn extend_from(&self, other: &Spill) {
+        if Rc::ptr_eq(&self.inner, &other.inner) {
+            return;
+        }
+        let result = other.flush().and_then(|_| SpillReader::open(other.dir())).and_then(|reader| {
+            for item in reader {
+                let (index, x, y) = item?;
+                self.add(index, x, y);
+            }
+            Ok(())
+        });
+        if let Err(err) = result {
+            self.inner.borrow_mut().error.get_or_insert(err);
+        }
+    }
+}
+
+impl SpillState {
+    fn prepare(&mut self) -> io::Result<()> {
+        if !self.is_prepared {
+            fs::create_dir_all(&self.dir)?;
+            for path in chunk_paths(&self.dir)? {
+                fs::remove_file(path)?;
+            }
+            self.is_prepared = true;
+        }
+        Ok(())
+    }
+
+    fn write_chunk(&mut self) {
+        let path = self.dir.join(format!("spill-{:06}.bin", self.chunk_count));
+        let result = self.prepare().and_then(|_| File::create(path)?.write_all(&self.buffer));
+        match result {
+            Ok(()) => self.chunk_count += 1,
+            Err(err) => self.error = Some(err),
+        }
+        self.buffer.clear();
+    }
+}
+
+// An iterator over the (index, x, y) items spilled to a directory, in the
+// order they were recorded, as returned by DiffSummary::spilled.
+// Reads one chunk file at a time, so memory use is bounded by the chunk size.
+pub struct SpillReader {
+    // The remaining chunk files, in reverse order.
+    chunks: Vec<PathBuf>,
+    current: Option<BufReader<File>>,
+}
+
+impl SpillReader {
+    // Read the items spilled to dir, such as by an earlier run.
+    pub fn open<P: AsRef<Path>>(dir: P) -> io::Result<Self> {
+        let mut chunks = chunk_paths(dir.as_ref())?;
+        chunks.reverse();
+        Ok(SpillReader { chunks, current: None })
+    }
+
+    fn read_item(reader: &mut BufReader<File>) -> io::Result<Option<(usize, f64, f64)>> {
+        let mut bytes = [0u8; ITEM_BYTES];
+        let mut filled = 0;
+        while filled < ITEM_BYTES {
+            match reader.read(&mut bytes[filled..])? {
+                0 if filled == 0 => return Ok(None),
+                0 => return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "truncated spill item")),
+                count => filled += count,
+            }
+        }
+        let field = |i: usize| {
+            let mut word = [0u8; 8];
+            word.copy_from_slice(&bytes[i * 8..(i + 1) * 8]);
+            u64::from_le_bytes(word)
+        };
+        Ok(Some((field(0) as usize, f64::from_bits(field(1)), f64::from_bits(field(2)))))
+    }
+}
+
+// The chunk files in dir, in the order written. They're sorted by chunk
+// number, since names past spill-999999.bin don't sort as text.
+fn chunk_paths(dir: &Path) -> io::Result<Vec<PathBuf>> {
+    let mut chunks = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        let name = path.file_name().and_then(|name| name.to_str()).unwrap_or("");
+        let number = name.strip_prefix("spill-").and_then(|rest| rest.strip_suffix(".bin")).and_then(|number| number.parse::<u64>().ok());
+        if let Some(number) = number {
+            chunks.push((number, path));
+        }
+    }
+    chunks.sort();
+    Ok(chunks.into_iter().map(|(_, path)| path).collect())
+}
+
+impl Iterator for SpillReader {
+    type Item = io::Result<(usize, f64, f64)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(reader) = &mut self.current {
+                match SpillReader::read_item(reader) {
+                    Ok(Some(item)) => return Some(Ok(item)),
+                    Ok(None) => self.current = None,
+                    Err(err) => {
+                        self.current = None;
+                        return Some(Err(err));
+                    }
+                }
+            }
+            let path = self.chunks.pop()?;
+            match File::open(path) {
+                Ok(file) => self.current = Some(BufReader::new(file)),
+                Err(err) => return Some(Err(err)),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{chunk_paths, Spill, SpillReader};
+
+    #[test]
+    fn test_spill() {
+        let dir = std::env::temp_dir().join(format!("float-diff-spill-{}", std::process::id()));
+        let spill = Spill::new(&dir, 2);
+        for i in 0..5 {
+            spill.add(i, i as f64 * 0.1, f64::NAN);
+        }
+        let shared = spill.clone();
+        shared.add(9, -0.0, f64::INFINITY);
+        spill.flush().unwrap();
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 3);
+        let items: Vec<(usize, f64, f64)> = SpillReader::open(&dir).unwrap().map(Result::unwrap).collect();
+        assert_eq!(items.iter().map(|item| item.0).collect::<Vec<_>>(), vec![0, 1, 2, 3, 4, 9]);
+        assert_eq!(items[3].1, 0.30000000000000004);
+        assert!(items[0].2.is_nan() && items[5].1.is_sign_negative());
+        let rerun = Spill::new(&dir, 2);
+        rerun.flush().unwrap();
+        assert_eq!(SpillReader::open(&dir).unwrap().count(), 0);
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert!(SpillReader::open(&dir).is_err());
+    }
+
+    #[test]
+    fn test_extend_from() {
+        let dir = std::env::temp_dir().join(format!("float-diff-spill-extend-{}", std::process::id()));
+        let (spill, other) = (Spill::new(&dir.join("a"), 2), Spill::new(&dir.join("b"), 2));
+        spill.add(0, 1.0, 1.5);
+        for i in 1..4 {
+            other.add(i, i as f64, 0.0);
+        }
+        spill.extend_from(&other);
+        spill.extend_from(&spill.clone());
+        let indexes: Vec<usize> = SpillReader::open(spill.dir()).unwrap().map(|item| item.unwrap().0).collect();
+        assert_eq!(indexes, vec![0, 1, 2, 3]);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_chunk_order() {
+        let dir = std::env::temp_dir().join(format!("float-diff-spill-order-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        for name in &["spill-1000000.bin", "spill-999999.bin", "spill-000002.bin", "spill-x.bin", "notes.txt"] {
+            std::fs::write(dir.join(name), "").unwrap();
+        }
+        let names: Vec<_> = chunk_paths(&dir).unwrap().iter().map(|path| path.file_name().unwrap().to_owned()).collect();
+        assert_eq!(names, vec!["spill-000002.bin", "spill-999999.bin", "spill-1000000.bin"]);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
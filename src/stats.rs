@@ -0,0 +1,307 @@
+// Statistical tests for comparing the error distributions of two runs,
+// so that accuracy jobs can distinguish significant drift from noise, and
+// error model fitting, for describing a single run's errors.
+
+use std::fmt::Display;
+use crate::diff_summary_f64::DiffSummary;
+use crate::log_histogram::LogHistogram;
+
+// The result of a two-sample test.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TestResult {
+    // The test statistic. For Kolmogorov-Smirnov, the maximum distance
+    // between the two empirical cumulative distributions.
+    pub statistic: f64,
+    // The approximate probability of a statistic at least this large if
+    // both samples came from the same distribution.
+    pub p_value: f64,
+}
+
+impl TestResult {
+    // Indicate whether the difference is significant at level alpha, e.g. 0.05.
+    pub fn is_significant(&self, alpha: f64) -> bool {
+        self.p_value < alpha
+    }
+}
+
+// Two-sample Kolmogorov-Smirnov test over the recorded diffs of two summaries.
+// See ks_test_histograms for caveats.
+pub fn ks_test(a: &DiffSummary, b: &DiffSummary) -> TestResult {
+    ks_test_histograms(a.histogram(), b.histogram())
+}
+
+// Two-sample Kolmogorov-Smirnov test over the contents of two histograms.
+// Since values are only known to bucket resolution, the statistic can only
+// be evaluated at bucket boundaries. This underestimates the true statistic,
+// making the test conservative: differences within a bucket go undetected.
+pub fn ks_test_histograms(a: &LogHistogram, b: &LogHistogram) -> TestResult {
+    let n_a = a.count_total();
+    let n_b = b.count_total();
+    if n_a == 0 || n_b == 0 {
+        return TestResult { statistic: 0.0, p_value: 1.0 };
+    }
+    let counts_a = a.bucket_counts();
+    let counts_b = b.bucket_counts();
+    let mut keys: Vec<_> = counts_a.keys().chain(counts_b.keys()).cloned().collect();
+    keys.sort();
+    keys.dedup();
+    let (mut cum_a, mut cum_b) = (0usize, 0usize);
+    let mut statistic = 0f64;
+    for key in keys {
+        cum_a += counts_a.get(&key).cloned().unwrap_or(0);
+        cum_b += counts_b.get(&key).cloned().unwrap_or(0);
+        let distance = (cum_a as f64 / n_a as f64 - cum_b as f64 / n_b as f64).abs();
+        statistic = statistic.max(distance);
+    }
+    ks_result(statistic, n_a, n_b)
+}
+
+// Two-sample Kolmogorov-Smirnov test over raw samples.
+// Nan values are ordered after all other values.
+pub fn ks_test_samples(a: &[f64], b: &[f64]) -> TestResult {
+    if a.is_empty() || b.is_empty() {
+        return TestResult { statistic: 0.0, p_value: 1.0 };
+    }
+    let sorted = |values: &[f64]| {
+        let mut values = values.to_vec();
+        values.sort_by(|x, y| x.partial_cmp(y).unwrap_or_else(|| x.is_nan().cmp(&y.is_nan())));
+        values
+    };
+    let (a, b) = (sorted(a), sorted(b));
+    let (n_a, n_b) = (a.len(), b.len());
+    let (mut i, mut j) = (0, 0);
+    let mut statistic = 0f64;
+    while i < n_a && j < n_b {
+        let (x, y) = (a[i], b[j]);
+        // Step past all copies of the smaller value, so that ties are handled together.
+        if x <= y || y.is_nan() {
+            while i < n_a && (a[i] == x || (a[i].is_nan() && x.is_nan())) {
+                i += 1;
+            }
+        }
+        if y <= x || x.is_nan() {
+            while j < n_b && (b[j] == y || (b[j].is_nan() && y.is_nan())) {
+                j += 1;
+            }
+        }
+        let distance = (i as f64 / n_a as f64 - j as f64 / n_b as f64).abs();
+        statistic = statistic.max(distance);
+    }
+    ks_result(statistic, n_a, n_b)
+}
+
+// A fit of a computation's error model, from each item's signed error in
+// ulps of its expected value, for checking that a math library's errors
+// are consistent with a few ulps of rounding noise, e.g. "1.7 ulps RMS".
+// Errors are modeled as normally distributed, with the mean as bias and
+// the standard deviation as noise. Items with non-finite x, y or error are
+// excluded from the fit.
+#[derive(Clone, Copy, Debug, PartialEq, Default)]
+pub struct ErrorModel {
+    count: usize,
+    sum: f64,
+    sum_sq: f64,
+    max_abs: f64,
+    // The number of errors within half an ulp, as for correct rounding.
+    num_rounded: usize,
+}
+
+impl ErrorModel {
+    pub fn new() -> Self {
+        ErrorModel::default()
+    }
+
+    // Add a computed (x) and expected (y) value.
+    pub fn add(&mut self, x: f64, y: f64) {
+        let error = (x - y) / ulp(y);
+        if !x.is_finite() || !error.is_finite() {
+            return;
+        }
+        self.count += 1;
+        self.sum += error;
+        self.sum_sq += error * error;
+        self.max_abs = self.max_abs.max(error.abs());
+        if error.abs() <= 0.5 {
+            self.num_rounded += 1;
+        }
+    }
+
+    pub fn merge(&mut self, other: &ErrorModel) {
+        self.count += other.count;
+        self.sum += other.sum;
+        self.sum_sq += other.sum_sq;
+        self.max_abs = self.max_abs.max(other.max_abs);
+        self.num_rounded += other.num_rounded;
+    }
+
+    // The number of items fitted.
+    pub fn count(&self) -> usize {
+        self.count
+    }
+
+    // The mean signed error in ulps, positive when x tends to be over y,
+    // or nan if no items were fitted.
+    pub fn bias_ulps(&self) -> f64 {
+        self.sum / self.count as f64
+    }
+
+    // The root mean square error in ulps, or nan if no items were fitted.
+    pub fn rms_ulps(&self) -> f64 {
+        (self.sum_sq / self.count as f64).sqrt()
+    }
+
+    // The standard deviation of the error in ulps about the bias, or nan
+    // if fewer than 2 items were fitted.
+    pub fn std_dev_ulps(&self) -> f64 {
+        if self.count < 2 {
+            return f64::NAN;
+        }
+        let n = self.count as f64;
+        ((self.sum_sq - self.sum * self.sum / n).max(0.0) / (n - 1.0)).sqrt()
+    }
+
+    // The largest error magnitude in ulps.
+    pub fn max_ulps(&self) -> f64 {
+        self.max_abs
+    }
+
+    // The fraction of errors within half an ulp, from 0 to 1, or nan if no
+    // items were fitted.
+    pub fn rounded_fraction(&self) -> f64 {
+        self.num_rounded as f64 / self.count as f64
+    }
+}
+
+// Shows the fitted parameters, e.g.
+//   errors consistent with 1.7 ulps RMS, bias +2e-1 std dev 1.7 max 6, 41.2% within 0.5 ulps
+impl Display for ErrorModel {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.count == 0 {
+            return write!(f, "errors unfitted, no finite items");
+        }
+        write!(
+            f,
+            "errors consistent with {:.2} ulps RMS, bias {:+.1e} std dev {:.2} max {}, {:.1}% within 0.5 ulps",
+            self.rms_ulps(),
+            self.bias_ulps(),
+            self.std_dev_ulps(),
+            self.max_abs,
+            100.0 * self.rounded_fraction()
+        )
+    }
+}
+
+// The spacing of f64 values at y's magnitude: the distance from |y| to the
+// next larger value, or for f64::MAX, to the next smaller.
+fn ulp(y: f64) -> f64 {
+    let magnitude = y.abs();
+    if magnitude == f64::MAX {
+        magnitude - f64::from_bits(magnitude.to_bits() - 1)
+    } else {
+        f64::from_bits(magnitude.to_bits() + 1) - magnitude
+    }
+}
+
+// Calculate the p-value for a KS statistic using the asymptotic Kolmogorov
+// distribution, with the small-sample correction from Numerical Recipes.
+fn ks_result(statistic: f64, n_a: usize, n_b: usize) -> TestResult {
+    let n_eff = (n_a as f64 * n_b as f64) / (n_a + n_b) as f64;
+    let sqrt_n = n_eff.sqrt();
+    let lambda = (sqrt_n + 0.12 + 0.11 / sqrt_n) * statistic;
+    TestResult { statistic, p_value: kolmogorov_q(lambda) }
+}
+
+// The complementary Kolmogorov distribution function:
+// Q(lambda) = 2 * sum_{j >= 1} (-1)^(j-1) exp(-2 j^2 lambda^2)
+fn kolmogorov_q(lambda: f64) -> f64 {
+    if lambda < 1e-3 {
+        return 1.0;
+    }
+    let mut sum = 0.0;
+    let mut sign = 1.0;
+    for j in 1..=100 {
+        let j = j as f64;
+        let term = sign * (-2.0 * j * j * lambda * lambda).exp();
+        sum += term;
+        if term.abs() < 1e-12 * sum.abs() {
+            break;
+        }
+        sign = -sign;
+    }
+    (2.0 * sum).clamp(0.0, 1.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ks_test, ks_test_histograms, ks_test_samples, kolmogorov_q, ulp, ErrorModel};
+    use crate::diff;
+    use crate::diff_summary_f64::DiffSummary;
+    use crate::log_histogram::LogHistogram;
+
+    #[test]
+    fn test_kolmogorov_q() {
+        // Reference values of the Kolmogorov distribution.
+        assert!((kolmogorov_q(1.36) - 0.049).abs() < 1e-3);
+        assert!((kolmogorov_q(1.63) - 0.010).abs() < 1e-3);
+        assert_eq!(kolmogorov_q(0.0), 1.0);
+    }
+
+    #[test]
+    fn test_error_model() {
+        assert_eq!((ulp(1.0), ulp(-1.5), ulp(0.0)), (f64::EPSILON, f64::EPSILON, 5e-324));
+        assert_eq!(ulp(f64::MAX), ulp(f64::MAX / 1.5));
+        let mut model = ErrorModel::new();
+        assert_eq!(model.to_string(), "errors unfitted, no finite items");
+        let mut other = ErrorModel::new();
+        for i in 0..1000 {
+            let y = 1.0 + i as f64 * 1e-3;
+            // Errors of -2 to 2 ulps, evenly.
+            let x = y + (i % 5) as f64 * ulp(y) - 2.0 * ulp(y);
+            if i < 300 { &mut model } else { &mut other }.add(x, y);
+        }
+        model.add(f64::NAN, 1.0);
+        model.add(f64::INFINITY, f64::INFINITY);
+        model.merge(&other);
+        assert_eq!(model.count(), 1000);
+        assert!(model.bias_ulps().abs() < 1e-12);
+        assert!((model.rms_ulps() - 2f64.sqrt()).abs() < 1e-12);
+        assert_eq!((model.max_ulps(), model.rounded_fraction()), (2.0, 0.2));
+        assert_eq!(model.to_string(), "errors consistent with 1.41 ulps RMS, bias +0.0e0 std dev 1.41 max 2, 20.0% within 0.5 ulps");
+    }
+
+    #[test]
+    fn test_ks_samples() {
+        let a: Vec<f64> = (0..200).map(|i| i as f64).collect();
+        let b: Vec<f64> = (0..200).map(|i| i as f64 + 0.5).collect();
+        let c: Vec<f64> = (0..200).map(|i| i as f64 + 100.0).collect();
+        let same = ks_test_samples(&a, &b);
+        assert!(same.statistic <= 0.01);
+        assert!(!same.is_significant(0.05));
+        let shifted = ks_test_samples(&a, &c);
+        assert_eq!(shifted.statistic, 0.5);
+        assert!(shifted.is_significant(0.001));
+        assert_eq!(ks_test_samples(&[1.0, f64::NAN], &[1.0, f64::NAN]).statistic, 0.0);
+    }
+
+    #[test]
+    fn test_ks_histograms() {
+        let mut a = LogHistogram::new(4);
+        let mut b = LogHistogram::new(4);
+        for i in 0..500 {
+            a.add(1e-3 * (1 + i % 7) as f64);
+            b.add(1e-3 * (1 + i % 5) as f64);
+        }
+        assert!(!ks_test_histograms(&a, &b).is_significant(0.05));
+        for _ in 0..500 {
+            b.add(1e2);
+        }
+        let drift = ks_test_histograms(&a, &b);
+        assert!((drift.statistic - 0.5).abs() < 1e-12);
+        assert!(drift.is_significant(0.001));
+
+        let mut before = DiffSummary::new("before", 1.0, false, 4, &diff::diff_abs);
+        let after = before.clone();
+        before.add(1.0, 2.0, 0);
+        assert_eq!(ks_test(&before, &after).p_value, 1.0);
+    }
+}
@@ -0,0 +1,97 @@
+use std::fmt::Display;
+use crate::diff_summary_f64::{SignLimit, Truncation};
+
+// One criterion a summary failed, or warned about, with the relevant counts.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum StatusReason {
+    // Items had differences outside tolerance, including any nan differences.
+    Tolerance { fail_count: usize, worst_diff: f64, allow_diff: f64 },
+    // Items had nan differences, which never satisfy tolerance.
+    Nan { nan_count: usize },
+    // Items changed sign. A failure unless allowed, outright or within limit.
+    Sign { sign_count: usize, allow_sign: bool, limit: Option<SignLimit> },
+    // Items had subnormal x values, under SubnormalPolicy::Fail.
+    Subnormal { subnormal_count: usize },
+    // Differences shrank more slowly than the minimum order of convergence.
+    Convergence { order: f64, min_order: f64 },
+    // Items within tolerance had differences above the warning level.
+    Warn { warn_count: usize, warn_diff: f64 },
+    // A watchdog limit stopped ingestion, so only the items gathered before
+    // it are summarized.
+    Truncated { truncation: Truncation, offered_count: usize },
+}
+
+impl Display for StatusReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            StatusReason::Tolerance { fail_count, worst_diff, allow_diff } => {
+                write!(f, "tolerance: {} failed, worst diff {:e} vs allowed {:e}", fail_count, worst_diff, allow_diff)
+            }
+            StatusReason::Nan { nan_count } => write!(f, "nan: {} nan diffs", nan_count),
+            StatusReason::Sign { sign_count, allow_sign, limit } => {
+                write!(f, "sign: {} sign diffs", sign_count)?;
+                match (allow_sign, limit) {
+                    (true, _) => write!(f, " allowed"),
+                    (false, Some(limit)) => write!(f, " {}", limit),
+                    (false, None) => write!(f, " disallowed"),
+                }
+            }
+            StatusReason::Subnormal { subnormal_count } => write!(f, "subnormal: {} subnormal x", subnormal_count),
+            StatusReason::Convergence { order, min_order } => {
+                write!(f, "convergence: order {:e} below min {:e}", order, min_order)
+            }
+            StatusReason::Warn { warn_count, warn_diff } => write!(f, "warn: {} warned above {:e}", warn_count, warn_diff),
+            StatusReason::Truncated { truncation, offered_count } => {
+                write!(f, "truncated: stopped by {} after {} items", truncation, offered_count)
+            }
+        }
+    }
+}
+
+// Why a summary is or isn't ok, as returned by DiffSummary::status, for
+// branching on specific criteria in harness code.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SummaryStatus {
+    // The number of items added.
+    pub count: usize,
+    // The criteria the summary failed. Empty exactly when the summary is ok.
+    pub failures: Vec<StatusReason>,
+    // Conditions that don't fail the summary, such as warnings or allowed sign changes.
+    pub warnings: Vec<StatusReason>,
+}
+
+impl SummaryStatus {
+    pub fn is_ok(&self) -> bool {
+        self.failures.is_empty()
+    }
+
+    // Indicate whether the summary failed tolerance, including for nan differences.
+    pub fn failed_tolerance(&self) -> bool {
+        self.failures.iter().any(|reason| matches!(reason, StatusReason::Tolerance { .. }))
+    }
+
+    // Indicate whether the summary failed due to nan differences.
+    pub fn failed_nan(&self) -> bool {
+        self.failures.iter().any(|reason| matches!(reason, StatusReason::Nan { .. }))
+    }
+
+    // Indicate whether the summary failed due to sign changes.
+    pub fn failed_sign(&self) -> bool {
+        self.failures.iter().any(|reason| matches!(reason, StatusReason::Sign { .. }))
+    }
+}
+
+// Shows "ok" or "failed", then each failure and warning, e.g.
+//   failed: tolerance: 2 failed, worst diff NaN vs allowed 1e-1; nan: 1 nan diffs
+impl Display for SummaryStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", if self.is_ok() { "ok" } else { "failed" })?;
+        for (i, reason) in self.failures.iter().enumerate() {
+            write!(f, "{}{}", if i == 0 { ": " } else { "; " }, reason)?;
+        }
+        for (i, reason) in self.warnings.iter().enumerate() {
+            write!(f, "{}{}", if i == 0 { ", with warnings: " } else { "; " }, reason)?;
+        }
+        Ok(())
+    }
+}
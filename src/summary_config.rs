@@ -0,0 +1,96 @@
+use num_traits::ToPrimitive;
+use std::iter::FromIterator;
+use crate::diff::{self, Direction};
+use crate::diff_summary_f64::DiffSummary;
+
+// The settings needed to create a DiffSummary, so that summaries can be
+// built in one expression from iterator pipelines, e.g.
+//   let summary = config.collect(xs.iter().copied().zip(ys.iter().copied()));
+#[derive(Clone, Copy)]
+pub struct DiffSummaryConfig<'a> {
+    // The name of created summaries.
+    pub name: &'a str,
+
+    // The maximum allowable difference for an item to be considered successful.
+    pub allow_diff: f64,
+
+    // Whether sign changes are allowed.
+    pub allow_sign: bool,
+
+    // The maximum number of histogram buckets to display.
+    pub bucket_count: usize,
+
+    // The function used to calculate differences and sign change status.
+    pub calc_diff: &'a dyn Fn(f64, f64) -> (f64, bool),
+
+    // The function used to decide whether x is over or under y, per
+    // DiffSummary::with_direction.
+    pub calc_direction: &'a dyn Fn(f64, f64) -> Option<Direction>,
+}
+
+impl<'a> DiffSummaryConfig<'a> {
+    pub fn new(name: &'a str, allow_diff: f64, allow_sign: bool, bucket_count: usize, calc_diff: &'a dyn Fn(f64, f64) -> (f64, bool)) -> Self {
+        DiffSummaryConfig { name, allow_diff, allow_sign, bucket_count, calc_diff, calc_direction: &diff::direction }
+    }
+
+    // Set the function used to decide whether x is over or under y.
+    pub fn with_direction(mut self, calc_direction: &'a dyn Fn(f64, f64) -> Option<Direction>) -> Self {
+        self.calc_direction = calc_direction;
+        self
+    }
+
+    // Create an empty summary with these settings.
+    pub fn build(&self) -> DiffSummary<'a> {
+        DiffSummary::new(self.name, self.allow_diff, self.allow_sign, self.bucket_count, self.calc_diff).with_direction(self.calc_direction)
+    }
+
+    // Create a summary with these settings, and add each (x, y) pair from
+    // items, with each item's position as its index.
+    pub fn collect<X: ToPrimitive, Y: ToPrimitive, I: IntoIterator<Item = (X, Y)>>(&self, items: I) -> DiffSummary<'a> {
+        let mut summary = self.build();
+        summary.add_iter(items);
+        summary
+    }
+}
+
+// An unnamed exact comparison: absolute differences with zero tolerance,
+// sign changes disallowed, and 5 histogram buckets.
+impl Default for DiffSummaryConfig<'_> {
+    fn default() -> Self {
+        DiffSummaryConfig::new("", 0.0, false, 5, &diff::diff_abs)
+    }
+}
+
+// Collect (x, y) pairs into a summary with the default configuration.
+// Use DiffSummaryConfig::collect for other settings.
+impl<X: ToPrimitive, Y: ToPrimitive> FromIterator<(X, Y)> for DiffSummary<'_> {
+    fn from_iter<I: IntoIterator<Item = (X, Y)>>(items: I) -> Self {
+        DiffSummaryConfig::default().collect(items)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::DiffSummaryConfig;
+    use crate::diff;
+    use crate::diff_summary_f64::DiffSummary;
+
+    #[test]
+    fn test_collect() {
+        let xs = [1.0, 2.0, 3.5];
+        let ys = [1.0, 2.0, 3.0];
+        let config = DiffSummaryConfig::new("collected", 0.1, false, 4, &diff::diff_rel);
+        let summary = config.collect(xs.iter().copied().zip(ys.iter().copied()));
+        assert_eq!(summary.name, "collected");
+        assert_eq!(summary.count(), 3);
+        assert_eq!(summary.fail_count(), 1);
+        assert_eq!(summary.worst_sample().unwrap().index, 2);
+        assert_eq!(config.build().count(), 0);
+
+        let exact: DiffSummary = xs.iter().copied().zip(ys.iter().copied()).collect();
+        assert_eq!(exact.allow_diff(), 0.0);
+        assert_eq!(exact.fail_count(), 1);
+        let ints: DiffSummary = vec![(1, 1u8), (2, 2u8)].into_iter().collect();
+        assert!(ints.is_ok());
+    }
+}
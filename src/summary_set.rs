@@ -0,0 +1,231 @@
+use num_traits::ToPrimitive;
+use std::collections::HashMap;
+use std::fmt::Display;
+use crate::diff_summary_f64::{DiffSummary, SummaryInfo};
+use crate::input_error::DiffInputError;
+use crate::util;
+
+// An object managing several summaries fed the same indices, such as one
+// per output channel, with two-phase adding so that an index can be dropped
+// from every channel at once.
+// Items are staged, one (x, y) pair per channel for each index, then either
+// committed to all summaries together or aborted, e.g. when validation of
+// one channel's input fails after the others have been computed.
+// Items can also be added to a single channel by summary name, with add.
+pub struct SummarySet<'a> {
+    // One summary per channel.
+    summaries: Vec<DiffSummary<'a>>,
+
+    // The position of each channel's summary, by name. Where names repeat,
+    // the first summary with the name is used.
+    positions: HashMap<&'a str, usize>,
+
+    // If set, the configuration of summaries created for unknown names by add.
+    prototype: Option<DiffSummary<'a>>,
+
+    // The indices of the staged items, in staging order.
+    staged_indices: Vec<usize>,
+
+    // The staged (x, y) pairs, one per channel for each staged index.
+    staged_values: Vec<(f64, f64)>,
+}
+
+impl<'a> SummarySet<'a> {
+    // Create a set from already configured per-channel summaries.
+    pub fn new(summaries: Vec<DiffSummary<'a>>) -> Self {
+        assert!(!summaries.is_empty(), "SummarySet requires at least one summary");
+        let mut positions = HashMap::new();
+        for (i, summary) in summaries.iter().enumerate() {
+            positions.entry(summary.name).or_insert(i);
+        }
+        SummarySet {
+            summaries,
+            positions,
+            prototype: None,
+            staged_indices: Vec::new(),
+            staged_values: Vec::new(),
+        }
+    }
+
+    // Create a set with no channels, where add creates each channel's
+    // summary on first use, as a copy of prototype with the channel's name.
+    // prototype should have no items added.
+    pub fn new_lazy(prototype: DiffSummary<'a>) -> Self {
+        SummarySet {
+            summaries: Vec::new(),
+            positions: HashMap::new(),
+            prototype: Some(prototype),
+            staged_indices: Vec::new(),
+            staged_values: Vec::new(),
+        }
+    }
+
+    // Have add create summaries for unknown names, as new_lazy does, rather
+    // than returning DiffInputError::UnknownName.
+    pub fn with_prototype(mut self, prototype: DiffSummary<'a>) -> Self {
+        self.prototype = Some(prototype);
+        self
+    }
+
+    // Add an item to the summary with the given name, for code that
+    // identifies channels by name rather than position, e.g.
+    //   set.add("azimuth", x, y, index)?;
+    // Unknown names create a summary if the set has a prototype, and
+    // otherwise return DiffInputError::UnknownName. Summaries can't be
+    // created while items are staged.
+    pub fn add<X: ToPrimitive, Y: ToPrimitive>(&mut self, name: &'a str, x: X, y: Y, index: usize) -> Result<(), DiffInputError> {
+        let position = match (self.positions.get(name), &self.prototype) {
+            (Some(&position), _) => position,
+            (None, Some(prototype)) => {
+                assert!(self.staged_indices.is_empty(), "Cannot create summary {} while items are staged", name);
+                let mut summary = prototype.clone();
+                summary.name = name;
+                self.summaries.push(summary);
+                self.positions.insert(name, self.summaries.len() - 1);
+                self.summaries.len() - 1
+            }
+            (None, None) => return Err(DiffInputError::UnknownName(name.to_string())),
+        };
+        self.summaries[position].add(x, y, index);
+        Ok(())
+    }
+
+    // The summary with the given name, if any.
+    pub fn get(&self, name: &str) -> Option<&DiffSummary<'a>> {
+        self.positions.get(name).map(|&position| &self.summaries[position])
+    }
+
+    // Create a set with one summary per tuple, as DiffSummary::new_vec does.
+    pub fn new_vec(bucket_count: usize, infos: &'a [SummaryInfo<'a>]) -> Self {
+        SummarySet::new(DiffSummary::new_vec(bucket_count, infos))
+    }
+
+    // Stage an item for the given index, holding it until commit or abort.
+    // values must have one (x, y) pair per summary, in summary order.
+    pub fn stage<X: ToPrimitive + Copy, Y: ToPrimitive + Copy>(&mut self, index: usize, values: &[(X, Y)]) {
+        assert_eq!(values.len(), self.summaries.len(), "staged item {} has wrong channel count", index);
+        self.staged_indices.push(index);
+        self.staged_values.extend(values.iter().map(|&(x, y)| (util::to_f64(x), util::to_f64(y))));
+    }
+
+    // Add every staged item to the summaries, returning the number of items committed.
+    pub fn commit(&mut self) -> usize {
+        let channel_count = self.summaries.len();
+        for (&index, values) in self.staged_indices.iter().zip(self.staged_values.chunks(channel_count)) {
+            for (summary, &(x, y)) in self.summaries.iter_mut().zip(values) {
+                summary.add(x, y, index);
+            }
+        }
+        self.clear_staged()
+    }
+
+    // Drop every staged item from all summaries, returning the number of items dropped.
+    pub fn abort(&mut self) -> usize {
+        self.clear_staged()
+    }
+
+    fn clear_staged(&mut self) -> usize {
+        let count = self.staged_indices.len();
+        self.staged_indices.clear();
+        self.staged_values.clear();
+        count
+    }
+
+    // The number of items staged since the last commit or abort.
+    pub fn staged_count(&self) -> usize {
+        self.staged_indices.len()
+    }
+
+    // The per-channel summaries, in channel order, reflecting only committed
+    // items. Channels created by add follow, in order of creation.
+    pub fn summaries(&self) -> &[DiffSummary<'a>] {
+        &self.summaries
+    }
+
+    // The per-channel summaries, discarding any staged items.
+    pub fn into_summaries(self) -> Vec<DiffSummary<'a>> {
+        self.summaries
+    }
+
+    // Indicate whether every summary satisfies its tolerance and sign change acceptance.
+    pub fn is_ok(&self) -> bool {
+        self.summaries.iter().all(|summary| summary.is_ok())
+    }
+
+    // Each summary's snapshot_report, in order, one after another.
+    pub fn snapshot_report(&self) -> String {
+        self.summaries.iter().map(|summary| summary.snapshot_report() + "\n").collect()
+    }
+}
+
+// Shows each summary on its own line.
+impl Display for SummarySet<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (i, summary) in self.summaries.iter().enumerate() {
+            if i > 0 {
+                writeln!(f)?;
+            }
+            write!(f, "{}", summary)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SummarySet;
+    use crate::diff;
+    use crate::diff_summary_f64::{DiffSummary, SummaryInfo};
+
+    #[test]
+    fn test_stage() {
+        let infos: &[SummaryInfo] = &[("re", 1e-6, false, &diff::diff_abs), ("im", 1e-6, false, &diff::diff_abs)];
+        let mut set = SummarySet::new_vec(4, infos);
+        set.stage(0, &[(1.0, 1.0), (2.0, 2.0)]);
+        set.stage(1, &[(3.0, 3.0), (4.0, 4.0)]);
+        assert_eq!(set.staged_count(), 2);
+        assert_eq!(set.summaries()[0].count(), 0);
+        assert_eq!(set.commit(), 2);
+        set.stage(2, &[(5.0, 5.0), (6.0, f64::NAN)]);
+        assert_eq!(set.abort(), 1);
+        set.stage(3, &[(7, 7.0), (8, 8.0)]);
+        set.commit();
+        assert_eq!(set.staged_count(), 0);
+        assert!(set.summaries().iter().all(|summary| summary.count() == 3));
+        assert!(set.is_ok());
+        let text = set.to_string();
+        assert!(text.starts_with("re: ") && text.contains("\nim: "));
+    }
+
+    #[test]
+    fn test_add_by_name() {
+        let infos: &[SummaryInfo] = &[("range", 1e-6, false, &diff::diff_abs), ("azimuth", 1e-6, false, &diff::diff_abs)];
+        let mut set = SummarySet::new_vec(4, infos);
+        set.add("azimuth", 1.0, 1.5, 0).unwrap();
+        let err = set.add("elevation", 1.0, 1.0, 0).unwrap_err();
+        assert_eq!(err.to_string(), "unknown summary name \"elevation\"");
+        assert_eq!((set.get("azimuth").unwrap().fail_count(), set.get("range").unwrap().count()), (1, 0));
+
+        let mut lazy = SummarySet::new_lazy(DiffSummary::new("", 0.1, true, 4, &diff::diff_rel));
+        for i in 0..3 {
+            lazy.add("speed", 1.0, 1.0, i).unwrap();
+            lazy.add("heading", i as f64, 1.0, i).unwrap();
+        }
+        let names: Vec<&str> = lazy.summaries().iter().map(|summary| summary.name).collect();
+        assert_eq!(names, vec!["speed", "heading"]);
+        assert_eq!((lazy.get("heading").unwrap().count(), lazy.get("heading").unwrap().allow_diff()), (3, 0.1));
+        assert!(!lazy.is_ok());
+
+        let mut extended = SummarySet::new_vec(4, infos).with_prototype(DiffSummary::new("", 1e-6, false, 4, &diff::diff_abs));
+        extended.add("elevation", 1.0, 1.0, 0).unwrap();
+        assert_eq!(extended.summaries().len(), 3);
+    }
+
+    #[test]
+    #[should_panic(expected = "wrong channel count")]
+    fn test_stage_length() {
+        let infos: &[SummaryInfo] = &[("a", 0.0, false, &diff::diff_abs)];
+        let mut set = SummarySet::new_vec(4, infos);
+        set.stage(0, &[(1.0, 1.0), (2.0, 2.0)]);
+    }
+}
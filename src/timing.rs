@@ -0,0 +1,70 @@
+// Elapsed-time statistics for the computations behind compared items, for
+// checking that a new implementation (x) isn't slower than a reference (y).
+use std::fmt::Display;
+use std::time::Duration;
+use crate::duration_diff::FmtSeconds;
+
+#[derive(Clone, Copy, Debug, PartialEq, Default)]
+pub struct Timing {
+    // The number of timed items.
+    pub count: usize,
+
+    // The total and longest time taken to compute x and y values.
+    pub total_x: Duration,
+    pub total_y: Duration,
+    pub max_x: Duration,
+    pub max_y: Duration,
+}
+
+impl Timing {
+    pub fn new() -> Self {
+        Timing::default()
+    }
+
+    pub fn add(&mut self, dur_x: Duration, dur_y: Duration) {
+        self.count += 1;
+        self.total_x += dur_x;
+        self.total_y += dur_y;
+        self.max_x = self.max_x.max(dur_x);
+        self.max_y = self.max_y.max(dur_y);
+    }
+
+    // Combine another set of timings into this one.
+    pub fn merge(&mut self, other: &Timing) {
+        self.count += other.count;
+        self.total_x += other.total_x;
+        self.total_y += other.total_y;
+        self.max_x = self.max_x.max(other.max_x);
+        self.max_y = self.max_y.max(other.max_y);
+    }
+
+    // The mean time taken to compute x values, or zero if none were timed.
+    pub fn mean_x(&self) -> Duration {
+        if self.count == 0 { Duration::ZERO } else { Duration::from_secs_f64(self.total_x.as_secs_f64() / self.count as f64) }
+    }
+
+    // The mean time taken to compute y values, or zero if none were timed.
+    pub fn mean_y(&self) -> Duration {
+        if self.count == 0 { Duration::ZERO } else { Duration::from_secs_f64(self.total_y.as_secs_f64() / self.count as f64) }
+    }
+
+    // The total time for x over the total time for y, so that values over 1
+    // mean x was slower. Nan if nothing was timed.
+    pub fn speed_ratio(&self) -> f64 {
+        self.total_x.as_secs_f64() / self.total_y.as_secs_f64()
+    }
+}
+
+// Shows mean times and the speed ratio, e.g.
+//   time x 1.5 ms y 1 ms ratio 1.5e0
+impl Display for Timing {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "time x {} y {} ratio {:e}",
+            FmtSeconds(self.mean_x().as_secs_f64()),
+            FmtSeconds(self.mean_y().as_secs_f64()),
+            self.speed_ratio()
+        )
+    }
+}
@@ -0,0 +1,144 @@
+use std::fmt::Display;
+
+// The kinds of comparator in the diff module, for checking that a
+// tolerance's scale is plausible for the comparator it's used with, such
+// as catching an ulps tolerance of 1e-9, or a relative tolerance of 50.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ComparatorKind {
+    Abs,
+    Rel,
+    Ulps,
+    Lesser,
+    Log,
+    Percent,
+    Ratio,
+    Degrees,
+    PeriodicDegrees,
+    PeriodicRadians,
+    TotalOrder,
+}
+
+impl ComparatorKind {
+    // The kind of the comparator with the given config name, as accepted
+    // by config::comparator, e.g. "rel" or "total_order".
+    pub fn from_name(name: &str) -> Option<Self> {
+        Some(match name {
+            "abs" => ComparatorKind::Abs,
+            "rel" => ComparatorKind::Rel,
+            "ulps" => ComparatorKind::Ulps,
+            "lesser" => ComparatorKind::Lesser,
+            "log" => ComparatorKind::Log,
+            "percent" => ComparatorKind::Percent,
+            "ratio" => ComparatorKind::Ratio,
+            "degrees" => ComparatorKind::Degrees,
+            "periodic_degrees" => ComparatorKind::PeriodicDegrees,
+            "periodic_radians" => ComparatorKind::PeriodicRadians,
+            "total_order" => ComparatorKind::TotalOrder,
+            _ => return None,
+        })
+    }
+
+    // The config name of this kind of comparator.
+    pub fn name(self) -> &'static str {
+        match self {
+            ComparatorKind::Abs => "abs",
+            ComparatorKind::Rel => "rel",
+            ComparatorKind::Ulps => "ulps",
+            ComparatorKind::Lesser => "lesser",
+            ComparatorKind::Log => "log",
+            ComparatorKind::Percent => "percent",
+            ComparatorKind::Ratio => "ratio",
+            ComparatorKind::Degrees => "degrees",
+            ComparatorKind::PeriodicDegrees => "periodic_degrees",
+            ComparatorKind::PeriodicRadians => "periodic_radians",
+            ComparatorKind::TotalOrder => "total_order",
+        }
+    }
+
+    // The (min, max) plausible non-zero tolerance for this kind of comparator.
+    // Below min, a tolerance is finer than the comparator can resolve for
+    // f64 values, e.g. a fraction of an ulp. Above max, it accepts
+    // differences that the comparator treats as wholly unrelated values,
+    // e.g. relative differences over 1.
+    pub fn plausible_range(self) -> (f64, f64) {
+        match self {
+            ComparatorKind::Abs => (0.0, f64::INFINITY),
+            ComparatorKind::Rel | ComparatorKind::Ratio => (f64::EPSILON / 16.0, 1.0),
+            ComparatorKind::Lesser => (0.0, 1.0),
+            ComparatorKind::Log => (f64::EPSILON / 16.0, 10.0),
+            ComparatorKind::Percent => (f64::EPSILON * 100.0 / 16.0, 100.0),
+            ComparatorKind::Ulps | ComparatorKind::TotalOrder => (1.0, f64::INFINITY),
+            ComparatorKind::Degrees | ComparatorKind::PeriodicDegrees => (0.0, 180.0),
+            ComparatorKind::PeriodicRadians => (0.0, std::f64::consts::PI),
+        }
+    }
+
+    // Check that tolerance is zero, for exact comparison, or within
+    // plausible_range. Negative and nan tolerances are never plausible.
+    pub fn check(self, tolerance: f64) -> Result<(), ImplausibleTolerance> {
+        let (min, max) = self.plausible_range();
+        if tolerance == 0.0 || (min <= tolerance && tolerance <= max) {
+            Ok(())
+        } else {
+            Err(ImplausibleTolerance { kind: self, tolerance })
+        }
+    }
+}
+
+// A tolerance whose scale doesn't fit its comparator, per ComparatorKind::check.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ImplausibleTolerance {
+    pub kind: ComparatorKind,
+    pub tolerance: f64,
+}
+
+impl Display for ImplausibleTolerance {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let (min, max) = self.kind.plausible_range();
+        write!(
+            f,
+            "tolerance {:e} is implausible for the {} comparator, which expects 0 or {:e} to {:e}",
+            self.tolerance,
+            self.kind.name(),
+            min,
+            max
+        )
+    }
+}
+
+impl std::error::Error for ImplausibleTolerance {}
+
+// What DiffSummary::with_tolerance_check does with an implausible tolerance.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ToleranceCheck {
+    // Accept it, as an explicit opt-out for deliberately unusual tolerances.
+    Off,
+    // Print a warning to stderr.
+    Warn,
+    // Panic, as other builder misconfigurations do.
+    Error,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ComparatorKind;
+
+    #[test]
+    fn test_check() {
+        assert!(ComparatorKind::Ulps.check(4.0).is_ok());
+        assert!(ComparatorKind::Ulps.check(0.0).is_ok());
+        assert!(ComparatorKind::Ulps.check(1e-9).is_err());
+        assert!(ComparatorKind::Rel.check(1e-9).is_ok());
+        assert!(ComparatorKind::Rel.check(1e-20).is_err());
+        assert_eq!(
+            ComparatorKind::Rel.check(50.0).unwrap_err().to_string(),
+            "tolerance 5e1 is implausible for the rel comparator, which expects 0 or 1.3877787807814457e-17 to 1e0"
+        );
+        assert!(ComparatorKind::Abs.check(1e6).is_ok());
+        assert!(ComparatorKind::Abs.check(-1.0).is_err());
+        assert!(ComparatorKind::Degrees.check(f64::NAN).is_err());
+        assert_eq!(ComparatorKind::from_name("total_order"), Some(ComparatorKind::TotalOrder));
+        assert!(ComparatorKind::from_name("periodic_radians").unwrap().check(4.0).is_err());
+        assert_eq!(ComparatorKind::from_name(ComparatorKind::Percent.name()), Some(ComparatorKind::Percent));
+    }
+}
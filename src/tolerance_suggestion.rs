@@ -0,0 +1,160 @@
+use std::fmt::Display;
+use crate::diff;
+use crate::tolerance_check::ComparatorKind;
+
+// The comparators that suggestions are made for, in display order.
+const KINDS: [ComparatorKind; 3] = [ComparatorKind::Abs, ComparatorKind::Rel, ComparatorKind::Ulps];
+
+// The worst difference each of the abs, rel and ulps comparators gives
+// over a summary's items, for suggesting the tightest tolerance each would
+// pass when onboarding a channel whose achievable accuracy isn't known.
+// Display shows a suggested configuration block, in the config module's
+// TOML form, with one channel per comparator to choose from.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ToleranceSuggestion {
+    name: String,
+    // The factor suggested tolerances are widened by over the worst difference.
+    margin: f64,
+    count: usize,
+    // The worst difference per comparator, in KINDS order.
+    worst: [f64; 3],
+    num_sign: usize,
+}
+
+impl ToleranceSuggestion {
+    pub fn new(name: &str, margin: f64) -> Self {
+        assert!(margin >= 1.0, "tolerance suggestion margin must be at least 1, got {}", margin);
+        ToleranceSuggestion { name: name.to_string(), margin, count: 0, worst: [0.0; 3], num_sign: 0 }
+    }
+
+    pub fn add(&mut self, x: f64, y: f64) {
+        self.count += 1;
+        for (worst, kind) in self.worst.iter_mut().zip(KINDS.iter()) {
+            let diff = match kind {
+                ComparatorKind::Abs => diff::diff_abs(x, y).0,
+                ComparatorKind::Rel => diff::diff_rel(x, y).0,
+                _ => diff::diff_ulps(x, y).0,
+            };
+            if diff::is_diff_worse(diff, *worst) {
+                *worst = diff;
+            }
+        }
+        if x.is_sign_negative() != y.is_sign_negative() && !(x.is_nan() && y.is_nan()) {
+            self.num_sign += 1;
+        }
+    }
+
+    // Combine another suggestion's observations into this one.
+    pub fn merge(&mut self, other: &ToleranceSuggestion) {
+        self.count += other.count;
+        for (worst, &other_worst) in self.worst.iter_mut().zip(other.worst.iter()) {
+            if diff::is_diff_worse(other_worst, *worst) {
+                *worst = other_worst;
+            }
+        }
+        self.num_sign += other.num_sign;
+    }
+
+    pub fn count(&self) -> usize {
+        self.count
+    }
+
+    // The worst difference seen under an abs, rel or ulps comparator.
+    pub fn worst_diff(&self, kind: ComparatorKind) -> f64 {
+        let position = KINDS.iter().position(|&k| k == kind);
+        self.worst[position.expect("tolerance suggestions are only made for abs, rel and ulps")]
+    }
+
+    // The suggested tolerance for an abs, rel or ulps comparator: the worst
+    // difference times the margin, rounded up to two significant digits, or
+    // to a whole number of ulps. None if no finite tolerance passes every
+    // item, such as when a nan is compared with a number.
+    pub fn suggested(&self, kind: ComparatorKind) -> Option<f64> {
+        let widened = self.worst_diff(kind) * self.margin;
+        if !widened.is_finite() {
+            None
+        } else if kind == ComparatorKind::Ulps {
+            Some(widened.ceil())
+        } else {
+            Some(round_up(widened))
+        }
+    }
+
+    // Whether sign changes were seen, so that allow_sign must be true to pass.
+    pub fn needs_allow_sign(&self) -> bool {
+        self.num_sign > 0
+    }
+}
+
+// Round a non-negative value up to two significant digits.
+fn round_up(value: f64) -> f64 {
+    if value == 0.0 {
+        return 0.0;
+    }
+    let exponent = value.log10().floor() as i32 - 1;
+    let digits = (value / 10f64.powi(exponent)).ceil();
+    // Parse the decimal, rather than multiply, so the result displays as
+    // written. The nearest f64 to a decimal above value can't be below it.
+    let rounded: f64 = format!("{}e{}", digits, exponent).parse().unwrap();
+    // Guard against the division rounding down at a digit boundary.
+    if rounded < value { round_up(rounded * (1.0 + f64::EPSILON)) } else { rounded }
+}
+
+// Shows the suggestions as config channels, e.g.
+//   # suggested configuration for pressure from 1000 items, margin 2; keep one channel
+//   [[channels]]
+//   name = "pressure"
+//   comparator = "abs"
+//   tolerance = 3e-10
+//   allow_sign = false
+impl Display for ToleranceSuggestion {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(
+            f,
+            "# suggested configuration for {} from {} items, margin {}; keep one channel",
+            self.name, self.count, self.margin
+        )?;
+        for &kind in KINDS.iter() {
+            match self.suggested(kind) {
+                Some(tolerance) => {
+                    writeln!(f, "[[channels]]")?;
+                    writeln!(f, "name = {:?}", self.name)?;
+                    writeln!(f, "comparator = {:?}", kind.name())?;
+                    writeln!(f, "tolerance = {:e}", tolerance)?;
+                    writeln!(f, "allow_sign = {}", self.needs_allow_sign())?;
+                }
+                None => writeln!(f, "# no finite {} tolerance passes every item", kind.name())?,
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{round_up, ToleranceSuggestion};
+    use crate::tolerance_check::ComparatorKind;
+
+    #[test]
+    fn test_suggestion() {
+        assert_eq!((round_up(2.4e-10), round_up(3.0), round_up(0.123), round_up(0.0)), (2.4e-10, 3.0, 0.13, 0.0));
+        let mut suggestion = ToleranceSuggestion::new("pressure", 2.0);
+        suggestion.add(1.0, 1.0);
+        suggestion.add(1e-3 + 1e-13, 1e-3);
+        let mut other = ToleranceSuggestion::new("pressure", 2.0);
+        other.add(100.0, 100.0 + 1e-11);
+        suggestion.merge(&other);
+        assert_eq!(suggestion.count(), 3);
+        assert_eq!(suggestion.suggested(ComparatorKind::Abs), Some(2.1e-11));
+        assert_eq!(suggestion.suggested(ComparatorKind::Rel), Some(2.1e-10));
+        assert!(suggestion.worst_diff(ComparatorKind::Ulps) > 1000.0);
+        assert!(!suggestion.needs_allow_sign());
+        let text = suggestion.to_string();
+        assert!(text.starts_with("# suggested configuration for pressure from 3 items, margin 2; keep one channel\n"), "{}", text);
+        assert!(text.contains("[[channels]]\nname = \"pressure\"\ncomparator = \"abs\"\ntolerance = 2.1e-11\nallow_sign = false\n"), "{}", text);
+        suggestion.add(f64::NAN, -1.0);
+        assert_eq!(suggestion.suggested(ComparatorKind::Rel), None);
+        assert!(suggestion.needs_allow_sign());
+        assert!(suggestion.to_string().contains("# no finite rel tolerance passes every item"));
+    }
+}
@@ -0,0 +1,101 @@
+use crate::diff::is_diff_worse;
+use crate::sample_record::SampleRecord;
+
+// Retains records of the n items with the worst differences
+// seen so far, using the same ordering as for the worst diff: nan is worse
+// than infinity is worse than anything finite. Ties keep the earlier item.
+#[derive(Clone, Debug)]
+pub(crate) struct TopN {
+    capacity: usize,
+    items: Vec<SampleRecord>,
+    // Position in items of the least-bad retained item, once at capacity.
+    least: usize,
+}
+
+impl TopN {
+    pub fn new(capacity: usize) -> Self {
+        TopN {
+            capacity,
+            items: Vec::with_capacity(capacity),
+            least: 0,
+        }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    // Add an item with the given diff. Its record is only created if it's retained.
+    pub fn add(&mut self, diff: f64, sample: impl FnOnce() -> SampleRecord) {
+        if self.items.len() < self.capacity {
+            self.items.push(sample());
+            if self.items.len() == self.capacity {
+                self.find_least();
+            }
+        } else if self.capacity > 0 && is_diff_worse(diff, self.items[self.least].diff) {
+            self.items[self.least] = sample();
+            self.find_least();
+        }
+    }
+
+    // Combine another tracker's items into this one.
+    pub fn merge(&mut self, other: &TopN) {
+        let mut items = self.items.clone();
+        items.extend_from_slice(&other.items);
+        items.sort_by_key(|item| item.index);
+        self.items.clear();
+        for item in items {
+            self.add(item.diff, || item);
+        }
+    }
+
+    // The retained items, from worst to least bad.
+    pub fn sorted(&self) -> Vec<SampleRecord> {
+        let mut items = self.items.clone();
+        items.sort_by(|a, b| {
+            if is_diff_worse(a.diff, b.diff) {
+                std::cmp::Ordering::Less
+            } else if is_diff_worse(b.diff, a.diff) {
+                std::cmp::Ordering::Greater
+            } else {
+                a.index.cmp(&b.index)
+            }
+        });
+        items
+    }
+
+    fn find_least(&mut self) {
+        let mut least = 0;
+        for (i, item) in self.items.iter().enumerate() {
+            // Among equals, replace the latest item first.
+            let current = &self.items[least];
+            if !is_diff_worse(item.diff, current.diff) && (is_diff_worse(current.diff, item.diff) || item.index > current.index) {
+                least = i;
+            }
+        }
+        self.least = least;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::TopN;
+    use crate::sample_record::SampleRecord;
+
+    #[test]
+    fn test_top_n() {
+        let mut top = TopN::new(3);
+        for (i, &diff) in [1.0, 5.0, 2.0, 2.0, f64::NAN, 0.5, 7.0].iter().enumerate() {
+            top.add(diff, || SampleRecord::new(i, 0.0, diff, diff, None));
+        }
+        let indices: Vec<usize> = top.sorted().iter().map(|item| item.index).collect();
+        assert_eq!(indices, vec![4, 6, 1]);
+
+        let mut other = TopN::new(3);
+        other.add(6.0, || SampleRecord::new(10, 0.0, 6.0, 6.0, None));
+        top.merge(&other);
+        let indices: Vec<usize> = top.sorted().iter().map(|item| item.index).collect();
+        assert_eq!(indices, vec![4, 6, 10]);
+        assert!(TopN::new(0).sorted().is_empty());
+    }
+}
@@ -0,0 +1,290 @@
+// Trend files, for tracking accuracy across CI runs. Each run appends one
+// snapshot per channel, tagged with a run label such as a commit or date,
+// and trend renders each channel's worst diff and failure percent over the
+// last runs as text sparklines, or as CSV for plotting, as does
+//   float-diff trend [--csv] <file> [<runs>]
+// e.g.
+//   trend::append("target/accuracy.fdtrend", &commit, &registry::entries()).unwrap();
+// Each file line holds a tab-separated, escaped run label, followed by the
+// snapshot's fields, as in accumulation files.
+// Files with binary::EXTENSION are instead written in the binary module's
+// format, a header followed by each run label and encoded snapshot.
+use std::fmt::Display;
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::Path;
+use crate::accumulate;
+use crate::binary::{self, Reader};
+use crate::input_error::DiffInputError;
+use crate::registry::RegistryEntry;
+
+// Sparkline levels, from lowest to highest.
+const LEVELS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+
+// Append a snapshot of each entry, tagged with run, to the trend file at
+// path, creating it and its parent directories as needed.
+pub fn append<P: AsRef<Path>>(path: P, run: &str, entries: &[RegistryEntry]) -> io::Result<()> {
+    let path = path.as_ref();
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    if binary::has_extension(path) {
+        return binary::append(path, |writer| {
+            for entry in entries {
+                writer.str(run);
+                writer.entry(entry);
+            }
+        });
+    }
+    let mut text = String::new();
+    for entry in entries {
+        text.push_str(&accumulate::escape(run));
+        text.push('\t');
+        text.push_str(&accumulate::format_record(entry));
+    }
+    OpenOptions::new().create(true).append(true).open(path)?.write_all(text.as_bytes())
+}
+
+// Read every (run, snapshot) from the trend file at path, in file order.
+pub fn load<P: AsRef<Path>>(path: P) -> Result<Vec<(String, RegistryEntry)>, DiffInputError> {
+    let path = path.as_ref();
+    let bytes = fs::read(path)?;
+    let mut snapshots = Vec::new();
+    if binary::is_binary(&bytes) {
+        let source = path.display().to_string();
+        let mut reader = Reader::new(&bytes, &source)?;
+        while !reader.is_done() {
+            snapshots.push((reader.str()?, reader.entry()?));
+        }
+        return Ok(snapshots);
+    }
+    let text = String::from_utf8(bytes).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+    for (line_index, line) in text.lines().enumerate() {
+        if line.is_empty() {
+            continue;
+        }
+        let missing = || DiffInputError::MissingColumn { source: path.display().to_string(), line: line_index + 1, column: "name" };
+        let (run, record) = line.split_once('\t').ok_or_else(missing)?;
+        let run = accumulate::unescape(run).ok_or_else(|| DiffInputError::Parse {
+            source: path.display().to_string(),
+            line: line_index + 1,
+            text: run.to_string(),
+        })?;
+        snapshots.push((run, accumulate::parse_record(record, path, line_index + 1)?));
+    }
+    Ok(snapshots)
+}
+
+// The trend of the last_runs runs in the trend file at path.
+pub fn trend<P: AsRef<Path>>(path: P, last_runs: usize) -> Result<Trend, DiffInputError> {
+    Trend::new(&load(path)?, last_runs)
+}
+
+// One channel's snapshots over a trend's runs.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ChannelTrend {
+    pub name: String,
+    // The channel's snapshot for each of the trend's runs, or None for runs without one.
+    pub points: Vec<Option<RegistryEntry>>,
+}
+
+impl ChannelTrend {
+    // The worst diff for each run.
+    pub fn worst_diffs(&self) -> Vec<Option<f64>> {
+        self.points.iter().map(|point| point.as_ref().map(|entry| entry.worst_diff)).collect()
+    }
+
+    // The percent of items that failed for each run.
+    pub fn fail_percents(&self) -> Vec<Option<f64>> {
+        self.points.iter().map(|point| point.as_ref().map(fail_percent)).collect()
+    }
+}
+
+// Per-channel results over a series of runs.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Trend {
+    // Run labels, oldest first.
+    pub runs: Vec<String>,
+    // Channels in order of first appearance.
+    pub channels: Vec<ChannelTrend>,
+}
+
+impl Trend {
+    // Collect the last last_runs runs of snapshots, with runs ordered by
+    // first appearance. Snapshots of a channel within one run are merged,
+    // via RegistryEntry::merge, returning the first error merging them.
+    pub fn new(snapshots: &[(String, RegistryEntry)], last_runs: usize) -> Result<Self, DiffInputError> {
+        let mut runs: Vec<String> = Vec::new();
+        for (run, _) in snapshots {
+            if !runs.contains(run) {
+                runs.push(run.clone());
+            }
+        }
+        let runs = runs.split_off(runs.len().saturating_sub(last_runs));
+        let mut channels: Vec<ChannelTrend> = Vec::new();
+        for (run, entry) in snapshots {
+            let run_index = match runs.iter().position(|kept| kept == run) {
+                Some(run_index) => run_index,
+                None => continue,
+            };
+            let channel_index = match channels.iter().position(|channel| channel.name == entry.name) {
+                Some(channel_index) => channel_index,
+                None => {
+                    channels.push(ChannelTrend { name: entry.name.clone(), points: vec![None; runs.len()] });
+                    channels.len() - 1
+                }
+            };
+            match &mut channels[channel_index].points[run_index] {
+                Some(existing) => existing.merge(entry)?,
+                point => *point = Some(entry.clone()),
+            }
+        }
+        Ok(Trend { runs, channels })
+    }
+
+    // The trend as CSV, with a header row and a row per channel per run with a snapshot:
+    //   run,channel,worst_diff,fail_percent,ok
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::from("run,channel,worst_diff,fail_percent,ok\n");
+        for channel in &self.channels {
+            for (run, point) in self.runs.iter().zip(&channel.points) {
+                if let Some(entry) = point {
+                    csv.push_str(&format!(
+                        "{},{},{:e},{},{}\n",
+                        csv_field(run),
+                        csv_field(&channel.name),
+                        entry.worst_diff,
+                        fail_percent(entry),
+                        entry.is_ok
+                    ));
+                }
+            }
+        }
+        csv
+    }
+}
+
+// Shows the runs, then a line per channel with sparklines of worst diff
+// (on a log scale) and failure percent, oldest first, followed by the
+// first and last values shown, e.g.
+//   trend over 3 runs, a1 to c3
+//     pressure: worst ▁▄█ 1e-9 -> 4e-9, failed ▁▁█ 0.00% -> 1.50%
+// Runs without a snapshot are blank, and non-finite worst diffs are "!".
+impl Display for Trend {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "trend over {} runs", self.runs.len())?;
+        if let (Some(first), Some(last)) = (self.runs.first(), self.runs.last()) {
+            write!(f, ", {} to {}", first, last)?;
+        }
+        for channel in &self.channels {
+            let worst = channel.worst_diffs();
+            let failed = channel.fail_percents();
+            write!(f, "\n  {}: worst {}", channel.name, sparkline(&worst, true))?;
+            if let (Some(first), Some(last)) = (worst.iter().flatten().next(), worst.iter().flatten().last()) {
+                write!(f, " {:e} -> {:e}", first, last)?;
+            }
+            write!(f, ", failed {}", sparkline(&failed, false))?;
+            if let (Some(first), Some(last)) = (failed.iter().flatten().next(), failed.iter().flatten().last()) {
+                write!(f, " {:.2}% -> {:.2}%", first, last)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+fn fail_percent(entry: &RegistryEntry) -> f64 {
+    if entry.count == 0 { 0.0 } else { 100.0 * entry.fail_count as f64 / entry.count as f64 }
+}
+
+// Render values as sparkline levels scaled between the lowest and highest
+// finite values, on a log10 scale if log is set, where zero is lowest.
+fn sparkline(values: &[Option<f64>], log: bool) -> String {
+    let scaled = |value: f64| if log { value.log10() } else { value };
+    let finite: Vec<f64> = values.iter().flatten().map(|&value| scaled(value)).filter(|value| value.is_finite()).collect();
+    let min = finite.iter().cloned().fold(f64::INFINITY, f64::min);
+    let max = finite.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+    values
+        .iter()
+        .map(|value| match value {
+            None => ' ',
+            Some(value) if log && *value == 0.0 => LEVELS[0],
+            Some(value) if !value.is_finite() => '!',
+            Some(value) if max > min => {
+                let level = (scaled(*value) - min) / (max - min) * (LEVELS.len() - 1) as f64;
+                LEVELS[level.round() as usize]
+            }
+            Some(_) => LEVELS[0],
+        })
+        .collect()
+}
+
+// Quote a CSV field if it contains a comma, quote or line break.
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{append, load, trend, Trend};
+    use crate::diff;
+    use crate::diff_summary_f64::DiffSummary;
+    use crate::registry::RegistryEntry;
+
+    #[test]
+    fn test_trend() {
+        let path = std::env::temp_dir().join(format!("float-diff-trend-{}", std::process::id())).join("runs.fdtrend");
+        let snapshot = |name, ys: &[f64]| {
+            let mut summary = DiffSummary::new(name, 0.1, false, 4, &diff::diff_abs);
+            summary.add_slices(&vec![1.0; ys.len()], ys);
+            RegistryEntry::new(&summary)
+        };
+        append(&path, "r1", &[snapshot("pressure", &[1.0009765625, 1.0]), snapshot("phase", &[1.0])]).unwrap();
+        append(&path, "r2", &[snapshot("pressure", &[1.0078125, 1.0])]).unwrap();
+        append(&path, "r3\t2026", &[snapshot("pressure", &[1.0, 2.0]), snapshot("phase", &[1.0])]).unwrap();
+        append(&path, "r3\t2026", &[snapshot("pressure", &[1.0, 1.0])]).unwrap();
+        assert_eq!(load(&path).unwrap().len(), 6);
+
+        let all = trend(&path, 10).unwrap();
+        assert_eq!(all.runs, vec!["r1", "r2", "r3\t2026"]);
+        assert_eq!(all.channels.len(), 2);
+        assert_eq!(all.channels[0].worst_diffs(), vec![Some(9.765625e-4), Some(7.8125e-3), Some(1.0)]);
+        assert_eq!(all.channels[0].fail_percents(), vec![Some(0.0), Some(0.0), Some(25.0)]);
+        assert_eq!(all.channels[1].points[1], None);
+        assert_eq!(
+            all.to_string(),
+            "trend over 3 runs, r1 to r3\t2026\n  \
+                pressure: worst ▁▃█ 9.765625e-4 -> 1e0, failed ▁▁█ 0.00% -> 25.00%\n  \
+                phase: worst ▁ ▁ 0e0 -> 0e0, failed ▁ ▁ 0.00% -> 0.00%"
+        );
+
+        let last = trend(&path, 2).unwrap();
+        assert_eq!(last.runs.len(), 2);
+        assert_eq!(
+            last.to_csv(),
+            "run,channel,worst_diff,fail_percent,ok\n\
+                r2,pressure,7.8125e-3,0,true\n\
+                r3\t2026,pressure,1e0,25,false\n\
+                r3\t2026,phase,0e0,0,true\n"
+        );
+
+        let binary_path = path.with_extension(crate::binary::EXTENSION);
+        for (run, entry) in load(&path).unwrap() {
+            append(&binary_path, &run, &[entry]).unwrap();
+        }
+        assert!(crate::binary::is_binary(&std::fs::read(&binary_path).unwrap()));
+        assert_eq!(trend(&binary_path, 10).unwrap(), all);
+
+        let mut retuned = snapshot("phase", &[1.0]);
+        retuned.allow_diff = 0.2;
+        let snapshots = vec![("r1".to_string(), snapshot("phase", &[1.0])), ("r1".to_string(), retuned)];
+        assert_eq!(Trend::new(&snapshots, 10).unwrap_err().to_string(), "phase: tolerance mismatch, 1e-1 vs 2e-1");
+
+        std::fs::write(&path, "r1\n").unwrap();
+        assert_eq!(load(&path).unwrap_err().to_string(), format!("{} line 1: missing column name", path.display()));
+        std::fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+}
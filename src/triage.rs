@@ -0,0 +1,193 @@
+use std::cmp::Reverse;
+use std::collections::BTreeMap;
+use std::fmt::Display;
+use crate::log_histogram::{self, BucketKey};
+
+// The number of categories of each kind shown by Display.
+const DISPLAY_LIMIT: usize = 3;
+
+// The likely cause of a failing item, for triage.
+// Each item gets the first cause that applies, in declaration order.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum FailureCause {
+    // x or y is nan.
+    Nan,
+    // x or y is infinite.
+    Infinite,
+    // x and y differ in sign.
+    SignChange,
+    // The expected value is below the near-zero threshold, where
+    // cancellation tends to inflate differences.
+    NearZero,
+    // The expected value is above the large-magnitude threshold.
+    LargeMagnitude,
+    // None of the above.
+    Other,
+}
+
+impl Display for FailureCause {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let label = match self {
+            FailureCause::Nan => "nan",
+            FailureCause::Infinite => "inf",
+            FailureCause::SignChange => "sign",
+            FailureCause::NearZero => "near-zero",
+            FailureCause::LargeMagnitude => "large",
+            FailureCause::Other => "other",
+        };
+        write!(f, "{}", label)
+    }
+}
+
+// A breakdown of failing items by likely cause, and by the magnitude band
+// (log10 bucket) of their expected value, to show at a glance where
+// failures are concentrated.
+#[derive(Clone, Debug)]
+pub struct Triage {
+    // Expected values with magnitude below this are classified NearZero.
+    near_zero: f64,
+    // Expected values with magnitude above this are classified LargeMagnitude.
+    large: f64,
+    causes: BTreeMap<FailureCause, usize>,
+    bands: BTreeMap<BucketKey, usize>,
+}
+
+impl Triage {
+    pub fn new(near_zero: f64, large: f64) -> Self {
+        assert!(0.0 <= near_zero && near_zero <= large, "Triage requires 0 <= near_zero <= large");
+        Triage {
+            near_zero,
+            large,
+            causes: BTreeMap::new(),
+            bands: BTreeMap::new(),
+        }
+    }
+
+    // Classify a failing item.
+    pub fn classify(&self, x: f64, y: f64, sign_change: bool) -> FailureCause {
+        if x.is_nan() || y.is_nan() {
+            FailureCause::Nan
+        } else if x.is_infinite() || y.is_infinite() {
+            FailureCause::Infinite
+        } else if sign_change {
+            FailureCause::SignChange
+        } else if y.abs() < self.near_zero {
+            FailureCause::NearZero
+        } else if y.abs() > self.large {
+            FailureCause::LargeMagnitude
+        } else {
+            FailureCause::Other
+        }
+    }
+
+    // Count a failing item.
+    pub fn add(&mut self, x: f64, y: f64, sign_change: bool) {
+        *self.causes.entry(self.classify(x, y, sign_change)).or_insert(0) += 1;
+        *self.bands.entry(log_histogram::bucket_key(y.abs(), 0.0)).or_insert(0) += 1;
+    }
+
+    // Combine another breakdown's counts into this one.
+    pub fn merge(&mut self, other: &Triage) {
+        assert!(
+            self.near_zero == other.near_zero && self.large == other.large,
+            "Cannot merge triage breakdowns with different thresholds"
+        );
+        for (&cause, &count) in &other.causes {
+            *self.causes.entry(cause).or_insert(0) += count;
+        }
+        for (&band, &count) in &other.bands {
+            *self.bands.entry(band).or_insert(0) += count;
+        }
+    }
+
+    // The number of failing items for each cause, from most to least common.
+    // Ties are in declaration order.
+    pub fn by_cause(&self) -> Vec<(FailureCause, usize)> {
+        ranked(&self.causes)
+    }
+
+    // The number of failing items for each magnitude band of the expected
+    // value, from most to least common. Ties are in ascending magnitude.
+    pub fn by_band(&self) -> Vec<(BucketKey, usize)> {
+        ranked(&self.bands)
+    }
+
+    // The total number of failing items counted.
+    pub fn count(&self) -> usize {
+        self.causes.values().sum()
+    }
+}
+
+fn ranked<K: Copy + Ord>(counts: &BTreeMap<K, usize>) -> Vec<(K, usize)> {
+    let mut ranked: Vec<(K, usize)> = counts.iter().map(|(&key, &count)| (key, count)).collect();
+    // A stable sort keeps ties in key order.
+    ranked.sort_by_key(|&(_, count)| Reverse(count));
+    ranked
+}
+
+// Shows the most common causes and bands, e.g.
+// "triage nan 12, near-zero 5, other 1, y band e-9 14, e2 3, e6 1"
+impl Display for Triage {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "triage")?;
+        let causes = self.by_cause();
+        for (i, (cause, count)) in causes.iter().take(DISPLAY_LIMIT).enumerate() {
+            write!(f, "{} {} {}", if i > 0 { "," } else { "" }, cause, count)?;
+        }
+        if causes.len() > DISPLAY_LIMIT {
+            write!(f, ", ...")?;
+        }
+        let bands = self.by_band();
+        for (i, (band, count)) in bands.iter().take(DISPLAY_LIMIT).enumerate() {
+            write!(f, "{} {} {}", if i > 0 { "," } else { ", y band" }, band, count)?;
+        }
+        if bands.len() > DISPLAY_LIMIT {
+            write!(f, ", ...")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{FailureCause, Triage};
+    use crate::log_histogram::BucketKey;
+
+    #[test]
+    fn test_triage() {
+        let mut triage = Triage::new(1e-6, 1e6);
+        let items = [
+            (f64::NAN, 1.0, false),
+            (1.0, f64::NAN, false),
+            (f64::INFINITY, 1.0, false),
+            (-1.0, 1.0, true),
+            (1e-8, 2e-9, false),
+            (3e-9, 2e-9, false),
+            (1e-9, 5e-9, false),
+            (2e7, 1e7, false),
+            (1.5, 1.0, false),
+        ];
+        for &(x, y, sign_change) in &items {
+            triage.add(x, y, sign_change);
+        }
+        assert_eq!(triage.count(), 9);
+        assert_eq!(triage.by_cause(), vec![
+            (FailureCause::NearZero, 3),
+            (FailureCause::Nan, 2),
+            (FailureCause::Infinite, 1),
+            (FailureCause::SignChange, 1),
+            (FailureCause::LargeMagnitude, 1),
+            (FailureCause::Other, 1),
+        ]);
+        assert_eq!(triage.by_band()[0], (BucketKey::Pos(0), 4));
+        assert_eq!(
+            format!("{}", triage),
+            "triage near-zero 3, nan 2, inf 1, ..., y band e0 4, e-8 3, e7 1, ..."
+        );
+
+        let mut other = Triage::new(1e-6, 1e6);
+        other.add(f64::NAN, f64::NAN, false);
+        triage.merge(&other);
+        assert_eq!(triage.by_cause()[0], (FailureCause::Nan, 3));
+    }
+}
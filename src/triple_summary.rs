@@ -0,0 +1,136 @@
+use std::fmt::Display;
+use crate::diff::is_diff_worse;
+use crate::diff_summary_f64::DiffSummary;
+
+// One of the pairings compared by a TripleDiffSummary.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Pairing {
+    CandidateVsA,
+    CandidateVsB,
+    AVsB,
+}
+
+impl Display for Pairing {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Pairing::CandidateVsA => "candidate vs a",
+            Pairing::CandidateVsB => "candidate vs b",
+            Pairing::AVsB => "a vs b",
+        })
+    }
+}
+
+// An object for comparing a candidate implementation against two references,
+// a and b, such as older code and an external library.
+// Each item records candidate vs a, candidate vs b, and a vs b, each in its
+// own summary copied from a template, so the pairings share configuration.
+pub struct TripleDiffSummary<'a> {
+    // The name of this summary.
+    pub name: &'a str,
+
+    // Summaries for each pairing, in the order of Pairing's variants.
+    summaries: [DiffSummary<'a>; 3],
+}
+
+impl<'a> TripleDiffSummary<'a> {
+    // Create a summary, using template as the configuration for each pairing.
+    // The template must not have had any items added.
+    pub fn new(name: &'a str, template: DiffSummary<'a>) -> Self {
+        assert_eq!(template.count(), 0, "TripleDiffSummary template must be empty");
+        let named = |pairing_name| {
+            let mut summary = template.clone();
+            summary.name = pairing_name;
+            summary
+        };
+        TripleDiffSummary {
+            name,
+            summaries: [named("candidate vs a"), named("candidate vs b"), named("a vs b")],
+        }
+    }
+
+    // Add an item, recording all three pairings.
+    pub fn add(&mut self, candidate: f64, a: f64, b: f64, index: usize) {
+        self.summaries[0].add(candidate, a, index);
+        self.summaries[1].add(candidate, b, index);
+        self.summaries[2].add(a, b, index);
+    }
+
+    // The summary for a given pairing.
+    pub fn pairing(&self, pairing: Pairing) -> &DiffSummary<'a> {
+        match pairing {
+            Pairing::CandidateVsA => &self.summaries[0],
+            Pairing::CandidateVsB => &self.summaries[1],
+            Pairing::AVsB => &self.summaries[2],
+        }
+    }
+
+    // The number of items added.
+    pub fn count(&self) -> usize {
+        self.summaries[0].count()
+    }
+
+    // The pairing with the worst single difference.
+    // Ties prefer the earlier pairing in the order candidate vs a, candidate vs b, a vs b.
+    pub fn worst_pairing(&self) -> Pairing {
+        [Pairing::CandidateVsB, Pairing::AVsB].iter().fold(Pairing::CandidateVsA, |worst, &pairing| {
+            if is_diff_worse(self.pairing(pairing).worst_diff(), self.pairing(worst).worst_diff()) {
+                pairing
+            } else {
+                worst
+            }
+        })
+    }
+
+    // The candidate pairing with the lower rms difference, indicating which
+    // reference the candidate tracks better. Ties prefer reference a.
+    pub fn closer_reference(&self) -> Pairing {
+        if self.summaries[1].rms_diff() < self.summaries[0].rms_diff() {
+            Pairing::CandidateVsB
+        } else {
+            Pairing::CandidateVsA
+        }
+    }
+}
+
+impl Display for TripleDiffSummary<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}{}count {}, worst disagreement {}, closer reference {}",
+            self.name,
+            if !self.name.is_empty() { ": " } else { "" },
+            self.count(),
+            self.worst_pairing(),
+            self.closer_reference()
+        )?;
+        for summary in &self.summaries {
+            write!(f, "\n  {}", summary)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Pairing, TripleDiffSummary};
+    use crate::diff;
+    use crate::diff_summary_f64::DiffSummary;
+
+    #[test]
+    fn test_triple() {
+        let template = DiffSummary::new("", 0.1, false, 4, &diff::diff_abs);
+        let mut triple = TripleDiffSummary::new("sqrt", template);
+        triple.add(1.0, 1.0, 1.25, 0);
+        triple.add(2.0, 2.0, 1.5, 1);
+        triple.add(3.0, 2.75, 3.0, 2);
+        assert_eq!(triple.count(), 3);
+        assert_eq!(triple.pairing(Pairing::CandidateVsA).worst_diff(), 0.25);
+        assert_eq!(triple.pairing(Pairing::CandidateVsB).worst_diff(), 0.5);
+        assert_eq!(triple.pairing(Pairing::AVsB).worst_diff(), 0.5);
+        assert_eq!(triple.worst_pairing(), Pairing::CandidateVsB);
+        assert_eq!(triple.closer_reference(), Pairing::CandidateVsA);
+        assert!(format!("{}", triple).starts_with(
+            "sqrt: count 3, worst disagreement candidate vs b, closer reference candidate vs a\n  candidate vs a: count 3"
+        ));
+    }
+}
@@ -1,22 +1,71 @@
+use num_traits::ToPrimitive;
+use std::fmt::{Display, Formatter, Result};
+
 // Round a value for use in LogHistogram display.
 // Never round to 0 or 100. Only accept those values naturally.
 pub fn to_percent(num_part: usize, num_all: usize) -> usize {
     let percent = 100f64 * num_part as f64 / num_all as f64;
-    let rounded = if percent < 1.0 && num_part != 0 {
+    if percent < 1.0 && num_part != 0 {
         1
     } else if percent > 99.0 && num_part != num_all {
         99
     } else {
         percent.round() as usize
-    };
-    rounded
+    }
+}
+
+// Convert a primitive numeric value to f64, for accepting mixed-type inputs.
+// Values that have no f64 representation become nan, and so fail comparison.
+pub(crate) fn to_f64<T: ToPrimitive>(value: T) -> f64 {
+    value.to_f64().unwrap_or(f64::NAN)
+}
+
+// Mix the bits of a value, per the splitmix64 finalizer, for cheap
+// reproducible pseudo-random choices.
+pub(crate) fn mix64(x: u64) -> u64 {
+    let mut z = x.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+// Formatting style for f64 values in summary output and assert messages.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FloatFormat {
+    // Concise scientific notation, e.g. 3.14e0
+    Exp,
+    // Hexadecimal floating point, e.g. 0x1.91eb851eb851fp+1
+    // Represents the value exactly, for reproducing failing cases.
+    Hex,
+    // The raw IEEE 754 bit pattern, e.g. 0x40091eb851eb851f
+    // Distinguishes between different nan payloads.
+    Bits,
+    // Scientific notation followed by hexfloat and bit pattern, e.g.
+    // 3.14e0 (0x1.91eb851eb851fp+1, 0x40091eb851eb851f)
+    Full,
+}
+
+// Formatting style for percentages in summary and histogram output.
+// Every style shows 0% and 100% only for exactly none or all items.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PercentFormat {
+    // Whole percents, e.g. 12%, with non-zero fractions under 1% shown as 1%,
+    // and fractions of all items over 99% shown as 99%.
+    Whole,
+    // Decimal percents with enough decimals to show the given number of
+    // significant digits of rare events, e.g. 0.0003% or 99.9997%.
+    Fractional(usize),
+    // Scientific notation with the given number of significant digits for
+    // percents under 1%, e.g. 3e-4%, and whole percents otherwise.
+    Scientific(usize),
 }
 
+// A wrapper for displaying an f64 without allocation.
 // When displaying f64, we want to make sure to display the "-" for values like
 // -0.0, -f64::NAN, and f64::NEG_INFINITY. We also want to display concise
 // values, which calls for using scientific notation in cases like 5e-200
 // (we don't care as much about representation of more moderate values).
-// As of Rust 1.50, I do not see any combination of format specifiers that 
+// As of Rust 1.50, I do not see any combination of format specifiers that
 // yields this combination of qualities, since the debug specifier seems to be
 // required to get a reliably - sign, but the debug specifier doesn't seem to
 // be compatible with the exponent specifiers (e, E).
@@ -27,11 +76,242 @@ pub fn to_percent(num_part: usize, num_all: usize) -> usize {
 //   https://github.com/rust-lang/rust/issues/24556
 //   https://github.com/rust-lang/rust/issues/24623
 //   https://github.com/rust-lang/rust/issues/24624
-// For now, here's a lame work-around.
-pub fn help_sign(x: f64) -> String {
-    if (x == 0.0 || x.is_nan()) && x.is_sign_negative() {
-        "-".to_string()
-    } else {
-        "".to_string()
+// Sign handling differs between Rust versions for values like -0.0, so
+// we always write the sign ourselves and format the absolute value.
+// A precision specified in the format string (e.g. "{:.3}") is applied to
+// the scientific notation portion.
+#[derive(Clone, Copy, Debug)]
+pub struct FmtF64(pub f64, pub FloatFormat);
+
+impl FmtF64 {
+    fn fmt_exp(&self, f: &mut Formatter<'_>) -> Result {
+        let x = self.0;
+        if x.is_sign_negative() {
+            write!(f, "-")?;
+        }
+        match f.precision() {
+            Some(precision) => write!(f, "{:.*e}", precision, x.abs()),
+            None => write!(f, "{:e}", x.abs()),
+        }
+    }
+
+    fn fmt_hex(&self, f: &mut Formatter<'_>) -> Result {
+        let x = self.0;
+        if x.is_sign_negative() {
+            write!(f, "-")?;
+        }
+        if x.is_nan() {
+            return write!(f, "NaN");
+        } else if x.is_infinite() {
+            return write!(f, "inf");
+        }
+        let bits = x.to_bits();
+        let biased_exp = ((bits >> 52) & 0x7ff) as i64;
+        let mut mantissa = bits & 0x000f_ffff_ffff_ffff;
+        let (lead, exp) = if biased_exp == 0 {
+            // Zero and subnormals
+            (0, if mantissa == 0 { 0 } else { -1022 })
+        } else {
+            (1, biased_exp - 1023)
+        };
+        write!(f, "0x{}", lead)?;
+        if mantissa != 0 {
+            // Trim trailing zero nibbles from the 13 nibble mantissa.
+            let mut digits = 13;
+            while mantissa & 0xf == 0 {
+                mantissa >>= 4;
+                digits -= 1;
+            }
+            write!(f, ".{:0width$x}", mantissa, width = digits)?;
+        }
+        write!(f, "p{:+}", exp)
+    }
+
+    fn fmt_bits(&self, f: &mut Formatter<'_>) -> Result {
+        write!(f, "0x{:016x}", self.0.to_bits())
+    }
+}
+
+impl Display for FmtF64 {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        match self.1 {
+            FloatFormat::Exp => self.fmt_exp(f),
+            FloatFormat::Hex => self.fmt_hex(f),
+            FloatFormat::Bits => self.fmt_bits(f),
+            FloatFormat::Full => {
+                self.fmt_exp(f)?;
+                write!(f, " (")?;
+                self.fmt_hex(f)?;
+                write!(f, ", ")?;
+                self.fmt_bits(f)?;
+                write!(f, ")")
+            }
+        }
+    }
+}
+
+// Display a count as a percent of a total, per a PercentFormat, e.g. "1%",
+// or when the bool is set, preceded by the count itself, e.g. "3 (1%)".
+pub(crate) struct Percent(pub usize, pub usize, pub bool, pub PercentFormat);
+
+impl Display for Percent {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        let Percent(num_part, num_all, show_count, format) = *self;
+        if show_count {
+            write!(f, "{} (", num_part)?;
+        }
+        match format {
+            PercentFormat::Whole => write!(f, "{}", to_percent(num_part, num_all))?,
+            PercentFormat::Fractional(digits) => {
+                // Avoid 0 / 0 for an empty total.
+                let percent = if num_part == 0 { 0.0 } else { 100f64 * num_part as f64 / num_all as f64 };
+                write!(f, "{:.*}", fractional_decimals(percent, digits), percent)?;
+            }
+            PercentFormat::Scientific(digits) => {
+                let percent = 100f64 * num_part as f64 / num_all as f64;
+                if percent < 1.0 && num_part != 0 {
+                    write!(f, "{:.*e}", digits - 1, percent)?;
+                } else {
+                    write!(f, "{}", to_percent(num_part, num_all))?;
+                }
+            }
+        }
+        write!(f, "%{}", if show_count { ")" } else { "" })
+    }
+}
+
+// The number of decimals that shows the lesser of percent and its
+// complement, 100 - percent, to digits significant digits. Rounding then
+// can't reach 0 or 100 unless the percent is exactly that.
+fn fractional_decimals(percent: f64, digits: usize) -> usize {
+    let lesser = percent.min(100.0 - percent);
+    if lesser <= 0.0 {
+        return 0;
+    }
+    let magnitude = lesser.log10().floor() as i64;
+    (digits as i64 - 1 - magnitude).max(0) as usize
+}
+
+// Display a value using an optional precision.
+pub(crate) struct Precise<T>(pub T, pub Option<usize>);
+
+impl<T: Display> Display for Precise<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        match self.1 {
+            Some(precision) => write!(f, "{:.*}", precision, self.0),
+            None => write!(f, "{}", self.0),
+        }
+    }
+}
+
+// Display a value followed by a unit, if the unit isn't empty.
+// A "%" unit directly follows the value, and other units follow a space.
+pub(crate) struct WithUnit<'u, T>(pub T, pub &'u str);
+
+impl<T: Display> Display for WithUnit<'_, T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        match self.1 {
+            "" => write!(f, "{}", self.0),
+            "%" => write!(f, "{}%", self.0),
+            unit => write!(f, "{} {}", self.0, unit),
+        }
+    }
+}
+
+// Color used to highlight pass/fail status in terminal output.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Highlight {
+    Pass,
+    Marginal,
+    Fail,
+}
+
+// Display a value wrapped in ANSI color escapes, or unchanged if no highlight is given.
+pub(crate) struct Paint<T>(pub T, pub Option<Highlight>);
+
+impl<T: Display> Display for Paint<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        match self.1 {
+            Some(highlight) => {
+                let start = match highlight {
+                    Highlight::Pass => "\x1b[32m",
+                    Highlight::Marginal => "\x1b[33m",
+                    Highlight::Fail => "\x1b[31m",
+                };
+                write!(f, "{}{}\x1b[0m", start, self.0)
+            }
+            None => write!(f, "{}", self.0),
+        }
+    }
+}
+
+// Indicate whether colored output is permitted by the environment.
+// Per https://no-color.org, a non-empty NO_COLOR variable disables color.
+pub(crate) fn color_allowed() -> bool {
+    match std::env::var_os("NO_COLOR") {
+        Some(val) => val.is_empty(),
+        None => true,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{FloatFormat, FmtF64, Highlight, Paint, Percent, PercentFormat};
+
+    #[test]
+    fn test_fmt_exp() {
+        let fmt = |x| format!("{}", FmtF64(x, FloatFormat::Exp));
+        assert_eq!(fmt(0.0), "0e0");
+        assert_eq!(fmt(-0.0), "-0e0");
+        assert_eq!(fmt(-f64::NAN), "-NaN");
+        assert_eq!(fmt(f64::NAN), "NaN");
+        assert_eq!(fmt(f64::NEG_INFINITY), "-inf");
+        assert_eq!(fmt(-5e-200), "-5e-200");
+        assert_eq!(format!("{:.2}", FmtF64(1.23456, FloatFormat::Exp)), "1.23e0");
+    }
+
+    #[test]
+    fn test_fmt_hex() {
+        let fmt = |x| format!("{}", FmtF64(x, FloatFormat::Hex));
+        assert_eq!(fmt(0.1), "0x1.999999999999ap-4");
+        assert_eq!(fmt(1.0), "0x1p+0");
+        assert_eq!(fmt(-0.5), "-0x1p-1");
+        assert_eq!(fmt(0.0), "0x0p+0");
+        assert_eq!(fmt(-0.0), "-0x0p+0");
+        assert_eq!(fmt(f64::MIN_POSITIVE / 2.0), "0x0.8p-1022");
+        assert_eq!(fmt(5e-324), "0x0.0000000000001p-1022");
+        assert_eq!(fmt(f64::MAX), "0x1.fffffffffffffp+1023");
+        assert_eq!(fmt(f64::NEG_INFINITY), "-inf");
+        assert_eq!(format!("{}", FmtF64(1.0, FloatFormat::Bits)), "0x3ff0000000000000");
+        assert_eq!(format!("{}", FmtF64(1.0, FloatFormat::Full)), "1e0 (0x1p+0, 0x3ff0000000000000)");
+    }
+
+    #[test]
+    fn test_percent() {
+        assert_eq!(Percent(3, 300, false, PercentFormat::Whole).to_string(), "1%");
+        assert_eq!(Percent(3, 3_000_000, true, PercentFormat::Whole).to_string(), "3 (1%)");
+        assert_eq!(Percent(0, 0, true, PercentFormat::Whole).to_string(), "0 (0%)");
+
+        let fractional = |num_part, num_all| Percent(num_part, num_all, false, PercentFormat::Fractional(1)).to_string();
+        assert_eq!(fractional(3, 1_000_000), "0.0003%");
+        assert_eq!(fractional(999_997, 1_000_000), "99.9997%");
+        assert_eq!(fractional(1, 3), "33%");
+        assert_eq!(fractional(0, 3), "0%");
+        assert_eq!(fractional(0, 0), "0%");
+        assert_eq!(fractional(3, 3), "100%");
+        assert_eq!(Percent(1, 3, false, PercentFormat::Fractional(3)).to_string(), "33.3%");
+        assert_eq!(Percent(1, 7_000_000, false, PercentFormat::Fractional(2)).to_string(), "0.000014%");
+
+        let scientific = |num_part, num_all| Percent(num_part, num_all, false, PercentFormat::Scientific(2)).to_string();
+        assert_eq!(scientific(3, 1_000_000), "3.0e-4%");
+        assert_eq!(scientific(1, 3), "33%");
+        assert_eq!(scientific(0, 3), "0%");
+    }
+
+    #[test]
+    fn test_paint() {
+        assert_eq!(format!("{}", Paint("ok", None)), "ok");
+        assert_eq!(format!("{}", Paint("ok", Some(Highlight::Pass))), "\x1b[32mok\x1b[0m");
+        assert_eq!(format!("{}", Paint(1, Some(Highlight::Fail))), "\x1b[31m1\x1b[0m");
     }
 }
@@ -0,0 +1,76 @@
+use std::fmt::Display;
+use crate::extremes::HUGE;
+
+// The IEEE 754 class of a value, with finite normal values split at
+// sqrt(f64::MAX), above which squaring overflows, since accuracy often
+// differs between these regimes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum IeeeClass {
+    Zero,
+    Subnormal,
+    Normal,
+    // Finite values with magnitudes above sqrt(f64::MAX).
+    Huge,
+    Infinite,
+    Nan,
+}
+
+// The class and sign of an expected value, used to key the sub-summaries
+// of DiffSummary::with_class_strata.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ValueClass {
+    pub class: IeeeClass,
+    // Whether the sign bit is set, including for -0.0 and negative nans.
+    pub negative: bool,
+}
+
+impl ValueClass {
+    pub fn of(value: f64) -> Self {
+        let class = if value.is_nan() {
+            IeeeClass::Nan
+        } else if value.is_infinite() {
+            IeeeClass::Infinite
+        } else if value == 0.0 {
+            IeeeClass::Zero
+        } else if value.is_subnormal() {
+            IeeeClass::Subnormal
+        } else if value.abs() > HUGE {
+            IeeeClass::Huge
+        } else {
+            IeeeClass::Normal
+        };
+        ValueClass { class, negative: value.is_sign_negative() }
+    }
+}
+
+// Shows the sign and class, e.g. "-subnormal" or "+normal".
+impl Display for ValueClass {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let label = match self.class {
+            IeeeClass::Zero => "zero",
+            IeeeClass::Subnormal => "subnormal",
+            IeeeClass::Normal => "normal",
+            IeeeClass::Huge => "huge",
+            IeeeClass::Infinite => "inf",
+            IeeeClass::Nan => "nan",
+        };
+        write!(f, "{}{}", if self.negative { "-" } else { "+" }, label)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{IeeeClass, ValueClass};
+
+    #[test]
+    fn test_value_class() {
+        let classes: Vec<String> = [0.0, -0.0, 1e-310, -1.0, 1e200, f64::NEG_INFINITY, f64::NAN]
+            .iter()
+            .map(|&value| ValueClass::of(value).to_string())
+            .collect();
+        assert_eq!(classes, vec!["+zero", "-zero", "+subnormal", "-normal", "+huge", "-inf", "+nan"]);
+        assert_eq!(ValueClass::of(f64::MAX).class, IeeeClass::Huge);
+        assert_eq!(ValueClass::of(f64::MIN_POSITIVE).class, IeeeClass::Normal);
+        assert!(ValueClass::of(1e-320) < ValueClass::of(-1.0));
+    }
+}
@@ -0,0 +1,30 @@
+use float_diff::{registry, DiffSummary64};
+
+#[float_diff::test(channels = "tests/channels.toml")]
+fn test_channels(pressure: &mut DiffSummary64, phase: &mut DiffSummary64) {
+    assert_eq!(pressure.name, "pressure");
+    assert_eq!(phase.allow_diff(), 0.5);
+    pressure.add(1.0, 1.0, 0);
+    phase.add(-0.25, 0.25, 0);
+}
+
+#[float_diff::test(channels = "tests/channels.toml")]
+#[should_panic(expected = "assert failed for 1 of 2 summaries")]
+fn test_channels_fail(pressure: &mut DiffSummary64) {
+    pressure.add(1.5, 1.0, 0);
+}
+
+#[float_diff::test(channels = "tests/channels.toml")]
+fn test_channels_result(phase: &mut DiffSummary64) -> Result<(), String> {
+    phase.add(1.0, 1.25, 7);
+    Ok(())
+}
+
+#[test]
+fn test_channels_registered() {
+    // Other tests register concurrently, so look for this call's entry.
+    assert_eq!(test_channels_result(), Ok(()));
+    let entries = registry::entries();
+    assert!(entries.iter().any(|entry| entry.name == "phase" && entry.count == 1 && entry.worst_diff == 0.25));
+    assert!(entries.iter().any(|entry| entry.name == "pressure" && entry.count == 0 && entry.is_ok));
+}
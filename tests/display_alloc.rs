@@ -0,0 +1,74 @@
+// Display of summaries whose histograms need no reduction, or whose
+// reduction is already cached, must not allocate, since it's used for
+// progress printing in hot loops.
+use float_diff::{diff, DiffSummary64, DisplayOptions};
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+use std::fmt::Write;
+
+// Counts allocations made by the current thread, so that concurrently
+// running tests don't interfere.
+struct CountingAlloc;
+
+thread_local! {
+    static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+}
+
+unsafe impl GlobalAlloc for CountingAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.with(|count| count.set(count.get() + 1));
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static ALLOC: CountingAlloc = CountingAlloc;
+
+// A fixed-capacity buffer, so that formatting itself doesn't allocate.
+struct StackBuffer {
+    bytes: [u8; 4096],
+    len: usize,
+}
+
+impl Write for StackBuffer {
+    fn write_str(&mut self, s: &str) -> std::fmt::Result {
+        let end = self.len + s.len();
+        self.bytes.get_mut(self.len..end).ok_or(std::fmt::Error)?.copy_from_slice(s.as_bytes());
+        self.len = end;
+        Ok(())
+    }
+}
+
+// The number of allocations made while displaying summary with options,
+// checking that the output matches to_string.
+fn display_allocations(summary: &DiffSummary64, options: &DisplayOptions) -> usize {
+    let mut buffer = StackBuffer { bytes: [0; 4096], len: 0 };
+    let before = ALLOCATIONS.with(Cell::get);
+    write!(buffer, "{}", summary.display_with(options)).unwrap();
+    let allocations = ALLOCATIONS.with(Cell::get) - before;
+    assert_eq!(std::str::from_utf8(&buffer.bytes[..buffer.len]).unwrap(), summary.display_with(options).to_string());
+    allocations
+}
+
+#[test]
+fn test_display_small_histogram() {
+    let mut summary = DiffSummary64::new("small", 1e-3, false, 4, &diff::diff_abs).with_warn_diff(1e-4);
+    summary.add_slices(&[1.0, 1.0, 1.0, -1.0, 2.0], &[1.0, 1.00001, 1.1, 1.0, f64::NAN]);
+    assert_eq!(display_allocations(&summary, &DisplayOptions::new()), 0);
+    assert_eq!(display_allocations(&summary, &DisplayOptions::detailed()), 0);
+}
+
+#[test]
+fn test_display_cached_histogram() {
+    let mut summary = DiffSummary64::new("cached", 1e-3, false, 4, &diff::diff_abs);
+    for exp in 1..12 {
+        summary.add(1.0, 1.0 + 10f64.powi(-exp), exp as usize);
+    }
+    summary.reduce();
+    assert_eq!(display_allocations(&summary, &DisplayOptions::new()), 0);
+    assert_eq!(display_allocations(&summary, &DisplayOptions::detailed()), 0);
+}
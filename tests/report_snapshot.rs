@@ -0,0 +1,58 @@
+// Snapshot reports must be identical from run to run and machine to
+// machine, so that downstream insta snapshots only change along with the
+// compared data.
+use float_diff::demo::DemoData;
+use float_diff::{diff, DiffSummary64, SummarySet};
+
+#[test]
+fn test_summary_snapshot() {
+    let data = DemoData::new(200, 3).with_noise(1e-9).with_heavy_tail(0.05).with_nan_rate(0.02).with_sign_flip_rate(0.02);
+    let mut summary = DiffSummary64::new("demo", 1e-8, false, 5, &diff::diff_rel).with_warn_diff(1e-9);
+    data.feed(&mut summary);
+    summary.add_timed_with(|| 1.0, || 1.0, 200);
+    insta::assert_snapshot!(summary.snapshot_report(), @r"
+    demo: count 201
+      identical 1 (1%)
+      status failed
+      nonfinite lenient
+      worst index 52 NaN vs 6.15779e1 diff NaN
+      11 (5%) failed tolerance 1.00000e-8 first index 4 -8.78949e-2 vs 8.78949e-2 diff 2.00000e0
+      5 (2%) warned above 1.00000e-9
+      mean 7.10660e-2 rms 3.77004e-1
+      over 94 (47%) under 102 (51%)
+      zero 1 (1%), e-11 1 (1%), e-10 16 (8%), e-9 167 (83%), e-8 5 (2%) | e0 7 (3%), nan 4 (2%)
+      p50 3.11898e-10 p95 1.38950e-1 p99 NaN
+      sign diffs 7 (3%) disallowed first index 4 -8.78949e-2 vs 8.78949e-2
+    ");
+}
+
+#[test]
+fn test_set_snapshot() {
+    let mut set = SummarySet::new_lazy(DiffSummary64::new("", 1e-6, false, 4, &diff::diff_abs));
+    set.add("pressure", 1.0, 1.0 + 1e-7, 0).unwrap();
+    set.add("flux", -2.0, 2.0, 0).unwrap();
+    set.add("pressure", 3.0, 3.0, 1).unwrap();
+    insta::assert_snapshot!(set.snapshot_report(), @r"
+    pressure: count 2
+      identical 1 (50%)
+      status ok
+      nonfinite lenient
+      worst index 0 1.00000e0 vs 1.00000e0 diff 1.00000e-7
+      0 (0%) failed tolerance 1.00000e-6
+      mean 5.00000e-8 rms 7.07107e-8
+      over 0 (0%) under 1 (50%)
+      zero 1 (50%), e-6 1 (50%) |
+      p50 0.00000e0 p95 0.00000e0 p99 0.00000e0
+      sign diffs 0 (0%) disallowed
+    flux: count 1
+      status failed
+      nonfinite lenient
+      worst index 0 -2.00000e0 vs 2.00000e0 diff 4.00000e0
+      1 (100%) failed tolerance 1.00000e-6 first index 0 -2.00000e0 vs 2.00000e0 diff 4.00000e0
+      mean 4.00000e0 rms 4.00000e0
+      over 0 (0%) under 1 (100%)
+      | e0 1 (100%)
+      p50 1.00000e0 p95 1.00000e0 p99 1.00000e0
+      sign diffs 1 (100%) disallowed first index 0 -2.00000e0 vs 2.00000e0
+    ");
+}